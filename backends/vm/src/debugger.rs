@@ -421,6 +421,9 @@ impl<SE: ScriptExpression + std::fmt::Debug> VmDebugger<SE> for PrintDebugger {
                     operation.label().to_owned()
                 }
             );
+            if let Some(span) = scope.span() {
+                println!("- span: {}", span);
+            }
             self.print_extra(context);
             self.try_halt();
         }
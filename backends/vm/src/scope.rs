@@ -1,19 +1,90 @@
 use crate::debugger::VmDebuggerHandle;
 use intuicio_core::{
-    context::Context,
-    function::FunctionBody,
-    registry::Registry,
-    script::{ScriptExpression, ScriptFunctionGenerator, ScriptHandle, ScriptOperation},
+    context::{Context, HeapId, OperationTrace},
+    function::{Function, FunctionBody, FunctionHandle, FunctionQuery, FunctionSignature},
+    future::FuturePoller,
+    registry::{FunctionResolveError, Registry},
+    script::{
+        ScriptError, ScriptExpression, ScriptFunction, ScriptFunctionGenerator, ScriptHandle,
+        ScriptOperation, ScriptSpan,
+    },
+};
+use intuicio_data::data_stack::DataStackToken;
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
 };
 use typid::ID;
 
 pub type VmScopeSymbol = ID<()>;
+pub type VmCallCacheHandle = Arc<RwLock<VmCallCache>>;
+
+/// Targets of `CallFunction` operations of single script, resolved once and
+/// reused by its later executions. Targets are keyed by operation address, so
+/// cache has to live as long as script it is used with. It gets cleared when
+/// functions of registry it was filled from change. Bodies swapped in place by
+/// `Registry::replace_function` are picked up, since resolved handles stay.
+#[derive(Debug, Default)]
+pub struct VmCallCache {
+    functions_generation: u64,
+    targets: HashMap<usize, FunctionHandle>,
+    resolutions: usize,
+}
+
+impl VmCallCache {
+    pub fn into_handle(self) -> VmCallCacheHandle {
+        Arc::new(RwLock::new(self))
+    }
+
+    /// Number of registry lookups done by cache misses.
+    pub fn resolutions(&self) -> usize {
+        self.resolutions
+    }
+
+    pub fn len(&self) -> usize {
+        self.targets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.targets.clear();
+    }
+}
+
+struct VmCatch<'a, SE: ScriptExpression> {
+    scope: ScriptHandle<'a, SE>,
+    stack: DataStackToken,
+    registers_barriers: usize,
+    registers_count: usize,
+}
 
 pub struct VmScope<'a, SE: ScriptExpression> {
     handle: ScriptHandle<'a, SE>,
     symbol: VmScopeSymbol,
     position: usize,
     child: Option<Box<Self>>,
+    catch: Option<VmCatch<'a, SE>>,
+    /// Signature of function this scope is tail of - nothing gets executed
+    /// in that function after this scope completes.
+    tail: Option<Arc<FunctionSignature>>,
+    /// Handle of function called with self-recursive tail call.
+    tail_call: Option<FunctionHandle>,
+    returned: bool,
+    /// Set when scope stopped on `Await` operation with pending future.
+    suspended: bool,
+    /// Registers count at the start of scope pushed as registers frame.
+    registers_frame: Option<usize>,
+    /// Module of function this scope belongs to, used to check visibility
+    /// of called functions. Scopes without it bypass visibility checks.
+    caller_module: Option<Arc<str>>,
+    /// Signature of function this scope belongs to, reported in operation traces.
+    function: Option<Arc<FunctionSignature>>,
+    call_cache: Option<VmCallCacheHandle>,
+    /// Source location of currently executed operation, set by span markers.
+    span: Option<ScriptSpan>,
     debugger: Option<VmDebuggerHandle<SE>>,
 }
 
@@ -24,15 +95,52 @@ impl<'a, SE: ScriptExpression> VmScope<'a, SE> {
             symbol,
             position: 0,
             child: None,
+            catch: None,
+            tail: None,
+            tail_call: None,
+            returned: false,
+            suspended: false,
+            registers_frame: None,
+            caller_module: None,
+            function: None,
+            call_cache: None,
+            span: None,
             debugger: None,
         }
     }
 
+    pub fn with_caller_module(mut self, module_name: Option<Arc<str>>) -> Self {
+        self.caller_module = module_name;
+        self
+    }
+
+    pub fn with_function(mut self, function: Option<Arc<FunctionSignature>>) -> Self {
+        self.function = function;
+        self
+    }
+
+    /// Scopes without call cache resolve called functions on every call.
+    pub fn with_call_cache(mut self, call_cache: Option<VmCallCacheHandle>) -> Self {
+        self.call_cache = call_cache;
+        self
+    }
+
     pub fn with_debugger(mut self, debugger: Option<VmDebuggerHandle<SE>>) -> Self {
         self.debugger = debugger;
         self
     }
 
+    pub fn with_span(mut self, span: Option<ScriptSpan>) -> Self {
+        self.span = span;
+        self
+    }
+
+    /// Source location of operation this scope currently executes, as set by
+    /// last span marker of this scope or of its parent scopes.
+    pub fn span(&self) -> Option<&ScriptSpan> {
+        self.span.as_ref()
+    }
+
     pub fn symbol(&self) -> VmScopeSymbol {
         self.symbol
     }
@@ -45,25 +153,179 @@ impl<'a, SE: ScriptExpression> VmScope<'a, SE> {
         self.position >= self.handle.len()
     }
 
+    /// Tells if scope has completed with self-recursive tail call, which
+    /// arguments are left on the stack for the function to restart with.
+    pub fn has_tail_call(&self) -> bool {
+        self.tail_call.is_some()
+    }
+
+    /// Tells if scope has completed with `ReturnScope` operation, either its
+    /// own or from any of its child scopes.
+    pub fn has_returned(&self) -> bool {
+        self.returned
+    }
+
+    /// Tells if scope has stopped on `Await` operation with future not ready
+    /// yet. Running it again resumes from that operation.
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
+    fn new_child(&self, handle: ScriptHandle<'a, SE>) -> Self {
+        Self::new(handle, self.symbol)
+            .with_debugger(self.debugger.clone())
+            .with_caller_module(self.caller_module.clone())
+            .with_function(self.function.clone())
+            .with_call_cache(self.call_cache.clone())
+            .with_span(self.span.clone())
+    }
+
+    fn with_tail(mut self, tail: Option<Arc<FunctionSignature>>) -> Self {
+        self.tail = tail;
+        self
+    }
+
+    fn child_tail(&self) -> Option<Arc<FunctionSignature>> {
+        if self.position >= self.handle.len() && self.catch.is_none() {
+            self.tail.clone()
+        } else {
+            None
+        }
+    }
+
+    fn resolve_call(&self, query: &FunctionQuery, registry: &Registry) -> FunctionHandle {
+        let resolve = || {
+            registry
+                .resolve_function(query, self.caller_module.as_deref())
+                .unwrap_or_else(|error| match error {
+                    FunctionResolveError::NotFound => {
+                        panic!(
+                            "Could not call non-existent function{}: {:#?}",
+                            self.location(),
+                            query
+                        )
+                    }
+                    error => panic!(
+                        "Could not call function{}: {:#?}. {}",
+                        self.location(),
+                        query,
+                        error
+                    ),
+                })
+        };
+        let Some(call_cache) = self.call_cache.as_ref() else {
+            return resolve();
+        };
+        let key = query as *const FunctionQuery as usize;
+        let generation = registry.functions_generation();
+        if let Ok(call_cache) = call_cache.read() {
+            if call_cache.functions_generation == generation {
+                if let Some(handle) = call_cache.targets.get(&key) {
+                    return handle.clone();
+                }
+            }
+        }
+        let handle = resolve();
+        if let Ok(mut call_cache) = call_cache.write() {
+            if call_cache.functions_generation != generation {
+                call_cache.functions_generation = generation;
+                call_cache.targets.clear();
+            }
+            call_cache.targets.insert(key, handle.clone());
+            call_cache.resolutions += 1;
+        }
+        handle
+    }
+
+    fn location(&self) -> String {
+        self.span
+            .as_ref()
+            .map(|span| format!(" at {}", span))
+            .unwrap_or_default()
+    }
+
+    /// When budget gets exceeded, stack and registers are restored to state
+    /// from before the run, finalizing values left by aborted scopes. Run
+    /// also stops when scope gets suspended on pending future - see
+    /// `is_suspended`.
     pub fn run(&mut self, context: &mut Context, registry: &Registry) {
+        let token = context.store();
         while self.step(context, registry) {}
+        if context.budget_exceeded().is_some() {
+            context.restore(token);
+        }
+    }
+
+    /// Function bodies cannot suspend their callers, so suspended scope
+    /// gets resumed here, giving up thread time slice between polls.
+    fn run_to_completion(&mut self, context: &mut Context, registry: &Registry) {
+        let token = context.store();
+        loop {
+            while self.step(context, registry) {}
+            if !self.suspended {
+                break;
+            }
+            std::thread::yield_now();
+        }
+        if context.budget_exceeded().is_some() {
+            context.restore(token);
+        }
     }
 
     pub fn step(&mut self, context: &mut Context, registry: &Registry) -> bool {
+        let resumed = std::mem::take(&mut self.suspended);
         if let Some(child) = &mut self.child {
             if child.step(context, registry) {
                 return true;
+            } else if child.suspended {
+                self.suspended = true;
+                return false;
             } else {
+                let tail_call = child.tail_call.take();
+                let returned = child.returned;
+                if let Some(count) = child.registers_frame {
+                    context.truncate_registers(count);
+                }
                 self.child = None;
+                if tail_call.is_some() {
+                    self.tail_call = tail_call;
+                    self.position = self.handle.len();
+                    return false;
+                }
+                if returned {
+                    self.returned = true;
+                    self.position = self.handle.len();
+                    return false;
+                }
+                let catch = self.catch.take();
+                if context.has_error() {
+                    if let Some(catch) = catch {
+                        context.stack().restore(catch.stack);
+                        while context.registers_barriers().len() > catch.registers_barriers {
+                            context.restore_registers();
+                        }
+                        context.truncate_registers(catch.registers_count);
+                        let error = context.take_error().unwrap();
+                        context.stack().push(error);
+                        self.child = Some(Box::new(self.new_child(catch.scope)));
+                        return true;
+                    }
+                    self.position = self.handle.len();
+                    return false;
+                }
             }
         }
-        if self.position == 0 {
+        if self.position == 0 && !resumed {
             if let Some(debugger) = self.debugger.as_ref() {
                 if let Ok(mut debugger) = debugger.try_write() {
                     debugger.on_enter_scope(self, context, registry);
                 }
             }
         }
+        if self.position < self.handle.len() && !context.consume_budget() {
+            // exceeded budget unwinds all scopes, it cannot be caught.
+            self.position = self.handle.len();
+        }
         let result = if let Some(operation) = self.handle.get(self.position) {
             if let Some(debugger) = self.debugger.as_ref() {
                 if let Ok(mut debugger) = debugger.try_write() {
@@ -71,6 +333,14 @@ impl<'a, SE: ScriptExpression> VmScope<'a, SE> {
                 }
             }
             let position = self.position;
+            if context.is_tracing_operations() {
+                context.trace_operation(OperationTrace {
+                    label: operation.label(),
+                    position,
+                    function: self.function.clone(),
+                    span: self.span.clone(),
+                });
+            }
             let result = match operation {
                 ScriptOperation::None => {
                     self.position += 1;
@@ -81,21 +351,27 @@ impl<'a, SE: ScriptExpression> VmScope<'a, SE> {
                     self.position += 1;
                     true
                 }
-                ScriptOperation::DefineRegister { query } => {
+                ScriptOperation::DefineRegister { query, immutable } => {
                     let handle = registry
                         .types()
                         .find(|handle| query.is_valid(handle))
                         .unwrap_or_else(|| {
                             panic!(
-                                "Could not define register for non-existent type: {:#?}",
+                                "Could not define register for non-existent type{}: {:#?}",
+                                self.location(),
                                 query
                             )
                         });
-                    unsafe {
-                        context
-                            .registers()
-                            .push_register_raw(handle.type_hash(), *handle.layout())
+                    let registers = context.registers();
+                    let index = unsafe {
+                        registers.push_register_raw(handle.type_hash(), *handle.layout())
                     };
+                    if let Some(mut register) = index
+                        .filter(|_| *immutable)
+                        .and_then(|index| registers.access_register(index))
+                    {
+                        register.set_immutable(true);
+                    }
                     self.position += 1;
                     true
                 }
@@ -105,7 +381,11 @@ impl<'a, SE: ScriptExpression> VmScope<'a, SE> {
                         .registers()
                         .access_register(index)
                         .unwrap_or_else(|| {
-                            panic!("Could not access non-existent register: {}", index)
+                            panic!(
+                                "Could not access non-existent register{}: {}",
+                                self.location(),
+                                index
+                            )
                         })
                         .free();
                     self.position += 1;
@@ -115,10 +395,18 @@ impl<'a, SE: ScriptExpression> VmScope<'a, SE> {
                     let index = context.absolute_register_index(*index);
                     let (stack, registers) = context.stack_and_registers();
                     let mut register = registers.access_register(index).unwrap_or_else(|| {
-                        panic!("Could not access non-existent register: {}", index)
+                        panic!(
+                            "Could not access non-existent register{}: {}",
+                            self.location(),
+                            index
+                        )
                     });
                     if !stack.push_from_register(&mut register) {
-                        panic!("Could not push data from register: {}", index);
+                        panic!(
+                            "Could not push data from register{}: {}",
+                            self.location(),
+                            index
+                        );
                     }
                     self.position += 1;
                     true
@@ -127,10 +415,52 @@ impl<'a, SE: ScriptExpression> VmScope<'a, SE> {
                     let index = context.absolute_register_index(*index);
                     let (stack, registers) = context.stack_and_registers();
                     let mut register = registers.access_register(index).unwrap_or_else(|| {
-                        panic!("Could not access non-existent register: {}", index)
+                        panic!(
+                            "Could not access non-existent register{}: {}",
+                            self.location(),
+                            index
+                        )
                     });
+                    if register.is_immutable() && register.is_assigned() {
+                        panic!(
+                            "Could not reassign immutable register{}: {}",
+                            self.location(),
+                            index
+                        );
+                    }
                     if !stack.pop_to_register(&mut register) {
-                        panic!("Could not pop data to register: {}", index);
+                        panic!(
+                            "Could not pop data to register{}: {}",
+                            self.location(),
+                            index
+                        );
+                    }
+                    self.position += 1;
+                    true
+                }
+                ScriptOperation::RestoreRegister { index } => {
+                    let index = context.absolute_register_index(*index);
+                    let (stack, registers) = context.stack_and_registers();
+                    let mut register = registers.access_register(index).unwrap_or_else(|| {
+                        panic!(
+                            "Could not access non-existent register{}: {}",
+                            self.location(),
+                            index
+                        )
+                    });
+                    if register.has_value() {
+                        panic!(
+                            "Could not restore occupied register{}: {}",
+                            self.location(),
+                            index
+                        );
+                    }
+                    if !stack.pop_to_register(&mut register) {
+                        panic!(
+                            "Could not pop data to register{}: {}",
+                            self.location(),
+                            index
+                        );
                     }
                     self.position += 1;
                     true
@@ -143,64 +473,103 @@ impl<'a, SE: ScriptExpression> VmScope<'a, SE> {
                         .access_registers_pair(from, to)
                         .unwrap_or_else(|| {
                             panic!(
-                                "Could not access non-existent registers pair: {} and {}",
-                                from, to
+                                "Could not access non-existent registers pair{}: {} and {}",
+                                self.location(),
+                                from,
+                                to
                             )
                         });
+                    if target.is_immutable() && target.is_assigned() {
+                        panic!(
+                            "Could not reassign immutable register{}: {}",
+                            self.location(),
+                            to
+                        );
+                    }
                     source.move_to(&mut target);
                     self.position += 1;
                     true
                 }
-                ScriptOperation::CallFunction { query } => {
-                    let handle = registry
-                        .functions()
-                        .find(|handle| query.is_valid(handle.signature()))
-                        .unwrap_or_else(|| {
-                            panic!("Could not call non-existent function: {:#?}", query)
-                        });
-                    handle.invoke(context, registry);
+                ScriptOperation::HeapPromote => {
+                    let id = context.heap_promote(registry).unwrap_or_else(|| {
+                        panic!("Could not promote stack value to heap{}", self.location())
+                    });
+                    context.stack().push(id);
+                    self.position += 1;
+                    true
+                }
+                ScriptOperation::HeapDemote => {
+                    let id = context.stack().pop::<HeapId>().unwrap_or_else(|| {
+                        panic!("Could not pop heap id from stack{}", self.location())
+                    });
+                    if !context.heap_demote(id) {
+                        panic!(
+                            "Could not demote heap value to stack{}: {}",
+                            self.location(),
+                            id
+                        );
+                    }
                     self.position += 1;
                     true
                 }
+                ScriptOperation::CallFunction { query } => {
+                    let handle = self.resolve_call(query, registry);
+                    self.position += 1;
+                    if self
+                        .child_tail()
+                        .map(|signature| handle.signature() == signature.as_ref())
+                        .unwrap_or_default()
+                    {
+                        self.tail_call = Some(handle);
+                        false
+                    } else {
+                        handle.invoke(context, registry);
+                        true
+                    }
+                }
                 ScriptOperation::BranchScope {
                     scope_success,
                     scope_failure,
                 } => {
+                    self.position += 1;
                     if context.stack().pop::<bool>().unwrap() {
                         self.child = Some(Box::new(
-                            Self::new(scope_success.clone(), self.symbol)
-                                .with_debugger(self.debugger.clone()),
+                            self.new_child(scope_success.clone())
+                                .with_tail(self.child_tail()),
                         ));
                     } else if let Some(scope_failure) = scope_failure {
                         self.child = Some(Box::new(
-                            Self::new(scope_failure.clone(), self.symbol)
-                                .with_debugger(self.debugger.clone()),
+                            self.new_child(scope_failure.clone())
+                                .with_tail(self.child_tail()),
                         ));
                     }
-                    self.position += 1;
                     true
                 }
                 ScriptOperation::LoopScope { scope } => {
                     if !context.stack().pop::<bool>().unwrap() {
                         self.position += 1;
                     } else {
-                        self.child = Some(Box::new(
-                            Self::new(scope.clone(), self.symbol)
-                                .with_debugger(self.debugger.clone()),
-                        ));
+                        self.child = Some(Box::new(self.new_child(scope.clone())));
                     }
                     true
                 }
-                ScriptOperation::PushScope { scope } => {
-                    context.store_registers();
-                    self.child = Some(Box::new(
-                        Self::new(scope.clone(), self.symbol).with_debugger(self.debugger.clone()),
-                    ));
+                ScriptOperation::PushScope { scope, frame } => {
+                    let mut child = self.new_child(scope.clone());
+                    if *frame {
+                        child.registers_frame = Some(context.registers().registers_count());
+                    } else {
+                        context.store_registers();
+                    }
+                    self.child = Some(Box::new(child));
                     self.position += 1;
                     true
                 }
                 ScriptOperation::PopScope => {
-                    context.restore_registers();
+                    if let Some(count) = self.registers_frame {
+                        context.truncate_registers(count);
+                    } else {
+                        context.restore_registers();
+                    }
                     self.position = self.handle.len();
                     false
                 }
@@ -213,6 +582,78 @@ impl<'a, SE: ScriptExpression> VmScope<'a, SE> {
                     }
                     result
                 }
+                ScriptOperation::ReturnScope => {
+                    self.returned = true;
+                    self.position = self.handle.len();
+                    false
+                }
+                ScriptOperation::Await => {
+                    let poller = context
+                        .custom::<FuturePoller>(FuturePoller::CUSTOM)
+                        .unwrap_or_else(|| {
+                            panic!(
+                                "Could not await without `FuturePoller` in context custom data{}",
+                                self.location()
+                            )
+                        })
+                        .clone();
+                    let mut ready = poller.is_ready(context.stack());
+                    if ready == Some(false) {
+                        poller.poll();
+                        ready = poller.is_ready(context.stack());
+                    }
+                    match ready {
+                        Some(true) => {
+                            self.position += 1;
+                            true
+                        }
+                        Some(false) => {
+                            self.suspended = true;
+                            false
+                        }
+                        None => panic!(
+                            "Could not await value that is not future handle{}",
+                            self.location()
+                        ),
+                    }
+                }
+                ScriptOperation::Throw => {
+                    let error = context
+                        .stack()
+                        .pop::<ScriptError>()
+                        .expect("Could not pop script error to throw");
+                    context.throw(error);
+                    self.position += 1;
+                    true
+                }
+                ScriptOperation::Span { span } => {
+                    self.span = Some(span.clone());
+                    self.position += 1;
+                    true
+                }
+                ScriptOperation::TryScope {
+                    scope_try,
+                    scope_catch,
+                } => {
+                    self.catch = Some(VmCatch {
+                        scope: scope_catch.clone(),
+                        stack: context.stack().store(),
+                        registers_barriers: context.registers_barriers().len(),
+                        registers_count: context.registers().registers_count(),
+                    });
+                    self.child = Some(Box::new(self.new_child(scope_try.clone())));
+                    self.position += 1;
+                    true
+                }
+            };
+            let result = if let Some(error) = context.error_mut() {
+                if error.span.is_none() {
+                    error.span = self.span.clone();
+                }
+                self.position = self.handle.len();
+                false
+            } else {
+                result
             };
             if let Some(debugger) = self.debugger.as_ref() {
                 if let Ok(mut debugger) = debugger.try_write() {
@@ -223,7 +664,7 @@ impl<'a, SE: ScriptExpression> VmScope<'a, SE> {
         } else {
             false
         };
-        if !result || self.position >= self.handle.len() {
+        if (!result && !self.suspended) || self.position >= self.handle.len() {
             if let Some(debugger) = self.debugger.as_ref() {
                 if let Ok(mut debugger) = debugger.try_write() {
                     debugger.on_exit_scope(self, context, registry);
@@ -234,6 +675,67 @@ impl<'a, SE: ScriptExpression> VmScope<'a, SE> {
     }
 }
 
+impl<SE: ScriptExpression + 'static> VmScope<'static, SE> {
+    fn generate_body(
+        script: ScriptHandle<'static, SE>,
+        debugger: Option<VmDebuggerHandle<SE>>,
+        signature: Option<Arc<FunctionSignature>>,
+    ) -> (FunctionBody, VmScopeSymbol) {
+        let symbol = VmScopeSymbol::new();
+        let call_cache = VmCallCache::default().into_handle();
+        let caller_module = signature
+            .as_ref()
+            .and_then(|signature| signature.module_name.as_deref())
+            .map(Arc::<str>::from);
+        (
+            FunctionBody::closure(move |context, registry| {
+                let mut tail_call = None::<FunctionHandle>;
+                loop {
+                    let registers_barriers = context.registers_barriers().len();
+                    let mut scope = Self::new(script.clone(), symbol)
+                        .with_debugger(debugger.clone())
+                        .with_caller_module(caller_module.clone())
+                        .with_function(signature.clone())
+                        .with_call_cache(Some(call_cache.clone()))
+                        .with_tail(signature.clone());
+                    // restarted invocation gets observed as call on its own.
+                    match tail_call.take() {
+                        Some(handle) => {
+                            handle.observe(registry, || scope.run_to_completion(context, registry))
+                        }
+                        None => scope.run_to_completion(context, registry),
+                    }
+                    // unwinding skips `PopScope` operations, so restore barriers
+                    // to let function invocation restore its own one.
+                    while context.registers_barriers().len() > registers_barriers {
+                        context.restore_registers();
+                    }
+                    tail_call = scope.tail_call.take();
+                    if tail_call.is_none() {
+                        break;
+                    }
+                    // self-recursive tail call reuses this invocation, so only
+                    // registers frame gets replaced, as function invocation does.
+                    context.restore_registers();
+                    context.store_registers();
+                }
+                // aborted run restores stack with inputs it started with, so
+                // they get dropped to not be taken for outputs by caller.
+                if context.budget_exceeded().is_some() {
+                    let inputs = signature
+                        .as_ref()
+                        .map(|signature| signature.inputs.len())
+                        .unwrap_or_default();
+                    for _ in 0..inputs {
+                        context.stack().drop();
+                    }
+                }
+            }),
+            symbol,
+        )
+    }
+}
+
 impl<SE: ScriptExpression + 'static> ScriptFunctionGenerator<SE> for VmScope<'static, SE> {
     type Input = Option<VmDebuggerHandle<SE>>;
     type Output = VmScopeSymbol;
@@ -242,13 +744,25 @@ impl<SE: ScriptExpression + 'static> ScriptFunctionGenerator<SE> for VmScope<'st
         script: ScriptHandle<'static, SE>,
         debugger: Self::Input,
     ) -> Option<(FunctionBody, Self::Output)> {
-        let symbol = VmScopeSymbol::new();
+        Some(Self::generate_body(script, debugger, None))
+    }
+
+    /// Unlike bare function body, generated function knows its own signature
+    /// so self-recursive calls in tail position reuse current invocation
+    /// instead of growing the stack.
+    fn generate_function(
+        function: &ScriptFunction<'static, SE>,
+        registry: &Registry,
+        debugger: Self::Input,
+    ) -> Option<(Function, Self::Output)> {
+        let signature = function.signature.build(registry);
+        let (body, symbol) = Self::generate_body(
+            function.script.clone(),
+            debugger,
+            Some(Arc::new(signature.clone())),
+        );
         Some((
-            FunctionBody::closure(move |context, registry| {
-                Self::new(script.clone(), symbol)
-                    .with_debugger(debugger.clone())
-                    .run(context, registry);
-            }),
+            Function::new(signature, body).with_calls(function.calls()),
             symbol,
         ))
     }
@@ -258,6 +772,13 @@ impl<SE: ScriptExpression + 'static> ScriptFunctionGenerator<SE> for VmScope<'st
 mod tests {
     use crate::scope::*;
     use intuicio_core::prelude::*;
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
 
     #[test]
     fn test_vm_scope() {
@@ -343,4 +864,1130 @@ mod tests {
         assert_eq!(context.stack().position(), 0);
         assert_eq!(context.registers().position(), 0);
     }
+
+    #[test]
+    fn test_frame_scope() {
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(Default)]
+        struct Tracked;
+
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                DROPPED.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let mut registry = Registry::default().with_basic_types();
+        registry.add_type(NativeStructBuilder::new_uninitialized::<Tracked>().build());
+        let script = ScriptBuilder::<()>::default()
+            .define_register(TypeQuery::of::<i32>())
+            .pop_to_register(0)
+            .push_frame_scope(
+                ScriptBuilder::<()>::default()
+                    .define_register(TypeQuery::of::<Tracked>())
+                    .pop_to_register(1)
+                    .build(),
+            )
+            .push_frame_scope(
+                ScriptBuilder::<()>::default()
+                    .define_register(TypeQuery::of::<Tracked>())
+                    .pop_to_register(1)
+                    .pop_scope()
+                    .define_register(TypeQuery::of::<Tracked>())
+                    .build(),
+            )
+            .push_from_register(0)
+            .build();
+        let mut context = Context::new(10240, 10240);
+        context.stack().push(Tracked);
+        context.stack().push(Tracked);
+        context.stack().push(42i32);
+        VmScope::new(script, VmScopeSymbol::new()).run(&mut context, &registry);
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 2);
+        assert_eq!(context.registers().registers_count(), 1);
+        assert!(context.access_register(1).is_none());
+        assert_eq!(context.stack().pop::<i32>().unwrap(), 42);
+        assert_eq!(context.stack().position(), 0);
+    }
+
+    #[test]
+    fn test_immutable_register() {
+        let registry = Registry::default().with_basic_types();
+        let run = |script, values: &[i32]| {
+            let mut context = Context::new(10240, 10240);
+            for value in values {
+                context.stack().push(*value);
+            }
+            VmScope::new(script, VmScopeSymbol::new()).run(&mut context, &registry);
+            context.stack().pop::<i32>()
+        };
+
+        let script = ScriptBuilder::<()>::default()
+            .define_immutable_register(TypeQuery::of::<i32>())
+            .pop_to_register(0)
+            .push_from_register(0)
+            .restore_register(0)
+            .push_from_register(0)
+            .restore_register(0)
+            .push_from_register(0)
+            .build();
+        assert_eq!(run(script, &[42]), Some(42));
+
+        let reassign = |script| {
+            let result =
+                std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| run(script, &[1, 42])));
+            *result.unwrap_err().downcast::<String>().unwrap()
+        };
+        assert_eq!(
+            reassign(
+                ScriptBuilder::<()>::default()
+                    .define_immutable_register(TypeQuery::of::<i32>())
+                    .pop_to_register(0)
+                    .pop_to_register(0)
+                    .build()
+            ),
+            "Could not reassign immutable register: 0"
+        );
+        // moving value out does not make register assignable again.
+        assert_eq!(
+            reassign(
+                ScriptBuilder::<()>::default()
+                    .define_immutable_register(TypeQuery::of::<i32>())
+                    .pop_to_register(0)
+                    .push_from_register(0)
+                    .pop_to_register(0)
+                    .build()
+            ),
+            "Could not reassign immutable register: 0"
+        );
+        assert_eq!(
+            reassign(
+                ScriptBuilder::<()>::default()
+                    .define_immutable_register(TypeQuery::of::<i32>())
+                    .define_register(TypeQuery::of::<i32>())
+                    .pop_to_register(0)
+                    .pop_to_register(1)
+                    .push_from_register(0)
+                    .move_register(1, 0)
+                    .build()
+            ),
+            "Could not reassign immutable register: 0"
+        );
+        assert_eq!(
+            reassign(
+                ScriptBuilder::<()>::default()
+                    .define_immutable_register(TypeQuery::of::<i32>())
+                    .pop_to_register(0)
+                    .restore_register(0)
+                    .build()
+            ),
+            "Could not restore occupied register: 0"
+        );
+
+        let script = ScriptBuilder::<()>::default()
+            .define_register(TypeQuery::of::<i32>())
+            .pop_to_register(0)
+            .pop_to_register(0)
+            .push_from_register(0)
+            .build();
+        assert_eq!(run(script, &[1, 42]), Some(1));
+    }
+
+    #[test]
+    fn test_heap_promote() {
+        static FINALIZED: AtomicUsize = AtomicUsize::new(0);
+
+        struct Tracked(i32);
+
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                FINALIZED.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let mut registry = Registry::default().with_basic_types();
+        registry.add_type(
+            unsafe {
+                NativeStructBuilder::new_uninitialized::<Tracked>()
+                    .override_send(true)
+                    .override_sync(true)
+            }
+            .build(),
+        );
+        let mut context = Context::new(10240, 10240);
+        context.stack().push(Tracked(42));
+        // value stored in register of frame scope escapes it through heap.
+        let script = ScriptBuilder::<()>::default()
+            .push_frame_scope(
+                ScriptBuilder::<()>::default()
+                    .define_register(TypeQuery::of::<Tracked>())
+                    .pop_to_register(0)
+                    .push_from_register(0)
+                    .heap_promote()
+                    .build(),
+            )
+            .build();
+        VmScope::new(script, VmScopeSymbol::new()).run(&mut context, &registry);
+        assert_eq!(context.registers().registers_count(), 0);
+        assert_eq!(context.heap_len(), 1);
+        assert_eq!(FINALIZED.load(Ordering::SeqCst), 0);
+        let id = context.stack().pop::<HeapId>().unwrap();
+        assert_eq!(context.heap_read::<Tracked>(id).unwrap().0, 42);
+
+        context.stack().push(id);
+        let script = ScriptBuilder::<()>::default().heap_demote().build();
+        VmScope::new(script, VmScopeSymbol::new()).run(&mut context, &registry);
+        assert_eq!(context.heap_len(), 0);
+        assert_eq!(context.stack().pop::<Tracked>().unwrap().0, 42);
+        assert_eq!(FINALIZED.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_custom_expression() {
+        let registry = Registry::default()
+            .with_basic_types()
+            .with_custom_expression("double", |_: &[u8], context: &mut Context, _: &Registry| {
+                let value = context.stack().pop::<i32>().unwrap();
+                context.stack().push(value * 2);
+            })
+            .with_custom_expression(
+                "multiply",
+                |payload: &[u8], context: &mut Context, _: &Registry| {
+                    let factor = i32::from_le_bytes(payload.try_into().unwrap());
+                    let value = context.stack().pop::<i32>().unwrap();
+                    context.stack().push(value * factor);
+                },
+            );
+        let run = |script, registry: &Registry| {
+            let mut context = Context::new(10240, 10240);
+            context.stack().push(21i32);
+            VmScope::new(script, VmScopeSymbol::new()).run(&mut context, registry);
+            context.stack().pop::<i32>()
+        };
+
+        let script = ScriptBuilder::<CustomScriptExpression>::default()
+            .expression(CustomScriptExpression::new("double"))
+            .build();
+        assert_eq!(run(script, &registry), Some(42));
+
+        let script = ScriptBuilder::<CustomScriptExpression>::default()
+            .expression(CustomScriptExpression::new("double"))
+            .expression(CustomScriptExpression::new("multiply").with_payload(3i32.to_le_bytes()))
+            .build();
+        assert_eq!(run(script.clone(), &registry), Some(126));
+
+        let child = Registry::default().with_parent(registry.into());
+        assert_eq!(run(script.clone(), &child), Some(126));
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            run(script, &Registry::default())
+        }));
+        assert_eq!(
+            *result.unwrap_err().downcast::<String>().unwrap(),
+            "Could not find custom expression: double"
+        );
+    }
+
+    #[test]
+    fn test_budget() {
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        struct Tracked;
+
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                DROPPED.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let mut registry = Registry::default().with_basic_types();
+        let forever = ScriptBuilder::<InlineExpression>::default()
+            .expression(InlineExpression::closure(|context, _| {
+                context.stack().push(Tracked);
+            }))
+            .expression(InlineExpression::copied(true))
+            .loop_scope(
+                ScriptBuilder::default()
+                    .expression(InlineExpression::copied(true))
+                    .build(),
+            )
+            .build();
+        registry.add_function(Function::new(
+            function_signature!(&registry => mod test fn forever() -> ()),
+            VmScope::generate_function_body(forever, None).unwrap().0,
+        ));
+        let answer = ScriptBuilder::<InlineExpression>::default()
+            .expression(InlineExpression::copied(42i32))
+            .build();
+        registry.add_function(Function::new(
+            function_signature!(&registry => mod test fn answer() -> (result: i32)),
+            VmScope::generate_function_body(answer, None).unwrap().0,
+        ));
+        let mut host = Host::new(Context::new(10240, 10240), registry.into());
+
+        let result = host
+            .call_function::<(), ()>("forever", "test", None)
+            .unwrap()
+            .run_budgeted((), Budget::default().with_max_operations(100));
+        assert_eq!(result, Err(BudgetExceeded::Operations { limit: 100 }));
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 1);
+        assert_eq!(host.context().stack().position(), 0);
+        assert_eq!(host.context().registers_barriers().len(), 0);
+        assert!(host.context().budget().is_none());
+
+        let limit = Duration::from_millis(10);
+        let result = host
+            .call_function::<(), ()>("forever", "test", None)
+            .unwrap()
+            .run_budgeted((), Budget::default().with_max_duration(limit));
+        assert_eq!(result, Err(BudgetExceeded::Duration { limit }));
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 2);
+        assert_eq!(host.context().stack().position(), 0);
+
+        let result = host
+            .call_function::<(i32,), ()>("answer", "test", None)
+            .unwrap()
+            .run_budgeted((), Budget::default().with_max_operations(100));
+        assert_eq!(result, Ok((42,)));
+
+        // aborted scope cleans up after itself, without host restoring it.
+        let registry = Registry::default()
+            .with_basic_types()
+            .with_type(NativeStructBuilder::new_uninitialized::<Tracked>().build());
+        let mut context = Context::new(10240, 10240);
+        context.stack().push(42u8);
+        context.set_budget(Some(Budget::default().with_max_operations(100)));
+        let script = ScriptBuilder::<InlineExpression>::default()
+            .define_register(TypeQuery::of::<Tracked>())
+            .expression(InlineExpression::closure(|context, _| {
+                context.stack().push(Tracked);
+                context.stack().push(Tracked);
+            }))
+            .pop_to_register(0)
+            .expression(InlineExpression::copied(true))
+            .loop_scope(
+                ScriptBuilder::default()
+                    .expression(InlineExpression::copied(true))
+                    .build(),
+            )
+            .build();
+        VmScope::new(script, VmScopeSymbol::new()).run(&mut context, &registry);
+        assert!(context.budget_exceeded().is_some());
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 4);
+        assert_eq!(context.registers().registers_count(), 0);
+        assert_eq!(context.stack().pop::<u8>(), Some(42));
+        assert_eq!(context.stack().position(), 0);
+    }
+
+    #[test]
+    fn test_operations_trace() {
+        let mut registry = Registry::default().with_basic_types();
+        registry.add_function(define_function! {
+            registry => fn add(a: i32, b: i32) -> (result: i32) {
+                (a + b,)
+            }
+        });
+        registry.add_function(Function::new(
+            FunctionSignature::new("explode"),
+            FunctionBody::closure(|_, _| panic!("Boom!")),
+        ));
+        let script_function = |name: &str, script| ScriptFunction {
+            signature: ScriptFunctionSignature {
+                meta: None,
+                name: name.to_owned(),
+                module_name: Some("test".to_owned()),
+                type_query: None,
+                visibility: Visibility::Public,
+                inputs: vec![],
+                outputs: vec![],
+            },
+            script,
+        };
+        for function in [
+            script_function(
+                "add_script",
+                ScriptBuilder::<()>::default()
+                    .define_register(TypeQuery::of::<i32>())
+                    .pop_to_register(0)
+                    .push_from_register(0)
+                    .call_function(FunctionQuery {
+                        name: Some("add".into()),
+                        ..Default::default()
+                    })
+                    .build(),
+            ),
+            script_function(
+                "crash",
+                ScriptBuilder::<()>::default()
+                    .call_function(FunctionQuery {
+                        name: Some("explode".into()),
+                        ..Default::default()
+                    })
+                    .build(),
+            ),
+        ] {
+            registry.add_function(
+                VmScope::<()>::generate_function(&function, &registry, None)
+                    .unwrap()
+                    .0,
+            );
+        }
+        let find = |name: &'static str| {
+            registry
+                .find_function(FunctionQuery {
+                    name: Some(name.into()),
+                    ..Default::default()
+                })
+                .unwrap()
+        };
+        let mut context = Context::new(10240, 10240);
+
+        find("add_script").call::<(i32,), _>(&mut context, &registry, (40, 2), false);
+        assert_eq!(context.recent_operations().count(), 0);
+
+        context.set_operations_trace(Some(3));
+        let (result,) =
+            find("add_script").call::<(i32,), _>(&mut context, &registry, (40, 2), false);
+        assert_eq!(result, 42);
+        assert_eq!(
+            context
+                .recent_operations()
+                .map(|trace| trace.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "test::add_script #1: PopToRegister",
+                "test::add_script #2: PushFromRegister",
+                "test::add_script #3: CallFunction",
+            ]
+        );
+
+        let crash = find("crash");
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            crash.call::<(), _>(&mut context, &registry, (), false);
+        }));
+        assert!(result.is_err());
+        let trace = context.recent_operations().last().unwrap();
+        assert_eq!(trace.label, "CallFunction");
+        assert_eq!(trace.function.as_ref().unwrap().name, "crash");
+        assert_eq!(context.recent_operations().count(), 3);
+
+        context.set_operations_trace(None);
+        assert!(!context.is_tracing_operations());
+        assert_eq!(context.recent_operations().count(), 0);
+    }
+
+    #[test]
+    fn test_span() {
+        let mut registry = Registry::default().with_basic_types();
+        registry.add_function(Function::new(
+            FunctionSignature::new("fail"),
+            FunctionBody::closure(|context, _| {
+                context.throw(ScriptError::new("fail"));
+            }),
+        ));
+        let span = |line, column| ScriptSpan::new(line, column).with_file("test.script");
+        let call = |name: &'static str| FunctionQuery {
+            name: Some(name.into()),
+            ..Default::default()
+        };
+        let mut context = Context::new(10240, 10240);
+        context.set_operations_trace(Some(2));
+
+        let script = ScriptBuilder::<()>::default()
+            .span(span(1, 1))
+            .define_register(TypeQuery::of::<i32>())
+            .span(span(2, 5))
+            .push_scope(
+                ScriptBuilder::<()>::default()
+                    .call_function(call("fail"))
+                    .pop_scope()
+                    .build(),
+            )
+            .build();
+        VmScope::new(script, VmScopeSymbol::new()).run(&mut context, &registry);
+        // child scope reports span it was entered from.
+        let error = context.take_error().unwrap();
+        assert_eq!(error.span, Some(span(2, 5)));
+        assert_eq!(error.to_string(), "fail at test.script:2:5");
+        assert_eq!(
+            context.recent_operations().last().unwrap().to_string(),
+            "<unknown> #0: CallFunction at test.script:2:5"
+        );
+
+        let script = ScriptBuilder::<()>::default()
+            .span(span(1, 1))
+            .call_function(call("fail"))
+            .build();
+        let error = ScriptError::new("custom").with_span(Some(span(7, 3)));
+        registry.add_function(Function::new(
+            FunctionSignature::new("rethrow"),
+            FunctionBody::closure(move |context, _| {
+                context.throw(error.clone());
+            }),
+        ));
+        VmScope::new(script, VmScopeSymbol::new()).run(&mut context, &registry);
+        assert_eq!(context.take_error().unwrap().span, Some(span(1, 1)));
+        let script = ScriptBuilder::<()>::default()
+            .span(span(1, 1))
+            .call_function(call("rethrow"))
+            .build();
+        VmScope::new(script, VmScopeSymbol::new()).run(&mut context, &registry);
+        // spans of errors raised deeper are kept.
+        assert_eq!(context.take_error().unwrap().span, Some(span(7, 3)));
+
+        let script = ScriptBuilder::<()>::default()
+            .call_function(call("fail"))
+            .span(span(3, 9))
+            .build();
+        VmScope::new(script, VmScopeSymbol::new()).run(&mut context, &registry);
+        assert_eq!(context.take_error().unwrap().span, None);
+
+        let script = ScriptBuilder::<()>::default()
+            .span(span(3, 9))
+            .call_function(call("missing"))
+            .build();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            VmScope::new(script, VmScopeSymbol::new()).run(&mut context, &registry);
+        }));
+        assert!(result
+            .unwrap_err()
+            .downcast::<String>()
+            .unwrap()
+            .starts_with("Could not call non-existent function at test.script:3:9"));
+    }
+
+    #[test]
+    fn test_reentry() {
+        let mut registry = Registry::default().with_basic_types();
+        registry.add_type(NativeStructBuilder::new::<Vec<i32>>().build());
+        registry.add_function(Function::new(
+            function_signature!(&registry => mod test fn map(values: Vec<i32>, function: String) -> (result: Vec<i32>)),
+            FunctionBody::closure(|context, registry| {
+                let values = context.stack().pop::<Vec<i32>>().unwrap();
+                let function = context.stack().pop::<String>().unwrap();
+                let mut reentry = Reentry::new(context, registry);
+                let mut result = Vec::with_capacity(values.len());
+                for value in values {
+                    let query = FunctionQuery {
+                        name: Some(function.as_str().into()),
+                        module_name: Some("test".into()),
+                        ..Default::default()
+                    };
+                    match reentry.invoke::<(i32,), _>(query, (value,)) {
+                        Ok((value,)) => result.push(value),
+                        Err(error) => {
+                            context.throw(ScriptError::new(error));
+                            return;
+                        }
+                    }
+                }
+                context.stack().push(result);
+            }),
+        ));
+        let square = ScriptBuilder::<InlineExpression>::default()
+            .expression(InlineExpression::closure(|context, _| {
+                let value = context.stack().pop::<i32>().unwrap();
+                context.stack().push(value * value);
+            }))
+            .build();
+        registry.add_function(Function::new(
+            function_signature!(&registry => mod test fn square(value: i32) -> (result: i32)),
+            VmScope::generate_function_body(square, None).unwrap().0,
+        ));
+        let odd_only = ScriptBuilder::<InlineExpression>::default()
+            .expression(InlineExpression::closure(|context, _| {
+                let value = context.stack().pop::<i32>().unwrap();
+                if value % 2 == 0 {
+                    context.throw(ScriptError::new(format!("{} is even", value)));
+                } else {
+                    context.stack().push(value);
+                }
+            }))
+            .build();
+        registry.add_function(Function::new(
+            function_signature!(&registry => mod test fn odd_only(value: i32) -> (result: i32)),
+            VmScope::generate_function_body(odd_only, None).unwrap().0,
+        ));
+        let mut host = Host::new(Context::new(10240, 10240), registry.into());
+        // values of caller below callback calls stay intact.
+        host.context().stack().push(42u8);
+
+        let (result,) = host
+            .call_function::<(Vec<i32>,), _>("map", "test", None)
+            .unwrap()
+            .run((vec![1, 2, 3, 4], "square".to_owned()));
+        assert_eq!(result, vec![1, 4, 9, 16]);
+
+        let (context, registry) = host.context_and_registry();
+        let error = Reentry::new(context, registry)
+            .invoke::<(Vec<i32>,), _>(
+                FunctionQuery {
+                    name: Some("map".into()),
+                    module_name: Some("test".into()),
+                    ..Default::default()
+                },
+                (vec![1, 3, 4, 5], "odd_only".to_owned()),
+            )
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Function `map` raised error: Function `odd_only` raised error: 4 is even"
+        );
+        assert!(!host.context().has_error());
+        assert_eq!(host.context().stack().pop::<u8>(), Some(42));
+        assert_eq!(host.context().stack().position(), 0);
+    }
+
+    #[test]
+    fn test_events() {
+        #[derive(Debug, Default, PartialEq)]
+        struct Hit {
+            damage: i32,
+        }
+
+        let mut registry = Registry::default().with_basic_types();
+        registry.add_type(NativeStructBuilder::new::<Hit>().build());
+        registry.add_function(Function::new(
+            function_signature!(&registry => mod test fn hit(damage: i32) -> (result: Hit)),
+            FunctionBody::closure(|context, _| {
+                let damage = context.stack().pop::<i32>().unwrap();
+                context.stack().push(Hit { damage });
+            }),
+        ));
+        registry.add_function(
+            EventBus::define_emit_function::<Hit>("emit_hit", "test", &registry).unwrap(),
+        );
+        let main = ScriptBuilder::<InlineExpression>::default()
+            .expression(InlineExpression::closure(|context, _| {
+                context.stack().push(42i32);
+            }))
+            .call_function(FunctionQuery {
+                name: Some("hit".into()),
+                module_name: Some("test".into()),
+                ..Default::default()
+            })
+            .call_function(FunctionQuery {
+                name: Some("emit_hit".into()),
+                module_name: Some("test".into()),
+                ..Default::default()
+            })
+            .build();
+        registry.add_function(Function::new(
+            function_signature!(&registry => mod test fn main() -> ()),
+            VmScope::generate_function_body(main, None).unwrap().0,
+        ));
+        let mut host = Host::new(Context::new(10240, 10240), registry.into());
+
+        // events persist across runs until host drains them.
+        for _ in 0..2 {
+            host.call_function::<(), _>("main", "test", None)
+                .unwrap()
+                .run(());
+        }
+        assert_eq!(host.context().stack().position(), 0);
+        assert_eq!(
+            host.context().events_mut().drain::<Hit>(),
+            vec![Hit { damage: 42 }, Hit { damage: 42 }]
+        );
+        assert!(host.context().events().is_empty());
+    }
+
+    #[test]
+    fn test_try_scope() {
+        let mut registry = Registry::default().with_basic_types().with_error_types();
+        registry.add_function(Function::new(
+            FunctionSignature::new("fail").with_output(FunctionParameter::new(
+                "result",
+                registry.find_type(TypeQuery::of::<i32>()).unwrap(),
+            )),
+            FunctionBody::closure(|context, _| {
+                context.throw(ScriptError::new("fail"));
+            }),
+        ));
+        registry.add_function(Function::new(
+            FunctionSignature::new("recover")
+                .with_input(FunctionParameter::new(
+                    "error",
+                    registry.find_type(TypeQuery::of::<ScriptError>()).unwrap(),
+                ))
+                .with_output(FunctionParameter::new(
+                    "result",
+                    registry.find_type(TypeQuery::of::<i32>()).unwrap(),
+                )),
+            FunctionBody::closure(|context, _| {
+                let error = context.stack().pop::<ScriptError>().unwrap();
+                assert_eq!(error.message, "fail");
+                context.stack().push(42i32);
+            }),
+        ));
+        let signature = |name: &str| ScriptFunctionSignature {
+            meta: None,
+            name: name.to_owned(),
+            module_name: None,
+            type_query: None,
+            visibility: Visibility::Public,
+            inputs: vec![],
+            outputs: vec![ScriptFunctionParameter {
+                meta: None,
+                name: "result".to_owned(),
+                type_query: TypeQuery::of::<i32>(),
+            }],
+        };
+        registry.add_function(
+            VmScope::<()>::generate_function(
+                &ScriptFunction {
+                    signature: signature("middle"),
+                    script: ScriptBuilder::<()>::default()
+                        .define_register(TypeQuery::of::<i32>())
+                        .push_scope(
+                            ScriptBuilder::<()>::default()
+                                .call_function(FunctionQuery {
+                                    name: Some("fail".into()),
+                                    ..Default::default()
+                                })
+                                .pop_scope()
+                                .build(),
+                        )
+                        .build(),
+                },
+                &registry,
+                None,
+            )
+            .unwrap()
+            .0,
+        );
+        registry.add_function(
+            VmScope::<()>::generate_function(
+                &ScriptFunction {
+                    signature: signature("outer"),
+                    script: ScriptBuilder::<()>::default()
+                        .try_scope(
+                            ScriptBuilder::<()>::default()
+                                .define_register(TypeQuery::of::<i32>())
+                                .call_function(FunctionQuery {
+                                    name: Some("middle".into()),
+                                    ..Default::default()
+                                })
+                                .build(),
+                            ScriptBuilder::<()>::default()
+                                .call_function(FunctionQuery {
+                                    name: Some("recover".into()),
+                                    ..Default::default()
+                                })
+                                .build(),
+                        )
+                        .build(),
+                },
+                &registry,
+                None,
+            )
+            .unwrap()
+            .0,
+        );
+        let mut context = Context::new(10240, 10240);
+        let (result,) = registry
+            .find_function(FunctionQuery {
+                name: Some("outer".into()),
+                ..Default::default()
+            })
+            .unwrap()
+            .call::<(i32,), _>(&mut context, &registry, (), true);
+        assert_eq!(result, 42);
+        assert!(!context.has_error());
+        assert_eq!(context.stack().position(), 0);
+        assert_eq!(context.registers().position(), 0);
+        assert!(context.registers_barriers().is_empty());
+    }
+
+    #[test]
+    fn test_tail_call() {
+        let mut registry = Registry::default().with_basic_types();
+        let usize_handle = registry.find_type(TypeQuery::of::<usize>()).unwrap();
+        let bool_handle = registry.find_type(TypeQuery::of::<bool>()).unwrap();
+        registry.add_function(Function::new(
+            FunctionSignature::new("check")
+                .with_input(FunctionParameter::new("n", usize_handle.clone()))
+                .with_output(FunctionParameter::new("zero", bool_handle))
+                .with_output(FunctionParameter::new("n", usize_handle.clone())),
+            FunctionBody::closure(|context, _| {
+                let n = context.stack().pop::<usize>().unwrap();
+                context.stack().push(n);
+                context.stack().push(n == 0);
+            }),
+        ));
+        registry.add_function(Function::new(
+            FunctionSignature::new("dec")
+                .with_input(FunctionParameter::new("n", usize_handle.clone()))
+                .with_output(FunctionParameter::new("result", usize_handle.clone())),
+            FunctionBody::closure(|context, _| {
+                let n = context.stack().pop::<usize>().unwrap();
+                context.stack().push(n - 1);
+            }),
+        ));
+        registry.add_function(Function::new(
+            FunctionSignature::new("inc")
+                .with_input(FunctionParameter::new("n", usize_handle.clone()))
+                .with_output(FunctionParameter::new("result", usize_handle)),
+            FunctionBody::closure(|context, _| {
+                let n = context.stack().pop::<usize>().unwrap();
+                context.stack().push(n + 1);
+            }),
+        ));
+        let call = |name: &'static str| FunctionQuery {
+            name: Some(name.into()),
+            ..Default::default()
+        };
+        let countdown = |name: &'static str, tail: bool| {
+            let mut failure = ScriptBuilder::<()>::default()
+                .call_function(call("dec"))
+                .call_function(call(name));
+            if !tail {
+                failure = failure.call_function(call("inc"));
+            }
+            ScriptFunction {
+                signature: ScriptFunctionSignature {
+                    meta: None,
+                    name: name.to_owned(),
+                    module_name: None,
+                    type_query: None,
+                    visibility: Visibility::Public,
+                    inputs: vec![ScriptFunctionParameter {
+                        meta: None,
+                        name: "n".to_owned(),
+                        type_query: TypeQuery::of::<usize>(),
+                    }],
+                    outputs: vec![ScriptFunctionParameter {
+                        meta: None,
+                        name: "result".to_owned(),
+                        type_query: TypeQuery::of::<usize>(),
+                    }],
+                },
+                script: ScriptBuilder::<()>::default()
+                    .define_register(TypeQuery::of::<usize>())
+                    .pop_to_register(0)
+                    .push_from_register(0)
+                    .call_function(call("check"))
+                    .branch_scope(
+                        ScriptBuilder::<()>::default().build(),
+                        Some(failure.build()),
+                    )
+                    .build(),
+            }
+        };
+        let function =
+            VmScope::<()>::generate_function(&countdown("countdown", true), &registry, None)
+                .unwrap()
+                .0;
+        registry.add_function(function);
+        let function =
+            VmScope::<()>::generate_function(&countdown("count", false), &registry, None)
+                .unwrap()
+                .0;
+        registry.add_function(function);
+        let mut context = Context::new(1024, 1024);
+        let (result,) = registry
+            .find_function(FunctionQuery {
+                name: Some("countdown".into()),
+                ..Default::default()
+            })
+            .unwrap()
+            .call::<(usize,), _>(&mut context, &registry, (100_000usize,), true);
+        assert_eq!(result, 0);
+        assert_eq!(context.stack().position(), 0);
+        assert_eq!(context.registers().position(), 0);
+        assert!(context.registers_barriers().is_empty());
+        let (result,) = registry
+            .find_function(FunctionQuery {
+                name: Some("count".into()),
+                ..Default::default()
+            })
+            .unwrap()
+            .call::<(usize,), _>(&mut context, &registry, (10usize,), true);
+        assert_eq!(result, 10);
+        assert_eq!(context.stack().position(), 0);
+        assert_eq!(context.registers().position(), 0);
+        assert!(context.registers_barriers().is_empty());
+        // restarts are observed like calls and count against budget.
+        let metrics = Arc::new(FunctionMetrics::default());
+        registry.set_metrics_sink(Some(metrics.clone()));
+        let countdown = registry
+            .find_function(FunctionQuery {
+                name: Some("countdown".into()),
+                ..Default::default()
+            })
+            .unwrap();
+        let (result,) = countdown.call::<(usize,), _>(&mut context, &registry, (10usize,), true);
+        assert_eq!(result, 0);
+        assert_eq!(metrics.stats(countdown.id()).unwrap().calls, 11);
+        context.set_budget(Some(Budget::default().with_max_operations(1000)));
+        context.stack().push(100_000usize);
+        countdown.invoke(&mut context, &registry);
+        assert_eq!(
+            context.budget_exceeded(),
+            Some(BudgetExceeded::Operations { limit: 1000 })
+        );
+        assert!(metrics.stats(countdown.id()).unwrap().calls < 1000);
+        assert_eq!(context.stack().position(), 0);
+        assert_eq!(context.registers().position(), 0);
+    }
+
+    #[test]
+    fn test_call_visibility() {
+        let mut registry = Registry::default().with_basic_types();
+        let usize_handle = registry.find_type(TypeQuery::of::<usize>()).unwrap();
+        registry.add_function(Function::new(
+            FunctionSignature::new("secret")
+                .with_module_name("a")
+                .with_visibility(Visibility::Private)
+                .with_output(FunctionParameter::new("result", usize_handle)),
+            FunctionBody::closure(|context, _| {
+                context.stack().push(42usize);
+            }),
+        ));
+        let caller = |name: &str, module_name: &str| ScriptFunction {
+            signature: ScriptFunctionSignature {
+                meta: None,
+                name: name.to_owned(),
+                module_name: Some(module_name.to_owned()),
+                type_query: None,
+                visibility: Visibility::Public,
+                inputs: vec![],
+                outputs: vec![ScriptFunctionParameter {
+                    meta: None,
+                    name: "result".to_owned(),
+                    type_query: TypeQuery::of::<usize>(),
+                }],
+            },
+            script: ScriptBuilder::<()>::default()
+                .call_function(FunctionQuery {
+                    name: Some("secret".into()),
+                    ..Default::default()
+                })
+                .build(),
+        };
+        for (name, module_name) in [("inside", "a"), ("outside", "b")] {
+            let function =
+                VmScope::<()>::generate_function(&caller(name, module_name), &registry, None)
+                    .unwrap()
+                    .0;
+            registry.add_function(function);
+        }
+        let call = |name: &str| {
+            let mut context = Context::new(1024, 1024);
+            registry
+                .find_function(FunctionQuery {
+                    name: Some(name.into()),
+                    ..Default::default()
+                })
+                .unwrap()
+                .call::<(usize,), _>(&mut context, &registry, (), true)
+                .0
+        };
+        assert_eq!(call("inside"), 42);
+        assert!(
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| call("outside"))).is_err()
+        );
+    }
+
+    #[test]
+    fn test_async_function() {
+        struct Tick(bool);
+
+        impl std::future::Future for Tick {
+            type Output = ();
+
+            fn poll(
+                mut self: std::pin::Pin<&mut Self>,
+                _: &mut std::task::Context<'_>,
+            ) -> std::task::Poll<Self::Output> {
+                if self.0 {
+                    std::task::Poll::Ready(())
+                } else {
+                    self.0 = true;
+                    std::task::Poll::Pending
+                }
+            }
+        }
+
+        let mut registry = Registry::default().with_basic_types();
+        let function = define_async_function! {
+            registry => fn delayed_add(a: i32, b: i32) -> (result: i32) {
+                Tick(false).await;
+                (a + b,)
+            }
+        };
+        registry.add_function(function);
+        registry.add_function(define_function! {
+            registry => fn take(future: FutureHandle<(i32,)>) -> (result: i32) {
+                future.take().unwrap()
+            }
+        });
+        let call = |name: &'static str| FunctionQuery {
+            name: Some(name.into()),
+            ..Default::default()
+        };
+        let function = VmScope::<()>::generate_function(
+            &ScriptFunction {
+                signature: ScriptFunctionSignature {
+                    meta: None,
+                    name: "add".to_owned(),
+                    module_name: None,
+                    type_query: None,
+                    visibility: Visibility::Public,
+                    inputs: vec![
+                        ScriptFunctionParameter {
+                            meta: None,
+                            name: "a".to_owned(),
+                            type_query: TypeQuery::of::<i32>(),
+                        },
+                        ScriptFunctionParameter {
+                            meta: None,
+                            name: "b".to_owned(),
+                            type_query: TypeQuery::of::<i32>(),
+                        },
+                    ],
+                    outputs: vec![ScriptFunctionParameter {
+                        meta: None,
+                        name: "result".to_owned(),
+                        type_query: TypeQuery::of::<i32>(),
+                    }],
+                },
+                // return take(await delayed_add(a, b));
+                script: ScriptBuilder::<()>::default()
+                    .call_function(call("delayed_add"))
+                    .await_future()
+                    .call_function(call("take"))
+                    .build(),
+            },
+            &registry,
+            None,
+        )
+        .unwrap()
+        .0;
+        registry.add_function(function);
+        let poller = FuturePoller::default();
+        let mut context = Context::new(10240, 10240);
+        context.set_custom(FuturePoller::CUSTOM, poller.clone());
+        let (result,) = registry
+            .find_function(FunctionQuery {
+                name: Some("add".into()),
+                ..Default::default()
+            })
+            .unwrap()
+            .call::<(i32,), _>(&mut context, &registry, (40, 2), true);
+        assert_eq!(result, 42);
+        assert_eq!(poller.pending(), 0);
+        assert_eq!(context.stack().position(), 0);
+        assert_eq!(context.registers().position(), 0);
+
+        // top level scope gives control back to host while future is pending.
+        let mut scope = VmScope::new(
+            ScriptBuilder::<()>::default()
+                .call_function(call("delayed_add"))
+                .await_future()
+                .call_function(call("take"))
+                .build(),
+            VmScopeSymbol::new(),
+        );
+        context.stack().push(2i32);
+        context.stack().push(40i32);
+        scope.run(&mut context, &registry);
+        assert!(scope.is_suspended());
+        assert!(!scope.has_completed());
+        assert_eq!(poller.pending(), 1);
+        scope.run(&mut context, &registry);
+        assert!(!scope.is_suspended());
+        assert!(scope.has_completed());
+        assert_eq!(context.stack().pop::<i32>(), Some(42));
+        assert_eq!(context.stack().position(), 0);
+    }
+
+    #[test]
+    fn test_call_cache() {
+        let mut registry = Registry::default().with_basic_types();
+        registry.add_function(Function::new(
+            function_signature!(&registry => mod test fn add(a: i32, b: i32) -> (result: i32)),
+            FunctionBody::closure(|context, _| {
+                let a = context.stack().pop::<i32>().unwrap();
+                let b = context.stack().pop::<i32>().unwrap();
+                context.stack().push(a + b);
+            }),
+        ));
+        let iterations = Arc::new(AtomicUsize::new(0));
+        let total = Arc::new(AtomicUsize::new(0));
+        let script = {
+            let iterations = iterations.clone();
+            let total = total.clone();
+            ScriptBuilder::<InlineExpression>::default()
+                .expression(InlineExpression::copied(true))
+                .loop_scope(
+                    ScriptBuilder::default()
+                        .expression(InlineExpression::copied(2i32))
+                        .expression(InlineExpression::copied(1i32))
+                        .call_function(FunctionQuery {
+                            name: Some("add".into()),
+                            ..Default::default()
+                        })
+                        .expression(InlineExpression::closure(move |context, _| {
+                            let result = context.stack().pop::<i32>().unwrap();
+                            total.fetch_add(result as usize, Ordering::SeqCst);
+                            let index = iterations.fetch_add(1, Ordering::SeqCst);
+                            context.stack().push(index + 1 < 1000);
+                        }))
+                        .build(),
+                )
+                .build()
+        };
+        let call_cache = VmCallCache::default().into_handle();
+        let mut context = Context::new(10240, 10240);
+        let mut run = |registry: &Registry| {
+            iterations.store(0, Ordering::SeqCst);
+            total.store(0, Ordering::SeqCst);
+            VmScope::new(script.clone(), VmScopeSymbol::new())
+                .with_call_cache(Some(call_cache.clone()))
+                .run(&mut context, registry);
+            assert_eq!(iterations.load(Ordering::SeqCst), 1000);
+            assert_eq!(context.stack().position(), 0);
+            total.load(Ordering::SeqCst)
+        };
+
+        assert_eq!(run(&registry), 3000);
+        assert_eq!(call_cache.read().unwrap().resolutions(), 1);
+        assert_eq!(run(&registry), 3000);
+        assert_eq!(call_cache.read().unwrap().resolutions(), 1);
+
+        // replaced body is called through already resolved handle.
+        registry
+            .replace_function(
+                FunctionQuery {
+                    name: Some("add".into()),
+                    ..Default::default()
+                },
+                Function::new(
+                    function_signature!(&registry => mod test fn add(a: i32, b: i32) -> (result: i32)),
+                    FunctionBody::closure(|context, _| {
+                        let a = context.stack().pop::<i32>().unwrap();
+                        let b = context.stack().pop::<i32>().unwrap();
+                        context.stack().push(a * b);
+                    }),
+                ),
+            )
+            .unwrap();
+        assert_eq!(run(&registry), 2000);
+        assert_eq!(call_cache.read().unwrap().resolutions(), 1);
+
+        // changing set of functions invalidates cached targets.
+        registry.add_function(Function::new(
+            function_signature!(&registry => mod test fn sub(a: i32, b: i32) -> (result: i32)),
+            FunctionBody::closure(|context, _| {
+                let a = context.stack().pop::<i32>().unwrap();
+                let b = context.stack().pop::<i32>().unwrap();
+                context.stack().push(a - b);
+            }),
+        ));
+        assert_eq!(run(&registry), 2000);
+        assert_eq!(call_cache.read().unwrap().resolutions(), 2);
+        assert_eq!(call_cache.read().unwrap().len(), 1);
+    }
 }
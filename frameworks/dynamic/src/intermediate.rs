@@ -0,0 +1,390 @@
+use crate::{Array, Boolean, Integer, Map, ObjectId, Real, Reference, Text};
+use intuicio_core::{
+    object::Object,
+    registry::Registry,
+    types::{Type as CoreType, TypeQuery},
+};
+use intuicio_data::type_hash::TypeHash;
+use intuicio_framework_serde::{Intermediate, SerializationRegistry};
+use std::{collections::HashMap, error::Error};
+
+const OBJECT_VARIANT: &str = "Object";
+const REFERENCE_VARIANT: &str = "Reference";
+
+impl Reference {
+    /// Converts whole graph of references into `Intermediate`:
+    /// - null becomes unit,
+    /// - booleans, integers, reals and texts become their scalar variants,
+    /// - arrays become sequences and maps become maps with string keys,
+    /// - other objects become `Object` struct variant with `type` name,
+    ///   optional `module` name and either `value` produced by serializer
+    ///   registered for their type, or `fields` if they are structs made of
+    ///   reference fields only.
+    ///
+    /// Every object gets index in order of first visit, and objects reached
+    /// again (shared or cyclic) become `Reference` new type variant holding
+    /// that index, so `from_intermediate` restores the same sharing.
+    pub fn to_intermediate(
+        &self,
+        serialization: &SerializationRegistry,
+    ) -> Result<Intermediate, Box<dyn Error>> {
+        self.intermediate_interned(serialization, &mut HashMap::default())
+    }
+
+    fn intermediate_interned(
+        &self,
+        serialization: &SerializationRegistry,
+        interned: &mut HashMap<ObjectId, usize>,
+    ) -> Result<Intermediate, Box<dyn Error>> {
+        if self.is_null() {
+            return Ok(Intermediate::Unit);
+        }
+        let id = self
+            .object_id()
+            .ok_or("Cannot convert object that is currently written")?;
+        if let Some(index) = interned.get(&id) {
+            return Ok(Intermediate::NewTypeVariant(
+                REFERENCE_VARIANT.to_owned(),
+                Box::new(Intermediate::U64(*index as u64)),
+            ));
+        }
+        interned.insert(id, interned.len());
+        if let Some(value) = self.read::<Boolean>() {
+            return Ok(Intermediate::Bool(*value));
+        }
+        if let Some(value) = self.read::<Integer>() {
+            return Ok(Intermediate::I64(*value));
+        }
+        if let Some(value) = self.read::<Real>() {
+            return Ok(Intermediate::F64(*value));
+        }
+        if let Some(value) = self.read::<Text>() {
+            return Ok(Intermediate::String(value.to_owned()));
+        }
+        if let Some(array) = self.read::<Array>() {
+            return Ok(Intermediate::Seq(
+                array
+                    .iter()
+                    .map(|item| item.intermediate_interned(serialization, interned))
+                    .collect::<Result<_, _>>()?,
+            ));
+        }
+        if let Some(map) = self.read::<Map>() {
+            return Ok(Intermediate::Map(
+                map.iter()
+                    .map(|(key, value)| {
+                        Ok((
+                            Intermediate::String(key.to_owned()),
+                            value.intermediate_interned(serialization, interned)?,
+                        ))
+                    })
+                    .collect::<Result<_, Box<dyn Error>>>()?,
+            ));
+        }
+        let object = self.read_object().unwrap();
+        let handle = object.type_handle();
+        let content = if let Ok(value) =
+            unsafe { serialization.dynamic_serialize_from(handle.type_hash(), object.as_ptr()) }
+        {
+            ("value".to_owned(), value)
+        } else if let Some(fields) = reference_fields(handle) {
+            let fields = fields
+                .iter()
+                .map(|name| {
+                    let value = object.read_field::<Reference>(name).unwrap();
+                    Ok((
+                        name.to_owned(),
+                        value.intermediate_interned(serialization, interned)?,
+                    ))
+                })
+                .collect::<Result<_, Box<dyn Error>>>()?;
+            ("fields".to_owned(), Intermediate::Struct(fields))
+        } else {
+            return Err(format!(
+                "Type `{}` has neither serializer nor only reference fields",
+                handle.name()
+            )
+            .into());
+        };
+        Ok(Intermediate::StructVariant(
+            OBJECT_VARIANT.to_owned(),
+            vec![
+                (
+                    "type".to_owned(),
+                    Intermediate::String(handle.name().to_owned()),
+                ),
+                (
+                    "module".to_owned(),
+                    Intermediate::Option(
+                        handle
+                            .module_name()
+                            .map(|name| Box::new(Intermediate::String(name.to_owned()))),
+                    ),
+                ),
+                content,
+            ],
+        ))
+    }
+
+    /// Reconstructs graph of references produced by `to_intermediate`.
+    /// Objects are found in `registry` by their type and module names.
+    pub fn from_intermediate(
+        value: &Intermediate,
+        serialization: &SerializationRegistry,
+        registry: &Registry,
+    ) -> Result<Self, Box<dyn Error>> {
+        Self::from_intermediate_interned(value, serialization, registry, &mut vec![])
+    }
+
+    fn from_intermediate_interned(
+        value: &Intermediate,
+        serialization: &SerializationRegistry,
+        registry: &Registry,
+        interned: &mut Vec<Reference>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let result = match value {
+            Intermediate::Unit | Intermediate::Option(None) => return Ok(Self::null()),
+            Intermediate::NewTypeVariant(name, index) if name == REFERENCE_VARIANT => {
+                let index = match &**index {
+                    Intermediate::U64(index) => *index as usize,
+                    _ => return Err("Expected index of interned reference".into()),
+                };
+                return interned
+                    .get(index)
+                    .cloned()
+                    .ok_or_else(|| format!("Interned reference #{} not found", index).into());
+            }
+            Intermediate::Bool(value) => Self::new_boolean(*value, registry),
+            Intermediate::I8(value) => Self::new_integer(*value as _, registry),
+            Intermediate::I16(value) => Self::new_integer(*value as _, registry),
+            Intermediate::I32(value) => Self::new_integer(*value as _, registry),
+            Intermediate::I64(value) => Self::new_integer(*value, registry),
+            Intermediate::U8(value) => Self::new_integer(*value as _, registry),
+            Intermediate::U16(value) => Self::new_integer(*value as _, registry),
+            Intermediate::U32(value) => Self::new_integer(*value as _, registry),
+            Intermediate::U64(value) => Self::new_integer(*value as _, registry),
+            Intermediate::F32(value) => Self::new_real(*value as _, registry),
+            Intermediate::F64(value) => Self::new_real(*value, registry),
+            Intermediate::Char(value) => Self::new_text(value.to_string(), registry),
+            Intermediate::String(value) => Self::new_text(value.to_owned(), registry),
+            Intermediate::Seq(items) => {
+                // interned before items, so cyclic items can point to it.
+                let mut result = Self::new_array(Array::with_capacity(items.len()), registry);
+                interned.push(result.clone());
+                for item in items {
+                    let item =
+                        Self::from_intermediate_interned(item, serialization, registry, interned)?;
+                    result.write::<Array>().unwrap().push(item);
+                }
+                return Ok(result);
+            }
+            Intermediate::Map(pairs) => {
+                let mut result = Self::new_map(Map::with_capacity(pairs.len()), registry);
+                interned.push(result.clone());
+                for (key, value) in pairs {
+                    let Intermediate::String(key) = key else {
+                        return Err("Expected text map key".into());
+                    };
+                    let value =
+                        Self::from_intermediate_interned(value, serialization, registry, interned)?;
+                    result.write::<Map>().unwrap().insert(key.to_owned(), value);
+                }
+                return Ok(result);
+            }
+            Intermediate::StructVariant(name, fields) if name == OBJECT_VARIANT => {
+                return Self::object_from_intermediate(fields, serialization, registry, interned);
+            }
+            _ => return Err(format!("Unsupported intermediate value: {:?}", value).into()),
+        };
+        interned.push(result.clone());
+        Ok(result)
+    }
+
+    fn object_from_intermediate(
+        fields: &[(String, Intermediate)],
+        serialization: &SerializationRegistry,
+        registry: &Registry,
+        interned: &mut Vec<Reference>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let field = |name: &str| {
+            fields
+                .iter()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value)
+        };
+        let Some(Intermediate::String(type_name)) = field("type") else {
+            return Err("Expected object type name".into());
+        };
+        let module_name = match field("module") {
+            Some(Intermediate::Option(Some(name))) => match &**name {
+                Intermediate::String(name) => Some(name.as_str()),
+                _ => return Err("Expected object module name".into()),
+            },
+            _ => None,
+        };
+        let handle = registry
+            .find_type(TypeQuery {
+                name: Some(type_name.into()),
+                module_name: module_name.map(|name| name.into()),
+                ..Default::default()
+            })
+            .ok_or_else(|| format!("Type `{}` not found in registry", type_name))?;
+        let object = Object::try_new(handle.clone())
+            .ok_or_else(|| format!("Objects of type `{}` cannot be initialized", type_name))?;
+        let mut result = Self::new_raw(object);
+        interned.push(result.clone());
+        if let Some(value) = field("value") {
+            let mut object = result.write_object().unwrap();
+            unsafe {
+                serialization.dynamic_deserialize_to(
+                    handle.type_hash(),
+                    object.as_mut_ptr(),
+                    value,
+                )?;
+            }
+        } else if let Some(Intermediate::Struct(values)) = field("fields") {
+            for name in reference_fields(&handle)
+                .ok_or_else(|| format!("Type `{}` has not only reference fields", type_name))?
+            {
+                let Some((_, value)) = values.iter().find(|(field, _)| *field == name) else {
+                    continue;
+                };
+                let value =
+                    Self::from_intermediate_interned(value, serialization, registry, interned)?;
+                *result
+                    .write_object()
+                    .unwrap()
+                    .write_field::<Reference>(&name)
+                    .unwrap() = value;
+            }
+        } else {
+            return Err(format!("Expected content of object of type `{}`", type_name).into());
+        }
+        Ok(result)
+    }
+}
+
+/// Names of fields of struct made only of reference fields.
+fn reference_fields(handle: &CoreType) -> Option<Vec<String>> {
+    let CoreType::Struct(type_) = handle else {
+        return None;
+    };
+    type_
+        .fields()
+        .iter()
+        .map(|field| {
+            if field.type_handle().type_hash() == TypeHash::of::<Reference>() {
+                Some(field.name.to_owned())
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Array, Integer, Map, Real, Reference, Text};
+    use intuicio_core::prelude::*;
+    use intuicio_derive::*;
+    use intuicio_framework_serde::{Intermediate, SerializationRegistry};
+
+    #[derive(IntuicioStruct, Debug, Default, Clone, PartialEq)]
+    #[intuicio(name = "Vec2", module_name = "test")]
+    struct Vec2 {
+        x: f32,
+        y: f32,
+    }
+
+    #[derive(IntuicioStruct, Default)]
+    #[intuicio(name = "Person", module_name = "test")]
+    struct Person {
+        pub name: Reference,
+        pub position: Reference,
+        pub friends: Reference,
+    }
+
+    #[test]
+    fn test_intermediate_round_trip() {
+        let mut registry = Registry::default().with_basic_types();
+        crate::install(&mut registry);
+        registry.add_type(Vec2::define_struct(&registry));
+        registry.add_type(Person::define_struct(&registry));
+        let mut serialization = SerializationRegistry::default().with_basic_types();
+        serialization
+            .register_reflected(&registry.find_type(TypeQuery::of::<Vec2>()).unwrap())
+            .unwrap();
+
+        let shared = Reference::new_text(Text::from("shared"), &registry);
+        let friends = Reference::new_array(Array::default(), &registry);
+        let mut person = Reference::new(
+            Person {
+                name: Reference::new_text(Text::from("Grumpy"), &registry),
+                position: Reference::new(Vec2 { x: 1.0, y: 2.0 }, &registry),
+                friends: friends.clone(),
+            },
+            &registry,
+        );
+        let root = Reference::new_map(
+            Map::from([
+                ("person".to_owned(), person.clone()),
+                (
+                    "items".to_owned(),
+                    Reference::new_array(
+                        Array::from([
+                            Reference::new_integer(42, &registry),
+                            Reference::new_real(4.2, &registry),
+                            Reference::null(),
+                            shared.clone(),
+                            shared.clone(),
+                        ]),
+                        &registry,
+                    ),
+                ),
+            ]),
+            &registry,
+        );
+        // person is its own friend.
+        person.write::<Person>().unwrap().friends =
+            Reference::new_array(Array::from([person.clone(), friends]), &registry);
+
+        let serialized = root.to_intermediate(&serialization).unwrap();
+        let restored =
+            Reference::from_intermediate(&serialized, &serialization, &registry).unwrap();
+        let Intermediate::Map(pairs) = &serialized else {
+            panic!("`root` is not serialized as map!");
+        };
+        assert_eq!(pairs.len(), 2);
+
+        let items = restored.get_path("items");
+        assert_eq!(*items.get_path("[0]").read::<Integer>().unwrap(), 42);
+        assert_eq!(*items.get_path("[1]").read::<Real>().unwrap(), 4.2);
+        assert!(items.get_path("[2]").is_null());
+        assert!(items
+            .get_path("[3]")
+            .does_share_reference(&items.get_path("[4]"), false));
+        let person = restored.get_path("person");
+        assert_eq!(
+            person.get_path("name").read::<Text>().unwrap().as_str(),
+            "Grumpy"
+        );
+        assert_eq!(
+            *person.get_path("position").read::<Vec2>().unwrap(),
+            Vec2 { x: 1.0, y: 2.0 }
+        );
+        assert!(person
+            .get_path("friends[0]")
+            .does_share_reference(&person, false));
+
+        assert!(matches!(
+            Reference::new(Person::default(), &registry)
+                .to_intermediate(&SerializationRegistry::default()),
+            Ok(Intermediate::StructVariant(..))
+        ));
+        assert!(Reference::new(Vec2::default(), &registry)
+            .to_intermediate(&SerializationRegistry::default())
+            .is_err());
+        assert!(Reference::break_cycles(&[person, restored]) > 0);
+        assert!(Reference::break_cycles(&[root]) > 0);
+    }
+}
@@ -1,3 +1,6 @@
+#[cfg(feature = "serde")]
+mod intermediate;
+
 use intuicio_core::{
     define_native_struct,
     function::{FunctionHandle, FunctionQuery},
@@ -5,10 +8,15 @@ use intuicio_core::{
     registry::Registry,
     types::{struct_type::NativeStructBuilder, TypeHandle, TypeQuery},
 };
-use intuicio_data::{shared::Shared, type_hash::TypeHash};
+use intuicio_data::{
+    shared::{Shared, SharedBorrowError},
+    type_hash::TypeHash,
+};
 use std::{
+    alloc::Layout,
     cell::{Ref, RefMut},
-    collections::HashMap,
+    collections::{HashMap, HashSet, VecDeque},
+    hash::{Hash, Hasher},
 };
 
 pub type Boolean = bool;
@@ -17,6 +25,8 @@ pub type Real = f64;
 pub type Text = String;
 pub type Array = Vec<Reference>;
 pub type Map = HashMap<Text, Reference>;
+/// Address of referenced object, unique only as long as that object is alive.
+pub type ObjectId = usize;
 
 thread_local! {
     static TRANSFERRED_STRUCT_HANDLE: TypeHandle = NativeStructBuilder::new::<Transferred>().build().into_type().into_handle();
@@ -71,6 +81,22 @@ impl Type {
     }
 }
 
+/// Types are compared by their type hash. Unlike `is_same_as`, null types are
+/// equal to each other, so they can be used as map keys too.
+impl PartialEq for Type {
+    fn eq(&self, other: &Self) -> bool {
+        self.type_hash() == other.type_hash()
+    }
+}
+
+impl Eq for Type {}
+
+impl Hash for Type {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.type_hash().hash(state);
+    }
+}
+
 #[derive(Default, Clone)]
 pub struct Function {
     data: Option<FunctionHandle>,
@@ -102,6 +128,92 @@ impl Function {
     }
 }
 
+/// Functions are compared by their signatures. Unlike `is_same_as`, null
+/// functions are equal to each other, so they can be used as map keys too.
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        match (self.data.as_ref(), other.data.as_ref()) {
+            (Some(this), Some(other)) => this.signature() == other.signature(),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Function {}
+
+impl Hash for Function {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // hashes only part of signature, which is enough to stay consistent
+        // with equality of whole signatures. Replaceable function body is not
+        // part of identity, so function stays valid key after replacement.
+        if let Some(data) = self.data.as_ref() {
+            let signature = data.signature();
+            signature.name.hash(state);
+            signature.module_name.hash(state);
+            signature
+                .type_handle
+                .as_ref()
+                .map(|handle| handle.type_hash())
+                .hash(state);
+        } else {
+            0u8.hash(state);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypeMismatch {
+    NullType,
+    TypeHash {
+        expected: TypeHash,
+        provided: TypeHash,
+    },
+    Layout {
+        expected: Layout,
+        provided: Layout,
+    },
+}
+
+impl std::fmt::Display for TypeMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NullType => write!(f, "Type is null"),
+            Self::TypeHash { expected, provided } => write!(
+                f,
+                "Type hash mismatch - expected: {}, provided: {}",
+                expected, provided
+            ),
+            Self::Layout { expected, provided } => write!(
+                f,
+                "Type layout mismatch - expected: {:?}, provided: {:?}",
+                expected, provided
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TypeMismatch {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceAccessError {
+    Null,
+    Borrow(SharedBorrowError),
+    TypeMismatch(TypeMismatch),
+}
+
+impl std::fmt::Display for ReferenceAccessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Null => write!(f, "Reference is null"),
+            Self::Borrow(error) => write!(f, "Reference borrow conflict: {}", error),
+            Self::TypeMismatch(error) => write!(f, "Reference type mismatch: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ReferenceAccessError {}
+
 #[derive(Default, Clone)]
 pub struct Reference {
     data: Option<Shared<Object>>,
@@ -175,6 +287,8 @@ impl Reference {
         Self::new_raw(value)
     }
 
+    /// Unchecked fast path - caller has to ensure `ty` describes `T`.
+    /// Use [`Reference::try_new_custom`] when that is not guaranteed.
     pub fn new_custom<T: 'static>(data: T, ty: &Type) -> Self {
         let mut value =
             unsafe { Object::new_uninitialized(ty.data.as_ref().unwrap().clone()).unwrap() };
@@ -182,6 +296,24 @@ impl Reference {
         Self::new_raw(value)
     }
 
+    pub fn try_new_custom<T: 'static>(data: T, ty: &Type) -> Result<Self, TypeMismatch> {
+        let handle = ty.data.as_ref().ok_or(TypeMismatch::NullType)?;
+        if handle.type_hash() != TypeHash::of::<T>() {
+            return Err(TypeMismatch::TypeHash {
+                expected: TypeHash::of::<T>(),
+                provided: handle.type_hash(),
+            });
+        }
+        let layout = Layout::new::<T>();
+        if *handle.layout() != layout {
+            return Err(TypeMismatch::Layout {
+                expected: layout,
+                provided: *handle.layout(),
+            });
+        }
+        Ok(Self::new_custom(data, ty))
+    }
+
     pub fn new_raw(data: Object) -> Self {
         Self {
             data: Some(Shared::new(data)),
@@ -205,6 +337,7 @@ impl Reference {
         Some(Type::new(self.data.as_ref()?.read()?.type_handle().clone()))
     }
 
+    #[track_caller]
     pub fn read<T: 'static>(&self) -> Option<Ref<T>> {
         let result = self.data.as_ref()?.read()?;
         if result.type_handle().type_hash() == TypeHash::of::<T>() {
@@ -214,6 +347,7 @@ impl Reference {
         }
     }
 
+    #[track_caller]
     pub fn write<T: 'static>(&mut self) -> Option<RefMut<T>> {
         let result = self.data.as_mut()?.write()?;
         if result.type_handle().type_hash() == TypeHash::of::<T>() {
@@ -223,6 +357,42 @@ impl Reference {
         }
     }
 
+    #[track_caller]
+    pub fn read_or_err<T: 'static>(&self) -> Result<Ref<'_, T>, ReferenceAccessError> {
+        let result = self
+            .data
+            .as_ref()
+            .ok_or(ReferenceAccessError::Null)?
+            .try_read()
+            .map_err(ReferenceAccessError::Borrow)?;
+        if result.type_handle().type_hash() == TypeHash::of::<T>() {
+            Ok(Ref::map(result, |data| data.read::<T>().unwrap()))
+        } else {
+            Err(ReferenceAccessError::TypeMismatch(TypeMismatch::TypeHash {
+                expected: TypeHash::of::<T>(),
+                provided: result.type_handle().type_hash(),
+            }))
+        }
+    }
+
+    #[track_caller]
+    pub fn write_or_err<T: 'static>(&mut self) -> Result<RefMut<'_, T>, ReferenceAccessError> {
+        let result = self
+            .data
+            .as_mut()
+            .ok_or(ReferenceAccessError::Null)?
+            .try_write()
+            .map_err(ReferenceAccessError::Borrow)?;
+        if result.type_handle().type_hash() == TypeHash::of::<T>() {
+            Ok(RefMut::map(result, |data| data.write::<T>().unwrap()))
+        } else {
+            Err(ReferenceAccessError::TypeMismatch(TypeMismatch::TypeHash {
+                expected: TypeHash::of::<T>(),
+                provided: result.type_handle().type_hash(),
+            }))
+        }
+    }
+
     pub fn read_object(&self) -> Option<Ref<Object>> {
         self.data.as_ref()?.read()
     }
@@ -281,6 +451,308 @@ impl Reference {
             .write(Transferred(data.as_ptr() as usize));
         Some(Ok(std::mem::replace(&mut *data, object)))
     }
+
+    /// Returns `None` for null references and objects being currently written.
+    pub fn object_id(&self) -> Option<ObjectId> {
+        Some(unsafe { self.data.as_ref()?.read()?.as_ptr() as usize })
+    }
+
+    /// Finds groups of objects reachable from roots that keep each other alive.
+    /// This is a leak diagnostic only - objects being currently written are
+    /// treated as leaves, since their content cannot be inspected.
+    pub fn detect_cycles(roots: &[Reference]) -> Vec<Vec<ObjectId>> {
+        #[derive(Default)]
+        struct Search {
+            counter: usize,
+            indices: HashMap<ObjectId, usize>,
+            lowlinks: HashMap<ObjectId, usize>,
+            stack: Vec<ObjectId>,
+            on_stack: HashSet<ObjectId>,
+            result: Vec<Vec<ObjectId>>,
+        }
+
+        impl Search {
+            fn visit(&mut self, id: ObjectId, reference: &Reference) {
+                self.indices.insert(id, self.counter);
+                self.lowlinks.insert(id, self.counter);
+                self.counter += 1;
+                self.stack.push(id);
+                self.on_stack.insert(id);
+                let mut self_reference = false;
+                for (_, child) in reference.slots() {
+                    let Some(child_id) = child.object_id() else {
+                        continue;
+                    };
+                    self_reference |= child_id == id;
+                    let lowlink = if !self.indices.contains_key(&child_id) {
+                        self.visit(child_id, &child);
+                        self.lowlinks[&child_id]
+                    } else if self.on_stack.contains(&child_id) {
+                        self.indices[&child_id]
+                    } else {
+                        continue;
+                    };
+                    let current = self.lowlinks.get_mut(&id).unwrap();
+                    *current = (*current).min(lowlink);
+                }
+                if self.lowlinks[&id] == self.indices[&id] {
+                    let mut component = vec![];
+                    while let Some(item) = self.stack.pop() {
+                        self.on_stack.remove(&item);
+                        component.push(item);
+                        if item == id {
+                            break;
+                        }
+                    }
+                    if component.len() > 1 || self_reference {
+                        component.reverse();
+                        self.result.push(component);
+                    }
+                }
+            }
+        }
+
+        let mut search = Search::default();
+        for root in roots {
+            if let Some(id) = root.object_id() {
+                if !search.indices.contains_key(&id) {
+                    search.visit(id, root);
+                }
+            }
+        }
+        search.result
+    }
+
+    /// Nulls out references pointing back to objects on the path from roots,
+    /// which makes every cycle reachable from roots releasable. Returns number
+    /// of broken references.
+    pub fn break_cycles(roots: &[Reference]) -> usize {
+        fn visit(
+            id: ObjectId,
+            reference: &Reference,
+            path: &mut HashSet<ObjectId>,
+            visited: &mut HashSet<ObjectId>,
+            back_edges: &mut Vec<(Reference, DroppedSlot)>,
+        ) {
+            path.insert(id);
+            visited.insert(id);
+            for (slot, child) in reference.slots() {
+                let Some(child_id) = child.object_id() else {
+                    continue;
+                };
+                if path.contains(&child_id) {
+                    back_edges.push((reference.clone(), slot));
+                } else if !visited.contains(&child_id) {
+                    visit(child_id, &child, path, visited, back_edges);
+                }
+            }
+            path.remove(&id);
+        }
+
+        let mut path = HashSet::new();
+        let mut visited = HashSet::new();
+        let mut back_edges = vec![];
+        for root in roots {
+            if let Some(id) = root.object_id() {
+                if !visited.contains(&id) {
+                    visit(id, root, &mut path, &mut visited, &mut back_edges);
+                }
+            }
+        }
+        back_edges
+            .into_iter()
+            .filter(|(reference, slot)| reference.clone().set_slot(slot, Reference::null()))
+            .count()
+    }
+
+    /// Lists every object reachable from this reference through arrays, maps
+    /// and struct fields, each listed once even if it is part of a cycle, in
+    /// breadth-first order starting at this object. This is a leak diagnostic
+    /// only - objects being currently written are not listed.
+    pub fn ownership_report(&self) -> OwnershipReport {
+        let mut result = OwnershipReport::default();
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        if let Some(id) = self.object_id() {
+            visited.insert(id);
+            queue.push_back((id, self.clone()));
+        }
+        while let Some((id, reference)) = queue.pop_front() {
+            // queue holds exactly one extra reference to every listed object.
+            let references_count = reference.references_count() - 1;
+            result.total_references += references_count;
+            result.objects.push(OwnershipEntry {
+                id,
+                type_name: reference
+                    .type_of()
+                    .and_then(|type_| Some(type_.handle()?.name().to_owned())),
+                references_count,
+            });
+            for (_, child) in reference.slots() {
+                if let Some(child_id) = child.object_id() {
+                    if visited.insert(child_id) {
+                        queue.push_back((child_id, child));
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Reads value at path made of `.` separated field names or map keys and
+    /// `[index]` array indices, for example `person.skills[0].power`.
+    /// Returns null if any segment is missing, out of range indices included.
+    pub fn get_path(&self, path: &str) -> Reference {
+        let Some(segments) = path_segments(path) else {
+            return Reference::null();
+        };
+        let mut current = self.clone();
+        for segment in segments {
+            current = match current.path_slot(segment) {
+                Some(slot) => current.get_slot(&slot),
+                None => return Reference::null(),
+            };
+        }
+        current
+    }
+
+    /// Writes value at path, see `get_path`. Missing map keys are added, but
+    /// arrays are not grown. Returns false if path does not lead to a slot.
+    pub fn set_path(&mut self, path: &str, value: Reference) -> bool {
+        let Some(mut segments) = path_segments(path) else {
+            return false;
+        };
+        let Some(last) = segments.pop() else {
+            return false;
+        };
+        let mut parent = self.clone();
+        for segment in segments {
+            parent = match parent.path_slot(segment) {
+                Some(slot) => parent.get_slot(&slot),
+                None => return false,
+            };
+        }
+        if let Some(mut map) = parent.write::<Map>() {
+            map.insert(last.to_owned(), value);
+            return true;
+        }
+        match parent.path_slot(last) {
+            Some(slot) => parent.set_slot(&slot, value),
+            None => false,
+        }
+    }
+
+    fn path_slot(&self, segment: &str) -> Option<DroppedSlot> {
+        let object = self.read_object()?;
+        if object.read::<Array>().is_some() {
+            segment.parse().ok().map(DroppedSlot::Index)
+        } else if object.read::<Map>().is_some() {
+            Some(DroppedSlot::Key(segment.to_owned()))
+        } else {
+            Some(DroppedSlot::Field(segment.to_owned()))
+        }
+    }
+
+    fn get_slot(&self, slot: &DroppedSlot) -> Reference {
+        let Some(object) = self.read_object() else {
+            return Reference::null();
+        };
+        let value = match slot {
+            DroppedSlot::Index(index) => object.read::<Array>().and_then(|array| array.get(*index)),
+            DroppedSlot::Key(key) => object.read::<Map>().and_then(|map| map.get(key)),
+            DroppedSlot::Field(name) => object.read_field::<Reference>(name),
+        };
+        value.cloned().unwrap_or_default()
+    }
+
+    fn slots(&self) -> Vec<(DroppedSlot, Reference)> {
+        let Some(object) = self.read_object() else {
+            return vec![];
+        };
+        if let Some(array) = object.read::<Array>() {
+            array
+                .iter()
+                .enumerate()
+                .map(|(index, value)| (DroppedSlot::Index(index), value.clone()))
+                .collect()
+        } else if let Some(map) = object.read::<Map>() {
+            map.iter()
+                .map(|(key, value)| (DroppedSlot::Key(key.to_owned()), value.clone()))
+                .collect()
+        } else {
+            reference_field_names(&object)
+                .into_iter()
+                .filter_map(|name| {
+                    let value = object.read_field::<Reference>(&name)?.clone();
+                    Some((DroppedSlot::Field(name), value))
+                })
+                .collect()
+        }
+    }
+
+    fn set_slot(&mut self, slot: &DroppedSlot, value: Reference) -> bool {
+        let Some(mut object) = self.write_object() else {
+            return false;
+        };
+        let target = match slot {
+            DroppedSlot::Index(index) => object
+                .write::<Array>()
+                .and_then(|array| array.get_mut(*index)),
+            DroppedSlot::Key(key) => object.write::<Map>().and_then(|map| map.get_mut(key)),
+            DroppedSlot::Field(name) => object.write_field::<Reference>(name),
+        };
+        match target {
+            Some(target) => {
+                *target = value;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+fn path_segments(path: &str) -> Option<Vec<&str>> {
+    let mut result = vec![];
+    if path.is_empty() {
+        return Some(result);
+    }
+    for part in path.split('.') {
+        let (name, mut indices) = part.split_at(part.find('[').unwrap_or(part.len()));
+        if !name.is_empty() {
+            result.push(name);
+        } else if indices.is_empty() {
+            return None;
+        }
+        while !indices.is_empty() {
+            let (index, rest) = indices.strip_prefix('[')?.split_once(']')?;
+            result.push(index);
+            indices = rest;
+        }
+    }
+    Some(result)
+}
+
+fn reference_field_names(object: &Object) -> Vec<String> {
+    match &**object.type_handle() {
+        intuicio_core::types::Type::Struct(type_) => type_
+            .fields()
+            .iter()
+            .map(|field| field.name.to_owned())
+            .collect(),
+        intuicio_core::types::Type::Enum(type_) => {
+            let discriminant = unsafe { object.as_ptr().read() };
+            type_
+                .find_variant_by_discriminant(discriminant)
+                .map(|variant| {
+                    variant
+                        .fields
+                        .iter()
+                        .map(|field| field.name.to_owned())
+                        .collect()
+                })
+                .unwrap_or_default()
+        }
+    }
 }
 
 impl std::fmt::Debug for Reference {
@@ -385,13 +857,26 @@ impl Transferable {
     fn produce(
         value: Reference,
         objects: &mut HashMap<usize, TransferableObject>,
+        report: &mut Option<TransferReport>,
     ) -> Option<usize> {
         let mut object = match unsafe { value.transfer() } {
             Some(object) => match object {
                 Ok(object) => object,
                 Err(address) => return Some(address),
             },
-            None => return None,
+            None => {
+                if let Some(report) = report.as_mut() {
+                    if !value.is_null() {
+                        report.dropped.push(DroppedInfo {
+                            path: report.path.clone(),
+                            type_name: value
+                                .type_of()
+                                .and_then(|type_| Some(type_.handle()?.name().to_owned())),
+                        });
+                    }
+                }
+                return None;
+            }
         };
         let address = unsafe { object.as_ptr() as usize };
         if objects.iter().any(|object| *object.0 == address) {
@@ -400,7 +885,15 @@ impl Transferable {
         if let Some(array) = object.write::<Array>() {
             let items = array
                 .iter_mut()
-                .map(|value| Self::produce(std::mem::replace(value, Reference::null()), objects))
+                .enumerate()
+                .map(|(index, value)| {
+                    Self::produce_slot(
+                        DroppedSlot::Index(index),
+                        std::mem::replace(value, Reference::null()),
+                        objects,
+                        report,
+                    )
+                })
                 .collect();
             objects.insert(address, TransferableObject::Array { object, items });
         } else if let Some(map) = object.write::<Map>() {
@@ -409,7 +902,12 @@ impl Transferable {
                 .map(|(key, value)| {
                     (
                         key.to_owned(),
-                        Self::produce(std::mem::replace(value, Reference::null()), objects),
+                        Self::produce_slot(
+                            DroppedSlot::Key(key.to_owned()),
+                            std::mem::replace(value, Reference::null()),
+                            objects,
+                            report,
+                        ),
                     )
                 })
                 .collect();
@@ -424,7 +922,12 @@ impl Transferable {
                             let value = object.write_field::<Reference>(&field.name)?;
                             Some((
                                 field.name.to_owned(),
-                                Self::produce(std::mem::replace(value, Reference::null()), objects),
+                                Self::produce_slot(
+                                    DroppedSlot::Field(field.name.to_owned()),
+                                    std::mem::replace(value, Reference::null()),
+                                    objects,
+                                    report,
+                                ),
                             ))
                         })
                         .collect();
@@ -440,9 +943,11 @@ impl Transferable {
                                 let value = object.write_field::<Reference>(&field.name)?;
                                 Some((
                                     field.name.to_owned(),
-                                    Self::produce(
+                                    Self::produce_slot(
+                                        DroppedSlot::Field(field.name.to_owned()),
                                         std::mem::replace(value, Reference::null()),
                                         objects,
+                                        report,
                                     ),
                                 ))
                             })
@@ -455,6 +960,32 @@ impl Transferable {
         Some(address)
     }
 
+    fn produce_slot(
+        slot: DroppedSlot,
+        value: Reference,
+        objects: &mut HashMap<usize, TransferableObject>,
+        report: &mut Option<TransferReport>,
+    ) -> Option<usize> {
+        if let Some(report) = report.as_mut() {
+            report.path.push(slot);
+        }
+        let result = Self::produce(value, objects, report);
+        if let Some(report) = report.as_mut() {
+            report.path.pop();
+        }
+        result
+    }
+
+    /// Produces transferable graph, replacing objects that cannot be transferred
+    /// with nulls instead of failing, and reports where these were dropped.
+    pub fn from_lossy(value: Reference) -> (Self, Vec<DroppedInfo>) {
+        let mut objects = Default::default();
+        let mut report = Some(TransferReport::default());
+        let root = Transferable::produce(value, &mut objects, &mut report);
+        let dropped = report.map(|report| report.dropped).unwrap_or_default();
+        (Self { objects, root }, dropped)
+    }
+
     fn reproduce(self) -> Reference {
         let Some(root) = self.root else {
             return Reference::null();
@@ -557,7 +1088,7 @@ impl Transferable {
 impl From<Reference> for Transferable {
     fn from(value: Reference) -> Self {
         let mut objects = Default::default();
-        let root = Transferable::produce(value, &mut objects);
+        let root = Transferable::produce(value, &mut objects, &mut None);
         Self { objects, root }
     }
 }
@@ -565,6 +1096,52 @@ impl From<Reference> for Transferable {
 #[derive(Debug, Default)]
 pub struct Transferred(usize);
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DroppedSlot {
+    Index(usize),
+    Key(String),
+    Field(String),
+}
+
+/// Object that could not be transferred and got replaced with null.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DroppedInfo {
+    /// Slots leading from root to dropped object, empty if root was dropped.
+    pub path: Vec<DroppedSlot>,
+    pub type_name: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnershipEntry {
+    pub id: ObjectId,
+    pub type_name: Option<String>,
+    pub references_count: usize,
+}
+
+/// Objects reachable from reference, produced by `Reference::ownership_report`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct OwnershipReport {
+    pub objects: Vec<OwnershipEntry>,
+    /// Sum of references counts of all listed objects.
+    pub total_references: usize,
+}
+
+impl OwnershipReport {
+    pub fn objects_count(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn find(&self, id: ObjectId) -> Option<&OwnershipEntry> {
+        self.objects.iter().find(|entry| entry.id == id)
+    }
+}
+
+#[derive(Default)]
+struct TransferReport {
+    path: Vec<DroppedSlot>,
+    dropped: Vec<DroppedInfo>,
+}
+
 pub fn install(registry: &mut Registry) {
     registry.add_type(define_native_struct! {
         registry => mod reflect struct Reference (Reference) {}
@@ -590,18 +1167,129 @@ pub fn install(registry: &mut Registry) {
     });
     registry.add_type(define_native_struct! {
         registry => mod math struct Array (Array) {}
+        [override_send = true]
     });
     registry.add_type(define_native_struct! {
         registry => mod math struct Map (Map) {}
+        [override_send = true]
     });
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{Integer, Reference, Transferable};
+    use crate::{
+        Array, DroppedInfo, DroppedSlot, Function, Integer, Map, OwnershipEntry, Real, Reference,
+        ReferenceAccessError, Text, Transferable, Type, TypeMismatch,
+    };
     use intuicio_core::prelude::*;
     use intuicio_derive::*;
-    use std::thread::spawn;
+    use std::{collections::HashMap, thread::spawn};
+
+    #[test]
+    fn test_try_new_custom() {
+        let mut registry = Registry::default();
+        crate::install(&mut registry);
+        let integer_type = Type::of::<Integer>(&registry).unwrap();
+        let real_type = Type::of::<Real>(&registry).unwrap();
+
+        let value = Reference::try_new_custom(42 as Integer, &integer_type).unwrap();
+        assert_eq!(*value.read::<Integer>().unwrap(), 42);
+
+        assert!(matches!(
+            Reference::try_new_custom(42 as Integer, &real_type),
+            Err(TypeMismatch::TypeHash { .. })
+        ));
+        assert_eq!(
+            Reference::try_new_custom(42 as Integer, &Type::default()).err(),
+            Some(TypeMismatch::NullType)
+        );
+    }
+
+    #[test]
+    fn test_type_function_keys() {
+        #[derive(IntuicioStruct, Default)]
+        #[intuicio(name = "Position", module_name = "test")]
+        struct Position {
+            pub x: Real,
+            pub y: Real,
+        }
+
+        #[derive(IntuicioStruct, Default)]
+        #[intuicio(name = "Velocity", module_name = "test")]
+        struct Velocity {
+            pub x: Real,
+            pub y: Real,
+        }
+
+        let mut registry = Registry::default();
+        crate::install(&mut registry);
+        registry.add_type(Position::define_struct(&registry));
+        registry.add_type(Velocity::define_struct(&registry));
+
+        let mut components = HashMap::<Type, Vec<Reference>>::new();
+        for component in [
+            Reference::new(Position { x: 1.0, y: 2.0 }, &registry),
+            Reference::new(Velocity { x: 0.5, y: 0.0 }, &registry),
+            Reference::new(Position { x: 3.0, y: 4.0 }, &registry),
+            Reference::null(),
+        ] {
+            components
+                .entry(component.type_of().unwrap_or_default())
+                .or_default()
+                .push(component);
+        }
+        assert_eq!(components.len(), 3);
+        let positions = &components[&Type::of::<Position>(&registry).unwrap()];
+        assert_eq!(positions.len(), 2);
+        assert_eq!(positions[1].read::<Position>().unwrap().x, 3.0);
+        assert_eq!(
+            components[&Type::by_name("Velocity", "test", &registry).unwrap()].len(),
+            1
+        );
+        assert_eq!(components[&Type::default()].len(), 1);
+        assert!(!components.contains_key(&Type::of::<Integer>(&registry).unwrap()));
+        assert!(Type::default() == Type::default());
+        assert!(!Type::default().is_same_as(&Type::default()));
+
+        let function = Function::new(registry.add_function(
+            intuicio_core::function::Function::new(
+                FunctionSignature::new("tag").with_module_name("test"),
+                FunctionBody::closure(|_, _| {}),
+            ),
+        ));
+        assert!(Function::by_name("tag", "test", &registry).unwrap() == function);
+        #[allow(clippy::mutable_key_type)]
+        let mut tags = HashMap::<Function, &str>::new();
+        tags.insert(function.clone(), "first");
+        tags.insert(Function::default(), "none");
+        assert_eq!(tags[&function], "first");
+        assert_eq!(tags[&Function::default()], "none");
+        assert!(function != Function::default());
+    }
+
+    #[test]
+    fn test_borrow_conflict() {
+        let mut registry = Registry::default();
+        crate::install(&mut registry);
+        let mut value = Reference::new_integer(42, &registry);
+        let other = value.clone();
+        let guard = value.write_or_err::<Integer>().unwrap();
+        assert!(other.read::<Integer>().is_none());
+        assert!(matches!(
+            other.read_or_err::<Integer>(),
+            Err(ReferenceAccessError::Borrow(_))
+        ));
+        drop(guard);
+        assert_eq!(*other.read_or_err::<Integer>().unwrap(), 42);
+        assert!(matches!(
+            other.read_or_err::<Real>(),
+            Err(ReferenceAccessError::TypeMismatch(_))
+        ));
+        assert!(matches!(
+            Reference::null().read_or_err::<Integer>(),
+            Err(ReferenceAccessError::Null)
+        ));
+    }
 
     #[test]
     fn test_threading() {
@@ -676,4 +1364,218 @@ mod tests {
         assert!(value.me.type_of().unwrap().is::<Foo>());
         assert!(value.me.does_share_reference(&object, true));
     }
+
+    #[test]
+    fn test_transfer_lossy() {
+        #[derive(Default)]
+        struct Handle {
+            _inner: std::rc::Rc<()>,
+        }
+
+        let mut registry = Registry::default();
+        crate::install(&mut registry);
+        registry.add_type(define_native_struct! {
+            registry => mod test struct Handle (Handle) {}
+            [override_send = false]
+        });
+
+        let shared = Reference::new_integer(42, &registry);
+        let mut map = Map::new();
+        map.insert(
+            "handle".to_owned(),
+            Reference::new(Handle::default(), &registry),
+        );
+        map.insert("shared".to_owned(), shared.clone());
+        let value = Reference::new_array(
+            Array::from([
+                Reference::new_map(map, &registry),
+                shared,
+                Reference::new(Handle::default(), &registry),
+                Reference::null(),
+            ]),
+            &registry,
+        );
+        let (transferable, dropped) = Transferable::from_lossy(value);
+        assert_eq!(dropped.len(), 2);
+        assert!(dropped.contains(&DroppedInfo {
+            path: vec![DroppedSlot::Index(0), DroppedSlot::Key("handle".to_owned())],
+            type_name: Some("Handle".to_owned()),
+        }));
+        assert!(dropped.contains(&DroppedInfo {
+            path: vec![DroppedSlot::Index(2)],
+            type_name: Some("Handle".to_owned()),
+        }));
+
+        let transferable = spawn(|| Transferable::from(Reference::from(transferable)))
+            .join()
+            .unwrap();
+        let object = Reference::from(transferable);
+        let array = object.read::<Array>().unwrap();
+        assert_eq!(array.len(), 4);
+        let map = array[0].read::<Map>().unwrap();
+        assert!(map["handle"].is_null());
+        assert_eq!(*map["shared"].read::<Integer>().unwrap(), 42);
+        assert!(map["shared"].does_share_reference(&array[1], false));
+        assert!(array[2].is_null());
+        assert!(array[3].is_null());
+    }
+
+    #[test]
+    fn test_path() {
+        #[derive(IntuicioStruct, Default)]
+        #[intuicio(name = "Person", module_name = "test")]
+        struct Person {
+            pub name: Reference,
+            pub skill: Reference,
+            pub friends: Reference,
+        }
+
+        let mut registry = Registry::default();
+        crate::install(&mut registry);
+        registry.add_type(Person::define_struct(&registry));
+
+        let friend = Reference::new(
+            Person {
+                name: Reference::new_text(Text::from("Sleepy"), &registry),
+                ..Default::default()
+            },
+            &registry,
+        );
+        let mut person = Reference::new(
+            Person {
+                name: Reference::new_text(Text::from("Grumpy"), &registry),
+                skill: Reference::new_map(
+                    Map::from([("power".to_owned(), Reference::new_integer(42, &registry))]),
+                    &registry,
+                ),
+                friends: Reference::new_array(Array::from([friend.clone()]), &registry),
+            },
+            &registry,
+        );
+
+        assert_eq!(
+            *person.get_path("skill.power").read::<Integer>().unwrap(),
+            42
+        );
+        assert_eq!(
+            person
+                .get_path("friends[0].name")
+                .read::<Text>()
+                .unwrap()
+                .as_str(),
+            "Sleepy"
+        );
+        assert!(person.get_path("").does_share_reference(&person, false));
+        assert!(person
+            .get_path("friends[0]")
+            .does_share_reference(&friend, false));
+        assert!(person.get_path("friends[1]").is_null());
+        assert!(person.get_path("friends[first]").is_null());
+        assert!(person.get_path("friends[0").is_null());
+        assert!(person.get_path("skill.level").is_null());
+        assert!(person.get_path("age").is_null());
+        assert!(person.get_path("skill.power.value").is_null());
+        assert!(person.get_path("friends[0].skill.power").is_null());
+
+        assert!(person.set_path("skill.power", Reference::new_integer(7, &registry)));
+        assert_eq!(
+            *person.get_path("skill.power").read::<Integer>().unwrap(),
+            7
+        );
+        assert!(person.set_path("skill.level", Reference::new_integer(3, &registry)));
+        assert_eq!(
+            *person.get_path("skill.level").read::<Integer>().unwrap(),
+            3
+        );
+        assert!(person.set_path(
+            "friends[0].name",
+            Reference::new_text(Text::from("Happy"), &registry)
+        ));
+        assert_eq!(
+            friend
+                .read::<Person>()
+                .unwrap()
+                .name
+                .read::<Text>()
+                .unwrap()
+                .as_str(),
+            "Happy"
+        );
+        assert!(!person.set_path("friends[1]", Reference::null()));
+        assert!(!person.set_path("age", Reference::null()));
+        assert!(!person.set_path("friends[0].skill.power", Reference::null()));
+        assert!(!person.set_path("", Reference::null()));
+    }
+
+    #[test]
+    fn test_cycles() {
+        #[derive(IntuicioStruct, Default)]
+        #[intuicio(name = "Foo", module_name = "test")]
+        struct Foo {
+            pub v: Reference,
+            pub me: Reference,
+        }
+
+        let mut registry = Registry::default();
+        crate::install(&mut registry);
+        registry.add_type(Foo::define_struct(&registry));
+
+        let mut value = Reference::new(
+            Foo {
+                v: Reference::new_text(Text::from("foo"), &registry),
+                me: Default::default(),
+            },
+            &registry,
+        );
+        let me = value.clone();
+        value.write::<Foo>().unwrap().me = me;
+        assert_eq!(value.references_count(), 2);
+        let report = value.ownership_report();
+        assert_eq!(report.objects_count(), 2);
+        assert_eq!(report.total_references, 3);
+        assert_eq!(
+            report.objects[0],
+            OwnershipEntry {
+                id: value.object_id().unwrap(),
+                type_name: Some("Foo".to_owned()),
+                references_count: 2,
+            }
+        );
+        assert_eq!(report.objects[1].type_name.as_deref(), Some("Text"));
+        assert_eq!(report.objects[1].references_count, 1);
+        assert_eq!(value.references_count(), 2);
+        let cycles = Reference::detect_cycles(std::slice::from_ref(&value));
+        assert_eq!(cycles, vec![vec![value.object_id().unwrap()]]);
+
+        let mut other = Reference::new(Foo::default(), &registry);
+        other.write::<Foo>().unwrap().me = Reference::new_array(
+            Array::from([Reference::new_integer(42, &registry), other.clone()]),
+            &registry,
+        );
+        let roots = [value.clone(), other.clone()];
+        let cycles = Reference::detect_cycles(&roots);
+        assert_eq!(cycles.len(), 2);
+        assert_eq!(cycles[1].len(), 2);
+        assert!(cycles[1].contains(&other.object_id().unwrap()));
+        assert_eq!(other.references_count(), 3);
+
+        assert_eq!(Reference::break_cycles(&roots), 2);
+        assert!(Reference::detect_cycles(&roots).is_empty());
+        drop(roots);
+        assert_eq!(value.references_count(), 1);
+        assert_eq!(other.references_count(), 1);
+        assert!(value.read::<Foo>().unwrap().me.is_null());
+        assert!(!value.read::<Foo>().unwrap().v.is_null());
+        let report = value.ownership_report();
+        assert_eq!(report.objects_count(), 2);
+        assert_eq!(
+            report
+                .find(value.object_id().unwrap())
+                .unwrap()
+                .references_count,
+            1
+        );
+        assert!(value.try_consume().is_ok());
+        assert!(other.try_consume().is_ok());
+    }
 }
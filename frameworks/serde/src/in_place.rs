@@ -0,0 +1,449 @@
+use serde::{
+    ser::{
+        Error as _, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant,
+        SerializeTuple, SerializeTupleStruct, SerializeTupleVariant,
+    },
+    Serialize, Serializer,
+};
+use serde_intermediate::{
+    error::{Error, Result},
+    Intermediate,
+};
+
+/// Serializes value into existing `Intermediate`, reusing its strings and
+/// collections where buffer already has the shape of serialized value.
+/// Parts which shape does not match get replaced.
+pub fn serialize_into<T>(value: &T, out: &mut Intermediate) -> Result<()>
+where
+    T: Serialize + ?Sized,
+{
+    value.serialize(InPlaceSerializer { out })
+}
+
+pub struct InPlaceSerializer<'a> {
+    out: &'a mut Intermediate,
+}
+
+impl<'a> InPlaceSerializer<'a> {
+    pub fn new(out: &'a mut Intermediate) -> Self {
+        Self { out }
+    }
+}
+
+fn write_str(out: &mut String, value: &str) {
+    out.clear();
+    out.push_str(value);
+}
+
+macro_rules! impl_serialize {
+    ($name:ident, $variant:ident, $type:ident) => {
+        fn $name(self, v: $type) -> Result<Self::Ok> {
+            *self.out = Intermediate::$variant(v);
+            Ok(())
+        }
+    };
+}
+
+/// Makes sure `$out` is `$variant` (replacing it with `$default` otherwise)
+/// and binds its content.
+macro_rules! ensure_variant {
+    ($out:expr, $variant:ident ( $($field:ident),+ ), $default:expr) => {
+        if !matches!($out, Intermediate::$variant(..)) {
+            *$out = $default;
+        }
+        let Intermediate::$variant($($field),+) = $out else {
+            unreachable!()
+        };
+    };
+}
+
+impl<'a> Serializer for InPlaceSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = ItemsSerializer<'a>;
+    type SerializeTuple = ItemsSerializer<'a>;
+    type SerializeTupleStruct = ItemsSerializer<'a>;
+    type SerializeTupleVariant = ItemsSerializer<'a>;
+    type SerializeMap = EntriesSerializer<'a>;
+    type SerializeStruct = FieldsSerializer<'a>;
+    type SerializeStructVariant = FieldsSerializer<'a>;
+
+    impl_serialize!(serialize_bool, Bool, bool);
+    impl_serialize!(serialize_i8, I8, i8);
+    impl_serialize!(serialize_i16, I16, i16);
+    impl_serialize!(serialize_i32, I32, i32);
+    impl_serialize!(serialize_i64, I64, i64);
+    impl_serialize!(serialize_i128, I128, i128);
+    impl_serialize!(serialize_u8, U8, u8);
+    impl_serialize!(serialize_u16, U16, u16);
+    impl_serialize!(serialize_u32, U32, u32);
+    impl_serialize!(serialize_u64, U64, u64);
+    impl_serialize!(serialize_u128, U128, u128);
+    impl_serialize!(serialize_f32, F32, f32);
+    impl_serialize!(serialize_f64, F64, f64);
+    impl_serialize!(serialize_char, Char, char);
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok> {
+        ensure_variant!(
+            self.out,
+            String(value),
+            Intermediate::String(Default::default())
+        );
+        write_str(value, v);
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok> {
+        ensure_variant!(
+            self.out,
+            Bytes(value),
+            Intermediate::Bytes(Default::default())
+        );
+        value.clear();
+        value.extend_from_slice(v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok> {
+        *self.out = Intermediate::Option(None);
+        Ok(())
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok>
+    where
+        T: ?Sized + Serialize,
+    {
+        if !matches!(self.out, Intermediate::Option(Some(_))) {
+            *self.out = Intermediate::Option(Some(Default::default()));
+        }
+        let Intermediate::Option(Some(out)) = self.out else {
+            unreachable!()
+        };
+        serialize_into(value, out)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok> {
+        *self.out = Intermediate::Unit;
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Self::Ok> {
+        *self.out = Intermediate::UnitStruct;
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok> {
+        ensure_variant!(
+            self.out,
+            UnitVariant(name),
+            Intermediate::UnitVariant(Default::default())
+        );
+        write_str(name, variant);
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T>(self, _: &'static str, value: &T) -> Result<Self::Ok>
+    where
+        T: ?Sized + Serialize,
+    {
+        ensure_variant!(
+            self.out,
+            NewTypeStruct(out),
+            Intermediate::NewTypeStruct(Default::default())
+        );
+        serialize_into(value, out)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok>
+    where
+        T: ?Sized + Serialize,
+    {
+        ensure_variant!(
+            self.out,
+            NewTypeVariant(name, out),
+            Intermediate::NewTypeVariant(Default::default(), Default::default())
+        );
+        write_str(name, variant);
+        serialize_into(value, out)
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq> {
+        ensure_variant!(self.out, Seq(items), Intermediate::Seq(Default::default()));
+        Ok(ItemsSerializer::new(items))
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple> {
+        ensure_variant!(
+            self.out,
+            Tuple(items),
+            Intermediate::Tuple(Default::default())
+        );
+        Ok(ItemsSerializer::new(items))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        ensure_variant!(
+            self.out,
+            TupleStruct(items),
+            Intermediate::TupleStruct(Default::default())
+        );
+        Ok(ItemsSerializer::new(items))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        ensure_variant!(
+            self.out,
+            TupleVariant(name, items),
+            Intermediate::TupleVariant(Default::default(), Default::default())
+        );
+        write_str(name, variant);
+        Ok(ItemsSerializer::new(items))
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap> {
+        ensure_variant!(
+            self.out,
+            Map(entries),
+            Intermediate::Map(Default::default())
+        );
+        Ok(EntriesSerializer { entries, index: 0 })
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeStruct> {
+        ensure_variant!(
+            self.out,
+            Struct(fields),
+            Intermediate::Struct(Default::default())
+        );
+        Ok(FieldsSerializer { fields, index: 0 })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        ensure_variant!(
+            self.out,
+            StructVariant(name, fields),
+            Intermediate::StructVariant(Default::default(), Default::default())
+        );
+        write_str(name, variant);
+        Ok(FieldsSerializer { fields, index: 0 })
+    }
+}
+
+/// Writes items in order, dropping ones left over from longer buffer.
+pub struct ItemsSerializer<'a> {
+    items: &'a mut Vec<Intermediate>,
+    index: usize,
+}
+
+impl<'a> ItemsSerializer<'a> {
+    fn new(items: &'a mut Vec<Intermediate>) -> Self {
+        Self { items, index: 0 }
+    }
+
+    fn item<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        if self.index == self.items.len() {
+            self.items.push(Default::default());
+        }
+        serialize_into(value, &mut self.items[self.index])?;
+        self.index += 1;
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        self.items.truncate(self.index);
+        Ok(())
+    }
+}
+
+impl SerializeSeq for ItemsSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        self.finish()
+    }
+}
+
+impl SerializeTuple for ItemsSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        self.finish()
+    }
+}
+
+impl SerializeTupleStruct for ItemsSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        self.finish()
+    }
+}
+
+impl SerializeTupleVariant for ItemsSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.item(value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        self.finish()
+    }
+}
+
+/// Writes entries in order, dropping ones left over from longer buffer.
+pub struct EntriesSerializer<'a> {
+    entries: &'a mut Vec<(Intermediate, Intermediate)>,
+    index: usize,
+}
+
+impl SerializeMap for EntriesSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        if self.index == self.entries.len() {
+            self.entries.push(Default::default());
+        }
+        serialize_into(key, &mut self.entries[self.index].0)
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let Some((_, out)) = self.entries.get_mut(self.index) else {
+            return Err(Error::custom("Expected map key before value"));
+        };
+        serialize_into(value, out)?;
+        self.index += 1;
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        self.entries.truncate(self.index);
+        Ok(())
+    }
+}
+
+/// Writes fields in order, dropping ones left over from longer buffer.
+pub struct FieldsSerializer<'a> {
+    fields: &'a mut Vec<(String, Intermediate)>,
+    index: usize,
+}
+
+impl FieldsSerializer<'_> {
+    fn field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        if self.index == self.fields.len() {
+            self.fields.push(Default::default());
+        }
+        let (name, out) = &mut self.fields[self.index];
+        if name != key {
+            write_str(name, key);
+        }
+        serialize_into(value, out)?;
+        self.index += 1;
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        self.fields.truncate(self.index);
+        Ok(())
+    }
+}
+
+impl SerializeStruct for FieldsSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        self.finish()
+    }
+}
+
+impl SerializeStructVariant for FieldsSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        self.finish()
+    }
+}
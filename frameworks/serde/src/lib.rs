@@ -1,7 +1,15 @@
+use intuicio_core::{
+    meta::Meta,
+    registry::Registry,
+    types::{struct_type::StructField, Type, TypeHandle, TypeQuery},
+};
 use intuicio_data::type_hash::TypeHash;
 use serde::{de::DeserializeOwned, Serialize};
 use std::{collections::HashMap, error::Error};
 
+pub mod in_place;
+pub mod stream;
+
 pub use serde_intermediate::{
     de::intermediate::DeserializeMode,
     error::{Error as IntermediateError, Result as IntermediateResult},
@@ -9,16 +17,128 @@ pub use serde_intermediate::{
     to_object, to_string, to_string_compact, to_string_pretty, Intermediate, Object,
 };
 
-struct Serializer {
-    #[allow(clippy::type_complexity)]
-    serialize_from: Box<dyn Fn(*const u8) -> Result<Intermediate, Box<dyn Error>> + Send + Sync>,
-    #[allow(clippy::type_complexity)]
-    deserialize_to: Box<dyn Fn(*mut u8, &Intermediate) -> Result<(), Box<dyn Error>> + Send + Sync>,
+enum Serializer {
+    Native {
+        #[allow(clippy::type_complexity)]
+        serialize_from:
+            Box<dyn Fn(*const u8) -> Result<Intermediate, Box<dyn Error>> + Send + Sync>,
+        #[allow(clippy::type_complexity)]
+        deserialize_to:
+            Box<dyn Fn(*mut u8, &Intermediate) -> Result<(), Box<dyn Error>> + Send + Sync>,
+        /// Writes into existing value reusing its buffers, if registered.
+        #[allow(clippy::type_complexity)]
+        serialize_into: Option<
+            Box<dyn Fn(*const u8, &mut Intermediate) -> Result<(), Box<dyn Error>> + Send + Sync>,
+        >,
+    },
+    Reflected {
+        type_name: String,
+        fields: Vec<ReflectedField>,
+    },
+    ReflectedEnum {
+        type_name: String,
+        variants: Vec<ReflectedVariant>,
+    },
+}
+
+struct ReflectedVariant {
+    name: String,
+    discriminant: u8,
+    fields: Vec<ReflectedField>,
+    /// Used to finalize and initialize fields when deserialization switches
+    /// variant of existing value.
+    field_types: Vec<TypeHandle>,
+}
+
+struct ReflectedField {
+    name: String,
+    offset: usize,
+    type_hash: TypeHash,
+    /// Slot of field type serializer, resolved by `compile_dispatch`.
+    slot: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaField {
+    pub name: String,
+    pub schema: SchemaNode,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaVariant {
+    pub name: String,
+    pub discriminant: u8,
+    pub fields: Vec<SchemaField>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaShape {
+    Struct(Vec<SchemaField>),
+    Enum(Vec<SchemaVariant>),
+}
+
+/// Type layout as seen through its reflection definition in registry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaNode {
+    pub type_hash: TypeHash,
+    pub type_name: String,
+    /// Tells if serialization registry has serializer for this type.
+    pub serializable: bool,
+    pub shape: SchemaShape,
 }
 
+impl SchemaNode {
+    fn write_indented(&self, f: &mut std::fmt::Formatter<'_>, indent: usize) -> std::fmt::Result {
+        write!(f, "{}", self.type_name)?;
+        if !self.serializable {
+            write!(f, " (not serializable)")?;
+        }
+        match &self.shape {
+            SchemaShape::Struct(fields) => Self::write_fields(f, fields, indent),
+            SchemaShape::Enum(variants) => {
+                writeln!(f, " {{")?;
+                for variant in variants {
+                    write!(f, "{:indent$}{}", "", variant.name, indent = indent + 4)?;
+                    Self::write_fields(f, &variant.fields, indent + 4)?;
+                    writeln!(f)?;
+                }
+                write!(f, "{:indent$}}}", "", indent = indent)
+            }
+        }
+    }
+
+    fn write_fields(
+        f: &mut std::fmt::Formatter<'_>,
+        fields: &[SchemaField],
+        indent: usize,
+    ) -> std::fmt::Result {
+        if fields.is_empty() {
+            return Ok(());
+        }
+        writeln!(f, " {{")?;
+        for field in fields {
+            write!(f, "{:indent$}{}: ", "", field.name, indent = indent + 4)?;
+            field.schema.write_indented(f, indent + 4)?;
+            writeln!(f)?;
+        }
+        write!(f, "{:indent$}}}", "", indent = indent)
+    }
+}
+
+impl std::fmt::Display for SchemaNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write_indented(f, 0)
+    }
+}
+
+/// Serializers are stored in slots indexed by dense integers assigned at
+/// registration, `mapping` only translates type hashes into these slots.
+/// Slots are never reused for other types, so indices cached in reflected
+/// serializers never point to wrong serializer.
 #[derive(Default)]
 pub struct SerializationRegistry {
-    mapping: HashMap<TypeHash, Serializer>,
+    mapping: HashMap<TypeHash, usize>,
+    serializers: Vec<Option<Serializer>>,
 }
 
 impl SerializationRegistry {
@@ -379,6 +499,15 @@ impl SerializationRegistry {
                 }
             },
         );
+        self.register_serialize_into::<String>(|data, out| {
+            if let Intermediate::String(out) = out {
+                out.clear();
+                out.push_str(data);
+            } else {
+                *out = data.as_str().into();
+            }
+            Ok(())
+        });
         self
     }
 
@@ -395,6 +524,7 @@ impl SerializationRegistry {
                 Ok(())
             },
         );
+        self.register_serialize_into::<T>(|data, out| Ok(in_place::serialize_into(data, out)?));
     }
 
     pub fn register<T>(
@@ -427,21 +557,455 @@ impl SerializationRegistry {
             + Sync
             + 'static,
     ) {
-        self.mapping.insert(
+        self.insert(
             type_hash,
-            Serializer {
+            Serializer::Native {
                 serialize_from: Box::new(serialize_from),
                 deserialize_to: Box::new(deserialize_to),
+                serialize_into: None,
             },
         );
     }
 
+    /// Adds in-place serializer to already registered native type, used by
+    /// `serialize_into` to reuse buffers of existing value instead of making
+    /// new one. Returns `false` if type has no native serializer registered.
+    pub fn register_serialize_into<T>(
+        &mut self,
+        serialize_into: impl Fn(&T, &mut Intermediate) -> Result<(), Box<dyn Error>>
+            + Send
+            + Sync
+            + 'static,
+    ) -> bool {
+        let Some(Some(Serializer::Native {
+            serialize_into: slot,
+            ..
+        })) = self
+            .find_slot(TypeHash::of::<T>())
+            .map(|slot| self.serializers[slot].as_mut())
+        else {
+            return false;
+        };
+        *slot = Some(Box::new(move |data, out| unsafe {
+            serialize_into(data.cast::<T>().as_ref().unwrap(), out)
+        }));
+        true
+    }
+
+    /// Registers serializer of reflected struct, which (de)serializes its fields
+    /// with serializers registered for their types. Field types do not have to
+    /// be registered yet, they are looked up by type hash on every call until
+    /// `compile_dispatch` resolves them.
+    pub fn register_reflected(&mut self, handle: &TypeHandle) -> Result<(), Box<dyn Error>> {
+        let Type::Struct(type_) = &**handle else {
+            return Err(format!(
+                "Type `{}` is not a struct and cannot be registered as reflected",
+                handle.name()
+            )
+            .into());
+        };
+        let fields = type_
+            .fields()
+            .iter()
+            .map(|field| ReflectedField {
+                name: field.name.to_owned(),
+                offset: field.address_offset(),
+                type_hash: field.type_handle().type_hash(),
+                slot: None,
+            })
+            .collect();
+        self.insert(
+            handle.type_hash(),
+            Serializer::Reflected {
+                type_name: type_.name.to_owned(),
+                fields,
+            },
+        );
+        Ok(())
+    }
+
+    /// Registers serializer of reflected enum, which (de)serializes current
+    /// variant as unit variant, or as struct variant with its fields. Same as
+    /// with reflected structs, field types do not have to be registered yet.
+    pub fn register_reflected_enum(&mut self, handle: &TypeHandle) -> Result<(), Box<dyn Error>> {
+        let Type::Enum(type_) = &**handle else {
+            return Err(format!(
+                "Type `{}` is not an enum and cannot be registered as reflected enum",
+                handle.name()
+            )
+            .into());
+        };
+        let variants = type_
+            .variants()
+            .iter()
+            .map(|variant| ReflectedVariant {
+                name: variant.name.to_owned(),
+                discriminant: variant.discriminant(),
+                fields: variant
+                    .fields
+                    .iter()
+                    .map(|field| ReflectedField {
+                        name: field.name.to_owned(),
+                        offset: field.address_offset(),
+                        type_hash: field.type_handle().type_hash(),
+                        slot: None,
+                    })
+                    .collect(),
+                field_types: variant
+                    .fields
+                    .iter()
+                    .map(|field| field.type_handle().clone())
+                    .collect(),
+            })
+            .collect();
+        self.insert(
+            handle.type_hash(),
+            Serializer::ReflectedEnum {
+                type_name: type_.name.to_owned(),
+                variants,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn with_registry_reflection(mut self, registry: &Registry) -> Self {
+        self.register_registry_reflection(registry);
+        self
+    }
+
+    /// Registers reflected serializers of all structs and enums of `registry`
+    /// that have no serializer yet, then compiles dispatch. Skipped are types
+    /// with `no_serialize` meta and opaque native types, which have no fields
+    /// to reflect. Returns paths of fields which types still have no serializer
+    /// - these fail to (de)serialize until their types get registered.
+    pub fn register_registry_reflection(&mut self, registry: &Registry) -> Vec<String> {
+        fn is_skipped(meta: &Meta) -> bool {
+            match meta {
+                Meta::Identifier(name) => name == "no_serialize",
+                Meta::Array(items) => items.iter().any(is_skipped),
+                Meta::Map(items) => items
+                    .get("no_serialize")
+                    .and_then(|meta| meta.as_value())
+                    .and_then(|value| value.as_bool())
+                    .unwrap_or(false),
+                _ => false,
+            }
+        }
+
+        for handle in registry.types() {
+            if self.mapping.contains_key(&handle.type_hash())
+                || handle.meta().map(is_skipped).unwrap_or(false)
+            {
+                continue;
+            }
+            match &**handle {
+                Type::Struct(type_) => {
+                    if !type_.fields().is_empty() || type_.layout().size() == 0 {
+                        let _ = self.register_reflected(handle);
+                    }
+                }
+                Type::Enum(type_) => {
+                    if !type_.variants().is_empty() {
+                        let _ = self.register_reflected_enum(handle);
+                    }
+                }
+            }
+        }
+        self.compile_dispatch();
+        let mut result = vec![];
+        for serializer in self.serializers.iter().flatten() {
+            match serializer {
+                Serializer::Native { .. } => {}
+                Serializer::Reflected { type_name, fields } => {
+                    result.extend(
+                        fields
+                            .iter()
+                            .filter(|field| field.slot.is_none())
+                            .map(|field| format!("{}.{}", type_name, field.name)),
+                    );
+                }
+                Serializer::ReflectedEnum {
+                    type_name,
+                    variants,
+                } => {
+                    for variant in variants {
+                        result.extend(
+                            variant
+                                .fields
+                                .iter()
+                                .filter(|field| field.slot.is_none())
+                                .map(|field| {
+                                    format!("{}.{}.{}", type_name, variant.name, field.name)
+                                }),
+                        );
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Resolves serializer slots of all reflected struct fields, so nested values
+    /// are dispatched by index instead of type hash lookup. Should be called
+    /// again after registering field types that were missing before.
+    pub fn compile_dispatch(&mut self) {
+        let mapping = &self.mapping;
+        for serializer in self.serializers.iter_mut().flatten() {
+            match serializer {
+                Serializer::Native { .. } => {}
+                Serializer::Reflected { fields, .. } => {
+                    for field in fields {
+                        field.slot = mapping.get(&field.type_hash).copied();
+                    }
+                }
+                Serializer::ReflectedEnum { variants, .. } => {
+                    for field in variants.iter_mut().flat_map(|variant| &mut variant.fields) {
+                        field.slot = mapping.get(&field.type_hash).copied();
+                    }
+                }
+            }
+        }
+    }
+
     pub fn unregister<T>(&mut self) {
         self.unregister_raw(TypeHash::of::<T>());
     }
 
     pub fn unregister_raw(&mut self, type_hash: TypeHash) {
-        self.mapping.remove(&type_hash);
+        if let Some(slot) = self.mapping.remove(&type_hash) {
+            self.serializers[slot] = None;
+        }
+    }
+
+    fn insert(&mut self, type_hash: TypeHash, serializer: Serializer) {
+        if let Some(slot) = self.mapping.get(&type_hash) {
+            self.serializers[*slot] = Some(serializer);
+        } else {
+            self.mapping.insert(type_hash, self.serializers.len());
+            self.serializers.push(Some(serializer));
+        }
+    }
+
+    fn find_slot(&self, type_hash: TypeHash) -> Option<usize> {
+        self.mapping.get(&type_hash).copied()
+    }
+
+    fn field_slot(&self, field: &ReflectedField) -> Option<usize> {
+        field
+            .slot
+            .filter(|slot| self.serializers[*slot].is_some())
+            .or_else(|| self.find_slot(field.type_hash))
+    }
+
+    /// `path` collects names of fields leading to serialized value, starting
+    /// with name of outermost reflected type, to point where nested failures
+    /// happened.
+    unsafe fn serialize_slot<'a>(
+        &'a self,
+        slot: usize,
+        data: *const u8,
+        path: &mut Vec<&'a str>,
+    ) -> Result<Intermediate, Box<dyn Error>> {
+        match self.serializers[slot]
+            .as_ref()
+            .ok_or("Type not existent in serialization registry")?
+        {
+            Serializer::Native { serialize_from, .. } => serialize_from(data),
+            Serializer::Reflected { type_name, fields } => {
+                if path.is_empty() {
+                    path.push(type_name);
+                }
+                Ok(Intermediate::Struct(
+                    self.serialize_fields(fields, data, path)?,
+                ))
+            }
+            Serializer::ReflectedEnum {
+                type_name,
+                variants,
+            } => {
+                if path.is_empty() {
+                    path.push(type_name);
+                }
+                let discriminant = data.read();
+                let variant = variants
+                    .iter()
+                    .find(|variant| variant.discriminant == discriminant)
+                    .ok_or_else(|| {
+                        format!(
+                            "Unknown variant discriminant {} of type `{}` at `{}`",
+                            discriminant,
+                            type_name,
+                            path.join(".")
+                        )
+                    })?;
+                if variant.fields.is_empty() {
+                    return Ok(Intermediate::UnitVariant(variant.name.to_owned()));
+                }
+                path.push(&variant.name);
+                let fields = self.serialize_fields(&variant.fields, data, path)?;
+                path.pop();
+                Ok(Intermediate::StructVariant(variant.name.to_owned(), fields))
+            }
+        }
+    }
+
+    unsafe fn serialize_fields<'a>(
+        &'a self,
+        fields: &'a [ReflectedField],
+        data: *const u8,
+        path: &mut Vec<&'a str>,
+    ) -> Result<Vec<(String, Intermediate)>, Box<dyn Error>> {
+        let mut result = Vec::with_capacity(fields.len());
+        for field in fields {
+            path.push(&field.name);
+            let slot = self
+                .field_slot(field)
+                .ok_or_else(|| Self::unregistered_error(field, path))?;
+            result.push((
+                field.name.to_owned(),
+                self.serialize_slot(slot, data.add(field.offset), path)?,
+            ));
+            path.pop();
+        }
+        Ok(result)
+    }
+
+    unsafe fn deserialize_slot<'a>(
+        &'a self,
+        slot: usize,
+        data: *mut u8,
+        value: &Intermediate,
+        path: &mut Vec<&'a str>,
+    ) -> Result<(), Box<dyn Error>> {
+        match self.serializers[slot]
+            .as_ref()
+            .ok_or("Type not existent in serialization registry")?
+        {
+            Serializer::Native { deserialize_to, .. } => deserialize_to(data, value),
+            Serializer::Reflected { type_name, fields } => {
+                if path.is_empty() {
+                    path.push(type_name);
+                }
+                let Intermediate::Struct(values) = value else {
+                    return Err(format!(
+                        "Expected struct value of type `{}` at `{}`",
+                        type_name,
+                        path.join(".")
+                    )
+                    .into());
+                };
+                self.deserialize_fields(fields, data, values, path)
+            }
+            Serializer::ReflectedEnum {
+                type_name,
+                variants,
+            } => {
+                if path.is_empty() {
+                    path.push(type_name);
+                }
+                let indexed;
+                let (name, values) = match value {
+                    Intermediate::UnitVariant(name) => (name, [].as_slice()),
+                    Intermediate::StructVariant(name, values) => (name, values.as_slice()),
+                    // serde shape of tuple variants, with fields named by index.
+                    Intermediate::NewTypeVariant(name, value) => {
+                        indexed = vec![("0".to_owned(), value.as_ref().clone())];
+                        (name, indexed.as_slice())
+                    }
+                    Intermediate::TupleVariant(name, values) => {
+                        indexed = values
+                            .iter()
+                            .enumerate()
+                            .map(|(index, value)| (index.to_string(), value.clone()))
+                            .collect::<Vec<_>>();
+                        (name, indexed.as_slice())
+                    }
+                    _ => {
+                        return Err(format!(
+                            "Expected variant value of type `{}` at `{}`",
+                            type_name,
+                            path.join(".")
+                        )
+                        .into())
+                    }
+                };
+                let variant = variants
+                    .iter()
+                    .find(|variant| variant.name == *name)
+                    .ok_or_else(|| {
+                        format!(
+                            "Unknown variant `{}` of type `{}` at `{}`",
+                            name,
+                            type_name,
+                            path.join(".")
+                        )
+                    })?;
+                let discriminant = data.read();
+                if discriminant != variant.discriminant {
+                    if variant
+                        .field_types
+                        .iter()
+                        .any(|type_| !type_.can_initialize())
+                    {
+                        return Err(format!(
+                            "Could not initialize fields of variant `{}` of type `{}` at `{}`",
+                            variant.name,
+                            type_name,
+                            path.join(".")
+                        )
+                        .into());
+                    }
+                    if let Some(current) = variants
+                        .iter()
+                        .find(|variant| variant.discriminant == discriminant)
+                    {
+                        for (field, type_) in current.fields.iter().zip(&current.field_types) {
+                            type_.finalize(data.add(field.offset).cast::<()>());
+                        }
+                    }
+                    data.write(variant.discriminant);
+                    for (field, type_) in variant.fields.iter().zip(&variant.field_types) {
+                        type_.initialize(data.add(field.offset).cast::<()>());
+                    }
+                }
+                path.push(&variant.name);
+                self.deserialize_fields(&variant.fields, data, values, path)?;
+                path.pop();
+                Ok(())
+            }
+        }
+    }
+
+    unsafe fn deserialize_fields<'a>(
+        &'a self,
+        fields: &'a [ReflectedField],
+        data: *mut u8,
+        values: &[(String, Intermediate)],
+        path: &mut Vec<&'a str>,
+    ) -> Result<(), Box<dyn Error>> {
+        for field in fields {
+            path.push(&field.name);
+            let slot = self
+                .field_slot(field)
+                .ok_or_else(|| Self::unregistered_error(field, path))?;
+            let value = values
+                .iter()
+                .find(|(name, _)| *name == field.name)
+                .map(|(_, value)| value)
+                .ok_or_else(|| format!("Missing field at `{}`", path.join(".")))?;
+            self.deserialize_slot(slot, data.add(field.offset), value, path)?;
+            path.pop();
+        }
+        Ok(())
+    }
+
+    fn unregistered_error(field: &ReflectedField, path: &[&str]) -> Box<dyn Error> {
+        format!(
+            "Unregistered type at `{}` (hash {})",
+            path.join("."),
+            field.type_hash
+        )
+        .into()
     }
 
     pub fn serialize_from<T>(&self, data: &T) -> Result<Intermediate, Box<dyn Error>> {
@@ -454,12 +1018,203 @@ impl SerializationRegistry {
         type_hash: TypeHash,
         data: *const u8,
     ) -> Result<Intermediate, Box<dyn Error>> {
-        if let Some(serializer) = self.mapping.get(&type_hash) {
-            return (serializer.serialize_from)(data);
+        if let Some(slot) = self.find_slot(type_hash) {
+            return self.serialize_slot(slot, data, &mut vec![]);
+        }
+        Err("Type not existent in serialization registry".into())
+    }
+
+    /// Serializes into existing `Intermediate` buffer, writing reflected
+    /// fields and leaf values in place so their buffers get reused. Parts of
+    /// buffer which shape does not match serialized value (different variant
+    /// or fields) are rebuilt. Types registered with `with_serde` are written
+    /// in place too, other native types without in-place serializer are
+    /// replaced as whole. On error buffer is left partially updated.
+    pub fn serialize_into<T>(
+        &self,
+        data: &T,
+        out: &mut Intermediate,
+    ) -> Result<(), Box<dyn Error>> {
+        unsafe {
+            self.dynamic_serialize_into(TypeHash::of::<T>(), data as *const T as *const u8, out)
+        }
+    }
+
+    /// # Safety
+    pub unsafe fn dynamic_serialize_into(
+        &self,
+        type_hash: TypeHash,
+        data: *const u8,
+        out: &mut Intermediate,
+    ) -> Result<(), Box<dyn Error>> {
+        if let Some(slot) = self.find_slot(type_hash) {
+            return self.serialize_slot_into(slot, data, out, &mut vec![]);
         }
         Err("Type not existent in serialization registry".into())
     }
 
+    unsafe fn serialize_slot_into<'a>(
+        &'a self,
+        slot: usize,
+        data: *const u8,
+        out: &mut Intermediate,
+        path: &mut Vec<&'a str>,
+    ) -> Result<(), Box<dyn Error>> {
+        match self.serializers[slot]
+            .as_ref()
+            .ok_or("Type not existent in serialization registry")?
+        {
+            Serializer::Native {
+                serialize_from,
+                serialize_into,
+                ..
+            } => {
+                match serialize_into {
+                    Some(serialize_into) => serialize_into(data, out)?,
+                    None => *out = serialize_from(data)?,
+                }
+                Ok(())
+            }
+            Serializer::Reflected { type_name, fields } => {
+                if path.is_empty() {
+                    path.push(type_name);
+                }
+                if !matches!(out, Intermediate::Struct(values) if Self::fields_match(fields, values))
+                {
+                    *out = Intermediate::Struct(Self::fields_skeleton(fields));
+                }
+                let Intermediate::Struct(values) = out else {
+                    unreachable!()
+                };
+                self.serialize_fields_into(fields, data, values, path)
+            }
+            Serializer::ReflectedEnum {
+                type_name,
+                variants,
+            } => {
+                if path.is_empty() {
+                    path.push(type_name);
+                }
+                let discriminant = data.read();
+                let variant = variants
+                    .iter()
+                    .find(|variant| variant.discriminant == discriminant)
+                    .ok_or_else(|| {
+                        format!(
+                            "Unknown variant discriminant {} of type `{}` at `{}`",
+                            discriminant,
+                            type_name,
+                            path.join(".")
+                        )
+                    })?;
+                if variant.fields.is_empty() {
+                    if let Intermediate::UnitVariant(name) = out {
+                        name.clear();
+                        name.push_str(&variant.name);
+                    } else {
+                        *out = Intermediate::UnitVariant(variant.name.to_owned());
+                    }
+                    return Ok(());
+                }
+                if !matches!(
+                    out,
+                    Intermediate::StructVariant(name, values)
+                        if *name == variant.name && Self::fields_match(&variant.fields, values)
+                ) {
+                    *out = Intermediate::StructVariant(
+                        variant.name.to_owned(),
+                        Self::fields_skeleton(&variant.fields),
+                    );
+                }
+                let Intermediate::StructVariant(_, values) = out else {
+                    unreachable!()
+                };
+                path.push(&variant.name);
+                self.serialize_fields_into(&variant.fields, data, values, path)?;
+                path.pop();
+                Ok(())
+            }
+        }
+    }
+
+    unsafe fn serialize_fields_into<'a>(
+        &'a self,
+        fields: &'a [ReflectedField],
+        data: *const u8,
+        values: &mut [(String, Intermediate)],
+        path: &mut Vec<&'a str>,
+    ) -> Result<(), Box<dyn Error>> {
+        for (field, (_, value)) in fields.iter().zip(values.iter_mut()) {
+            path.push(&field.name);
+            let slot = self
+                .field_slot(field)
+                .ok_or_else(|| Self::unregistered_error(field, path))?;
+            self.serialize_slot_into(slot, data.add(field.offset), value, path)?;
+            path.pop();
+        }
+        Ok(())
+    }
+
+    fn fields_match(fields: &[ReflectedField], values: &[(String, Intermediate)]) -> bool {
+        fields.len() == values.len()
+            && fields
+                .iter()
+                .zip(values)
+                .all(|(field, (name, _))| field.name == *name)
+    }
+
+    fn fields_skeleton(fields: &[ReflectedField]) -> Vec<(String, Intermediate)> {
+        fields
+            .iter()
+            .map(|field| (field.name.to_owned(), Intermediate::Unit))
+            .collect()
+    }
+
+    /// Describes fields and variants of type registered in `registry`, as
+    /// reflection sees them, without needing its instance. Fields are listed
+    /// in registry order, which for native types follows memory layout rather
+    /// than declaration order. Returns `None` if type is not registered there.
+    pub fn describe_type(&self, type_hash: TypeHash, registry: &Registry) -> Option<SchemaNode> {
+        let handle = registry.find_type(TypeQuery {
+            type_hash: Some(type_hash),
+            ..Default::default()
+        })?;
+        Some(self.describe_type_handle(&handle))
+    }
+
+    fn describe_type_handle(&self, handle: &TypeHandle) -> SchemaNode {
+        let shape = match &**handle {
+            Type::Struct(type_) => SchemaShape::Struct(self.describe_fields(type_.fields())),
+            Type::Enum(type_) => SchemaShape::Enum(
+                type_
+                    .variants()
+                    .iter()
+                    .map(|variant| SchemaVariant {
+                        name: variant.name.to_owned(),
+                        discriminant: variant.discriminant(),
+                        fields: self.describe_fields(&variant.fields),
+                    })
+                    .collect(),
+            ),
+        };
+        SchemaNode {
+            type_hash: handle.type_hash(),
+            type_name: handle.name().to_owned(),
+            serializable: self.mapping.contains_key(&handle.type_hash()),
+            shape,
+        }
+    }
+
+    fn describe_fields(&self, fields: &[StructField]) -> Vec<SchemaField> {
+        fields
+            .iter()
+            .map(|field| SchemaField {
+                name: field.name.to_owned(),
+                schema: self.describe_type_handle(field.type_handle()),
+            })
+            .collect()
+    }
+
     pub fn deserialize_to<T: Default>(&self, value: &Intermediate) -> Result<T, Box<dyn Error>> {
         let mut result = T::default();
         unsafe {
@@ -479,9 +1234,8 @@ impl SerializationRegistry {
         data: *mut u8,
         value: &Intermediate,
     ) -> Result<(), Box<dyn Error>> {
-        if let Some(serializer) = self.mapping.get(&type_hash) {
-            (serializer.deserialize_to)(data, value)?;
-            return Ok(());
+        if let Some(slot) = self.find_slot(type_hash) {
+            return self.deserialize_slot(slot, data, value, &mut vec![]);
         }
         Err("Type not existent in serialization registry".into())
     }
@@ -490,10 +1244,12 @@ impl SerializationRegistry {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use intuicio_core::{IntuicioEnum as _, IntuicioStruct as _};
     use intuicio_derive::{IntuicioEnum, IntuicioStruct};
     use serde::Deserialize;
 
     #[derive(IntuicioEnum, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+    #[intuicio(name = "Skill")]
     #[repr(u8)]
     enum Skill {
         #[default]
@@ -505,12 +1261,48 @@ mod tests {
     }
 
     #[derive(IntuicioStruct, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+    #[intuicio(name = "Person")]
     struct Person {
         name: String,
         age: usize,
         skill: Skill,
     }
 
+    #[derive(IntuicioStruct, Debug, Default, Clone, PartialEq)]
+    #[intuicio(name = "Leaf")]
+    struct Leaf {
+        id: usize,
+        label: String,
+    }
+
+    #[derive(IntuicioStruct, Debug, Default, Clone, PartialEq)]
+    #[intuicio(name = "Branch")]
+    struct Branch {
+        left: Leaf,
+        right: Leaf,
+        weight: f32,
+    }
+
+    #[derive(IntuicioStruct, Debug, Default, Clone, PartialEq)]
+    #[intuicio(name = "Tree")]
+    struct Tree {
+        trunk: Branch,
+        crown: Branch,
+        name: String,
+    }
+
+    #[derive(IntuicioStruct, Debug, Default, PartialEq)]
+    #[intuicio(name = "Secret")]
+    struct Secret {
+        key: usize,
+    }
+
+    #[derive(IntuicioStruct, Debug, Default, PartialEq)]
+    #[intuicio(name = "Locker")]
+    struct Locker {
+        secret: Secret,
+    }
+
     #[test]
     fn test_serialization() {
         let serialization = SerializationRegistry::default()
@@ -527,4 +1319,500 @@ mod tests {
         let person2 = serialization.deserialize_to::<Person>(&serialized).unwrap();
         assert_eq!(person, person2);
     }
+
+    fn name_buffer(buffer: &Intermediate) -> (*const u8, usize) {
+        let Intermediate::Struct(fields) = buffer else {
+            panic!("Expected struct");
+        };
+        let Some((_, Intermediate::String(name))) = fields.iter().find(|(name, _)| name == "name")
+        else {
+            panic!("Expected name string");
+        };
+        (name.as_ptr(), name.capacity())
+    }
+
+    #[test]
+    fn test_serialize_into() {
+        let serialization = SerializationRegistry::default()
+            .with_basic_types()
+            .with_serde::<Skill>()
+            .with_serde::<Person>();
+
+        let mut buffer = Intermediate::default();
+        let mut name = None;
+        for index in 0..100 {
+            let person = Person {
+                name: format!("Grumpy {:>3}", index),
+                age: index,
+                skill: match index % 3 {
+                    0 => Skill::Brain,
+                    1 => Skill::Muscles(index % 2 == 0),
+                    _ => Skill::Magic { power: index as _ },
+                },
+            };
+            serialization.serialize_into(&person, &mut buffer).unwrap();
+            assert_eq!(buffer, serialization.serialize_from(&person).unwrap());
+            let person2 = serialization.deserialize_to::<Person>(&buffer).unwrap();
+            assert_eq!(person, person2);
+            let current = name_buffer(&buffer);
+            assert_eq!(*name.get_or_insert(current), current);
+        }
+    }
+
+    #[test]
+    fn test_serialize_into_reuse() {
+        let mut registry = Registry::default().with_basic_types();
+        registry.add_type(Skill::define_enum(&registry));
+        registry.add_type(Person::define_struct(&registry));
+        let mut serialization = SerializationRegistry::default().with_basic_types();
+        assert!(serialization
+            .register_registry_reflection(&registry)
+            .is_empty());
+
+        let mut buffer = Intermediate::default();
+        let mut name = None;
+        for index in 0..100 {
+            let person = Person {
+                name: format!("Grumpy {:>3}", index),
+                age: index,
+                skill: match index % 3 {
+                    0 => Skill::Brain,
+                    1 => Skill::Muscles(index % 2 == 0),
+                    _ => Skill::Magic { power: index as _ },
+                },
+            };
+            serialization.serialize_into(&person, &mut buffer).unwrap();
+            assert_eq!(buffer, serialization.serialize_from(&person).unwrap());
+            assert_eq!(
+                serialization.deserialize_to::<Person>(&buffer).unwrap(),
+                person
+            );
+            let current = name_buffer(&buffer);
+            assert_eq!(*name.get_or_insert(current), current);
+        }
+    }
+
+    #[test]
+    fn test_registry_reflection() {
+        let mut registry = Registry::default().with_basic_types();
+        registry.add_type(Skill::define_enum(&registry));
+        registry.add_type(Person::define_struct(&registry));
+        let mut secret = Secret::define_struct(&registry);
+        secret.meta = Some(Meta::Identifier("no_serialize".to_owned()));
+        registry.add_type(secret);
+        registry.add_type(Locker::define_struct(&registry));
+        let mut serialization = SerializationRegistry::default().with_basic_types();
+        assert_eq!(
+            serialization.register_registry_reflection(&registry),
+            vec!["Locker.secret".to_owned()]
+        );
+
+        for skill in [
+            Skill::Brain,
+            Skill::Muscles(true),
+            Skill::Magic { power: 42 },
+        ] {
+            let person = Person {
+                name: "Grumpy".to_owned(),
+                age: 24,
+                skill,
+            };
+            let serialized = serialization.serialize_from(&person).unwrap();
+            assert_eq!(
+                serialization.deserialize_to::<Person>(&serialized).unwrap(),
+                person
+            );
+        }
+        assert_eq!(
+            serialization.serialize_from(&Skill::Brain).unwrap(),
+            Intermediate::UnitVariant("Brain".to_owned())
+        );
+        assert_eq!(
+            serialization
+                .serialize_from(&Skill::Magic { power: 42 })
+                .unwrap(),
+            Intermediate::StructVariant(
+                "Magic".to_owned(),
+                vec![("power".to_owned(), Intermediate::I32(42))]
+            )
+        );
+        // switching variant of existing value reinitializes its fields.
+        let mut skill = Skill::Magic { power: 1 };
+        unsafe {
+            serialization
+                .dynamic_deserialize_to(
+                    TypeHash::of::<Skill>(),
+                    &mut skill as *mut Skill as *mut u8,
+                    &serialization.serialize_from(&Skill::Muscles(true)).unwrap(),
+                )
+                .unwrap();
+        }
+        assert_eq!(skill, Skill::Muscles(true));
+
+        // registered serializers are kept and opted out types are skipped.
+        assert_eq!(
+            serialization.serialize_from(&42i32).unwrap(),
+            Intermediate::I32(42)
+        );
+        assert!(serialization.serialize_from(&Secret::default()).is_err());
+        let error = serialization
+            .serialize_from(&Locker::default())
+            .unwrap_err()
+            .to_string();
+        assert!(error.starts_with("Unregistered type at `Locker.secret`"));
+    }
+
+    #[test]
+    fn test_compiled_dispatch() {
+        let mut registry = Registry::default().with_basic_types();
+        registry.add_type(Skill::define_enum(&registry));
+        registry.add_type(Leaf::define_struct(&registry));
+        registry.add_type(Branch::define_struct(&registry));
+        registry.add_type(Tree::define_struct(&registry));
+        let mut serialization = SerializationRegistry::default().with_basic_types();
+        for query in [
+            TypeQuery::of::<Tree>(),
+            TypeQuery::of::<Branch>(),
+            TypeQuery::of::<Leaf>(),
+        ] {
+            serialization
+                .register_reflected(&registry.find_type(query).unwrap())
+                .unwrap();
+        }
+        assert!(serialization
+            .register_reflected(&registry.find_type(TypeQuery::of::<Skill>()).unwrap())
+            .is_err());
+
+        let leaf = |id: usize| Leaf {
+            id,
+            label: format!("leaf {}", id),
+        };
+        let trees = (0..1000)
+            .map(|index| Tree {
+                trunk: Branch {
+                    left: leaf(index * 4),
+                    right: leaf(index * 4 + 1),
+                    weight: index as f32 * 0.5,
+                },
+                crown: Branch {
+                    left: leaf(index * 4 + 2),
+                    right: leaf(index * 4 + 3),
+                    weight: index as f32 * 0.25,
+                },
+                name: format!("tree {}", index),
+            })
+            .collect::<Vec<_>>();
+
+        // nested fields resolved by type hash lookup.
+        let hashed = trees
+            .iter()
+            .map(|tree| serialization.serialize_from(tree).unwrap())
+            .collect::<Vec<_>>();
+        // nested fields resolved by cached serializer slots.
+        serialization.compile_dispatch();
+        let compiled = trees
+            .iter()
+            .map(|tree| serialization.serialize_from(tree).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(hashed, compiled);
+        for (tree, serialized) in trees.iter().zip(compiled.iter()) {
+            assert_eq!(
+                &serialization.deserialize_to::<Tree>(serialized).unwrap(),
+                tree
+            );
+        }
+        let Intermediate::Struct(fields) = &compiled[1] else {
+            panic!("`Tree` is not serialized as struct!");
+        };
+        let trunk = fields
+            .iter()
+            .find(|(name, _)| name == "trunk")
+            .map(|(_, value)| value)
+            .unwrap();
+        let Intermediate::Struct(fields) = trunk else {
+            panic!("`Branch` is not serialized as struct!");
+        };
+        assert!(fields
+            .iter()
+            .any(|(name, value)| name == "weight" && *value == Intermediate::F32(0.5)));
+
+        // stale cached slots fall back to lookup of re-registered serializer.
+        serialization.unregister::<Leaf>();
+        assert!(serialization.serialize_from(&trees[0]).is_err());
+        serialization
+            .register_reflected(&registry.find_type(TypeQuery::of::<Leaf>()).unwrap())
+            .unwrap();
+        assert_eq!(serialization.serialize_from(&trees[0]).unwrap(), hashed[0]);
+    }
+
+    #[test]
+    fn test_unregistered_field_path() {
+        let mut registry = Registry::default().with_basic_types();
+        registry.add_type(Skill::define_enum(&registry));
+        registry.add_type(Person::define_struct(&registry));
+        registry.add_type(Leaf::define_struct(&registry));
+        registry.add_type(Branch::define_struct(&registry));
+        registry.add_type(Tree::define_struct(&registry));
+        let mut serialization = SerializationRegistry::default().with_basic_types();
+        for query in [
+            TypeQuery::of::<Person>(),
+            TypeQuery::of::<Tree>(),
+            TypeQuery::of::<Branch>(),
+        ] {
+            serialization
+                .register_reflected(&registry.find_type(query).unwrap())
+                .unwrap();
+        }
+
+        let error = serialization
+            .serialize_from(&Person::default())
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            error,
+            format!(
+                "Unregistered type at `Person.skill` (hash {})",
+                TypeHash::of::<Skill>()
+            )
+        );
+        let error = serialization
+            .serialize_from(&Tree::default())
+            .unwrap_err()
+            .to_string();
+        assert!(error.starts_with("Unregistered type at `Tree.trunk.left`"));
+        serialization.compile_dispatch();
+        let value = Intermediate::Struct(vec![("trunk".to_owned(), Intermediate::Struct(vec![]))]);
+        let error = serialization
+            .deserialize_to::<Tree>(&value)
+            .unwrap_err()
+            .to_string();
+        assert!(error.starts_with("Unregistered type at `Tree.trunk.left`"));
+
+        serialization
+            .register_reflected(&registry.find_type(TypeQuery::of::<Leaf>()).unwrap())
+            .unwrap();
+        let error = serialization
+            .deserialize_to::<Tree>(&value)
+            .unwrap_err()
+            .to_string();
+        assert_eq!(error, "Missing field at `Tree.trunk.left`");
+    }
+
+    #[test]
+    fn test_describe_type() {
+        let mut registry = Registry::default().with_basic_types();
+        registry.add_type(Skill::define_enum(&registry));
+        registry.add_type(Person::define_struct(&registry));
+        let serialization = SerializationRegistry::default()
+            .with_basic_types()
+            .with_serde::<Person>();
+
+        let schema = serialization
+            .describe_type(TypeHash::of::<Person>(), &registry)
+            .unwrap();
+        assert_eq!(schema.type_name, "Person");
+        assert!(schema.serializable);
+        let SchemaShape::Struct(fields) = &schema.shape else {
+            panic!("`Person` is not a struct!");
+        };
+        let field = |name: &str| {
+            &fields
+                .iter()
+                .find(|field| field.name == name)
+                .unwrap_or_else(|| panic!("`{}` field is missing!", name))
+                .schema
+        };
+        assert_eq!(fields.len(), 3);
+        assert_eq!(field("name").type_hash, TypeHash::of::<String>());
+        assert_eq!(field("age").type_hash, TypeHash::of::<usize>());
+        let skill = field("skill");
+        assert!(!skill.serializable);
+        let SchemaShape::Enum(variants) = &skill.shape else {
+            panic!("`Skill` is not an enum!");
+        };
+        assert_eq!(
+            variants
+                .iter()
+                .map(|variant| (variant.name.as_str(), variant.discriminant))
+                .collect::<Vec<_>>(),
+            vec![("Brain", 0), ("Muscles", 1), ("Magic", 2)]
+        );
+        assert!(variants[0].fields.is_empty());
+        assert_eq!(variants[1].fields[0].name, "0");
+        assert_eq!(
+            variants[1].fields[0].schema.type_hash,
+            TypeHash::of::<bool>()
+        );
+        assert_eq!(variants[2].fields[0].name, "power");
+        assert_eq!(
+            variants[2].fields[0].schema.type_hash,
+            TypeHash::of::<i32>()
+        );
+        let dump = schema.to_string();
+        assert!(dump.starts_with("Person {\n"));
+        assert!(dump.contains("\n    age: usize\n"));
+        assert!(dump.contains(
+            "\n    skill: Skill (not serializable) {\n        Brain\n        Muscles {\n            0: bool\n        }\n        Magic {\n            power: i32\n        }\n    }\n"
+        ));
+
+        assert!(serialization
+            .describe_type(TypeHash::of::<Vec<u8>>(), &registry)
+            .is_none());
+    }
+
+    #[test]
+    fn test_serialize_to_writer() {
+        use crate::stream::{read_binary_item, StreamFormat};
+
+        let mut registry = Registry::default().with_basic_types();
+        registry.add_type(Skill::define_enum(&registry));
+        registry.add_type(Person::define_struct(&registry));
+        let streaming = SerializationRegistry::default().with_basic_types();
+        let mut serialization = SerializationRegistry::default().with_basic_types();
+        assert!(serialization
+            .register_registry_reflection(&registry)
+            .is_empty());
+
+        let persons = (0..1000)
+            .map(|index| Person {
+                name: format!("Grumpy \"{}\"", index),
+                age: index,
+                skill: match index % 3 {
+                    0 => Skill::Brain,
+                    1 => Skill::Muscles(index % 2 == 0),
+                    _ => Skill::Magic {
+                        power: -(index as i32),
+                    },
+                },
+            })
+            .collect::<Vec<_>>();
+
+        let mut buffer = vec![];
+        let count = streaming
+            .serialize_iter_to_writer(&persons, &registry, &mut buffer, StreamFormat::Binary)
+            .unwrap();
+        assert_eq!(count, persons.len());
+        let mut reader = buffer.as_slice();
+        for person in &persons {
+            let item = read_binary_item(&mut reader).unwrap().unwrap();
+            assert_eq!(item, serialization.serialize_from(person).unwrap());
+            let person2 = serialization.deserialize_to::<Person>(&item).unwrap();
+            assert_eq!(person, &person2);
+        }
+        assert!(read_binary_item(&mut reader).unwrap().is_none());
+
+        let mut buffer = vec![];
+        streaming
+            .serialize_iter_to_writer(&persons, &registry, &mut buffer, StreamFormat::JsonLines)
+            .unwrap();
+        let lines = String::from_utf8(buffer).unwrap();
+        assert_eq!(lines.lines().count(), persons.len());
+        for (person, line) in persons.iter().zip(lines.lines()) {
+            let expected = serialization.serialize_from(person).unwrap();
+            assert_eq!(
+                serde_json::from_str::<serde_json::Value>(line).unwrap(),
+                serde_json::to_value(&expected).unwrap()
+            );
+        }
+
+        let mut buffer = vec![];
+        assert!(streaming
+            .serialize_to_writer(&vec![1u8], &registry, &mut buffer, StreamFormat::Binary)
+            .is_err());
+    }
+
+    #[test]
+    fn test_bytes_le() {
+        use crate::stream::{from_bytes_le, to_bytes_le};
+
+        let serialization = SerializationRegistry::default()
+            .with_basic_types()
+            .with_serde::<Skill>()
+            .with_serde::<Person>();
+
+        let person = Person {
+            name: "Grumpy".to_owned(),
+            age: 0x0102_0304,
+            skill: Skill::Magic { power: -42 },
+        };
+        let serialized = serialization.serialize_from(&person).unwrap();
+        let bytes = to_bytes_le(&serialized);
+        assert_eq!(&bytes[..6], b"IBLE\x01\x00");
+
+        // buffer built by hand the way foreign big-endian writer produces it:
+        // every scalar has to be byte-swapped before it gets written.
+        let encode = |age: [u8; 8], power: [u8; 4]| {
+            let mut bytes = b"IBLE\x01\x00\x1c\x03".to_vec();
+            bytes.extend_from_slice(b"\x04name\x0f\x06Grumpy");
+            bytes.extend_from_slice(b"\x03age\x0a");
+            bytes.extend_from_slice(&age);
+            bytes.extend_from_slice(b"\x05skill\x1d\x05Magic\x01\x05power\x04");
+            bytes.extend_from_slice(&power);
+            bytes
+        };
+        let age = 0x0102_0304u64;
+        let power = -42i32;
+        let foreign = encode(
+            age.swap_bytes().to_be_bytes(),
+            power.swap_bytes().to_be_bytes(),
+        );
+        assert_eq!(foreign, bytes);
+        assert_eq!(from_bytes_le(&foreign).unwrap(), serialized);
+        assert_eq!(
+            serialization
+                .deserialize_to::<Person>(&from_bytes_le(&foreign).unwrap())
+                .unwrap(),
+            person
+        );
+        // writer which skips swapping gets its scalars read reversed.
+        let unswapped = encode(age.to_be_bytes(), power.to_be_bytes());
+        let decoded = serialization
+            .deserialize_to::<Person>(&from_bytes_le(&unswapped).unwrap())
+            .unwrap();
+        assert_eq!(decoded.age as u64, age.swap_bytes());
+        assert_eq!(
+            decoded.skill,
+            Skill::Magic {
+                power: power.swap_bytes()
+            }
+        );
+
+        let mut bytes = bytes;
+        bytes[4] = 2;
+        assert!(from_bytes_le(&bytes).is_err());
+        bytes[4] = 1;
+        bytes.push(0);
+        assert!(from_bytes_le(&bytes).is_err());
+        assert!(from_bytes_le(b"JSON").is_err());
+
+        // string claiming way more bytes than there are.
+        let mut bytes = b"IBLE\x01\x00\x0f".to_vec();
+        bytes.extend_from_slice(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f]);
+        bytes.extend_from_slice(b"Grumpy");
+        assert!(from_bytes_le(&bytes).is_err());
+        let mut bytes = b"IBLE\x01\x00".to_vec();
+        bytes.extend(std::iter::repeat_n(18u8, 100_000));
+        bytes.push(0);
+        assert!(from_bytes_le(&bytes).is_err());
+
+        // values in serde shape deserialize with reflection as well.
+        let mut registry = Registry::default().with_basic_types();
+        registry.add_type(Skill::define_enum(&registry));
+        registry.add_type(Person::define_struct(&registry));
+        let mut reflection = SerializationRegistry::default().with_basic_types();
+        assert!(reflection
+            .register_registry_reflection(&registry)
+            .is_empty());
+        let person = Person {
+            name: "Grumpy".to_owned(),
+            age: 42,
+            skill: Skill::Muscles(true),
+        };
+        let serialized = serialization.serialize_from(&person).unwrap();
+        let deserialized = from_bytes_le(&to_bytes_le(&serialized)).unwrap();
+        assert_eq!(
+            reflection.deserialize_to::<Person>(&deserialized).unwrap(),
+            person
+        );
+    }
 }
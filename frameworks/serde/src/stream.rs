@@ -0,0 +1,753 @@
+use crate::SerializationRegistry;
+use intuicio_core::{
+    registry::Registry,
+    types::{Type, TypeHandle, TypeQuery},
+};
+use intuicio_data::type_hash::TypeHash;
+use serde_intermediate::Intermediate;
+use std::{
+    error::Error,
+    io::{Read, Write},
+};
+
+const TAG_UNIT: u8 = 0;
+const TAG_BOOL: u8 = 1;
+const TAG_I8: u8 = 2;
+const TAG_I16: u8 = 3;
+const TAG_I32: u8 = 4;
+const TAG_I64: u8 = 5;
+const TAG_I128: u8 = 6;
+const TAG_U8: u8 = 7;
+const TAG_U16: u8 = 8;
+const TAG_U32: u8 = 9;
+const TAG_U64: u8 = 10;
+const TAG_U128: u8 = 11;
+const TAG_F32: u8 = 12;
+const TAG_F64: u8 = 13;
+const TAG_CHAR: u8 = 14;
+const TAG_STRING: u8 = 15;
+const TAG_BYTES: u8 = 16;
+const TAG_NONE: u8 = 17;
+const TAG_SOME: u8 = 18;
+const TAG_UNIT_STRUCT: u8 = 19;
+const TAG_UNIT_VARIANT: u8 = 20;
+const TAG_NEW_TYPE_STRUCT: u8 = 21;
+const TAG_NEW_TYPE_VARIANT: u8 = 22;
+const TAG_SEQ: u8 = 23;
+const TAG_TUPLE: u8 = 24;
+const TAG_TUPLE_STRUCT: u8 = 25;
+const TAG_TUPLE_VARIANT: u8 = 26;
+const TAG_MAP: u8 = 27;
+const TAG_STRUCT: u8 = 28;
+const TAG_STRUCT_VARIANT: u8 = 29;
+
+const BYTES_LE_MAGIC: &[u8; 4] = b"IBLE";
+const BYTES_LE_VERSION: u16 = 1;
+const MAX_NESTING_DEPTH: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamFormat {
+    /// Tagged binary encoding of intermediate values, with varint lengths.
+    /// Items are written back to back - read them with `read_binary_item`.
+    Binary,
+    /// Every item written as single line of JSON, with enums externally
+    /// tagged, same as serde does by default.
+    JsonLines,
+}
+
+/// Enum variants are streamed in the same shape as serialization registry
+/// produces for reflected enums: unit variants by name, others as struct
+/// variants (fields of tuple variants are named by their index).
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum VariantKind {
+    Unit,
+    Struct,
+}
+
+struct StreamWriter<'a, W: Write> {
+    writer: &'a mut W,
+    format: StreamFormat,
+}
+
+impl<W: Write> StreamWriter<'_, W> {
+    fn bytes(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        self.writer.write_all(bytes)
+    }
+
+    fn length(&mut self, mut value: usize) -> std::io::Result<()> {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                return self.bytes(&[byte]);
+            }
+            self.bytes(&[byte | 0x80])?;
+        }
+    }
+
+    fn text(&mut self, value: &str) -> std::io::Result<()> {
+        match self.format {
+            StreamFormat::Binary => {
+                self.length(value.len())?;
+                self.bytes(value.as_bytes())
+            }
+            StreamFormat::JsonLines => {
+                self.bytes(b"\"")?;
+                for c in value.chars() {
+                    match c {
+                        '"' => self.bytes(b"\\\"")?,
+                        '\\' => self.bytes(b"\\\\")?,
+                        '\n' => self.bytes(b"\\n")?,
+                        '\r' => self.bytes(b"\\r")?,
+                        '\t' => self.bytes(b"\\t")?,
+                        c if (c as u32) < 0x20 => write!(self.writer, "\\u{:04x}", c as u32)?,
+                        c => write!(self.writer, "{}", c)?,
+                    }
+                }
+                self.bytes(b"\"")
+            }
+        }
+    }
+
+    fn float(&mut self, value: f64) -> std::io::Result<()> {
+        if value.is_finite() {
+            write!(self.writer, "{}", value)
+        } else {
+            self.bytes(b"null")
+        }
+    }
+
+    fn leaf(&mut self, value: &Intermediate) -> std::io::Result<()> {
+        match self.format {
+            StreamFormat::Binary => self.binary_leaf(value),
+            StreamFormat::JsonLines => self.json_leaf(value),
+        }
+    }
+
+    fn binary_leaf(&mut self, value: &Intermediate) -> std::io::Result<()> {
+        match value {
+            Intermediate::Unit => self.bytes(&[TAG_UNIT]),
+            Intermediate::Bool(value) => self.bytes(&[TAG_BOOL, *value as u8]),
+            Intermediate::I8(value) => {
+                self.bytes(&[TAG_I8])?;
+                self.bytes(&value.to_le_bytes())
+            }
+            Intermediate::I16(value) => {
+                self.bytes(&[TAG_I16])?;
+                self.bytes(&value.to_le_bytes())
+            }
+            Intermediate::I32(value) => {
+                self.bytes(&[TAG_I32])?;
+                self.bytes(&value.to_le_bytes())
+            }
+            Intermediate::I64(value) => {
+                self.bytes(&[TAG_I64])?;
+                self.bytes(&value.to_le_bytes())
+            }
+            Intermediate::I128(value) => {
+                self.bytes(&[TAG_I128])?;
+                self.bytes(&value.to_le_bytes())
+            }
+            Intermediate::U8(value) => self.bytes(&[TAG_U8, *value]),
+            Intermediate::U16(value) => {
+                self.bytes(&[TAG_U16])?;
+                self.bytes(&value.to_le_bytes())
+            }
+            Intermediate::U32(value) => {
+                self.bytes(&[TAG_U32])?;
+                self.bytes(&value.to_le_bytes())
+            }
+            Intermediate::U64(value) => {
+                self.bytes(&[TAG_U64])?;
+                self.bytes(&value.to_le_bytes())
+            }
+            Intermediate::U128(value) => {
+                self.bytes(&[TAG_U128])?;
+                self.bytes(&value.to_le_bytes())
+            }
+            Intermediate::F32(value) => {
+                self.bytes(&[TAG_F32])?;
+                self.bytes(&value.to_le_bytes())
+            }
+            Intermediate::F64(value) => {
+                self.bytes(&[TAG_F64])?;
+                self.bytes(&value.to_le_bytes())
+            }
+            Intermediate::Char(value) => {
+                self.bytes(&[TAG_CHAR])?;
+                self.bytes(&(*value as u32).to_le_bytes())
+            }
+            Intermediate::String(value) => {
+                self.bytes(&[TAG_STRING])?;
+                self.text(value)
+            }
+            Intermediate::Bytes(value) => {
+                self.bytes(&[TAG_BYTES])?;
+                self.length(value.len())?;
+                self.bytes(value)
+            }
+            Intermediate::Option(None) => self.bytes(&[TAG_NONE]),
+            Intermediate::Option(Some(value)) => {
+                self.bytes(&[TAG_SOME])?;
+                self.binary_leaf(value)
+            }
+            Intermediate::UnitStruct => self.bytes(&[TAG_UNIT_STRUCT]),
+            Intermediate::UnitVariant(name) => {
+                self.bytes(&[TAG_UNIT_VARIANT])?;
+                self.text(name)
+            }
+            Intermediate::NewTypeStruct(value) => {
+                self.bytes(&[TAG_NEW_TYPE_STRUCT])?;
+                self.binary_leaf(value)
+            }
+            Intermediate::NewTypeVariant(name, value) => {
+                self.bytes(&[TAG_NEW_TYPE_VARIANT])?;
+                self.text(name)?;
+                self.binary_leaf(value)
+            }
+            Intermediate::Seq(items) => self.binary_items(TAG_SEQ, items),
+            Intermediate::Tuple(items) => self.binary_items(TAG_TUPLE, items),
+            Intermediate::TupleStruct(items) => self.binary_items(TAG_TUPLE_STRUCT, items),
+            Intermediate::TupleVariant(name, items) => {
+                self.bytes(&[TAG_TUPLE_VARIANT])?;
+                self.text(name)?;
+                self.length(items.len())?;
+                for item in items {
+                    self.binary_leaf(item)?;
+                }
+                Ok(())
+            }
+            Intermediate::Map(items) => {
+                self.bytes(&[TAG_MAP])?;
+                self.length(items.len())?;
+                for (key, value) in items {
+                    self.binary_leaf(key)?;
+                    self.binary_leaf(value)?;
+                }
+                Ok(())
+            }
+            Intermediate::Struct(fields) => {
+                self.bytes(&[TAG_STRUCT])?;
+                self.binary_fields(fields)
+            }
+            Intermediate::StructVariant(name, fields) => {
+                self.bytes(&[TAG_STRUCT_VARIANT])?;
+                self.text(name)?;
+                self.binary_fields(fields)
+            }
+        }
+    }
+
+    fn binary_items(&mut self, tag: u8, items: &[Intermediate]) -> std::io::Result<()> {
+        self.bytes(&[tag])?;
+        self.length(items.len())?;
+        for item in items {
+            self.binary_leaf(item)?;
+        }
+        Ok(())
+    }
+
+    fn binary_fields(&mut self, fields: &[(String, Intermediate)]) -> std::io::Result<()> {
+        self.length(fields.len())?;
+        for (name, value) in fields {
+            self.text(name)?;
+            self.binary_leaf(value)?;
+        }
+        Ok(())
+    }
+
+    fn json_leaf(&mut self, value: &Intermediate) -> std::io::Result<()> {
+        match value {
+            Intermediate::Unit | Intermediate::UnitStruct | Intermediate::Option(None) => {
+                self.bytes(b"null")
+            }
+            Intermediate::Bool(value) => write!(self.writer, "{}", value),
+            Intermediate::I8(value) => write!(self.writer, "{}", value),
+            Intermediate::I16(value) => write!(self.writer, "{}", value),
+            Intermediate::I32(value) => write!(self.writer, "{}", value),
+            Intermediate::I64(value) => write!(self.writer, "{}", value),
+            Intermediate::I128(value) => write!(self.writer, "{}", value),
+            Intermediate::U8(value) => write!(self.writer, "{}", value),
+            Intermediate::U16(value) => write!(self.writer, "{}", value),
+            Intermediate::U32(value) => write!(self.writer, "{}", value),
+            Intermediate::U64(value) => write!(self.writer, "{}", value),
+            Intermediate::U128(value) => write!(self.writer, "{}", value),
+            Intermediate::F32(value) => self.float(*value as f64),
+            Intermediate::F64(value) => self.float(*value),
+            Intermediate::Char(value) => self.text(&value.to_string()),
+            Intermediate::String(value) | Intermediate::UnitVariant(value) => self.text(value),
+            Intermediate::Bytes(value) => {
+                self.bytes(b"[")?;
+                for (index, value) in value.iter().enumerate() {
+                    if index > 0 {
+                        self.bytes(b",")?;
+                    }
+                    write!(self.writer, "{}", value)?;
+                }
+                self.bytes(b"]")
+            }
+            Intermediate::Option(Some(value)) | Intermediate::NewTypeStruct(value) => {
+                self.json_leaf(value)
+            }
+            Intermediate::NewTypeVariant(name, value) => {
+                self.bytes(b"{")?;
+                self.text(name)?;
+                self.bytes(b":")?;
+                self.json_leaf(value)?;
+                self.bytes(b"}")
+            }
+            Intermediate::Seq(items)
+            | Intermediate::Tuple(items)
+            | Intermediate::TupleStruct(items) => self.json_items(items),
+            Intermediate::TupleVariant(name, items) => {
+                self.bytes(b"{")?;
+                self.text(name)?;
+                self.bytes(b":")?;
+                self.json_items(items)?;
+                self.bytes(b"}")
+            }
+            Intermediate::Map(items) => {
+                self.bytes(b"{")?;
+                for (index, (key, value)) in items.iter().enumerate() {
+                    if index > 0 {
+                        self.bytes(b",")?;
+                    }
+                    match key {
+                        Intermediate::String(key) => self.text(key)?,
+                        key => {
+                            let mut buffer = vec![];
+                            StreamWriter {
+                                writer: &mut buffer,
+                                format: StreamFormat::JsonLines,
+                            }
+                            .json_leaf(key)?;
+                            self.text(&String::from_utf8_lossy(&buffer))?;
+                        }
+                    }
+                    self.bytes(b":")?;
+                    self.json_leaf(value)?;
+                }
+                self.bytes(b"}")
+            }
+            Intermediate::Struct(fields) => self.json_fields(fields),
+            Intermediate::StructVariant(name, fields) => {
+                self.bytes(b"{")?;
+                self.text(name)?;
+                self.bytes(b":")?;
+                self.json_fields(fields)?;
+                self.bytes(b"}")
+            }
+        }
+    }
+
+    fn json_items(&mut self, items: &[Intermediate]) -> std::io::Result<()> {
+        self.bytes(b"[")?;
+        for (index, item) in items.iter().enumerate() {
+            if index > 0 {
+                self.bytes(b",")?;
+            }
+            self.json_leaf(item)?;
+        }
+        self.bytes(b"]")
+    }
+
+    fn json_fields(&mut self, fields: &[(String, Intermediate)]) -> std::io::Result<()> {
+        self.bytes(b"{")?;
+        for (index, (name, value)) in fields.iter().enumerate() {
+            if index > 0 {
+                self.bytes(b",")?;
+            }
+            self.text(name)?;
+            self.bytes(b":")?;
+            self.json_leaf(value)?;
+        }
+        self.bytes(b"}")
+    }
+
+    fn begin_struct(&mut self, count: usize) -> std::io::Result<()> {
+        match self.format {
+            StreamFormat::Binary => {
+                self.bytes(&[TAG_STRUCT])?;
+                self.length(count)
+            }
+            StreamFormat::JsonLines => self.bytes(b"{"),
+        }
+    }
+
+    fn begin_field(&mut self, index: usize, name: &str) -> std::io::Result<()> {
+        if self.format == StreamFormat::JsonLines && index > 0 {
+            self.bytes(b",")?;
+        }
+        self.text(name)?;
+        if self.format == StreamFormat::JsonLines {
+            self.bytes(b":")?;
+        }
+        Ok(())
+    }
+
+    fn end_struct(&mut self) -> std::io::Result<()> {
+        match self.format {
+            StreamFormat::Binary => Ok(()),
+            StreamFormat::JsonLines => self.bytes(b"}"),
+        }
+    }
+
+    fn begin_variant(
+        &mut self,
+        kind: VariantKind,
+        name: &str,
+        count: usize,
+    ) -> std::io::Result<()> {
+        match self.format {
+            StreamFormat::Binary => match kind {
+                VariantKind::Unit => {
+                    self.bytes(&[TAG_UNIT_VARIANT])?;
+                    self.text(name)
+                }
+                VariantKind::Struct => {
+                    self.bytes(&[TAG_STRUCT_VARIANT])?;
+                    self.text(name)?;
+                    self.length(count)
+                }
+            },
+            StreamFormat::JsonLines => match kind {
+                VariantKind::Unit => self.text(name),
+                VariantKind::Struct => {
+                    self.bytes(b"{")?;
+                    self.text(name)?;
+                    self.bytes(b":{")
+                }
+            },
+        }
+    }
+
+    fn end_variant(&mut self, kind: VariantKind) -> std::io::Result<()> {
+        match (self.format, kind) {
+            (StreamFormat::Binary, _) | (StreamFormat::JsonLines, VariantKind::Unit) => Ok(()),
+            (StreamFormat::JsonLines, VariantKind::Struct) => self.bytes(b"}}"),
+        }
+    }
+
+    fn end_item(&mut self) -> std::io::Result<()> {
+        match self.format {
+            StreamFormat::Binary => Ok(()),
+            StreamFormat::JsonLines => self.bytes(b"\n"),
+        }
+    }
+}
+
+impl SerializationRegistry {
+    /// Streams serialized data directly into writer, without building whole
+    /// intermediate tree first. Types with registered serializer are written
+    /// as whole values, other types are walked through their reflection found
+    /// in `registry`, emitting fields as they go.
+    ///
+    /// Walk follows reflected fields by value: shared data gets written once
+    /// per owner and since reflected fields cannot point back at their owner,
+    /// walk cannot cycle. Pointer-like types have to register serializer that
+    /// decides how to represent them (for example as ids).
+    pub fn serialize_to_writer<T, W: Write>(
+        &self,
+        data: &T,
+        registry: &Registry,
+        writer: &mut W,
+        format: StreamFormat,
+    ) -> Result<(), Box<dyn Error>> {
+        unsafe {
+            self.dynamic_serialize_to_writer(
+                TypeHash::of::<T>(),
+                data as *const T as *const u8,
+                registry,
+                writer,
+                format,
+            )
+        }
+    }
+
+    /// Streams every item as separate top-level value. Returns number of
+    /// written items.
+    pub fn serialize_iter_to_writer<'a, T: 'a, W: Write>(
+        &self,
+        items: impl IntoIterator<Item = &'a T>,
+        registry: &Registry,
+        writer: &mut W,
+        format: StreamFormat,
+    ) -> Result<usize, Box<dyn Error>> {
+        let mut count = 0;
+        for item in items {
+            self.serialize_to_writer(item, registry, writer, format)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// # Safety
+    pub unsafe fn dynamic_serialize_to_writer<W: Write>(
+        &self,
+        type_hash: TypeHash,
+        data: *const u8,
+        registry: &Registry,
+        writer: &mut W,
+        format: StreamFormat,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut stream = StreamWriter { writer, format };
+        if let Some(slot) = self.find_slot(type_hash) {
+            stream.leaf(&self.serialize_slot(slot, data, &mut vec![])?)?;
+        } else {
+            let handle = registry
+                .find_type(TypeQuery {
+                    type_hash: Some(type_hash),
+                    ..Default::default()
+                })
+                .ok_or("Type not existent in serialization registry nor in registry")?;
+            self.stream_type(&handle, data, &mut stream)?;
+        }
+        stream.end_item()?;
+        Ok(())
+    }
+
+    unsafe fn stream_type<W: Write>(
+        &self,
+        handle: &TypeHandle,
+        data: *const u8,
+        stream: &mut StreamWriter<W>,
+    ) -> Result<(), Box<dyn Error>> {
+        if let Some(slot) = self.find_slot(handle.type_hash()) {
+            stream.leaf(&self.serialize_slot(slot, data, &mut vec![])?)?;
+            return Ok(());
+        }
+        match &**handle {
+            Type::Struct(type_) => {
+                if type_.fields().is_empty() {
+                    return Err(format!(
+                        "Type `{}` has neither serializer nor reflected fields",
+                        type_.name
+                    )
+                    .into());
+                }
+                stream.begin_struct(type_.fields().len())?;
+                for (index, field) in type_.fields().iter().enumerate() {
+                    stream.begin_field(index, &field.name)?;
+                    self.stream_type(
+                        field.type_handle(),
+                        data.add(field.address_offset()),
+                        stream,
+                    )?;
+                }
+                stream.end_struct()?;
+            }
+            Type::Enum(type_) => {
+                let discriminant = data.read();
+                let variant = type_
+                    .find_variant_by_discriminant(discriminant)
+                    .ok_or_else(|| {
+                        format!(
+                            "Enum `{}` has no variant with discriminant: {}",
+                            type_.name, discriminant
+                        )
+                    })?;
+                let kind = if variant.fields.is_empty() {
+                    VariantKind::Unit
+                } else {
+                    VariantKind::Struct
+                };
+                stream.begin_variant(kind, &variant.name, variant.fields.len())?;
+                for (index, field) in variant.fields.iter().enumerate() {
+                    stream.begin_field(index, &field.name)?;
+                    self.stream_type(
+                        field.type_handle(),
+                        data.add(field.address_offset()),
+                        stream,
+                    )?;
+                }
+                stream.end_variant(kind)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Encodes value into byte order independent binary format, so files written
+/// on little-endian and big-endian machines are identical.
+///
+/// Layout:
+/// - 4 bytes of magic: `IBLE`.
+/// - format version as little-endian `u16`.
+/// - single value, encoded the same way as `StreamFormat::Binary` item: tag
+///   byte followed by payload. Multi-byte scalars (integers, floats and chars
+///   as `u32`) are little-endian, lengths of texts, bytes and collections are
+///   LEB128 varints, so neither depends on host byte order.
+pub fn to_bytes_le(value: &Intermediate) -> Vec<u8> {
+    let mut result = Vec::with_capacity(64);
+    result.extend_from_slice(BYTES_LE_MAGIC);
+    result.extend_from_slice(&BYTES_LE_VERSION.to_le_bytes());
+    StreamWriter {
+        writer: &mut result,
+        format: StreamFormat::Binary,
+    }
+    .binary_leaf(value)
+    .expect("Writing into vector cannot fail");
+    result
+}
+
+/// Decodes value written with `to_bytes_le`. Lengths and nesting depth are
+/// validated against input, so malformed data gives error instead of huge
+/// allocations or stack overflow.
+pub fn from_bytes_le(mut bytes: &[u8]) -> Result<Intermediate, Box<dyn Error>> {
+    let reader = &mut bytes;
+    if &read_array::<4, _>(reader)? != BYTES_LE_MAGIC {
+        return Err("Missing little-endian binary format header".into());
+    }
+    let version = u16::from_le_bytes(read_array(reader)?);
+    if version != BYTES_LE_VERSION {
+        return Err(format!("Unsupported binary format version: {}", version).into());
+    }
+    let result = read_binary_nested(reader, 0)?;
+    if !reader.is_empty() {
+        return Err("Unexpected bytes after value".into());
+    }
+    Ok(result)
+}
+
+/// Reads single item written in `StreamFormat::Binary`. Returns `None` when
+/// reader has no more items.
+pub fn read_binary_item<R: Read>(reader: &mut R) -> Result<Option<Intermediate>, Box<dyn Error>> {
+    let mut tag = [0u8];
+    if reader.read(&mut tag)? == 0 {
+        return Ok(None);
+    }
+    read_binary_value(tag[0], reader, 0).map(Some)
+}
+
+fn read_array<const N: usize, R: Read>(reader: &mut R) -> Result<[u8; N], Box<dyn Error>> {
+    let mut result = [0u8; N];
+    reader.read_exact(&mut result)?;
+    Ok(result)
+}
+
+fn read_length<R: Read>(reader: &mut R) -> Result<usize, Box<dyn Error>> {
+    let mut result = 0usize;
+    let mut shift = 0;
+    loop {
+        let [byte] = read_array::<1, _>(reader)?;
+        result |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(result);
+        }
+        shift += 7;
+        if shift >= usize::BITS {
+            return Err("Length overflow".into());
+        }
+    }
+}
+
+/// Length comes from input, so buffer grows with bytes actually read instead
+/// of being allocated upfront.
+fn read_bytes<R: Read>(reader: &mut R) -> Result<Vec<u8>, Box<dyn Error>> {
+    let length = read_length(reader)?;
+    let mut result = vec![];
+    reader.take(length as u64).read_to_end(&mut result)?;
+    if result.len() != length {
+        return Err("Unexpected end of binary stream".into());
+    }
+    Ok(result)
+}
+
+fn read_text<R: Read>(reader: &mut R) -> Result<String, Box<dyn Error>> {
+    Ok(String::from_utf8(read_bytes(reader)?)?)
+}
+
+fn read_binary_nested<R: Read>(
+    reader: &mut R,
+    depth: usize,
+) -> Result<Intermediate, Box<dyn Error>> {
+    if depth >= MAX_NESTING_DEPTH {
+        return Err(format!(
+            "Binary stream nesting exceeds depth limit: {}",
+            MAX_NESTING_DEPTH
+        )
+        .into());
+    }
+    let [tag] = read_array::<1, _>(reader)?;
+    read_binary_value(tag, reader, depth + 1)
+}
+
+fn read_binary_items<R: Read>(
+    reader: &mut R,
+    depth: usize,
+) -> Result<Vec<Intermediate>, Box<dyn Error>> {
+    (0..read_length(reader)?)
+        .map(|_| read_binary_nested(reader, depth))
+        .collect()
+}
+
+fn read_binary_fields<R: Read>(
+    reader: &mut R,
+    depth: usize,
+) -> Result<Vec<(String, Intermediate)>, Box<dyn Error>> {
+    (0..read_length(reader)?)
+        .map(|_| Ok((read_text(reader)?, read_binary_nested(reader, depth)?)))
+        .collect()
+}
+
+fn read_binary_value<R: Read>(
+    tag: u8,
+    reader: &mut R,
+    depth: usize,
+) -> Result<Intermediate, Box<dyn Error>> {
+    Ok(match tag {
+        TAG_UNIT => Intermediate::Unit,
+        TAG_BOOL => Intermediate::Bool(read_array::<1, _>(reader)?[0] != 0),
+        TAG_I8 => Intermediate::I8(i8::from_le_bytes(read_array(reader)?)),
+        TAG_I16 => Intermediate::I16(i16::from_le_bytes(read_array(reader)?)),
+        TAG_I32 => Intermediate::I32(i32::from_le_bytes(read_array(reader)?)),
+        TAG_I64 => Intermediate::I64(i64::from_le_bytes(read_array(reader)?)),
+        TAG_I128 => Intermediate::I128(i128::from_le_bytes(read_array(reader)?)),
+        TAG_U8 => Intermediate::U8(read_array::<1, _>(reader)?[0]),
+        TAG_U16 => Intermediate::U16(u16::from_le_bytes(read_array(reader)?)),
+        TAG_U32 => Intermediate::U32(u32::from_le_bytes(read_array(reader)?)),
+        TAG_U64 => Intermediate::U64(u64::from_le_bytes(read_array(reader)?)),
+        TAG_U128 => Intermediate::U128(u128::from_le_bytes(read_array(reader)?)),
+        TAG_F32 => Intermediate::F32(f32::from_le_bytes(read_array(reader)?)),
+        TAG_F64 => Intermediate::F64(f64::from_le_bytes(read_array(reader)?)),
+        TAG_CHAR => Intermediate::Char(
+            char::from_u32(u32::from_le_bytes(read_array(reader)?)).ok_or("Invalid char")?,
+        ),
+        TAG_STRING => Intermediate::String(read_text(reader)?),
+        TAG_BYTES => Intermediate::Bytes(read_bytes(reader)?),
+        TAG_NONE => Intermediate::Option(None),
+        TAG_SOME => Intermediate::Option(Some(Box::new(read_binary_nested(reader, depth)?))),
+        TAG_UNIT_STRUCT => Intermediate::UnitStruct,
+        TAG_UNIT_VARIANT => Intermediate::UnitVariant(read_text(reader)?),
+        TAG_NEW_TYPE_STRUCT => {
+            Intermediate::NewTypeStruct(Box::new(read_binary_nested(reader, depth)?))
+        }
+        TAG_NEW_TYPE_VARIANT => {
+            let name = read_text(reader)?;
+            Intermediate::NewTypeVariant(name, Box::new(read_binary_nested(reader, depth)?))
+        }
+        TAG_SEQ => Intermediate::Seq(read_binary_items(reader, depth)?),
+        TAG_TUPLE => Intermediate::Tuple(read_binary_items(reader, depth)?),
+        TAG_TUPLE_STRUCT => Intermediate::TupleStruct(read_binary_items(reader, depth)?),
+        TAG_TUPLE_VARIANT => {
+            let name = read_text(reader)?;
+            Intermediate::TupleVariant(name, read_binary_items(reader, depth)?)
+        }
+        TAG_MAP => Intermediate::Map(
+            (0..read_length(reader)?)
+                .map(|_| {
+                    Ok((
+                        read_binary_nested(reader, depth)?,
+                        read_binary_nested(reader, depth)?,
+                    ))
+                })
+                .collect::<Result<_, Box<dyn Error>>>()?,
+        ),
+        TAG_STRUCT => Intermediate::Struct(read_binary_fields(reader, depth)?),
+        TAG_STRUCT_VARIANT => {
+            let name = read_text(reader)?;
+            Intermediate::StructVariant(name, read_binary_fields(reader, depth)?)
+        }
+        tag => return Err(format!("Unknown binary stream tag: {}", tag).into()),
+    })
+}
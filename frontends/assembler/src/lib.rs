@@ -19,7 +19,7 @@ pub type AsmFile = SerdeFile;
 pub type AsmPackage = SerdePackage;
 pub type AsmNodeTypeInfo = SerdeNodeTypeInfo;
 pub type AsmNodes = SerdeNodes;
-pub type CompileAsmNodeGraphVisitor = CompileSerdeNodeGraphVisitor;
+pub type CompileAsmNodeGraphVisitor<'a> = CompileSerdeNodeGraphVisitor<'a>;
 
 pub fn frontend_assembly_version() -> IntuicioVersion {
     crate_version!()
@@ -53,6 +53,7 @@ fn parse_module(pair: Pair<Rule>) -> AsmModule {
     let mut pairs = pair.into_inner();
     let mut result = AsmModule {
         name: parse_identifier(pairs.next().unwrap()),
+        imports: vec![],
         structs: vec![],
         enums: vec![],
         functions: vec![],
@@ -265,6 +266,7 @@ fn parse_call_function(pair: Pair<Rule>) -> AsmOperation {
         module_name,
         type_name,
         visibility,
+        arguments: None,
     }
 }
 
@@ -301,7 +303,11 @@ fn parse_make_register(pair: Pair<Rule>) -> AsmOperation {
             rule => unreachable!("{:?}", rule),
         }
     }
-    AsmOperation::MakeRegister { name, module_name }
+    AsmOperation::MakeRegister {
+        name,
+        module_name,
+        register_name: None,
+    }
 }
 
 macro_rules! parse_literal {
@@ -397,6 +403,7 @@ fn parse_function_parameter(pair: Pair<Rule>) -> AsmFunctionParameter {
         name: parse_identifier(pairs.next().unwrap()),
         module_name: None,
         type_name: Default::default(),
+        default: None,
     };
     for pair in pairs {
         match pair.as_rule() {
@@ -1,23 +1,29 @@
 use intuicio_core::{
     context::Context,
     crate_version,
-    function::FunctionQuery,
+    function::{FunctionHandle, FunctionQuery},
+    literal_from_stack,
     meta::Meta,
     registry::Registry,
     script::{
+        CircularDependencyError, CustomScriptExpression, ScriptConstantExpression,
         ScriptContentProvider, ScriptEnum, ScriptEnumVariant, ScriptExpression, ScriptFunction,
         ScriptFunctionParameter, ScriptFunctionSignature, ScriptHandle, ScriptModule,
-        ScriptOperation, ScriptPackage, ScriptStruct, ScriptStructField,
+        ScriptModuleImport, ScriptOperation, ScriptPackage, ScriptSpan, ScriptStruct,
+        ScriptStructField,
     },
-    types::TypeQuery,
+    types::{TypeHandle, TypeQuery},
     IntuicioVersion, Visibility,
 };
 use intuicio_nodes::nodes::{
-    Node, NodeDefinition, NodeGraphVisitor, NodePin, NodeSuggestion, NodeTypeInfo, PropertyValue,
-    ResponseSuggestionNode,
+    Node, NodeDefinition, NodeGraph, NodeGraphVisitor, NodeId, NodePin, NodeSuggestion,
+    NodeTypeInfo, PropertyValue, ResponseSuggestionNode,
 };
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, error::Error};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+};
 
 pub type SerdeScript = Vec<SerdeOperation>;
 
@@ -45,6 +51,7 @@ pub enum SerdeLiteral {
     F64(f64),
     Char(char),
     String(String),
+    Bytes(Vec<u8>),
 }
 
 impl SerdeLiteral {
@@ -68,18 +75,54 @@ impl SerdeLiteral {
             Self::F64(value) => context.stack().push(*value),
             Self::Char(value) => context.stack().push(*value),
             Self::String(value) => context.stack().push(value.to_owned()),
+            Self::Bytes(value) => context.stack().push(value.to_owned()),
         };
     }
+
+    fn type_query<'a>(&self) -> TypeQuery<'a> {
+        match self {
+            Self::Unit => TypeQuery::of::<()>(),
+            Self::Bool(_) => TypeQuery::of::<bool>(),
+            Self::I8(_) => TypeQuery::of::<i8>(),
+            Self::I16(_) => TypeQuery::of::<i16>(),
+            Self::I32(_) => TypeQuery::of::<i32>(),
+            Self::I64(_) => TypeQuery::of::<i64>(),
+            Self::I128(_) => TypeQuery::of::<i128>(),
+            Self::Isize(_) => TypeQuery::of::<isize>(),
+            Self::U8(_) => TypeQuery::of::<u8>(),
+            Self::U16(_) => TypeQuery::of::<u16>(),
+            Self::U32(_) => TypeQuery::of::<u32>(),
+            Self::U64(_) => TypeQuery::of::<u64>(),
+            Self::U128(_) => TypeQuery::of::<u128>(),
+            Self::Usize(_) => TypeQuery::of::<usize>(),
+            Self::F32(_) => TypeQuery::of::<f32>(),
+            Self::F64(_) => TypeQuery::of::<f64>(),
+            Self::Char(_) => TypeQuery::of::<char>(),
+            Self::String(_) => TypeQuery::of::<String>(),
+            Self::Bytes(_) => TypeQuery::of::<Vec<u8>>(),
+        }
+    }
+
+    fn from_stack(context: &mut Context) -> Option<Self> {
+        literal_from_stack!(SerdeLiteral, context)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum SerdeExpression {
     Literal(SerdeLiteral),
     StackDrop,
+    /// Prints value on top of stack with debug printer of context, leaving
+    /// stack unchanged.
+    DebugPrint {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        label: Option<String>,
+    },
+    Custom(CustomScriptExpression),
 }
 
 impl ScriptExpression for SerdeExpression {
-    fn evaluate(&self, context: &mut Context, _: &Registry) {
+    fn evaluate(&self, context: &mut Context, registry: &Registry) {
         match self {
             Self::Literal(literal) => {
                 literal.evaluate(context);
@@ -87,10 +130,26 @@ impl ScriptExpression for SerdeExpression {
             Self::StackDrop => {
                 context.stack().drop();
             }
+            Self::DebugPrint { label } => {
+                context.debug_print(label.as_deref(), registry);
+            }
+            Self::Custom(expression) => {
+                expression.evaluate(context, registry);
+            }
         }
     }
 }
 
+impl ScriptConstantExpression for SerdeExpression {
+    fn is_constant(&self) -> bool {
+        matches!(self, Self::Literal(_))
+    }
+
+    fn constant_from_stack(context: &mut Context, _: &Registry) -> Option<Self> {
+        SerdeLiteral::from_stack(context).map(Self::Literal)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum SerdeOperation {
     Expression(SerdeExpression),
@@ -98,6 +157,9 @@ pub enum SerdeOperation {
         name: String,
         #[serde(default, skip_serializing_if = "Option::is_none")]
         module_name: Option<String>,
+        /// Name used by named register operations to refer to this register.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        register_name: Option<String>,
     },
     DropRegister {
         index: usize,
@@ -108,6 +170,30 @@ pub enum SerdeOperation {
     PopToRegister {
         index: usize,
     },
+    DropRegisterNamed {
+        name: String,
+    },
+    PushFromRegisterNamed {
+        name: String,
+    },
+    PopToRegisterNamed {
+        name: String,
+    },
+    /// Binds value pushed by `value` script to named register, making that
+    /// register first if name is not bound yet in this scope. Register type
+    /// is inferred from `value` ending with literal, unless `type_name` is set.
+    Let {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        type_name: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        module_name: Option<String>,
+        value: SerdeScript,
+    },
+    /// Pushes value of bound register, moving it out of that register.
+    Get {
+        name: String,
+    },
     CallFunction {
         name: String,
         #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -116,6 +202,10 @@ pub enum SerdeOperation {
         type_name: Option<String>,
         #[serde(default, skip_serializing_if = "Option::is_none")]
         visibility: Option<Visibility>,
+        /// Number of arguments pushed for this call. If package function has
+        /// more inputs, missing trailing ones get their default values.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        arguments: Option<usize>,
     },
     BranchScope {
         script_success: SerdeScript,
@@ -129,69 +219,293 @@ pub enum SerdeOperation {
         script: SerdeScript,
     },
     PopScope,
+    /// Marks following operations as compiled from given source location,
+    /// for tools generating scripts out of other sources.
+    Span(ScriptSpan),
 }
 
-fn build_script(script: &SerdeScript) -> ScriptHandle<'static, SerdeExpression> {
-    ScriptHandle::new(
-        script
-            .iter()
-            .map(|operation| match operation {
-                SerdeOperation::Expression(expression) => ScriptOperation::Expression {
-                    expression: expression.to_owned(),
-                },
-                SerdeOperation::MakeRegister { name, module_name } => {
-                    ScriptOperation::DefineRegister {
-                        query: TypeQuery {
-                            name: Some(name.to_owned().into()),
-                            module_name: module_name.as_ref().map(|name| name.to_owned().into()),
-                            ..Default::default()
-                        },
-                    }
-                }
-                SerdeOperation::DropRegister { index } => {
-                    ScriptOperation::DropRegister { index: *index }
-                }
-                SerdeOperation::PushFromRegister { index } => {
-                    ScriptOperation::PushFromRegister { index: *index }
+/// Maps register names to indices of registers made so far in given scope.
+/// Branch and loop scopes see registers of their parent scope, while pushed
+/// scope starts with no registers, same as registers barrier does at runtime.
+#[derive(Default, Clone)]
+struct RegisterNames {
+    indices: HashMap<String, usize>,
+    count: usize,
+}
+
+impl RegisterNames {
+    fn make(&mut self, name: Option<&str>) {
+        if let Some(name) = name {
+            if self.indices.insert(name.to_owned(), self.count).is_some() {
+                panic!("Duplicate register name: `{}`", name);
+            }
+        }
+        self.count += 1;
+    }
+
+    fn find(&self, name: &str) -> Option<usize> {
+        self.indices.get(name).copied()
+    }
+
+    fn index(&self, name: &str) -> usize {
+        self.find(name)
+            .unwrap_or_else(|| panic!("Unknown register name: `{}`", name))
+    }
+}
+
+/// Inputs of package functions by module, type and function name.
+type FunctionInputs<'a> =
+    HashMap<(&'a str, Option<&'a str>, &'a str), &'a [SerdeFunctionParameter]>;
+
+/// Functions visible to calls of compiled script, used to fill in default
+/// arguments.
+struct CallDefaults<'a> {
+    functions: &'a FunctionInputs<'a>,
+    module_name: &'a str,
+}
+
+impl CallDefaults<'_> {
+    fn find(
+        &self,
+        name: &str,
+        module_name: Option<&str>,
+        type_name: Option<&str>,
+    ) -> Option<&[SerdeFunctionParameter]> {
+        self.functions
+            .get(&(module_name.unwrap_or(self.module_name), type_name, name))
+            .copied()
+    }
+}
+
+fn collect_function_inputs<'a>(
+    modules: impl IntoIterator<Item = &'a SerdeModule>,
+) -> FunctionInputs<'a> {
+    modules
+        .into_iter()
+        .flat_map(|module| {
+            module.functions.iter().map(|function| {
+                (
+                    (
+                        module.name.as_str(),
+                        function.type_name.as_deref(),
+                        function.name.as_str(),
+                    ),
+                    function.inputs.as_slice(),
+                )
+            })
+        })
+        .collect()
+}
+
+fn build_script_with(
+    script: &SerdeScript,
+    defaults: &CallDefaults,
+) -> ScriptHandle<'static, SerdeExpression> {
+    build_script_scoped(script, &mut RegisterNames::default(), defaults)
+}
+
+fn build_script_scoped(
+    script: &SerdeScript,
+    registers: &mut RegisterNames,
+    defaults: &CallDefaults,
+) -> ScriptHandle<'static, SerdeExpression> {
+    let mut result = Vec::with_capacity(script.len());
+    compile_script(script, registers, defaults, &mut result);
+    ScriptHandle::new(result)
+}
+
+/// Supplied arguments are moved to temporary registers of frame scope, so
+/// defaults of missing trailing arguments can be pushed below them.
+fn compile_call_with_defaults(
+    query: FunctionQuery<'static>,
+    inputs: &[SerdeFunctionParameter],
+    supplied: usize,
+    registers: &RegisterNames,
+    defaults: &CallDefaults,
+    result: &mut Vec<ScriptOperation<'static, SerdeExpression>>,
+) {
+    let mut registers = registers.clone();
+    let base = registers.count;
+    let mut operations = vec![];
+    for input in &inputs[..supplied] {
+        operations.push(ScriptOperation::DefineRegister {
+            query: input.compile().type_query,
+            immutable: false,
+        });
+        operations.push(ScriptOperation::PopToRegister {
+            index: registers.count,
+        });
+        registers.make(None);
+    }
+    for input in inputs[supplied..].iter().rev() {
+        let script = input.default.as_ref().unwrap_or_else(|| {
+            panic!(
+                "Missing argument for `{}` parameter without default value",
+                input.name
+            )
+        });
+        compile_script(script, &mut registers, defaults, &mut operations);
+    }
+    for index in (base..base + supplied).rev() {
+        operations.push(ScriptOperation::PushFromRegister { index });
+    }
+    operations.push(ScriptOperation::CallFunction { query });
+    result.push(ScriptOperation::PushScope {
+        scope: ScriptHandle::new(operations),
+        frame: true,
+    });
+}
+
+fn compile_script(
+    script: &SerdeScript,
+    registers: &mut RegisterNames,
+    defaults: &CallDefaults,
+    result: &mut Vec<ScriptOperation<'static, SerdeExpression>>,
+) {
+    for operation in script {
+        let operation = match operation {
+            SerdeOperation::Expression(expression) => ScriptOperation::Expression {
+                expression: expression.to_owned(),
+            },
+            SerdeOperation::MakeRegister {
+                name,
+                module_name,
+                register_name,
+            } => {
+                registers.make(register_name.as_deref());
+                ScriptOperation::DefineRegister {
+                    query: type_query(name, module_name.as_deref()),
+                    immutable: false,
                 }
-                SerdeOperation::PopToRegister { index } => {
-                    ScriptOperation::PopToRegister { index: *index }
+            }
+            SerdeOperation::DropRegister { index } => {
+                ScriptOperation::DropRegister { index: *index }
+            }
+            SerdeOperation::PushFromRegister { index } => {
+                ScriptOperation::PushFromRegister { index: *index }
+            }
+            SerdeOperation::PopToRegister { index } => {
+                ScriptOperation::PopToRegister { index: *index }
+            }
+            SerdeOperation::DropRegisterNamed { name } => ScriptOperation::DropRegister {
+                index: registers.index(name),
+            },
+            SerdeOperation::PushFromRegisterNamed { name } => ScriptOperation::PushFromRegister {
+                index: registers.index(name),
+            },
+            SerdeOperation::PopToRegisterNamed { name } => ScriptOperation::PopToRegister {
+                index: registers.index(name),
+            },
+            SerdeOperation::Let {
+                name,
+                type_name,
+                module_name,
+                value,
+            } => {
+                compile_script(value, registers, defaults, result);
+                let index = match registers.find(name) {
+                    Some(index) => index,
+                    None => {
+                        let query = match type_name {
+                            Some(type_name) => type_query(type_name, module_name.as_deref()),
+                            None => match value.last() {
+                                Some(SerdeOperation::Expression(SerdeExpression::Literal(
+                                    literal,
+                                ))) => literal.type_query(),
+                                _ => panic!(
+                                    "Could not infer type of `{}` binding - provide its type name",
+                                    name
+                                ),
+                            },
+                        };
+                        let index = registers.count;
+                        registers.make(Some(name));
+                        result.push(ScriptOperation::DefineRegister {
+                            query,
+                            immutable: false,
+                        });
+                        index
+                    }
+                };
+                ScriptOperation::PopToRegister { index }
+            }
+            SerdeOperation::Get { name } => ScriptOperation::PushFromRegister {
+                index: registers.index(name),
+            },
+            SerdeOperation::CallFunction {
+                name,
+                module_name,
+                type_name,
+                visibility,
+                arguments,
+            } => {
+                let query = function_query(name, module_name, type_name, visibility);
+                if let Some(arguments) = *arguments {
+                    if let Some(inputs) =
+                        defaults.find(name, module_name.as_deref(), type_name.as_deref())
+                    {
+                        if arguments < inputs.len() {
+                            compile_call_with_defaults(
+                                query, inputs, arguments, registers, defaults, result,
+                            );
+                            continue;
+                        }
+                    }
                 }
-                SerdeOperation::CallFunction {
-                    name,
-                    module_name,
-                    type_name,
-                    visibility,
-                } => ScriptOperation::CallFunction {
-                    query: FunctionQuery {
-                        name: Some(name.to_owned().into()),
-                        module_name: module_name.as_ref().map(|name| name.to_owned().into()),
-                        type_query: type_name.as_ref().map(|name| TypeQuery {
-                            name: Some(name.to_owned().into()),
-                            module_name: module_name.as_ref().map(|name| name.to_owned().into()),
-                            ..Default::default()
-                        }),
-                        visibility: *visibility,
-                        ..Default::default()
-                    },
-                },
-                SerdeOperation::BranchScope {
-                    script_success: operations_success,
-                    script_failure: operations_failure,
-                } => ScriptOperation::BranchScope {
-                    scope_success: build_script(operations_success),
-                    scope_failure: operations_failure.as_ref().map(build_script),
-                },
-                SerdeOperation::LoopScope { script: operations } => ScriptOperation::LoopScope {
-                    scope: build_script(operations),
-                },
-                SerdeOperation::PushScope { script: operations } => ScriptOperation::PushScope {
-                    scope: build_script(operations),
-                },
-                SerdeOperation::PopScope => ScriptOperation::PopScope,
-            })
-            .collect(),
-    )
+                ScriptOperation::CallFunction { query }
+            }
+            SerdeOperation::BranchScope {
+                script_success: operations_success,
+                script_failure: operations_failure,
+            } => ScriptOperation::BranchScope {
+                scope_success: build_script_scoped(
+                    operations_success,
+                    &mut registers.clone(),
+                    defaults,
+                ),
+                scope_failure: operations_failure.as_ref().map(|operations| {
+                    build_script_scoped(operations, &mut registers.clone(), defaults)
+                }),
+            },
+            SerdeOperation::LoopScope { script: operations } => ScriptOperation::LoopScope {
+                scope: build_script_scoped(operations, &mut registers.clone(), defaults),
+            },
+            SerdeOperation::PushScope { script: operations } => ScriptOperation::PushScope {
+                scope: build_script_with(operations, defaults),
+                frame: false,
+            },
+            SerdeOperation::PopScope => ScriptOperation::PopScope,
+            SerdeOperation::Span(span) => ScriptOperation::Span { span: span.clone() },
+        };
+        result.push(operation);
+    }
+}
+
+fn type_query<'a>(name: &str, module_name: Option<&str>) -> TypeQuery<'a> {
+    let query = TypeQuery::named(name.to_owned());
+    match module_name {
+        Some(module_name) => query.in_module(module_name.to_owned()),
+        None => query,
+    }
+}
+
+fn function_query<'a>(
+    name: &str,
+    module_name: &Option<String>,
+    type_name: &Option<String>,
+    visibility: &Option<Visibility>,
+) -> FunctionQuery<'a> {
+    let mut query = FunctionQuery::named(name.to_owned());
+    if let Some(module_name) = module_name {
+        query = query.in_module(module_name.to_owned());
+    }
+    if let Some(type_name) = type_name {
+        query = query.of_type(type_query(type_name, module_name.as_deref()));
+    }
+    if let Some(visibility) = visibility {
+        query = query.with_visibility(*visibility);
+    }
+    query
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -200,6 +514,10 @@ pub struct SerdeFunctionParameter {
     pub name: String,
     pub module_name: Option<String>,
     pub type_name: String,
+    /// Script pushing value for this parameter when call omits it, compiled
+    /// into caller. Only trailing inputs can have default value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<SerdeScript>,
 }
 
 impl SerdeFunctionParameter {
@@ -207,14 +525,7 @@ impl SerdeFunctionParameter {
         ScriptFunctionParameter {
             meta: self.meta.to_owned(),
             name: self.name.to_owned(),
-            type_query: TypeQuery {
-                name: Some(self.type_name.to_owned().into()),
-                module_name: self
-                    .module_name
-                    .as_ref()
-                    .map(|module_name| module_name.to_owned().into()),
-                ..Default::default()
-            },
+            type_query: type_query(&self.type_name, self.module_name.as_deref()),
         }
     }
 }
@@ -237,15 +548,34 @@ pub struct SerdeFunction {
 
 impl SerdeFunction {
     pub fn compile(&self, module_name: &str) -> ScriptFunction<'static, SerdeExpression> {
+        self.compile_with(&CallDefaults {
+            functions: &FunctionInputs::new(),
+            module_name,
+        })
+    }
+
+    fn compile_with(&self, defaults: &CallDefaults) -> ScriptFunction<'static, SerdeExpression> {
+        if let Some(index) = self.inputs.iter().position(|input| input.default.is_some()) {
+            if let Some(input) = self.inputs[index..]
+                .iter()
+                .find(|input| input.default.is_none())
+            {
+                panic!(
+                    "Function `{}` has required parameter `{}` after parameter with default value",
+                    self.name, input.name
+                );
+            }
+        }
+        let module_name = defaults.module_name;
         ScriptFunction {
             signature: ScriptFunctionSignature {
                 meta: self.meta.to_owned(),
                 name: self.name.to_owned(),
                 module_name: Some(module_name.to_owned()),
-                type_query: self.type_name.as_ref().map(|type_name| TypeQuery {
-                    name: Some(type_name.to_owned().into()),
-                    ..Default::default()
-                }),
+                type_query: self
+                    .type_name
+                    .as_ref()
+                    .map(|type_name| TypeQuery::named(type_name.to_owned())),
                 visibility: self.visibility,
                 inputs: self
                     .inputs
@@ -258,7 +588,7 @@ impl SerdeFunction {
                     .map(|parameter| parameter.compile())
                     .collect(),
             },
-            script: build_script(&self.script),
+            script: build_script_with(&self.script, defaults),
         }
     }
 }
@@ -280,14 +610,7 @@ impl SerdeStructField {
             meta: self.meta.to_owned(),
             name: self.name.to_owned(),
             visibility: self.visibility,
-            type_query: TypeQuery {
-                name: Some(self.type_name.to_owned().into()),
-                module_name: self
-                    .module_name
-                    .as_ref()
-                    .map(|module_name| module_name.to_owned().into()),
-                ..Default::default()
-            },
+            type_query: type_query(&self.type_name, self.module_name.as_deref()),
         }
     }
 }
@@ -369,6 +692,8 @@ impl SerdeEnum {
 pub struct SerdeModule {
     pub name: String,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub imports: Vec<ScriptModuleImport>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub structs: Vec<SerdeStruct>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub enums: Vec<SerdeEnum>,
@@ -378,7 +703,15 @@ pub struct SerdeModule {
 
 impl SerdeModule {
     pub fn compile(&self) -> ScriptModule<'static, SerdeExpression> {
-        ScriptModule {
+        self.compile_with(&collect_function_inputs([self]))
+    }
+
+    fn compile_with(&self, functions: &FunctionInputs) -> ScriptModule<'static, SerdeExpression> {
+        let defaults = CallDefaults {
+            functions,
+            module_name: &self.name,
+        };
+        let mut result = ScriptModule {
             name: self.name.to_owned(),
             structs: self
                 .structs
@@ -393,9 +726,11 @@ impl SerdeModule {
             functions: self
                 .functions
                 .iter()
-                .map(|function| function.compile(&self.name))
+                .map(|function| function.compile_with(&defaults))
                 .collect(),
-        }
+        };
+        result.resolve_imports(&self.imports);
+        result
     }
 }
 
@@ -423,33 +758,57 @@ impl SerdePackage {
     }
 
     pub fn load<CP>(&mut self, path: &str, content_provider: &mut CP) -> Result<(), Box<dyn Error>>
+    where
+        CP: ScriptContentProvider<SerdeFile>,
+    {
+        self.load_chained(path, content_provider, &mut vec![])
+    }
+
+    /// `chain` holds paths being loaded that (transitively) depend on `path`.
+    fn load_chained<CP>(
+        &mut self,
+        path: &str,
+        content_provider: &mut CP,
+        chain: &mut Vec<String>,
+    ) -> Result<(), Box<dyn Error>>
     where
         CP: ScriptContentProvider<SerdeFile>,
     {
         let path = content_provider.sanitize_path(path)?;
+        if let Some(index) = chain.iter().position(|item| *item == path) {
+            let mut cycle = chain[index..].to_vec();
+            cycle.push(path);
+            return Err(Box::new(CircularDependencyError { cycle }));
+        }
         if self.files.contains_key(&path) {
             return Ok(());
         }
+        chain.push(path.to_owned());
         for content in content_provider.unpack_load(&path)? {
             if let Some(file) = content.data? {
                 let dependencies = file.dependencies.to_owned();
                 self.files.insert(content.name, file);
                 for relative in dependencies {
                     let path = content_provider.join_paths(&content.path, &relative)?;
-                    self.load(&path, content_provider)?;
+                    self.load_chained(&path, content_provider, chain)?;
                 }
             }
         }
+        chain.pop();
         Ok(())
     }
 
+    /// Calls to package functions that push fewer arguments than function
+    /// takes get default values of missing parameters pushed in.
     pub fn compile(&self) -> ScriptPackage<'static, SerdeExpression> {
+        let functions =
+            collect_function_inputs(self.files.values().flat_map(|file| file.modules.iter()));
         ScriptPackage {
             modules: self
                 .files
                 .values()
                 .flat_map(|file| file.modules.iter())
-                .map(|module| module.compile())
+                .map(|module| module.compile_with(&functions))
                 .collect(),
         }
     }
@@ -470,6 +829,12 @@ impl SerdeNodeTypeInfo {
     }
 }
 
+impl From<&TypeHandle> for SerdeNodeTypeInfo {
+    fn from(handle: &TypeHandle) -> Self {
+        Self::new(handle.name(), handle.module_name())
+    }
+}
+
 impl NodeTypeInfo for SerdeNodeTypeInfo {
     fn type_query(&self) -> TypeQuery {
         TypeQuery {
@@ -529,8 +894,13 @@ impl NodeDefinition for SerdeNodes {
                         SerdeLiteral::F64(_) => "64-bit float literal".to_owned(),
                         SerdeLiteral::Char(_) => "Character literal".to_owned(),
                         SerdeLiteral::String(_) => "String literal".to_owned(),
+                        SerdeLiteral::Bytes(_) => "Bytes literal".to_owned(),
                     },
                     SerdeExpression::StackDrop => "Stack drop".to_owned(),
+                    SerdeExpression::DebugPrint { .. } => "Debug print".to_owned(),
+                    SerdeExpression::Custom(expression) => {
+                        format!("Custom expression: `{}`", expression.id)
+                    }
                 },
                 SerdeOperation::MakeRegister { .. } => "Make register".to_owned(),
                 SerdeOperation::DropRegister { .. } => "Drop register".to_owned(),
@@ -540,6 +910,15 @@ impl NodeDefinition for SerdeNodes {
                 SerdeOperation::PopToRegister { .. } => {
                     "Pop data from stack to register".to_owned()
                 }
+                SerdeOperation::DropRegisterNamed { .. } => "Drop named register".to_owned(),
+                SerdeOperation::PushFromRegisterNamed { .. } => {
+                    "Push data from named register to stack".to_owned()
+                }
+                SerdeOperation::PopToRegisterNamed { .. } => {
+                    "Pop data from stack to named register".to_owned()
+                }
+                SerdeOperation::Let { name, .. } => format!("Let variable: `{}`", name),
+                SerdeOperation::Get { name } => format!("Get variable: `{}`", name),
                 SerdeOperation::CallFunction {
                     name, module_name, ..
                 } => format!(
@@ -551,11 +930,12 @@ impl NodeDefinition for SerdeNodes {
                 SerdeOperation::LoopScope { .. } => "Loop scope".to_owned(),
                 SerdeOperation::PushScope { .. } => "Push scope".to_owned(),
                 SerdeOperation::PopScope => "Pop scope".to_owned(),
+                SerdeOperation::Span(span) => format!("Source span: {}", span),
             },
         }
     }
 
-    fn node_pins_in(&self, _: &Registry) -> Vec<NodePin<Self::TypeInfo>> {
+    fn node_pins_in(&self, registry: &Registry) -> Vec<NodePin<Self::TypeInfo>> {
         match self {
             Self::Start => vec![],
             Self::Operation(operation) => match operation {
@@ -565,33 +945,98 @@ impl NodeDefinition for SerdeNodes {
                         _ => vec![NodePin::execute("In", false), NodePin::property("Value")],
                     },
                     SerdeExpression::StackDrop => vec![NodePin::execute("In", false)],
+                    SerdeExpression::DebugPrint { .. } => {
+                        vec![NodePin::execute("In", false), NodePin::property("Label")]
+                    }
+                    SerdeExpression::Custom(_) => {
+                        vec![NodePin::execute("In", false), NodePin::property("Id")]
+                    }
                 },
                 SerdeOperation::MakeRegister { .. } => vec![
                     NodePin::execute("In", false),
                     NodePin::property("Type name"),
                     NodePin::property("Type module name"),
+                    NodePin::property("Register name"),
                 ],
                 SerdeOperation::DropRegister { .. }
                 | SerdeOperation::PushFromRegister { .. }
                 | SerdeOperation::PopToRegister { .. } => {
                     vec![NodePin::execute("In", false), NodePin::property("Index")]
                 }
-                SerdeOperation::CallFunction { .. } => vec![
+                SerdeOperation::DropRegisterNamed { .. }
+                | SerdeOperation::PushFromRegisterNamed { .. }
+                | SerdeOperation::PopToRegisterNamed { .. }
+                | SerdeOperation::Get { .. } => {
+                    vec![NodePin::execute("In", false), NodePin::property("Name")]
+                }
+                SerdeOperation::Let { .. } => vec![
                     NodePin::execute("In", false),
                     NodePin::property("Name"),
-                    NodePin::property("Module name"),
                     NodePin::property("Type name"),
-                    NodePin::property("Visibility"),
+                    NodePin::property("Type module name"),
                 ],
+                SerdeOperation::CallFunction {
+                    name,
+                    module_name,
+                    type_name,
+                    visibility,
+                    ..
+                } => {
+                    let mut result = vec![
+                        NodePin::execute("In", false),
+                        NodePin::property("Name"),
+                        NodePin::property("Module name"),
+                        NodePin::property("Type name"),
+                        NodePin::property("Visibility"),
+                    ];
+                    if let Some(function) = registry.find_function(function_query(
+                        name,
+                        module_name,
+                        type_name,
+                        visibility,
+                    )) {
+                        result.extend(function.signature().inputs.iter().map(|parameter| {
+                            NodePin::parameter(&parameter.name, (&parameter.type_handle).into())
+                        }));
+                    }
+                    result
+                }
                 _ => vec![NodePin::execute("In", false)],
             },
         }
     }
 
-    fn node_pins_out(&self, _: &Registry) -> Vec<NodePin<Self::TypeInfo>> {
+    fn node_pins_out(&self, registry: &Registry) -> Vec<NodePin<Self::TypeInfo>> {
         match self {
             Self::Start => vec![NodePin::execute("Out", false)],
             Self::Operation(operation) => match operation {
+                SerdeOperation::Expression(SerdeExpression::Literal(literal)) => {
+                    let mut result = vec![NodePin::execute("Out", false)];
+                    if let Some(handle) = registry.find_type(literal.type_query()) {
+                        result.push(NodePin::parameter("Value", (&handle).into()));
+                    }
+                    result
+                }
+                SerdeOperation::CallFunction {
+                    name,
+                    module_name,
+                    type_name,
+                    visibility,
+                    ..
+                } => {
+                    let mut result = vec![NodePin::execute("Out", false)];
+                    if let Some(function) = registry.find_function(function_query(
+                        name,
+                        module_name,
+                        type_name,
+                        visibility,
+                    )) {
+                        result.extend(function.signature().outputs.iter().map(|parameter| {
+                            NodePin::parameter(&parameter.name, (&parameter.type_handle).into())
+                        }));
+                    }
+                    result
+                }
                 SerdeOperation::BranchScope { .. } => vec![
                     NodePin::execute("Out", false),
                     NodePin::execute("Success body", true),
@@ -601,6 +1046,10 @@ impl NodeDefinition for SerdeNodes {
                     NodePin::execute("Out", false),
                     NodePin::execute("Body", true),
                 ],
+                SerdeOperation::Let { .. } => vec![
+                    NodePin::execute("Out", false),
+                    NodePin::execute("Value", true),
+                ],
                 SerdeOperation::PopScope => vec![],
                 _ => vec![NodePin::execute("Out", false)],
             },
@@ -816,6 +1265,17 @@ impl NodeDefinition for SerdeNodes {
                 ),
                 registry,
             ),
+            ResponseSuggestionNode::new(
+                "Literal",
+                Node::new(
+                    x,
+                    y,
+                    SerdeNodes::Operation(SerdeOperation::Expression(SerdeExpression::Literal(
+                        SerdeLiteral::Bytes(vec![]),
+                    ))),
+                ),
+                registry,
+            ),
             ResponseSuggestionNode::new(
                 "Expression",
                 Node::new(
@@ -825,6 +1285,17 @@ impl NodeDefinition for SerdeNodes {
                 ),
                 registry,
             ),
+            ResponseSuggestionNode::new(
+                "Expression",
+                Node::new(
+                    x,
+                    y,
+                    SerdeNodes::Operation(SerdeOperation::Expression(
+                        SerdeExpression::DebugPrint { label: None },
+                    )),
+                ),
+                registry,
+            ),
             ResponseSuggestionNode::new(
                 "Register",
                 Node::new(
@@ -833,6 +1304,7 @@ impl NodeDefinition for SerdeNodes {
                     SerdeNodes::Operation(SerdeOperation::MakeRegister {
                         name: "Type".to_owned(),
                         module_name: None,
+                        register_name: None,
                     }),
                 ),
                 registry,
@@ -864,6 +1336,64 @@ impl NodeDefinition for SerdeNodes {
                 ),
                 registry,
             ),
+            ResponseSuggestionNode::new(
+                "Register",
+                Node::new(
+                    x,
+                    y,
+                    SerdeNodes::Operation(SerdeOperation::DropRegisterNamed {
+                        name: "register".to_owned(),
+                    }),
+                ),
+                registry,
+            ),
+            ResponseSuggestionNode::new(
+                "Register",
+                Node::new(
+                    x,
+                    y,
+                    SerdeNodes::Operation(SerdeOperation::PushFromRegisterNamed {
+                        name: "register".to_owned(),
+                    }),
+                ),
+                registry,
+            ),
+            ResponseSuggestionNode::new(
+                "Register",
+                Node::new(
+                    x,
+                    y,
+                    SerdeNodes::Operation(SerdeOperation::PopToRegisterNamed {
+                        name: "register".to_owned(),
+                    }),
+                ),
+                registry,
+            ),
+            ResponseSuggestionNode::new(
+                "Variable",
+                Node::new(
+                    x,
+                    y,
+                    SerdeNodes::Operation(SerdeOperation::Let {
+                        name: "variable".to_owned(),
+                        type_name: None,
+                        module_name: None,
+                        value: vec![],
+                    }),
+                ),
+                registry,
+            ),
+            ResponseSuggestionNode::new(
+                "Variable",
+                Node::new(
+                    x,
+                    y,
+                    SerdeNodes::Operation(SerdeOperation::Get {
+                        name: "variable".to_owned(),
+                    }),
+                ),
+                registry,
+            ),
             ResponseSuggestionNode::new(
                 "Call",
                 Node::new(
@@ -874,6 +1404,7 @@ impl NodeDefinition for SerdeNodes {
                         module_name: None,
                         type_name: None,
                         visibility: None,
+                        arguments: None,
                     }),
                 ),
                 registry,
@@ -940,15 +1471,36 @@ impl NodeDefinition for SerdeNodes {
                             SerdeLiteral::F64(value) => PropertyValue::new(value).ok(),
                             SerdeLiteral::Char(value) => PropertyValue::new(value).ok(),
                             SerdeLiteral::String(value) => PropertyValue::new(value).ok(),
+                            SerdeLiteral::Bytes(value) => PropertyValue::new(value).ok(),
                         },
                         _ => None,
                     }
                 }
-                SerdeOperation::MakeRegister { name, module_name } => match property_name {
+                SerdeOperation::Expression(SerdeExpression::DebugPrint { label })
+                    if property_name == "Label" =>
+                {
+                    label
+                        .as_ref()
+                        .and_then(|label| PropertyValue::new(label).ok())
+                }
+                SerdeOperation::Expression(SerdeExpression::Custom(expression)) => {
+                    match property_name {
+                        "Id" => PropertyValue::new(&expression.id).ok(),
+                        _ => None,
+                    }
+                }
+                SerdeOperation::MakeRegister {
+                    name,
+                    module_name,
+                    register_name,
+                } => match property_name {
                     "Type name" => PropertyValue::new(name).ok(),
                     "Type module name" => module_name
                         .as_ref()
                         .and_then(|name| PropertyValue::new(name).ok()),
+                    "Register name" => register_name
+                        .as_ref()
+                        .and_then(|name| PropertyValue::new(name).ok()),
                     _ => None,
                 },
                 SerdeOperation::DropRegister { index } => match property_name {
@@ -963,14 +1515,37 @@ impl NodeDefinition for SerdeNodes {
                     "Index" => PropertyValue::new(index).ok(),
                     _ => None,
                 },
-                SerdeOperation::CallFunction {
+                SerdeOperation::DropRegisterNamed { name }
+                | SerdeOperation::PushFromRegisterNamed { name }
+                | SerdeOperation::PopToRegisterNamed { name }
+                | SerdeOperation::Get { name } => match property_name {
+                    "Name" => PropertyValue::new(name).ok(),
+                    _ => None,
+                },
+                SerdeOperation::Let {
                     name,
-                    module_name,
                     type_name,
-                    visibility,
+                    module_name,
+                    ..
                 } => match property_name {
                     "Name" => PropertyValue::new(name).ok(),
-                    "Module name" => module_name
+                    "Type name" => type_name
+                        .as_ref()
+                        .and_then(|name| PropertyValue::new(name).ok()),
+                    "Type module name" => module_name
+                        .as_ref()
+                        .and_then(|name| PropertyValue::new(name).ok()),
+                    _ => None,
+                },
+                SerdeOperation::CallFunction {
+                    name,
+                    module_name,
+                    type_name,
+                    visibility,
+                    ..
+                } => match property_name {
+                    "Name" => PropertyValue::new(name).ok(),
+                    "Module name" => module_name
                         .as_ref()
                         .and_then(|name| PropertyValue::new(name).ok()),
                     "Type name" => type_name
@@ -1079,10 +1654,31 @@ impl NodeDefinition for SerdeNodes {
                                     *value = v;
                                 }
                             }
+                            SerdeLiteral::Bytes(value) => {
+                                if let Ok(v) = property_value.get_exact::<Vec<u8>>() {
+                                    *value = v;
+                                }
+                            }
                         }
                     }
                 }
-                SerdeOperation::MakeRegister { name, module_name } => match property_name {
+                SerdeOperation::Expression(SerdeExpression::DebugPrint { label })
+                    if property_name == "Label" =>
+                {
+                    *label = property_value.get_exact::<String>().ok();
+                }
+                SerdeOperation::Expression(SerdeExpression::Custom(expression))
+                    if property_name == "Id" =>
+                {
+                    if let Ok(v) = property_value.get_exact::<String>() {
+                        expression.id = v;
+                    }
+                }
+                SerdeOperation::MakeRegister {
+                    name,
+                    module_name,
+                    register_name,
+                } => match property_name {
                     "Type name" => {
                         if let Ok(v) = property_value.get_exact::<String>() {
                             *name = v;
@@ -1095,6 +1691,9 @@ impl NodeDefinition for SerdeNodes {
                             None
                         };
                     }
+                    "Register name" => {
+                        *register_name = property_value.get_exact::<String>().ok();
+                    }
                     _ => {}
                 },
                 SerdeOperation::DropRegister { index } => {
@@ -1118,11 +1717,39 @@ impl NodeDefinition for SerdeNodes {
                         }
                     }
                 }
+                SerdeOperation::DropRegisterNamed { name }
+                | SerdeOperation::PushFromRegisterNamed { name }
+                | SerdeOperation::PopToRegisterNamed { name }
+                | SerdeOperation::Get { name } => {
+                    if let ("Name", Ok(v)) = (property_name, property_value.get_exact::<String>()) {
+                        *name = v;
+                    }
+                }
+                SerdeOperation::Let {
+                    name,
+                    type_name,
+                    module_name,
+                    ..
+                } => match property_name {
+                    "Name" => {
+                        if let Ok(v) = property_value.get_exact::<String>() {
+                            *name = v;
+                        }
+                    }
+                    "Type name" => {
+                        *type_name = property_value.get_exact::<String>().ok();
+                    }
+                    "Type module name" => {
+                        *module_name = property_value.get_exact::<String>().ok();
+                    }
+                    _ => {}
+                },
                 SerdeOperation::CallFunction {
                     name,
                     module_name,
                     type_name,
                     visibility,
+                    ..
                 } => match property_name {
                     "Name" => {
                         if let Ok(v) = property_value.get_exact::<String>() {
@@ -1158,16 +1785,121 @@ impl NodeDefinition for SerdeNodes {
     }
 }
 
-pub struct CompileSerdeNodeGraphVisitor;
+/// Parameter pins connected to other nodes data outputs are compiled into
+/// values pushed right before the function call, in reverse order of
+/// function inputs. Function call nodes are evaluated only once - if they
+/// feed parameter pins, their outputs are moved to named registers right
+/// after the call and pins push values from these registers, so each data
+/// output can feed single parameter pin. Literals are inlined at every use.
+pub struct CompileSerdeNodeGraphVisitor<'a> {
+    graph: &'a NodeGraph<SerdeNodes>,
+    registry: &'a Registry,
+    evaluated: HashSet<NodeId<SerdeNodes>>,
+}
+
+impl<'a> CompileSerdeNodeGraphVisitor<'a> {
+    pub fn new(graph: &'a NodeGraph<SerdeNodes>, registry: &'a Registry) -> Self {
+        Self {
+            graph,
+            registry,
+            evaluated: Default::default(),
+        }
+    }
+
+    fn output_register(id: NodeId<SerdeNodes>, pin: &str) -> String {
+        format!("{}/{}", id, pin)
+    }
+
+    fn find_function(&self, operation: &SerdeOperation) -> Option<FunctionHandle> {
+        if let SerdeOperation::CallFunction {
+            name,
+            module_name,
+            type_name,
+            visibility,
+            ..
+        } = operation
+        {
+            self.registry
+                .find_function(function_query(name, module_name, type_name, visibility))
+        } else {
+            None
+        }
+    }
 
-impl NodeGraphVisitor<SerdeNodes> for CompileSerdeNodeGraphVisitor {
-    type Input = ();
+    /// Finds function call node output connected to given input pin.
+    fn call_source(&self, id: NodeId<SerdeNodes>, pin: &str) -> Option<(NodeId<SerdeNodes>, &str)> {
+        let connection = self
+            .graph
+            .connections()
+            .find(|connection| connection.to_node == id && connection.to_pin == pin)?;
+        match &self.graph.node(connection.from_node)?.data {
+            SerdeNodes::Operation(SerdeOperation::CallFunction { .. }) => {
+                Some((connection.from_node, connection.from_pin.as_str()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns empty script for nodes that were already evaluated.
+    fn compile_call(
+        &mut self,
+        node: &Node<SerdeNodes>,
+        mut inputs: HashMap<String, SerdeScript>,
+    ) -> Option<SerdeScript> {
+        let SerdeNodes::Operation(operation) = &node.data else {
+            return None;
+        };
+        let function = self.find_function(operation)?;
+        if !self.evaluated.insert(node.id()) {
+            return Some(vec![]);
+        }
+        let mut result = vec![];
+        for parameter in function.signature().inputs.iter().rev() {
+            if let Some(script) = inputs.remove(&parameter.name) {
+                result.extend(script);
+                if let Some((source, pin)) = self.call_source(node.id(), &parameter.name) {
+                    result.push(SerdeOperation::PushFromRegisterNamed {
+                        name: Self::output_register(source, pin),
+                    });
+                }
+            }
+        }
+        result.push(operation.to_owned());
+        let feeds_data = self.graph.connections().any(|connection| {
+            connection.from_node == node.id()
+                && function
+                    .signature()
+                    .outputs
+                    .iter()
+                    .any(|parameter| parameter.name == connection.from_pin)
+        });
+        if feeds_data {
+            // first output ends up on top of stack.
+            for parameter in &function.signature().outputs {
+                let name = Self::output_register(node.id(), &parameter.name);
+                result.push(SerdeOperation::MakeRegister {
+                    name: parameter.type_handle.name().to_owned(),
+                    module_name: parameter
+                        .type_handle
+                        .module_name()
+                        .map(|name| name.to_owned()),
+                    register_name: Some(name.clone()),
+                });
+                result.push(SerdeOperation::PopToRegisterNamed { name });
+            }
+        }
+        Some(result)
+    }
+}
+
+impl NodeGraphVisitor<SerdeNodes> for CompileSerdeNodeGraphVisitor<'_> {
+    type Input = SerdeScript;
     type Output = SerdeOperation;
 
     fn visit_statement(
         &mut self,
         node: &Node<SerdeNodes>,
-        _: HashMap<String, Self::Input>,
+        inputs: HashMap<String, Self::Input>,
         mut scopes: HashMap<String, Vec<Self::Output>>,
         result: &mut Vec<Self::Output>,
     ) -> bool {
@@ -1191,6 +1923,25 @@ impl NodeGraphVisitor<SerdeNodes> for CompileSerdeNodeGraphVisitor {
                         result.push(SerdeOperation::PushScope { script });
                     }
                 }
+                SerdeOperation::Let {
+                    name,
+                    type_name,
+                    module_name,
+                    ..
+                } => {
+                    if let Some(value) = scopes.remove("Value") {
+                        result.push(SerdeOperation::Let {
+                            name: name.to_owned(),
+                            type_name: type_name.to_owned(),
+                            module_name: module_name.to_owned(),
+                            value,
+                        });
+                    }
+                }
+                SerdeOperation::CallFunction { .. } => match self.compile_call(node, inputs) {
+                    Some(script) => result.extend(script),
+                    None => result.push(operation.to_owned()),
+                },
                 _ => result.push(operation.to_owned()),
             }
         }
@@ -1199,10 +1950,18 @@ impl NodeGraphVisitor<SerdeNodes> for CompileSerdeNodeGraphVisitor {
 
     fn visit_expression(
         &mut self,
-        _: &Node<SerdeNodes>,
-        _: HashMap<String, Self::Input>,
+        node: &Node<SerdeNodes>,
+        inputs: HashMap<String, Self::Input>,
     ) -> Option<Self::Input> {
-        None
+        match &node.data {
+            SerdeNodes::Operation(
+                operation @ SerdeOperation::Expression(SerdeExpression::Literal(_)),
+            ) => Some(vec![operation.to_owned()]),
+            SerdeNodes::Operation(SerdeOperation::CallFunction { .. }) => {
+                self.compile_call(node, inputs)
+            }
+            _ => None,
+        }
     }
 }
 
@@ -1213,6 +1972,16 @@ mod tests {
     use intuicio_core::prelude::*;
     use intuicio_nodes::nodes::*;
 
+    fn build_script(script: &SerdeScript) -> ScriptHandle<'static, SerdeExpression> {
+        build_script_with(
+            script,
+            &CallDefaults {
+                functions: &FunctionInputs::new(),
+                module_name: "",
+            },
+        )
+    }
+
     pub struct LexprContentParser;
 
     impl BytesContentParser<SerdeFile> for LexprContentParser {
@@ -1222,6 +1991,51 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_circular_dependency() {
+        struct MemoryContentProvider(HashMap<String, SerdeFile>);
+
+        impl ScriptContentProvider<SerdeFile> for MemoryContentProvider {
+            fn load(&mut self, path: &str) -> Result<Option<SerdeFile>, Box<dyn Error>> {
+                Ok(self.0.get(path).cloned())
+            }
+
+            fn join_paths(&self, _: &str, relative: &str) -> Result<String, Box<dyn Error>> {
+                Ok(relative.to_owned())
+            }
+        }
+
+        let file = |dependencies: &[&str]| SerdeFile {
+            dependencies: dependencies.iter().map(|name| name.to_string()).collect(),
+            modules: vec![],
+        };
+        let cycle = |content_provider: &mut MemoryContentProvider| {
+            SerdePackage::new("a", content_provider)
+                .unwrap_err()
+                .downcast::<CircularDependencyError>()
+                .unwrap()
+                .cycle
+        };
+
+        let mut content_provider = MemoryContentProvider(HashMap::from([
+            ("a".to_owned(), file(&["b"])),
+            ("b".to_owned(), file(&["a"])),
+        ]));
+        assert_eq!(cycle(&mut content_provider), vec!["a", "b", "a"]);
+
+        let mut content_provider =
+            MemoryContentProvider(HashMap::from([("a".to_owned(), file(&["a"]))]));
+        assert_eq!(cycle(&mut content_provider), vec!["a", "a"]);
+
+        let mut content_provider = MemoryContentProvider(HashMap::from([
+            ("a".to_owned(), file(&["b", "c"])),
+            ("b".to_owned(), file(&["c"])),
+            ("c".to_owned(), file(&[])),
+        ]));
+        let package = SerdePackage::new("a", &mut content_provider).unwrap();
+        assert_eq!(package.files.len(), 3);
+    }
+
     #[test]
     fn test_frontend_lexpr() {
         let mut registry = Registry::default().with_basic_types();
@@ -1260,6 +2074,148 @@ mod tests {
         assert_eq!(result, 42);
     }
 
+    #[test]
+    fn test_fold_constants() {
+        let mut registry = Registry::default().with_basic_types();
+        registry.add_function(Function::new(
+            function_signature! {
+                registry => mod intrinsics fn add(a: usize, b: usize) -> (result: usize)
+            }
+            .with_meta(Meta::Identifier("pure".to_owned())),
+            FunctionBody::closure(|context, _| {
+                let a = context.stack().pop::<usize>().unwrap();
+                let b = context.stack().pop::<usize>().unwrap();
+                context.stack().push(a + b);
+            }),
+        ));
+        let mut script = build_script(&vec![
+            SerdeOperation::Expression(SerdeExpression::Literal(SerdeLiteral::Usize(2))),
+            SerdeOperation::Expression(SerdeExpression::Literal(SerdeLiteral::Usize(40))),
+            SerdeOperation::CallFunction {
+                name: "add".to_owned(),
+                module_name: Some("intrinsics".to_owned()),
+                type_name: None,
+                visibility: None,
+                arguments: None,
+            },
+        ]);
+        assert_eq!(script.len(), 3);
+        fold_constants(ScriptHandle::get_mut(&mut script).unwrap(), &registry);
+        assert_eq!(script.len(), 1);
+        assert!(matches!(
+            &script[0],
+            ScriptOperation::Expression {
+                expression: SerdeExpression::Literal(SerdeLiteral::Usize(42))
+            }
+        ));
+        registry.add_function(Function::new(
+            function_signature! {
+                registry => mod intrinsics fn div(a: usize, b: usize) -> (result: usize)
+            }
+            .with_meta(Meta::Identifier("pure".to_owned())),
+            FunctionBody::closure(|context, _| {
+                let a = context.stack().pop::<usize>().unwrap();
+                let b = context.stack().pop::<usize>().unwrap();
+                context.stack().push(a / b);
+            }),
+        ));
+        let mut failing = build_script(&vec![
+            SerdeOperation::Expression(SerdeExpression::Literal(SerdeLiteral::Usize(0))),
+            SerdeOperation::Expression(SerdeExpression::Literal(SerdeLiteral::Usize(42))),
+            SerdeOperation::CallFunction {
+                name: "div".to_owned(),
+                module_name: Some("intrinsics".to_owned()),
+                type_name: None,
+                visibility: None,
+                arguments: None,
+            },
+        ]);
+        fold_constants(ScriptHandle::get_mut(&mut failing).unwrap(), &registry);
+        assert_eq!(failing.len(), 3);
+        let function = ScriptFunction {
+            signature: ScriptFunctionSignature {
+                meta: None,
+                name: "main".to_owned(),
+                module_name: Some("test".to_owned()),
+                type_query: None,
+                visibility: Visibility::Public,
+                inputs: vec![],
+                outputs: vec![ScriptFunctionParameter {
+                    meta: None,
+                    name: "result".to_owned(),
+                    type_query: TypeQuery::of::<usize>(),
+                }],
+            },
+            script,
+        };
+        function.install::<VmScope<SerdeExpression>>(&mut registry, None);
+        let mut host = Host::new(Context::new(10240, 10240), RegistryHandle::new(registry));
+        let (result,) = host
+            .call_function::<(usize,), _>("main", "test", None)
+            .unwrap()
+            .run(());
+        assert_eq!(result, 42);
+    }
+
+    #[test]
+    fn test_metrics() {
+        let mut registry = Registry::default().with_basic_types();
+        let add = registry.add_function(define_function! {
+            registry => mod intrinsics fn add(a: usize, b: usize) -> (result: usize) {
+                (a + b,)
+            }
+        });
+        SerdeFunction {
+            meta: None,
+            name: "main".to_owned(),
+            type_name: None,
+            visibility: Visibility::Public,
+            inputs: vec![],
+            outputs: vec![SerdeFunctionParameter {
+                meta: None,
+                name: "result".to_owned(),
+                module_name: None,
+                type_name: "usize".to_owned(),
+                default: None,
+            }],
+            script: vec![
+                SerdeOperation::Expression(SerdeExpression::Literal(SerdeLiteral::Usize(2))),
+                SerdeOperation::Expression(SerdeExpression::Literal(SerdeLiteral::Usize(40))),
+                SerdeOperation::CallFunction {
+                    name: "add".to_owned(),
+                    module_name: Some("intrinsics".to_owned()),
+                    type_name: None,
+                    visibility: None,
+                    arguments: None,
+                },
+            ],
+        }
+        .compile("test")
+        .install::<VmScope<SerdeExpression>>(&mut registry, None);
+        let metrics = std::sync::Arc::new(FunctionMetrics::default());
+        registry.set_metrics_sink(Some(metrics.clone()));
+        let mut host = Host::new(Context::new(10240, 10240), RegistryHandle::new(registry));
+        let (result,) = host
+            .call_function::<(usize,), _>("main", "test", None)
+            .unwrap()
+            .run(());
+        assert_eq!(result, 42);
+        assert_eq!(metrics.stats(add.id()).unwrap().calls, 1);
+        let main = host
+            .registry()
+            .find_function(FunctionQuery {
+                name: Some("main".into()),
+                module_name: Some("test".into()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(metrics.stats(main.id()).unwrap().calls, 1);
+        let (context, registry) = host.context_and_registry();
+        let (result,) = add.call::<(usize,), _>(context, registry, (1usize, 2usize), true);
+        assert_eq!(result, 3);
+        assert_eq!(metrics.stats(add.id()).unwrap().calls, 2);
+    }
+
     #[test]
     fn test_nodes() {
         let mut registry = Registry::default().with_basic_types();
@@ -1306,6 +2262,7 @@ mod tests {
                         module_name: Some("intrinsics".to_owned()),
                         type_name: None,
                         visibility: None,
+                        arguments: None,
                     }),
                 ),
                 &registry,
@@ -1316,7 +2273,12 @@ mod tests {
         graph.connect_nodes(NodeConnection::new(literal_b, call_add, "Out", "In"));
         graph.validate(&registry).unwrap();
         assert_eq!(
-            graph.visit(&mut CompileSerdeNodeGraphVisitor, &registry),
+            graph
+                .visit(
+                    &mut CompileSerdeNodeGraphVisitor::new(&graph, &registry),
+                    &registry,
+                )
+                .unwrap(),
             vec![
                 SerdeOperation::Expression(SerdeExpression::Literal(SerdeLiteral::I32(2))),
                 SerdeOperation::Expression(SerdeExpression::Literal(SerdeLiteral::I32(40))),
@@ -1325,9 +2287,14 @@ mod tests {
                     module_name: Some("intrinsics".to_owned()),
                     type_name: None,
                     visibility: None,
+                    arguments: None,
                 }
             ]
         );
+        assert_eq!(
+            graph.execution_order(&registry).unwrap(),
+            vec![start, literal_a, literal_b, call_add]
+        );
 
         {
             let mut graph = graph.clone();
@@ -1385,4 +2352,723 @@ mod tests {
             ));
         }
     }
+
+    #[test]
+    fn test_data_pins() {
+        let mut registry = Registry::default().with_basic_types();
+        registry.add_function(define_function! {
+            registry => mod intrinsics fn add(a: usize, b: usize) -> (result: usize) {
+                (a + b,)
+            }
+        });
+        let literal = |value| {
+            Node::new(
+                0,
+                0,
+                SerdeNodes::Operation(SerdeOperation::Expression(SerdeExpression::Literal(
+                    SerdeLiteral::Usize(value),
+                ))),
+            )
+        };
+        let call_add = SerdeOperation::CallFunction {
+            name: "add".to_owned(),
+            module_name: Some("intrinsics".to_owned()),
+            type_name: None,
+            visibility: None,
+            arguments: None,
+        };
+        let mut graph = NodeGraph::default();
+        let start = graph
+            .add_node(Node::new(0, 0, SerdeNodes::Start), &registry)
+            .unwrap();
+        let literal_a = graph.add_node(literal(40), &registry).unwrap();
+        let literal_b = graph.add_node(literal(1), &registry).unwrap();
+        let literal_c = graph.add_node(literal(1), &registry).unwrap();
+        let add_inner = graph
+            .add_node(
+                Node::new(0, 0, SerdeNodes::Operation(call_add.clone())),
+                &registry,
+            )
+            .unwrap();
+        let add_outer = graph
+            .add_node(
+                Node::new(0, 0, SerdeNodes::Operation(call_add.clone())),
+                &registry,
+            )
+            .unwrap();
+        graph.connect_nodes(NodeConnection::new(start, add_outer, "Out", "In"));
+        graph.connect_nodes(NodeConnection::new(literal_a, add_outer, "Value", "a"));
+        graph.connect_nodes(NodeConnection::new(add_inner, add_outer, "result", "b"));
+        graph.connect_nodes(NodeConnection::new(literal_b, add_inner, "Value", "a"));
+        graph.connect_nodes(NodeConnection::new(literal_c, add_inner, "Value", "b"));
+        graph.validate(&registry).unwrap();
+        let script = graph
+            .visit(
+                &mut CompileSerdeNodeGraphVisitor::new(&graph, &registry),
+                &registry,
+            )
+            .unwrap();
+        let register = format!("{}/result", add_inner);
+        assert_eq!(
+            script,
+            vec![
+                SerdeOperation::Expression(SerdeExpression::Literal(SerdeLiteral::Usize(1))),
+                SerdeOperation::Expression(SerdeExpression::Literal(SerdeLiteral::Usize(1))),
+                call_add.clone(),
+                SerdeOperation::MakeRegister {
+                    name: "usize".to_owned(),
+                    module_name: None,
+                    register_name: Some(register.clone()),
+                },
+                SerdeOperation::PopToRegisterNamed {
+                    name: register.clone(),
+                },
+                SerdeOperation::PushFromRegisterNamed { name: register },
+                SerdeOperation::Expression(SerdeExpression::Literal(SerdeLiteral::Usize(40))),
+                call_add,
+            ]
+        );
+
+        // data source executed as statement is not evaluated again.
+        {
+            let mut graph = graph.clone();
+            graph.disconnect_nodes(start, add_outer, "Out", "In");
+            graph.connect_nodes(NodeConnection::new(start, add_inner, "Out", "In"));
+            graph.connect_nodes(NodeConnection::new(add_inner, add_outer, "Out", "In"));
+            graph.validate(&registry).unwrap();
+            assert_eq!(
+                graph
+                    .visit(
+                        &mut CompileSerdeNodeGraphVisitor::new(&graph, &registry),
+                        &registry,
+                    )
+                    .unwrap(),
+                script
+            );
+        }
+
+        {
+            let mut graph = graph.clone();
+            graph.connect_nodes(NodeConnection::new(add_outer, add_inner, "result", "a"));
+            assert!(matches!(
+                graph.validate(&registry).unwrap_err()[0],
+                NodeGraphError::Connection(ConnectionError::CycleNodeFound { .. })
+            ));
+        }
+
+        let function = ScriptFunction {
+            signature: ScriptFunctionSignature {
+                meta: None,
+                name: "main".to_owned(),
+                module_name: Some("test".to_owned()),
+                type_query: None,
+                visibility: Visibility::Public,
+                inputs: vec![],
+                outputs: vec![ScriptFunctionParameter {
+                    meta: None,
+                    name: "result".to_owned(),
+                    type_query: TypeQuery::of::<usize>(),
+                }],
+            },
+            script: build_script(&script),
+        };
+        function.install::<VmScope<SerdeExpression>>(&mut registry, None);
+        let mut host = Host::new(Context::new(10240, 10240), RegistryHandle::new(registry));
+        let (result,) = host
+            .call_function::<(usize,), _>("main", "test", None)
+            .unwrap()
+            .run(());
+        assert_eq!(result, 42);
+    }
+
+    #[test]
+    fn test_module_imports() {
+        let field = |name: &str, type_name: &str| SerdeStructField {
+            meta: None,
+            name: name.to_owned(),
+            visibility: Visibility::Public,
+            module_name: None,
+            type_name: type_name.to_owned(),
+        };
+        let thing = |fields| SerdeStruct {
+            meta: None,
+            name: "Thing".to_owned(),
+            visibility: Visibility::Public,
+            fields,
+        };
+        let holder = SerdeStruct {
+            meta: None,
+            name: "Holder".to_owned(),
+            visibility: Visibility::Public,
+            fields: vec![field("local", "Thing"), field("other", "Other")],
+        };
+        let modules = [
+            SerdeModule {
+                name: "a".to_owned(),
+                imports: vec![],
+                structs: vec![thing(vec![field("value", "i32")])],
+                enums: vec![],
+                functions: vec![],
+            },
+            SerdeModule {
+                name: "b".to_owned(),
+                imports: vec![ScriptModuleImport::new("a", "Thing").alias("Other")],
+                structs: vec![thing(vec![field("value", "u8")]), holder.clone()],
+                enums: vec![],
+                functions: vec![],
+            },
+            SerdeModule {
+                name: "c".to_owned(),
+                imports: vec![
+                    ScriptModuleImport::new("a", "Thing").alias("Other"),
+                    ScriptModuleImport::new("b", "Thing"),
+                ],
+                structs: vec![thing(vec![field("value", "u16")]), holder],
+                enums: vec![],
+                functions: vec![],
+            },
+        ];
+        let mut registry = Registry::default().with_basic_types();
+        ScriptPackage {
+            modules: modules.iter().map(|module| module.compile()).collect(),
+        }
+        .install::<VmScope<SerdeExpression>>(&mut registry, None);
+        let field_module = |module_name: &str, field_name: &str| {
+            let handle = registry
+                .find_type(TypeQuery {
+                    name: Some("Holder".into()),
+                    module_name: Some(module_name.into()),
+                    ..Default::default()
+                })
+                .unwrap();
+            let field = handle
+                .as_struct()
+                .unwrap()
+                .fields()
+                .iter()
+                .find(|field| field.name == field_name)
+                .unwrap()
+                .type_handle()
+                .clone();
+            assert_eq!(field.name(), "Thing");
+            field.module_name().unwrap().to_owned()
+        };
+        assert_eq!(field_module("b", "local"), "b");
+        assert_eq!(field_module("b", "other"), "a");
+        // import shadows local type of the same name.
+        assert_eq!(field_module("c", "local"), "b");
+        assert_eq!(field_module("c", "other"), "a");
+    }
+
+    #[test]
+    fn test_bytes_literal() {
+        let literal = SerdeLiteral::Bytes(vec![0, 1, 2, 254, 255]);
+        let content = serde_lexpr::to_string(&literal).unwrap();
+        let literal = serde_lexpr::from_str::<SerdeLiteral>(&content).unwrap();
+        assert_eq!(literal, SerdeLiteral::Bytes(vec![0, 1, 2, 254, 255]));
+        let mut context = Context::new(10240, 10240);
+        literal.evaluate(&mut context);
+        assert_eq!(
+            context.stack().pop::<Vec<u8>>().unwrap(),
+            vec![0, 1, 2, 254, 255]
+        );
+    }
+
+    #[test]
+    fn test_default_arguments() {
+        let parameter = |name: &str, default: Option<usize>| SerdeFunctionParameter {
+            meta: None,
+            name: name.to_owned(),
+            module_name: None,
+            type_name: "usize".to_owned(),
+            default: default.map(|value| {
+                vec![SerdeOperation::Expression(SerdeExpression::Literal(
+                    SerdeLiteral::Usize(value),
+                ))]
+            }),
+        };
+        let literal = |value| {
+            SerdeOperation::Expression(SerdeExpression::Literal(SerdeLiteral::Usize(value)))
+        };
+        let function = |name: &str, inputs, script| SerdeFunction {
+            meta: None,
+            name: name.to_owned(),
+            type_name: None,
+            visibility: Visibility::Public,
+            inputs,
+            outputs: vec![parameter("result", None)],
+            script,
+        };
+        let call = |module_name: &str, name: &str, arguments| SerdeOperation::CallFunction {
+            name: name.to_owned(),
+            module_name: Some(module_name.to_owned()),
+            type_name: None,
+            visibility: None,
+            arguments,
+        };
+        let sub_default = function(
+            "sub_default",
+            vec![parameter("a", None), parameter("b", Some(2))],
+            vec![call("intrinsics", "sub", None)],
+        );
+        let mut package = SerdePackage::default();
+        package.files.insert(
+            "test".to_owned(),
+            SerdeFile {
+                dependencies: vec![],
+                modules: vec![SerdeModule {
+                    name: "test".to_owned(),
+                    imports: vec![],
+                    structs: vec![],
+                    enums: vec![],
+                    functions: vec![
+                        sub_default.clone(),
+                        function(
+                            "main",
+                            vec![],
+                            vec![
+                                SerdeOperation::Let {
+                                    name: "unused".to_owned(),
+                                    type_name: None,
+                                    module_name: None,
+                                    value: vec![literal(100)],
+                                },
+                                literal(44),
+                                call("test", "sub_default", Some(1)),
+                            ],
+                        ),
+                        function(
+                            "main_full",
+                            vec![],
+                            vec![
+                                literal(4),
+                                literal(44),
+                                call("test", "sub_default", Some(2)),
+                            ],
+                        ),
+                    ],
+                }],
+            },
+        );
+        let mut registry = Registry::default().with_basic_types();
+        registry.add_function(define_function! {
+            registry => mod intrinsics fn sub(a: usize, b: usize) -> (result: usize) {
+                (a - b,)
+            }
+        });
+        package
+            .compile()
+            .install::<VmScope<SerdeExpression>>(&mut registry, None);
+        let mut host = Host::new(Context::new(10240, 10240), registry.into());
+        let (result,) = host
+            .call_function::<(usize,), _>("main", "test", None)
+            .unwrap()
+            .run(());
+        assert_eq!(result, 42);
+        assert_eq!(host.context().stack().position(), 0);
+        assert_eq!(host.context().registers().position(), 0);
+        let (result,) = host
+            .call_function::<(usize,), _>("main_full", "test", None)
+            .unwrap()
+            .run(());
+        assert_eq!(result, 40);
+
+        let mut invalid = sub_default;
+        invalid.inputs = vec![parameter("a", Some(1)), parameter("b", None)];
+        assert!(std::panic::catch_unwind(|| invalid.compile("test")).is_err());
+    }
+
+    #[test]
+    fn test_call_graph() {
+        let mut registry = Registry::default().with_basic_types();
+        registry.add_function(define_function! {
+            registry => mod intrinsics fn add(a: usize, b: usize) -> (result: usize) {
+                (a + b,)
+            }
+        });
+        let module = SerdeModule {
+            name: "test".to_owned(),
+            imports: vec![],
+            structs: vec![],
+            enums: vec![],
+            functions: vec![SerdeFunction {
+                meta: None,
+                name: "main".to_owned(),
+                type_name: None,
+                visibility: Visibility::Public,
+                inputs: vec![],
+                outputs: vec![SerdeFunctionParameter {
+                    meta: None,
+                    name: "result".to_owned(),
+                    module_name: None,
+                    type_name: "usize".to_owned(),
+                    default: None,
+                }],
+                script: vec![
+                    SerdeOperation::Expression(SerdeExpression::Literal(SerdeLiteral::Usize(2))),
+                    SerdeOperation::Expression(SerdeExpression::Literal(SerdeLiteral::Usize(40))),
+                    SerdeOperation::CallFunction {
+                        name: "add".to_owned(),
+                        module_name: Some("intrinsics".to_owned()),
+                        type_name: None,
+                        visibility: None,
+                        arguments: None,
+                    },
+                ],
+            }],
+        };
+        ScriptPackage {
+            modules: vec![module.compile()],
+        }
+        .install::<VmScope<SerdeExpression>>(&mut registry, None);
+        let graph = registry.call_graph();
+        let add = registry
+            .find_function(FunctionQuery {
+                name: Some("add".into()),
+                ..Default::default()
+            })
+            .unwrap();
+        let main = registry
+            .find_function(FunctionQuery {
+                name: Some("main".into()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(graph[&add.id()].is_empty());
+        let calls = &graph[&main.id()];
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].name.as_deref(), Some("add"));
+        let resolved = registry
+            .functions()
+            .find(|function| calls[0].is_valid(function.signature()))
+            .unwrap();
+        assert_eq!(resolved.id(), add.id());
+    }
+
+    #[test]
+    fn test_install_report() {
+        let function = |name: &str, type_name: &str, script| SerdeFunction {
+            meta: None,
+            name: name.to_owned(),
+            type_name: None,
+            visibility: Visibility::Public,
+            inputs: vec![],
+            outputs: vec![SerdeFunctionParameter {
+                meta: None,
+                name: "result".to_owned(),
+                module_name: None,
+                type_name: type_name.to_owned(),
+                default: None,
+            }],
+            script,
+        };
+        let module = SerdeModule {
+            name: "a".to_owned(),
+            imports: vec![],
+            structs: vec![SerdeStruct {
+                meta: None,
+                name: "Holder".to_owned(),
+                visibility: Visibility::Public,
+                fields: vec![SerdeStructField {
+                    meta: None,
+                    name: "value".to_owned(),
+                    visibility: Visibility::Public,
+                    module_name: None,
+                    type_name: "Missing".to_owned(),
+                }],
+            }],
+            enums: vec![],
+            functions: vec![
+                function(
+                    "main",
+                    "usize",
+                    vec![SerdeOperation::CallFunction {
+                        name: "nowhere".to_owned(),
+                        module_name: None,
+                        type_name: None,
+                        visibility: None,
+                        arguments: None,
+                    }],
+                ),
+                function("broken", "Missing", vec![]),
+            ],
+        };
+        let mut registry = Registry::default().with_basic_types();
+        let report = ScriptPackage {
+            modules: vec![module.compile()],
+        }
+        .install::<VmScope<SerdeExpression>>(&mut registry, None);
+        assert_eq!(report.types, vec!["a::Holder".to_owned()]);
+        assert_eq!(report.functions, vec!["a::main".to_owned()]);
+        assert!(report.collisions.is_empty());
+        let unresolved = report
+            .unresolved
+            .iter()
+            .map(|unresolved| match &unresolved.query {
+                InstallUnresolvedQuery::Type(query) => {
+                    (unresolved.source.as_str(), query.name.as_deref().unwrap())
+                }
+                InstallUnresolvedQuery::Function(query) => {
+                    (unresolved.source.as_str(), query.name.as_deref().unwrap())
+                }
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            unresolved,
+            vec![
+                ("a::Holder", "Missing"),
+                ("a::broken", "Missing"),
+                ("a::main", "nowhere"),
+            ]
+        );
+        assert!(registry
+            .find_function(FunctionQuery {
+                name: Some("broken".into()),
+                ..Default::default()
+            })
+            .is_none());
+
+        let module = |name: &str| SerdeModule {
+            name: name.to_owned(),
+            imports: vec![],
+            structs: vec![],
+            enums: vec![],
+            functions: vec![function("main", "usize", vec![])],
+        };
+        let mut registry = Registry::default().with_basic_types();
+        let error = ScriptPackage {
+            modules: vec![module("b").compile(), module("b").compile()],
+        }
+        .install_or_err::<VmScope<SerdeExpression>>(&mut registry, None)
+        .unwrap_err();
+        assert_eq!(
+            error,
+            InstallError::Collisions(vec![InstallCollision {
+                name: "b::main".to_owned(),
+                first_source: Some("b".to_owned()),
+                second_source: "b".to_owned(),
+            }])
+        );
+        assert_eq!(registry.functions().count(), 0);
+        let report = ScriptPackage {
+            modules: vec![module("b").compile(), module("c").compile()],
+        }
+        .install_or_err::<VmScope<SerdeExpression>>(&mut registry, None)
+        .unwrap();
+        assert!(report.is_clean());
+        assert_eq!(report.functions, vec!["b::main", "c::main"]);
+    }
+
+    #[test]
+    fn test_named_registers() {
+        fn make(name: &str) -> SerdeOperation {
+            SerdeOperation::MakeRegister {
+                name: "usize".to_owned(),
+                module_name: None,
+                register_name: Some(name.to_owned()),
+            }
+        }
+
+        fn name(name: &str) -> String {
+            name.to_owned()
+        }
+
+        let literal = SerdeOperation::Expression(SerdeExpression::Literal(SerdeLiteral::Usize(1)));
+        let named = build_script(&vec![
+            make("a"),
+            make("b"),
+            literal.clone(),
+            SerdeOperation::PopToRegisterNamed { name: name("b") },
+            SerdeOperation::BranchScope {
+                script_success: vec![
+                    make("c"),
+                    SerdeOperation::PushFromRegisterNamed { name: name("a") },
+                    SerdeOperation::PopToRegisterNamed { name: name("c") },
+                ],
+                script_failure: Some(vec![SerdeOperation::DropRegisterNamed { name: name("b") }]),
+            },
+            SerdeOperation::PushScope {
+                script: vec![
+                    make("b"),
+                    SerdeOperation::DropRegisterNamed { name: name("b") },
+                    SerdeOperation::PopScope,
+                ],
+            },
+            SerdeOperation::PushFromRegisterNamed { name: name("b") },
+        ]);
+        let indexed = build_script(&vec![
+            make("a"),
+            make("b"),
+            literal,
+            SerdeOperation::PopToRegister { index: 1 },
+            SerdeOperation::BranchScope {
+                script_success: vec![
+                    make("c"),
+                    SerdeOperation::PushFromRegister { index: 0 },
+                    SerdeOperation::PopToRegister { index: 2 },
+                ],
+                script_failure: Some(vec![SerdeOperation::DropRegister { index: 1 }]),
+            },
+            SerdeOperation::PushScope {
+                script: vec![
+                    make("b"),
+                    SerdeOperation::DropRegister { index: 0 },
+                    SerdeOperation::PopScope,
+                ],
+            },
+            SerdeOperation::PushFromRegister { index: 1 },
+        ]);
+        assert_eq!(format!("{:?}", named), format!("{:?}", indexed));
+
+        assert!(std::panic::catch_unwind(|| build_script(&vec![make("a"), make("a")])).is_err());
+        assert!(std::panic::catch_unwind(|| build_script(&vec![
+            make("a"),
+            SerdeOperation::BranchScope {
+                script_success: vec![make("a")],
+                script_failure: None,
+            },
+        ]))
+        .is_err());
+        assert!(std::panic::catch_unwind(|| build_script(&vec![
+            SerdeOperation::PushFromRegisterNamed { name: name("a") }
+        ]))
+        .is_err());
+    }
+
+    #[test]
+    fn test_let_get() {
+        fn literal(value: usize) -> SerdeOperation {
+            SerdeOperation::Expression(SerdeExpression::Literal(SerdeLiteral::Usize(value)))
+        }
+
+        fn make() -> SerdeOperation {
+            SerdeOperation::MakeRegister {
+                name: "usize".to_owned(),
+                module_name: None,
+                register_name: None,
+            }
+        }
+
+        fn bind(name: &str, type_name: Option<&str>, value: SerdeScript) -> SerdeOperation {
+            SerdeOperation::Let {
+                name: name.to_owned(),
+                type_name: type_name.map(|name| name.to_owned()),
+                module_name: None,
+                value,
+            }
+        }
+
+        fn get(name: &str) -> SerdeOperation {
+            SerdeOperation::Get {
+                name: name.to_owned(),
+            }
+        }
+
+        fn run(script: SerdeScript) -> usize {
+            let mut registry = Registry::default().with_basic_types();
+            registry.add_function(define_function! {
+                registry => mod intrinsics fn sub(a: usize, b: usize) -> (result: usize) {
+                    (a - b,)
+                }
+            });
+            SerdeFunction {
+                meta: None,
+                name: "main".to_owned(),
+                type_name: None,
+                visibility: Visibility::Public,
+                inputs: vec![],
+                outputs: vec![SerdeFunctionParameter {
+                    meta: None,
+                    name: "result".to_owned(),
+                    module_name: None,
+                    type_name: "usize".to_owned(),
+                    default: None,
+                }],
+                script,
+            }
+            .compile("test")
+            .install::<VmScope<SerdeExpression>>(&mut registry, None);
+            let mut host = Host::new(Context::new(10240, 10240), RegistryHandle::new(registry));
+            let (result,) = host
+                .call_function::<(usize,), _>("main", "test", None)
+                .unwrap()
+                .run(());
+            assert_eq!(host.context().registers().position(), 0);
+            result
+        }
+
+        let sub = SerdeOperation::CallFunction {
+            name: "sub".to_owned(),
+            module_name: Some("intrinsics".to_owned()),
+            type_name: None,
+            visibility: None,
+            arguments: None,
+        };
+        // let a = 40; let b = 2; let a = a - b; return a - b;
+        let variables = vec![
+            bind("a", Some("usize"), vec![literal(40)]),
+            bind("b", Some("usize"), vec![literal(2)]),
+            bind("a", None, vec![get("b"), get("a"), sub.clone()]),
+            bind("b", None, vec![literal(2)]),
+            get("b"),
+            get("a"),
+            sub.clone(),
+        ];
+        let registers = vec![
+            literal(40),
+            make(),
+            SerdeOperation::PopToRegister { index: 0 },
+            literal(2),
+            make(),
+            SerdeOperation::PopToRegister { index: 1 },
+            SerdeOperation::PushFromRegister { index: 1 },
+            SerdeOperation::PushFromRegister { index: 0 },
+            sub.clone(),
+            SerdeOperation::PopToRegister { index: 0 },
+            literal(2),
+            SerdeOperation::PopToRegister { index: 1 },
+            SerdeOperation::PushFromRegister { index: 1 },
+            SerdeOperation::PushFromRegister { index: 0 },
+            sub,
+        ];
+        assert_eq!(
+            format!("{:?}", build_script(&variables)),
+            format!("{:?}", build_script(&registers))
+        );
+        assert_eq!(run(variables), 36);
+        assert_eq!(run(registers), 36);
+        assert_eq!(run(vec![bind("a", None, vec![literal(42)]), get("a")]), 42);
+
+        assert!(
+            std::panic::catch_unwind(|| build_script(&vec![bind("a", None, vec![get("b")])]))
+                .is_err()
+        );
+        assert!(std::panic::catch_unwind(|| build_script(&vec![get("a")])).is_err());
+    }
+
+    #[test]
+    fn test_debug_print() {
+        use std::sync::{Arc, Mutex};
+
+        let registry = Registry::default().with_basic_types();
+        let lines = Arc::new(Mutex::new(vec![]));
+        let lines2 = lines.clone();
+        let mut context = Context::new(1024, 1024);
+        context
+            .debug_printer_mut()
+            .set_sink(move |line| lines2.lock().unwrap().push(line.to_owned()));
+        context.stack().push(42usize);
+        let position = context.stack().position();
+        SerdeExpression::DebugPrint {
+            label: Some("answer".to_owned()),
+        }
+        .evaluate(&mut context, &registry);
+        SerdeExpression::DebugPrint { label: None }.evaluate(&mut context, &registry);
+        assert_eq!(context.stack().position(), position);
+        assert_eq!(context.stack().pop::<usize>().unwrap(), 42);
+        assert_eq!(
+            lines.lock().unwrap().as_slice(),
+            &["answer: 42".to_owned(), "42".to_owned()]
+        );
+    }
 }
@@ -10,7 +10,8 @@ pub mod prelude {
 use intuicio_core::{crate_version, IntuicioVersion};
 
 pub use intuicio_framework_dynamic::{
-    Array, Boolean, Function, Integer, Map, Real, Reference, Text, Transferable, Transferred, Type,
+    Array, Boolean, Function, Integer, Map, ObjectId, Real, Reference, Text, Transferable,
+    Transferred, Type,
 };
 
 pub fn frontend_simpleton_version() -> IntuicioVersion {
@@ -21,12 +22,29 @@ pub fn frontend_simpleton_version() -> IntuicioVersion {
 mod tests {
     use crate::{
         library::{jobs::Jobs, ObjectBuilder},
-        script::{SimpletonContentParser, SimpletonPackage, SimpletonScriptExpression},
+        script::{
+            SimpletonContentParser, SimpletonModule, SimpletonPackage, SimpletonScriptExpression,
+        },
         Integer, Real, Reference,
     };
     use intuicio_backend_vm::prelude::*;
     use intuicio_core::prelude::*;
 
+    /// Makes host with library, functions registered by `setup` and `test`
+    /// module parsed from `source` installed.
+    pub(crate) fn test_host(source: &str, setup: impl FnOnce(&mut Registry)) -> Host {
+        let module = SimpletonModule::parse(source).unwrap();
+        let mut package = SimpletonPackage::default();
+        package.modules.insert("test".to_owned(), module);
+        let mut registry = Registry::default();
+        crate::library::install(&mut registry);
+        setup(&mut registry);
+        package
+            .compile()
+            .install::<VmScope<SimpletonScriptExpression>>(&mut registry, None);
+        Host::new(Context::new(10240, 10240), registry.into())
+    }
+
     #[test]
     fn test_simpleton_script() {
         let mut content_provider = FileContentProvider::new("simp", SimpletonContentParser);
@@ -75,4 +93,100 @@ mod tests {
         assert_eq!(vm.context().stack().position(), 0);
         assert_eq!(*result.read::<Real>().unwrap(), 42.0);
     }
+
+    #[test]
+    fn test_statement_spans() {
+        let mut host = test_host(
+            "mod test {\n    func main() {\n        var a = 1;\n        return missing::call(a);\n    }\n}\n",
+            |_| {},
+        );
+        host.context().set_operations_trace(Some(1));
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            host.call_function::<(Reference,), _>("main", "test", None)
+                .unwrap()
+                .run(());
+        }));
+        assert!(result
+            .unwrap_err()
+            .downcast::<String>()
+            .unwrap()
+            .starts_with("Could not call non-existent function at 4:9"));
+        assert_eq!(
+            host.context()
+                .recent_operations()
+                .last()
+                .unwrap()
+                .to_string(),
+            "test::main #8: CallFunction at 4:9"
+        );
+    }
+
+    #[test]
+    fn test_zip_package() {
+        use std::io::Write;
+
+        let mut bytes = vec![];
+        {
+            let mut writer = zip::ZipWriter::new(std::io::Cursor::new(&mut bytes));
+            let options = zip::write::SimpleFileOptions::default();
+            writer.start_file("game/main.simp", options).unwrap();
+            writer
+                .write_all(
+                    br#"
+                    mod main {
+                        import "../lib/adder";
+
+                        func main() {
+                            return adder::add(adder::Adder { a: 40, b: 2 });
+                        }
+                    }
+                    "#,
+                )
+                .unwrap();
+            writer.start_file("lib/adder.simp", options).unwrap();
+            writer
+                .write_all(
+                    br#"
+                    mod adder {
+                        import "math.simp";
+
+                        struct Adder { a, b }
+
+                        func add(adder) {
+                            return math_ext::sum([adder.a, adder.b]);
+                        }
+                    }
+                    "#,
+                )
+                .unwrap();
+            writer.start_file("lib/math.simp", options).unwrap();
+            writer
+                .write_all(
+                    br#"
+                    mod math_ext {
+                        func sum(items) {
+                            return math::add(items[0], items[1]);
+                        }
+                    }
+                    "#,
+                )
+                .unwrap();
+            writer.finish().unwrap();
+        }
+        let mut content_provider =
+            ZipContentProvider::from_zip(bytes, "simp", SimpletonContentParser).unwrap();
+        let package = SimpletonPackage::new("game/main", &mut content_provider).unwrap();
+        assert_eq!(package.modules.len(), 3);
+        let mut registry = Registry::default();
+        crate::library::install(&mut registry);
+        package
+            .compile()
+            .install::<VmScope<SimpletonScriptExpression>>(&mut registry, None);
+        let mut host = Host::new(Context::new(10240, 10240), registry.into());
+        let (result,) = host
+            .call_function::<(Reference,), _>("main", "main", None)
+            .unwrap()
+            .run(());
+        assert_eq!(*result.read::<Integer>().unwrap(), 42);
+    }
 }
@@ -81,12 +81,14 @@ pub fn push(mut array: Reference, value: Reference) -> Reference {
     Reference::null()
 }
 
+/// Does nothing if `index` is out of bounds.
 #[intuicio_function(module_name = "array")]
 pub fn insert(mut array: Reference, index: Reference, value: Reference) -> Reference {
-    array
-        .write::<Array>()
-        .unwrap()
-        .insert(*index.read::<Integer>().unwrap() as _, value);
+    let mut array = array.write::<Array>().unwrap();
+    let index = *index.read::<Integer>().unwrap();
+    if index >= 0 && index as usize <= array.len() {
+        array.insert(index as usize, value);
+    }
     Reference::null()
 }
 
@@ -95,14 +97,19 @@ pub fn pop(mut array: Reference) -> Reference {
     array.write::<Array>().unwrap().pop().unwrap_or_default()
 }
 
+/// Returns removed item, or null if `index` is out of bounds.
 #[intuicio_function(module_name = "array")]
 pub fn remove(mut array: Reference, index: Reference) -> Reference {
-    array
-        .write::<Array>()
-        .unwrap()
-        .remove(*index.read::<Integer>().unwrap() as _)
+    let mut array = array.write::<Array>().unwrap();
+    let index = *index.read::<Integer>().unwrap();
+    if index >= 0 && (index as usize) < array.len() {
+        array.remove(index as usize)
+    } else {
+        Reference::null()
+    }
 }
 
+/// Returns replaced item, or null if `index` is out of bounds.
 #[intuicio_function(module_name = "array")]
 pub fn set(mut array: Reference, index: Reference, value: Reference) -> Reference {
     array
@@ -240,3 +247,54 @@ pub fn install(registry: &mut Registry) {
     registry.add_type(ArrayIter::define_struct(registry));
     registry.add_function(ArrayIter::next__define_function(registry));
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{Array, Integer, Reference};
+
+    #[test]
+    fn test_aliased_mutation() {
+        let mut host = crate::tests::test_host(
+            r#"
+            mod test {
+                func mutate(alias) {
+                    array::set(alias, 0, 42);
+                    array::push(alias, 4);
+                    array::insert(alias, 1, 10);
+                    array::remove(alias, 2);
+                    var replaced = array::set(alias, 100, 0);
+                    var removed = array::remove(alias, -1);
+                    return [replaced, removed, array::get(alias, 100)];
+                }
+            }
+            "#,
+            |_| {},
+        );
+
+        let array = Reference::new_array(
+            (1..=3)
+                .map(|value| Reference::new_integer(value, host.registry()))
+                .collect(),
+            host.registry(),
+        );
+        let alias = array.clone();
+        let (missing,) = host
+            .call_function::<(Reference,), _>("mutate", "test", None)
+            .unwrap()
+            .run((alias.clone(),));
+        for reference in [&array, &alias] {
+            let values = reference
+                .read::<Array>()
+                .unwrap()
+                .iter()
+                .map(|item| *item.read::<Integer>().unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(values, vec![42, 10, 3, 4]);
+        }
+        assert!(missing
+            .read::<Array>()
+            .unwrap()
+            .iter()
+            .all(|item| item.is_null()));
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{Array, Boolean, Integer, Map, Real, Reference, Text};
-use byteorder::{NativeEndian, NetworkEndian, ReadBytesExt, WriteBytesExt};
+use byteorder::{LittleEndian, NativeEndian, NetworkEndian, ReadBytesExt, WriteBytesExt};
 use intuicio_core::{registry::Registry, IntuicioStruct};
 use intuicio_derive::{intuicio_method, intuicio_methods, IntuicioStruct};
 use std::io::{Cursor, Read, Write};
@@ -485,6 +485,80 @@ impl Bytes {
         }
     }
 
+    #[intuicio_method(use_registry)]
+    pub fn push_f32_array(
+        registry: &Registry,
+        mut bytes: Reference,
+        array: Reference,
+    ) -> Reference {
+        let mut bytes = bytes.write::<Bytes>().unwrap();
+        let array = array.read::<Array>().unwrap();
+        let mut count = 0;
+        for value in array.iter() {
+            let value = *value.read::<Real>().unwrap() as f32;
+            if bytes.buffer.write_f32::<LittleEndian>(value).is_err() {
+                break;
+            }
+            count += 1;
+        }
+        Reference::new_integer(count, registry)
+    }
+
+    #[intuicio_method(use_registry)]
+    pub fn push_u32_array(
+        registry: &Registry,
+        mut bytes: Reference,
+        array: Reference,
+    ) -> Reference {
+        let mut bytes = bytes.write::<Bytes>().unwrap();
+        let array = array.read::<Array>().unwrap();
+        let mut count = 0;
+        for value in array.iter() {
+            let value = *value.read::<Integer>().unwrap() as u32;
+            if bytes.buffer.write_u32::<LittleEndian>(value).is_err() {
+                break;
+            }
+            count += 1;
+        }
+        Reference::new_integer(count, registry)
+    }
+
+    #[intuicio_method(use_registry)]
+    pub fn read_f32_array(
+        registry: &Registry,
+        mut bytes: Reference,
+        count: Reference,
+    ) -> Reference {
+        let mut bytes = bytes.write::<Bytes>().unwrap();
+        let count = *count.read::<Integer>().unwrap() as usize;
+        let mut result = Array::with_capacity(count);
+        for _ in 0..count {
+            match bytes.buffer.read_f32::<LittleEndian>() {
+                Ok(value) => result.push(Reference::new_real(value as Real, registry)),
+                Err(_) => break,
+            }
+        }
+        Reference::new_array(result, registry)
+    }
+
+    #[intuicio_method(use_registry)]
+    pub fn read_u32_array(
+        registry: &Registry,
+        mut bytes: Reference,
+        count: Reference,
+    ) -> Reference {
+        let mut bytes = bytes.write::<Bytes>().unwrap();
+        let count = *count.read::<Integer>().unwrap() as usize;
+        let mut result = Array::with_capacity(count);
+        for _ in 0..count {
+            match bytes.buffer.read_u32::<LittleEndian>() {
+                Ok(value) => result.push(Reference::new_integer(value as Integer, registry)),
+                Err(_) => break,
+            }
+        }
+        Reference::new_array(result, registry)
+    }
+
     #[intuicio_method()]
     pub fn serialize(mut bytes: Reference, value: Reference) -> Reference {
         let mut bytes = bytes.write::<Bytes>().unwrap();
@@ -705,6 +779,44 @@ pub fn install(registry: &mut Registry) {
     registry.add_function(Bytes::write_f64__define_function(registry));
     registry.add_function(Bytes::write_text__define_function(registry));
     registry.add_function(Bytes::write_bytes__define_function(registry));
+    registry.add_function(Bytes::push_f32_array__define_function(registry));
+    registry.add_function(Bytes::push_u32_array__define_function(registry));
+    registry.add_function(Bytes::read_f32_array__define_function(registry));
+    registry.add_function(Bytes::read_u32_array__define_function(registry));
     registry.add_function(Bytes::serialize__define_function(registry));
     registry.add_function(Bytes::deserialize__define_function(registry));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_typed_arrays() {
+        let mut registry = Registry::default();
+        crate::library::install(&mut registry);
+        let values = [0.0, 1.5, -2.25, 1024.0];
+        let array = values
+            .iter()
+            .map(|value| Reference::new_real(*value, &registry))
+            .collect();
+        let array = Reference::new_array(array, &registry);
+        let bytes = Bytes::new(&registry);
+        let count = Bytes::push_f32_array(&registry, bytes.clone(), array);
+        assert_eq!(*count.read::<Integer>().unwrap(), 4);
+        assert_eq!(bytes.read::<Bytes>().unwrap().get_ref().len(), 16);
+        assert_eq!(
+            &bytes.read::<Bytes>().unwrap().get_ref()[4..8],
+            &1.5f32.to_le_bytes()
+        );
+        Bytes::set_position(bytes.clone(), Reference::new_integer(0, &registry));
+        let result = Bytes::read_f32_array(&registry, bytes, Reference::new_integer(4, &registry));
+        let result = result
+            .read::<Array>()
+            .unwrap()
+            .iter()
+            .map(|value| *value.read::<Real>().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(result, values);
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{Array, Function, Reference};
-use intuicio_core::{context::Context, registry::Registry, IntuicioStruct};
+use intuicio_core::{context::Context, registry::Registry, script::ScriptError, IntuicioStruct};
 use intuicio_derive::{intuicio_method, intuicio_methods, IntuicioStruct};
 
 #[derive(IntuicioStruct, Default)]
@@ -25,6 +25,34 @@ impl Closure {
         )
     }
 
+    /// Creates closure capturing original closure captures followed by
+    /// `extra_args`, so arguments are supplied in order: original captures,
+    /// extra arguments, then call arguments. This composes with closures
+    /// called by ECS queries, which always pass the query as last argument.
+    #[intuicio_method(use_registry)]
+    pub fn partial(registry: &Registry, closure: Reference, extra_args: Reference) -> Reference {
+        let closure = closure
+            .read::<Closure>()
+            .expect("`closure` is not a Closure!");
+        let extra_args = extra_args
+            .read::<Array>()
+            .expect("`extra_args` is not an Array!");
+        Reference::new(
+            Closure {
+                function: closure.function.clone(),
+                captured: closure
+                    .captured
+                    .iter()
+                    .chain(extra_args.iter())
+                    .cloned()
+                    .collect(),
+            },
+            registry,
+        )
+    }
+
+    /// Calls closure from within running function, on top of its stack data.
+    /// Typed calls of registry functions can be made with `Reentry` instead.
     pub fn invoke(
         &self,
         context: &mut Context,
@@ -41,6 +69,30 @@ impl Closure {
         context.stack().pop::<Reference>().unwrap_or_default()
     }
 
+    /// Same as [`Closure::invoke`], but when called function throws script
+    /// error, context gets restored to state from before the call and error
+    /// is returned instead of being left pending.
+    pub fn try_invoke(
+        &self,
+        context: &mut Context,
+        registry: &Registry,
+        arguments: &[Reference],
+    ) -> Result<Reference, ScriptError> {
+        let token = context.store();
+        for argument in arguments.iter().rev() {
+            context.stack().push(argument.clone());
+        }
+        for argument in self.captured.iter().rev() {
+            context.stack().push(argument.clone());
+        }
+        self.function.handle().unwrap().invoke(context, registry);
+        if let Some(error) = context.take_error() {
+            context.restore(token);
+            return Err(error);
+        }
+        Ok(context.stack().pop::<Reference>().unwrap_or_default())
+    }
+
     #[intuicio_method(use_context, use_registry)]
     pub fn call(
         context: &mut Context,
@@ -59,5 +111,36 @@ impl Closure {
 pub fn install(registry: &mut Registry) {
     registry.add_type(Closure::define_struct(registry));
     registry.add_function(Closure::new__define_function(registry));
+    registry.add_function(Closure::partial__define_function(registry));
     registry.add_function(Closure::call__define_function(registry));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Integer;
+
+    #[test]
+    fn test_partial() {
+        let mut registry = Registry::default();
+        crate::library::install(&mut registry);
+        let mut context = Context::new(10240, 10240);
+        let function = Reference::new_function(
+            Function::by_name("sub", "math", &registry).unwrap(),
+            &registry,
+        );
+        let closure = Closure::new(&registry, function, Reference::new_array(vec![], &registry));
+        let closure = Closure::partial(
+            &registry,
+            closure,
+            Reference::new_array(vec![Reference::new_integer(10, &registry)], &registry),
+        );
+        let result = Closure::call(
+            &mut context,
+            &registry,
+            closure,
+            Reference::new_array(vec![Reference::new_integer(3, &registry)], &registry),
+        );
+        assert_eq!(*result.read::<Integer>().unwrap(), 7);
+    }
+}
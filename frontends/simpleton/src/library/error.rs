@@ -0,0 +1,200 @@
+use crate::{library::closure::Closure, Reference, Text};
+use intuicio_core::{
+    context::Context,
+    object::Object,
+    registry::Registry,
+    types::{struct_type::NativeStructBuilder, TypeHandle},
+    IntuicioStruct,
+};
+use intuicio_derive::{intuicio_function, intuicio_method, intuicio_methods, IntuicioStruct};
+use std::sync::{Arc, OnceLock};
+
+#[derive(IntuicioStruct, Default)]
+#[intuicio(name = "Error", module_name = "error")]
+pub struct Error {
+    pub message: Reference,
+    pub payload: Reference,
+}
+
+#[intuicio_methods(module_name = "error")]
+impl Error {
+    #[allow(clippy::new_ret_no_self)]
+    #[intuicio_method(use_registry)]
+    pub fn new(registry: &Registry, message: Reference, payload: Reference) -> Reference {
+        Reference::new(Error { message, payload }, registry)
+    }
+
+    #[intuicio_method()]
+    pub fn message(error: Reference) -> Reference {
+        error
+            .read::<Error>()
+            .map(|error| error.message.clone())
+            .unwrap_or_default()
+    }
+
+    #[intuicio_method()]
+    pub fn payload(error: Reference) -> Reference {
+        error
+            .read::<Error>()
+            .map(|error| error.payload.clone())
+            .unwrap_or_default()
+    }
+
+    #[intuicio_method(use_registry)]
+    pub fn is_error(registry: &Registry, value: Reference) -> Reference {
+        Reference::new_boolean(value.read::<Error>().is_some(), registry)
+    }
+
+    pub fn from_message(message: impl ToString, registry: &Registry) -> Reference {
+        Self::new(
+            registry,
+            Reference::new_text(message.to_string(), registry),
+            Reference::null(),
+        )
+    }
+
+    /// Makes `Error` with `message` without access to registry, for native
+    /// functions that do not take it. Values are typed by handles private to
+    /// this function, which is fine since references are read by type hash.
+    pub fn raise(message: impl ToString) -> Reference {
+        static TYPES: OnceLock<(TypeHandle, TypeHandle)> = OnceLock::new();
+        let (error_type, text_type) = TYPES.get_or_init(|| {
+            (
+                Arc::new(NativeStructBuilder::new::<Error>().build().into()),
+                Arc::new(NativeStructBuilder::new::<Text>().build().into()),
+            )
+        });
+        let message =
+            Reference::new_raw(Object::with_value(text_type.clone(), message.to_string()).unwrap());
+        Reference::new_raw(
+            Object::with_value(
+                error_type.clone(),
+                Error {
+                    message,
+                    payload: Reference::null(),
+                },
+            )
+            .unwrap(),
+        )
+    }
+}
+
+/// Returns `Error` with `message` from enclosing native function when calling
+/// crate enables its "safe" feature, so scripts can handle it with
+/// `error::catch`. Otherwise panics with `message`.
+#[macro_export]
+macro_rules! raise_error {
+    ($message:expr) => {{
+        #[cfg(feature = "safe")]
+        return $crate::library::error::Error::raise($message);
+        #[cfg(not(feature = "safe"))]
+        panic!("{}", $message);
+    }};
+}
+
+/// Unwraps option, otherwise raises error with `message` - see `raise_error`.
+#[macro_export]
+macro_rules! or_error {
+    ($value:expr, $message:expr) => {
+        match $value {
+            Some(value) => value,
+            None => $crate::raise_error!($message),
+        }
+    };
+}
+
+/// Calls closure and returns its result, or `Error` if closure threw script
+/// error - that makes failing native functions recoverable from scripts.
+#[intuicio_function(name = "try", module_name = "error", use_context, use_registry)]
+pub fn try_call(context: &mut Context, registry: &Registry, closure: Reference) -> Reference {
+    closure
+        .read::<Closure>()
+        .expect("`closure` is not a Closure!")
+        .try_invoke(context, registry, &[])
+        .unwrap_or_else(|error| Error::from_message(error.message, registry))
+}
+
+/// Returns result of handler called with error if `result` is an `Error`,
+/// otherwise returns `result` as is.
+#[intuicio_function(module_name = "error", use_context, use_registry)]
+pub fn catch(
+    context: &mut Context,
+    registry: &Registry,
+    result: Reference,
+    handler: Reference,
+) -> Reference {
+    if result.read::<Error>().is_none() {
+        return result;
+    }
+    handler
+        .read::<Closure>()
+        .expect("`handler` is not a Closure!")
+        .invoke(context, registry, std::slice::from_ref(&result))
+}
+
+pub fn install(registry: &mut Registry) {
+    registry.add_type(Error::define_struct(registry));
+    registry.add_function(Error::new__define_function(registry));
+    registry.add_function(Error::message__define_function(registry));
+    registry.add_function(Error::payload__define_function(registry));
+    registry.add_function(Error::is_error__define_function(registry));
+    registry.add_function(try_call::define_function(registry));
+    registry.add_function(catch::define_function(registry));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Integer, Reference, Text};
+    use intuicio_core::prelude::*;
+    use intuicio_derive::intuicio_function;
+
+    #[intuicio_function(module_name = "native", use_context, use_registry)]
+    pub fn double(context: &mut Context, registry: &Registry, value: Reference) -> Reference {
+        match value.read::<Integer>() {
+            Some(value) => Reference::new_integer(*value * 2, registry),
+            None => {
+                context.throw(ScriptError::new("`value` is not an Integer!"));
+                Reference::null()
+            }
+        }
+    }
+
+    #[test]
+    fn test_try_catch() {
+        let mut host = crate::tests::test_host(
+            r#"
+            mod test {
+                func main(value) {
+                    var result = error::try(@[value]() {
+                        return native::double(value);
+                    });
+                    return error::catch(result, @[](error) {
+                        return error::message(error);
+                    });
+                }
+            }
+            "#,
+            |registry| {
+                registry.add_function(double::define_function(registry));
+            },
+        );
+
+        let value = Reference::new_integer(21, host.registry());
+        let (result,) = host
+            .call_function::<(Reference,), _>("main", "test", None)
+            .unwrap()
+            .run((value,));
+        assert_eq!(*result.read::<Integer>().unwrap(), 42);
+
+        let value = Reference::new_text("21".to_owned(), host.registry());
+        let (result,) = host
+            .call_function::<(Reference,), _>("main", "test", None)
+            .unwrap()
+            .run((value,));
+        assert_eq!(host.context().stack().position(), 0);
+        assert_eq!(
+            result.read::<Text>().unwrap().as_str(),
+            "`value` is not an Integer!"
+        );
+    }
+}
@@ -4,6 +4,9 @@ use intuicio_derive::{intuicio_function, intuicio_method, intuicio_methods, Intu
 
 const GENERATOR: &str = "next";
 
+/// Iterator is any closure, map or object with `next` field holding closure,
+/// which gets iterator as argument and returns next value or null when done.
+/// All `iter` combinators accept and produce iterators following that rule.
 #[intuicio_function(module_name = "iter", use_context, use_registry)]
 pub fn next(context: &mut Context, registry: &Registry, iterator: Reference) -> Reference {
     let iter = iterator.clone();
@@ -63,6 +63,7 @@ pub fn find_key(registry: &Registry, map: Reference, value: Reference) -> Refere
         .unwrap_or_default()
 }
 
+/// Returns removed value, or null if `key` is absent.
 #[intuicio_function(module_name = "map")]
 pub fn remove(mut map: Reference, key: Reference) -> Reference {
     map.write::<Map>()
@@ -71,6 +72,7 @@ pub fn remove(mut map: Reference, key: Reference) -> Reference {
         .unwrap_or_default()
 }
 
+/// Returns replaced value, or null if `key` was absent.
 #[intuicio_function(module_name = "map")]
 pub fn set(mut map: Reference, key: Reference, value: Reference) -> Reference {
     map.write::<Map>()
@@ -271,3 +273,50 @@ macro_rules! map {
         result
     }};
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{Integer, Map, Reference};
+
+    #[test]
+    fn test_aliased_mutation() {
+        let mut host = crate::tests::test_host(
+            r#"
+            mod test {
+                func mutate(alias) {
+                    map::set(alias, "a", 42);
+                    map::set(alias, "c", 3);
+                    var removed = map::remove(alias, "b");
+                    var missing = map::remove(alias, "b");
+                    return [removed, missing, map::get(alias, "b")];
+                }
+            }
+            "#,
+            |_| {},
+        );
+
+        let map = Reference::new_map(
+            crate::map! {
+                a: Reference::new_integer(1, host.registry()),
+                b: Reference::new_integer(2, host.registry()),
+            },
+            host.registry(),
+        );
+        let alias = map.clone();
+        let (removed,) = host
+            .call_function::<(Reference,), _>("mutate", "test", None)
+            .unwrap()
+            .run((alias.clone(),));
+        for reference in [&map, &alias] {
+            let map = reference.read::<Map>().unwrap();
+            assert_eq!(map.len(), 2);
+            assert_eq!(*map.get("a").unwrap().read::<Integer>().unwrap(), 42);
+            assert_eq!(*map.get("c").unwrap().read::<Integer>().unwrap(), 3);
+            assert!(!map.contains_key("b"));
+        }
+        let removed = removed.read::<crate::Array>().unwrap();
+        assert_eq!(*removed[0].read::<Integer>().unwrap(), 2);
+        assert!(removed[1].is_null());
+        assert!(removed[2].is_null());
+    }
+}
@@ -1,9 +1,14 @@
+//! Mutating functions of `array` and `map` change collections in place, so
+//! change is observed through every reference sharing them. Items are stored
+//! as references, not copies.
+
 pub mod array;
 pub mod bytes;
 pub mod closure;
 #[cfg(feature = "console")]
 pub mod console;
 pub mod debug;
+pub mod error;
 pub mod event;
 #[cfg(feature = "ffi")]
 pub mod ffi;
@@ -21,6 +26,7 @@ pub mod net;
 pub mod process;
 pub mod promise;
 pub mod reflect;
+pub mod string_builder;
 pub mod text;
 pub mod toml;
 
@@ -67,6 +73,7 @@ pub fn install(registry: &mut Registry) {
     reflect::install(registry);
     math::install(registry);
     text::install(registry);
+    string_builder::install(registry);
     array::install(registry);
     map::install(registry);
     #[cfg(feature = "console")]
@@ -83,6 +90,7 @@ pub fn install(registry: &mut Registry) {
     json::install(registry);
     toml::install(registry);
     debug::install(registry);
+    error::install(registry);
     closure::install(registry);
     iter::install(registry);
     promise::install(registry);
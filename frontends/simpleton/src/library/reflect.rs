@@ -330,9 +330,22 @@ pub fn are_same(registry: &Registry, a: Reference, b: Reference) -> Reference {
     Reference::new_boolean(are_same_impl(&a, &b), registry)
 }
 
+/// Truncates toward zero. NaN, infinities and values outside of `Integer`
+/// range have no integer representation.
+fn real_to_integer(value: Real) -> Option<Integer> {
+    let value = value.trunc();
+    if value.is_finite() && value >= Integer::MIN as Real && value < -(Integer::MIN as Real) {
+        Some(value as Integer)
+    } else {
+        None
+    }
+}
+
+/// Coercions below return null for null values and values that cannot be
+/// converted, instead of failing.
 #[intuicio_function(module_name = "reflect", use_registry)]
 pub fn to_boolean(registry: &Registry, value: Reference) -> Reference {
-    if value.type_of().unwrap().is::<Boolean>() {
+    if value.read::<Boolean>().is_some() {
         return value;
     }
     if let Some(value) = value.read::<Integer>() {
@@ -342,7 +355,7 @@ pub fn to_boolean(registry: &Registry, value: Reference) -> Reference {
         return Reference::new_boolean(*value != 0.0, registry);
     }
     if let Some(value) = value.read::<Text>() {
-        if let Ok(value) = value.parse::<Boolean>() {
+        if let Ok(value) = value.trim().parse::<Boolean>() {
             return Reference::new_boolean(value, registry);
         }
     }
@@ -357,26 +370,35 @@ pub fn to_boolean(registry: &Registry, value: Reference) -> Reference {
 
 #[intuicio_function(module_name = "reflect", use_registry)]
 pub fn to_integer(registry: &Registry, value: Reference) -> Reference {
-    if value.type_of().unwrap().is::<Integer>() {
+    if value.read::<Integer>().is_some() {
         return value;
     }
     if let Some(value) = value.read::<Boolean>() {
         return Reference::new_integer(if *value { 1 } else { 0 }, registry);
     }
     if let Some(value) = value.read::<Real>() {
-        return Reference::new_integer(*value as Integer, registry);
+        return real_to_integer(*value)
+            .map(|value| Reference::new_integer(value, registry))
+            .unwrap_or_default();
     }
     if let Some(value) = value.read::<Text>() {
+        let value = value.trim();
         if let Ok(value) = value.parse::<Integer>() {
             return Reference::new_integer(value, registry);
         }
+        return value
+            .parse::<Real>()
+            .ok()
+            .and_then(real_to_integer)
+            .map(|value| Reference::new_integer(value, registry))
+            .unwrap_or_default();
     }
     Reference::null()
 }
 
 #[intuicio_function(module_name = "reflect", use_registry)]
 pub fn to_real(registry: &Registry, value: Reference) -> Reference {
-    if value.type_of().unwrap().is::<Real>() {
+    if value.read::<Real>().is_some() {
         return value;
     }
     if let Some(value) = value.read::<Boolean>() {
@@ -386,7 +408,7 @@ pub fn to_real(registry: &Registry, value: Reference) -> Reference {
         return Reference::new_real(*value as Real, registry);
     }
     if let Some(value) = value.read::<Text>() {
-        if let Ok(value) = value.parse::<Real>() {
+        if let Ok(value) = value.trim().parse::<Real>() {
             return Reference::new_real(value, registry);
         }
     }
@@ -521,3 +543,49 @@ pub fn install(registry: &mut Registry) {
     registry.add_function(stack_size::define_function(registry));
     registry.add_function(registers_size::define_function(registry));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coercion() {
+        let mut registry = Registry::default();
+        crate::library::install(&mut registry);
+        let real = |value| Reference::new_real(value, &registry);
+        let integer = |value| Reference::new_integer(value, &registry);
+        let text = |value: &str| Reference::new_text(value.to_owned(), &registry);
+        let read_integer = |value: Reference| *value.read::<Integer>().unwrap();
+        let read_real = |value: Reference| *value.read::<Real>().unwrap();
+        let read_boolean = |value: Reference| *value.read::<Boolean>().unwrap();
+
+        for value in [0, 1, -42, 1 << 52, -(1 << 52)] {
+            let result = to_integer(&registry, to_real(&registry, integer(value)));
+            assert_eq!(read_integer(result), value);
+        }
+        assert_eq!(read_integer(to_integer(&registry, real(2.9))), 2);
+        assert_eq!(read_integer(to_integer(&registry, real(-2.9))), -2);
+        assert_eq!(
+            read_integer(to_integer(&registry, real(-9223372036854775808.0))),
+            Integer::MIN
+        );
+        assert!(to_integer(&registry, real(9223372036854775808.0)).is_null());
+        assert!(to_integer(&registry, real(Real::NAN)).is_null());
+        assert!(to_integer(&registry, real(Real::INFINITY)).is_null());
+
+        assert_eq!(read_integer(to_integer(&registry, text(" 42 "))), 42);
+        assert_eq!(read_integer(to_integer(&registry, text("-4.7"))), -4);
+        assert_eq!(read_real(to_real(&registry, text("1.5"))), 1.5);
+        assert_eq!(read_real(to_real(&registry, text("7"))), 7.0);
+        assert!(read_boolean(to_boolean(&registry, text("true"))));
+        assert!(!read_boolean(to_boolean(&registry, real(0.0))));
+        assert!(read_boolean(to_boolean(&registry, integer(-3))));
+
+        assert!(to_integer(&registry, text("forty two")).is_null());
+        assert!(to_real(&registry, text("1.5x")).is_null());
+        assert!(to_boolean(&registry, text("yes")).is_null());
+        assert!(to_integer(&registry, Reference::null()).is_null());
+        assert!(to_real(&registry, Reference::null()).is_null());
+        assert!(to_boolean(&registry, Reference::null()).is_null());
+    }
+}
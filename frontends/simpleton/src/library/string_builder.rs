@@ -0,0 +1,194 @@
+use crate::{Integer, Reference, Text};
+use intuicio_core::{registry::Registry, IntuicioStruct};
+use intuicio_derive::{intuicio_method, intuicio_methods, IntuicioStruct};
+
+/// Growable text buffer for assembling large text out of many fragments,
+/// without reallocating whole text on every concatenation.
+#[derive(IntuicioStruct, Default)]
+#[intuicio(name = "StringBuilder", module_name = "string_builder")]
+pub struct StringBuilder {
+    #[intuicio(ignore)]
+    buffer: String,
+}
+
+#[intuicio_methods(module_name = "string_builder")]
+impl StringBuilder {
+    pub fn as_str(&self) -> &str {
+        &self.buffer
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+
+    #[allow(clippy::new_ret_no_self)]
+    #[intuicio_method(use_registry)]
+    pub fn new(registry: &Registry) -> Reference {
+        Reference::new(StringBuilder::default(), registry)
+    }
+
+    #[intuicio_method(use_registry)]
+    pub fn with_capacity(registry: &Registry, capacity: Reference) -> Reference {
+        let capacity = *capacity
+            .read::<Integer>()
+            .expect("`capacity` is not an Integer!");
+        Reference::new(
+            StringBuilder {
+                buffer: String::with_capacity(capacity.max(0) as usize),
+            },
+            registry,
+        )
+    }
+
+    #[intuicio_method()]
+    pub fn push(mut builder: Reference, text: Reference) -> Reference {
+        let mut builder = builder
+            .write::<StringBuilder>()
+            .expect("`builder` is not a StringBuilder!");
+        builder
+            .buffer
+            .push_str(&text.read::<Text>().expect("`text` is not a Text!"));
+        Reference::null()
+    }
+
+    /// `text` is optional, null pushes just new line.
+    #[intuicio_method()]
+    pub fn push_line(mut builder: Reference, text: Reference) -> Reference {
+        let mut builder = builder
+            .write::<StringBuilder>()
+            .expect("`builder` is not a StringBuilder!");
+        if !text.is_null() {
+            builder
+                .buffer
+                .push_str(&text.read::<Text>().expect("`text` is not a Text!"));
+        }
+        builder.buffer.push('\n');
+        Reference::null()
+    }
+
+    #[intuicio_method(use_registry)]
+    pub fn length(registry: &Registry, builder: Reference) -> Reference {
+        let builder = builder
+            .read::<StringBuilder>()
+            .expect("`builder` is not a StringBuilder!");
+        Reference::new_integer(builder.buffer.len() as Integer, registry)
+    }
+
+    #[intuicio_method()]
+    pub fn clear(mut builder: Reference) -> Reference {
+        builder
+            .write::<StringBuilder>()
+            .expect("`builder` is not a StringBuilder!")
+            .buffer
+            .clear();
+        Reference::null()
+    }
+
+    /// Returns copy of assembled text, builder can be used further.
+    #[intuicio_method(use_registry)]
+    pub fn build(registry: &Registry, builder: Reference) -> Reference {
+        let builder = builder
+            .read::<StringBuilder>()
+            .expect("`builder` is not a StringBuilder!");
+        Reference::new_text(builder.buffer.clone(), registry)
+    }
+}
+
+pub fn install(registry: &mut Registry) {
+    registry.add_type(StringBuilder::define_struct(registry));
+    registry.add_function(StringBuilder::new__define_function(registry));
+    registry.add_function(StringBuilder::with_capacity__define_function(registry));
+    registry.add_function(StringBuilder::push__define_function(registry));
+    registry.add_function(StringBuilder::push_line__define_function(registry));
+    registry.add_function(StringBuilder::length__define_function(registry));
+    registry.add_function(StringBuilder::clear__define_function(registry));
+    registry.add_function(StringBuilder::build__define_function(registry));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StringBuilder;
+    use crate::{Integer, Reference, Text};
+
+    #[test]
+    fn test_string_builder() {
+        let mut host = crate::tests::test_host(
+            r#"
+            mod test {
+                func build(builder, count) {
+                    var index = 0;
+                    while math::less_than(index, count) {
+                        string_builder::push(builder, reflect::to_text(index));
+                        string_builder::push_line(builder, ",");
+                        index = math::add(index, 1);
+                    }
+                    string_builder::push_line(builder, null);
+                    return string_builder::build(builder);
+                }
+
+                func concat(count) {
+                    var result = "";
+                    var index = 0;
+                    while math::less_than(index, count) {
+                        result = text::combine(result, reflect::to_text(index));
+                        result = text::combine(result, ",\n");
+                        index = math::add(index, 1);
+                    }
+                    return text::combine(result, "\n");
+                }
+            }
+            "#,
+            |_| {},
+        );
+
+        let count = Reference::new_integer(1000, host.registry());
+        let builder = StringBuilder::new(host.registry());
+        let (built,) = host
+            .call_function::<(Reference,), _>("build", "test", None)
+            .unwrap()
+            .run((builder.clone(), count.clone()));
+        let (concatenated,) = host
+            .call_function::<(Reference,), _>("concat", "test", None)
+            .unwrap()
+            .run((count,));
+        let built = built.read::<Text>().unwrap().to_owned();
+        assert_eq!(built, concatenated.read::<Text>().unwrap().as_str());
+        assert!(built.starts_with("0,\n1,\n2,\n"));
+        assert_eq!(
+            *StringBuilder::length(host.registry(), builder.clone())
+                .read::<Integer>()
+                .unwrap(),
+            built.len() as Integer
+        );
+        {
+            let builder = builder.read::<StringBuilder>().unwrap();
+            assert_eq!(builder.as_str(), built);
+            // growable buffer over-allocates at most twice what it holds.
+            assert!(builder.capacity() < built.len() * 2);
+        }
+
+        let builder = StringBuilder::with_capacity(
+            host.registry(),
+            Reference::new_integer(built.len() as Integer, host.registry()),
+        );
+        let capacity = builder.read::<StringBuilder>().unwrap().capacity();
+        let count = Reference::new_integer(1000, host.registry());
+        host.call_function::<(Reference,), _>("build", "test", None)
+            .unwrap()
+            .run((builder.clone(), count));
+        // no reallocation happened when capacity was reserved up front.
+        assert_eq!(
+            builder.read::<StringBuilder>().unwrap().capacity(),
+            capacity
+        );
+
+        StringBuilder::clear(builder.clone());
+        assert_eq!(
+            StringBuilder::build(host.registry(), builder)
+                .read::<Text>()
+                .unwrap()
+                .as_str(),
+            ""
+        );
+    }
+}
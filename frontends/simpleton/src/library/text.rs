@@ -1,4 +1,4 @@
-use crate::{Array, Boolean, Integer, Reference, Text};
+use crate::{Array, Boolean, Integer, Real, Reference, Text};
 use intuicio_core::{define_native_struct, registry::Registry};
 use intuicio_derive::intuicio_function;
 use regex::{Captures, Regex};
@@ -153,6 +153,63 @@ pub fn not_equals(registry: &Registry, a: Reference, b: Reference) -> Reference
     Reference::null()
 }
 
+/// Negative precision gives shortest text that parses back to the same value.
+/// NaN and infinities are formatted as `NaN`, `inf` and `-inf`.
+#[intuicio_function(module_name = "text", use_registry)]
+pub fn real_to_text(registry: &Registry, value: Reference, precision: Reference) -> Reference {
+    let value = *value.read::<Real>().unwrap();
+    let precision = *precision.read::<Integer>().unwrap();
+    let text = if precision < 0 {
+        format!("{}", value)
+    } else {
+        format!("{:.*}", precision as usize, value)
+    };
+    Reference::new_text(text, registry)
+}
+
+#[intuicio_function(module_name = "text", use_registry)]
+pub fn text_to_real(registry: &Registry, text: Reference) -> Reference {
+    text.read::<Text>()
+        .unwrap()
+        .parse::<Real>()
+        .map(|value| Reference::new_real(value, registry))
+        .unwrap_or_default()
+}
+
+#[intuicio_function(module_name = "text", use_registry)]
+pub fn integer_to_text_radix(registry: &Registry, value: Reference, radix: Reference) -> Reference {
+    let value = *value.read::<Integer>().unwrap();
+    let radix = *radix.read::<Integer>().unwrap();
+    if !(2..=36).contains(&radix) {
+        return Reference::null();
+    }
+    let radix = radix as u64;
+    let mut magnitude = value.unsigned_abs();
+    let mut digits = vec![];
+    loop {
+        digits.push(char::from_digit((magnitude % radix) as u32, radix as u32).unwrap());
+        magnitude /= radix;
+        if magnitude == 0 {
+            break;
+        }
+    }
+    if value < 0 {
+        digits.push('-');
+    }
+    Reference::new_text(digits.into_iter().rev().collect(), registry)
+}
+
+#[intuicio_function(module_name = "text", use_registry)]
+pub fn text_to_integer_radix(registry: &Registry, text: Reference, radix: Reference) -> Reference {
+    let radix = *radix.read::<Integer>().unwrap();
+    if !(2..=36).contains(&radix) {
+        return Reference::null();
+    }
+    Integer::from_str_radix(text.read::<Text>().unwrap().as_str(), radix as u32)
+        .map(|value| Reference::new_integer(value, registry))
+        .unwrap_or_default()
+}
+
 pub fn install(registry: &mut Registry) {
     registry.add_type(define_native_struct! {
         registry => mod text struct Text (Text) {}
@@ -169,4 +226,85 @@ pub fn install(registry: &mut Registry) {
     registry.add_function(from_bytes::define_function(registry));
     registry.add_function(equals::define_function(registry));
     registry.add_function(not_equals::define_function(registry));
+    registry.add_function(real_to_text::define_function(registry));
+    registry.add_function(text_to_real::define_function(registry));
+    registry.add_function(integer_to_text_radix::define_function(registry));
+    registry.add_function(text_to_integer_radix::define_function(registry));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_number_text() {
+        let mut registry = Registry::default();
+        crate::library::install(&mut registry);
+        let real = |value| Reference::new_real(value, &registry);
+        let integer = |value| Reference::new_integer(value, &registry);
+        let text = |value: &str| Reference::new_text(value.to_owned(), &registry);
+        let read_text = |value: Reference| value.read::<Text>().unwrap().to_owned();
+
+        for value in [0.1, -2.5, 1.0 / 3.0, 1.0e-300, 123456789.125] {
+            let result = text_to_real(&registry, real_to_text(&registry, real(value), integer(-1)));
+            assert_eq!(*result.read::<Real>().unwrap(), value);
+        }
+        assert_eq!(
+            read_text(real_to_text(&registry, real(1.0 / 3.0), integer(3))),
+            "0.333"
+        );
+        assert_eq!(
+            read_text(real_to_text(&registry, real(2.5), integer(0))),
+            "2"
+        );
+        assert_eq!(
+            read_text(real_to_text(&registry, real(Real::NAN), integer(2))),
+            "NaN"
+        );
+        assert_eq!(
+            read_text(real_to_text(
+                &registry,
+                real(Real::NEG_INFINITY),
+                integer(-1)
+            )),
+            "-inf"
+        );
+        assert!(text_to_real(&registry, text("NaN"))
+            .read::<Real>()
+            .unwrap()
+            .is_nan());
+        assert!(text_to_real(&registry, text("1.5x")).is_null());
+
+        assert_eq!(
+            read_text(integer_to_text_radix(&registry, integer(255), integer(16))),
+            "ff"
+        );
+        assert_eq!(
+            read_text(integer_to_text_radix(&registry, integer(-5), integer(2))),
+            "-101"
+        );
+        assert_eq!(
+            read_text(integer_to_text_radix(
+                &registry,
+                integer(Integer::MIN),
+                integer(16)
+            )),
+            "-8000000000000000"
+        );
+        assert!(integer_to_text_radix(&registry, integer(1), integer(1)).is_null());
+        assert_eq!(
+            *text_to_integer_radix(&registry, text("ff"), integer(16))
+                .read::<Integer>()
+                .unwrap(),
+            255
+        );
+        assert_eq!(
+            *text_to_integer_radix(&registry, text("-101"), integer(2))
+                .read::<Integer>()
+                .unwrap(),
+            -5
+        );
+        assert!(text_to_integer_radix(&registry, text("102"), integer(2)).is_null());
+        assert!(text_to_integer_radix(&registry, text("1"), integer(37)).is_null());
+    }
 }
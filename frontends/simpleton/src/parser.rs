@@ -5,6 +5,7 @@ use crate::{
     },
     Integer, Real, Text,
 };
+use intuicio_core::script::ScriptSpan;
 use pest::{iterators::Pair, Parser};
 use pest_derive::Parser;
 
@@ -86,7 +87,7 @@ fn parse_function(pair: Pair<Rule>) -> SimpletonFunction {
                 result.arguments.push(parse_identifier(pair));
             }
             Rule::statement => {
-                result.statements.push(parse_statement(pair));
+                push_statement(pair, &mut result.statements);
             }
             rule => unreachable!("{:?}", rule),
         }
@@ -94,6 +95,21 @@ fn parse_function(pair: Pair<Rule>) -> SimpletonFunction {
     result
 }
 
+fn parse_statements<'a>(pairs: impl Iterator<Item = Pair<'a, Rule>>) -> Vec<SimpletonStatement> {
+    let mut result = vec![];
+    for pair in pairs {
+        push_statement(pair, &mut result);
+    }
+    result
+}
+
+/// Pushes statement preceded by span of its source.
+fn push_statement(pair: Pair<Rule>, result: &mut Vec<SimpletonStatement>) {
+    let (line, column) = pair.line_col();
+    result.push(SimpletonStatement::Span(ScriptSpan::new(line, column)));
+    result.push(parse_statement(pair));
+}
+
 fn parse_statement(pair: Pair<Rule>) -> SimpletonStatement {
     let pair = pair.into_inner().next().unwrap();
     match pair.as_rule() {
@@ -113,10 +129,10 @@ fn parse_statement(pair: Pair<Rule>) -> SimpletonStatement {
             for pair in pairs {
                 match pair.as_rule() {
                     Rule::if_else_success => {
-                        success = pair.into_inner().map(parse_statement).collect();
+                        success = parse_statements(pair.into_inner());
                     }
                     Rule::if_else_failure => {
-                        failure = Some(pair.into_inner().map(parse_statement).collect());
+                        failure = Some(parse_statements(pair.into_inner()));
                     }
                     rule => unreachable!("{:?}", rule),
                 }
@@ -130,7 +146,7 @@ fn parse_statement(pair: Pair<Rule>) -> SimpletonStatement {
         Rule::while_loop => {
             let mut pairs = pair.into_inner();
             let condition = parse_expression_start(pairs.next().unwrap());
-            let statements = pairs.map(parse_statement).collect();
+            let statements = parse_statements(pairs);
             SimpletonStatement::While {
                 condition,
                 statements,
@@ -140,7 +156,7 @@ fn parse_statement(pair: Pair<Rule>) -> SimpletonStatement {
             let mut pairs = pair.into_inner();
             let variable = parse_identifier(pairs.next().unwrap());
             let iterator = parse_expression_start(pairs.next().unwrap());
-            let statements = pairs.map(parse_statement).collect();
+            let statements = parse_statements(pairs);
             SimpletonStatement::For {
                 variable,
                 iterator,
@@ -203,7 +219,7 @@ fn parse_expression_start(pair: Pair<Rule>) -> SimpletonExpressionStart {
                         arguments.push(parse_identifier(pair));
                     }
                     Rule::statement => {
-                        statements.push(parse_statement(pair));
+                        push_statement(pair, &mut statements);
                     }
                     Rule::expression_next => {
                         next = Some(parse_expression_next(pair));
@@ -9,7 +9,7 @@ use intuicio_core::{
     script::{
         BytesContentParser, ScriptContent, ScriptContentProvider, ScriptExpression, ScriptFunction,
         ScriptFunctionParameter, ScriptFunctionSignature, ScriptHandle, ScriptModule,
-        ScriptOperation, ScriptPackage, ScriptStruct, ScriptStructField,
+        ScriptOperation, ScriptPackage, ScriptSpan, ScriptStruct, ScriptStructField,
     },
     types::TypeQuery,
     IntuicioVersion, Visibility,
@@ -808,6 +808,8 @@ pub enum SimpletonStatement {
         iterator: SimpletonExpressionStart,
         statements: Vec<SimpletonStatement>,
     },
+    /// Source location of statements that follow it.
+    Span(ScriptSpan),
 }
 
 impl SimpletonStatement {
@@ -866,6 +868,7 @@ impl SimpletonStatement {
                 }
                 result.push(ScriptOperation::DefineRegister {
                     query: TypeQuery::of::<Reference>(),
+                    immutable: false,
                 });
                 value.compile(result, registers, closures, closures_index);
                 result.push(ScriptOperation::PopToRegister {
@@ -991,6 +994,7 @@ impl SimpletonStatement {
                 }
                 operations.push(ScriptOperation::DefineRegister {
                     query: TypeQuery::of::<Reference>(),
+                    immutable: false,
                 });
                 let index = registers
                     .iter()
@@ -1040,6 +1044,9 @@ impl SimpletonStatement {
                     expression: SimpletonScriptExpression::StackDrop,
                 });
             }
+            Self::Span(span) => {
+                result.push(ScriptOperation::Span { span: span.clone() });
+            }
         }
     }
 }
@@ -1087,6 +1094,7 @@ impl SimpletonFunction {
             }
             operations.push(ScriptOperation::DefineRegister {
                 query: TypeQuery::of::<Reference>(),
+                immutable: false,
             });
             operations.push(ScriptOperation::PopToRegister {
                 index: registers.iter().position(|n| n == name).unwrap(),
@@ -1,12 +1,15 @@
 use intuicio_core::{
     context::Context,
     crate_version,
-    function::{FunctionQuery, FunctionQueryParameter},
+    function::FunctionQuery,
+    literal_from_stack,
     registry::Registry,
     script::{
-        BytesContentParser, ScriptContentProvider, ScriptEnum, ScriptEnumVariant, ScriptExpression,
-        ScriptFunction, ScriptFunctionParameter, ScriptFunctionSignature, ScriptHandle,
-        ScriptModule, ScriptOperation, ScriptPackage, ScriptStruct, ScriptStructField,
+        BytesContentParser, CircularDependencyError, CustomScriptExpression,
+        ScriptConstantExpression, ScriptContentProvider, ScriptEnum, ScriptEnumVariant,
+        ScriptExpression, ScriptFunction, ScriptFunctionParameter, ScriptFunctionSignature,
+        ScriptHandle, ScriptModule, ScriptModuleImport, ScriptOperation, ScriptPackage, ScriptSpan,
+        ScriptStruct, ScriptStructField,
     },
     types::TypeQuery,
     IntuicioVersion, Visibility,
@@ -38,6 +41,7 @@ pub enum VaultLiteral {
     F64(f64),
     Char(char),
     String(String),
+    Bytes(Vec<u8>),
 }
 
 impl VaultLiteral {
@@ -61,15 +65,28 @@ impl VaultLiteral {
             Self::F64(value) => context.stack().push(*value),
             Self::Char(value) => context.stack().push(*value),
             Self::String(value) => context.stack().push(value.to_owned()),
+            Self::Bytes(value) => context.stack().push(value.to_owned()),
         };
     }
+
+    fn from_stack(context: &mut Context) -> Option<Self> {
+        literal_from_stack!(VaultLiteral, context)
+    }
 }
 
 #[derive(Debug)]
 pub enum VaultScriptExpression {
     Literal(VaultLiteral),
     StackDrop,
-    StackProduce { name: String },
+    StackProduce {
+        name: String,
+    },
+    /// Prints value on top of stack with debug printer of context, leaving
+    /// stack unchanged.
+    DebugPrint {
+        label: Option<String>,
+    },
+    Custom(CustomScriptExpression),
 }
 
 impl ScriptExpression for VaultScriptExpression {
@@ -84,30 +101,34 @@ impl ScriptExpression for VaultScriptExpression {
             Self::StackProduce { name } => {
                 let type_hash = context.stack().peek().unwrap();
                 registry
-                    .find_function(FunctionQuery {
-                        name: Some(name.into()),
-                        type_query: Some(TypeQuery {
-                            type_hash: Some(type_hash),
-                            ..Default::default()
-                        }),
-                        inputs: [FunctionQueryParameter {
-                            type_query: Some(TypeQuery {
-                                type_hash: Some(type_hash),
-                                ..Default::default()
-                            }),
-                            ..Default::default()
-                        }]
-                        .as_slice()
-                        .into(),
-                        ..Default::default()
-                    })
+                    .find_function(
+                        FunctionQuery::named(name)
+                            .of_type(TypeQuery::default().with_hash(type_hash))
+                            .taking([TypeQuery::default().with_hash(type_hash)]),
+                    )
                     .unwrap()
                     .invoke(context, registry);
             }
+            Self::DebugPrint { label } => {
+                context.debug_print(label.as_deref(), registry);
+            }
+            Self::Custom(expression) => {
+                expression.evaluate(context, registry);
+            }
         }
     }
 }
 
+impl ScriptConstantExpression for VaultScriptExpression {
+    fn is_constant(&self) -> bool {
+        matches!(self, Self::Literal(_))
+    }
+
+    fn constant_from_stack(context: &mut Context, _: &Registry) -> Option<Self> {
+        VaultLiteral::from_stack(context).map(Self::Literal)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum VaultExpression {
     DefineVariable {
@@ -147,6 +168,14 @@ pub enum VaultExpression {
         #[serde(default, skip_serializing_if = "Option::is_none")]
         failure: Option<Vec<VaultStatement>>,
     },
+    /// Calls `index` method of target type, which takes target and index
+    /// and gives back original target and item. Taken variable target gets
+    /// its value back, other targets are dropped. Out of bounds indices are
+    /// handled by that method.
+    Index {
+        target: Box<VaultExpression>,
+        index: Box<VaultExpression>,
+    },
 }
 
 impl VaultExpression {
@@ -177,7 +206,7 @@ impl VaultExpression {
                         name: "clone".to_owned(),
                     },
                 });
-                result.push(ScriptOperation::PopToRegister {
+                result.push(ScriptOperation::RestoreRegister {
                     index: registers.iter().position(|n| n == name.as_str()).unwrap(),
                 });
             }
@@ -190,7 +219,7 @@ impl VaultExpression {
                         name: "ref".to_owned(),
                     },
                 });
-                result.push(ScriptOperation::PopToRegister {
+                result.push(ScriptOperation::RestoreRegister {
                     index: registers.iter().position(|n| n == name.as_str()).unwrap(),
                 });
             }
@@ -203,7 +232,7 @@ impl VaultExpression {
                         name: "ref_mut".to_owned(),
                     },
                 });
-                result.push(ScriptOperation::PopToRegister {
+                result.push(ScriptOperation::RestoreRegister {
                     index: registers.iter().position(|n| n == name.as_str()).unwrap(),
                 });
             }
@@ -255,31 +284,170 @@ impl VaultExpression {
                 failure,
             } => {
                 condition.compile(result, registers);
-                let mut success_operations = vec![];
-                for statement in success {
-                    statement.compile(&mut success_operations, registers);
-                }
-                let failure_handle = failure.as_ref().map(|failure| {
-                    let mut operations = vec![];
-                    for statement in failure {
-                        statement.compile(&mut operations, registers);
-                    }
-                    ScriptHandle::new(operations)
-                });
                 result.push(ScriptOperation::BranchScope {
-                    scope_success: ScriptHandle::new(success_operations),
-                    scope_failure: failure_handle,
+                    scope_success: ScriptHandle::new(VaultStatement::compile_block(
+                        success, registers,
+                    )),
+                    scope_failure: failure.as_ref().map(|failure| {
+                        ScriptHandle::new(VaultStatement::compile_block(failure, registers))
+                    }),
+                });
+            }
+            Self::Index { target, index } => {
+                index.compile(result, registers);
+                target.compile(result, registers);
+                result.push(ScriptOperation::Expression {
+                    expression: VaultScriptExpression::StackProduce {
+                        name: "index".to_owned(),
+                    },
                 });
+                target.compile_give_back(result, registers);
+            }
+        }
+    }
+
+    /// Stores original value given back by intrinsic into variable it was
+    /// taken from, or drops it if it was temporary.
+    fn compile_give_back(
+        &self,
+        result: &mut Vec<ScriptOperation<VaultScriptExpression>>,
+        registers: &[String],
+    ) {
+        if let Self::TakeVariable { name } = self {
+            result.push(ScriptOperation::RestoreRegister {
+                index: registers.iter().position(|n| n == name.as_str()).unwrap(),
+            });
+        } else {
+            result.push(ScriptOperation::Expression {
+                expression: VaultScriptExpression::StackDrop,
+            });
+        }
+    }
+
+    /// Stores value changed by intrinsic back into this expression. Variables
+    /// get it `assign`ed, which immutable variables reject. Indexed items get
+    /// it with `set_index` of their container, which is then stored back the
+    /// same way, so `a[0][1] = v` updates `a`. Other expressions would only
+    /// hold temporary values, so they cannot be assigned to.
+    fn compile_assign(
+        &self,
+        result: &mut Vec<ScriptOperation<VaultScriptExpression>>,
+        registers: &mut Vec<String>,
+    ) {
+        match self {
+            Self::TakeVariable { name } => {
+                result.push(ScriptOperation::PopToRegister {
+                    index: registers.iter().position(|n| n == name.as_str()).unwrap(),
+                });
+            }
+            Self::Index { target, index } => {
+                Self::compile_set_index(target, index, result, registers)
+            }
+            _ => panic!("Index assignment target is neither variable nor indexed item"),
+        }
+    }
+
+    /// Calls `set_index` of `target` with `index` and value from stack, then
+    /// assigns changed target back.
+    fn compile_set_index(
+        target: &Self,
+        index: &Self,
+        result: &mut Vec<ScriptOperation<VaultScriptExpression>>,
+        registers: &mut Vec<String>,
+    ) {
+        index.compile(result, registers);
+        target.compile(result, registers);
+        result.push(ScriptOperation::Expression {
+            expression: VaultScriptExpression::StackProduce {
+                name: "set_index".to_owned(),
+            },
+        });
+        target.compile_assign(result, registers);
+    }
+}
+
+impl VaultExpression {
+    fn fill_default_arguments(&mut self, module_name: &str, functions: &FunctionParameters) {
+        match self {
+            Self::CallFunction {
+                module_name: call_module_name,
+                name,
+                arguments,
+            } => {
+                let callee_module_name = call_module_name.as_deref().unwrap_or(module_name);
+                fill_arguments(
+                    functions.get(&(callee_module_name, None, name.as_str())),
+                    arguments,
+                );
+                for argument in arguments {
+                    argument.fill_default_arguments(module_name, functions);
+                }
             }
+            Self::CallMethod {
+                module_name: call_module_name,
+                type_name,
+                name,
+                arguments,
+            } => {
+                let callee_module_name = call_module_name.as_deref().unwrap_or(module_name);
+                fill_arguments(
+                    functions.get(&(callee_module_name, Some(type_name.as_str()), name.as_str())),
+                    arguments,
+                );
+                for argument in arguments {
+                    argument.fill_default_arguments(module_name, functions);
+                }
+            }
+            Self::If {
+                condition,
+                success,
+                failure,
+            } => {
+                condition.fill_default_arguments(module_name, functions);
+                for statement in success.iter_mut().chain(failure.iter_mut().flatten()) {
+                    statement.fill_default_arguments(module_name, functions);
+                }
+            }
+            Self::Index { target, index } => {
+                target.fill_default_arguments(module_name, functions);
+                index.fill_default_arguments(module_name, functions);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Parameters of package functions by module, type and function name.
+type FunctionParameters<'a> =
+    HashMap<(&'a str, Option<&'a str>, &'a str), &'a [VaultFunctionParameter]>;
+
+fn fill_arguments(
+    parameters: Option<&&[VaultFunctionParameter]>,
+    arguments: &mut Vec<VaultExpression>,
+) {
+    if let Some(parameters) = parameters {
+        if let Some(parameters) = parameters.get(arguments.len()..) {
+            arguments.extend(
+                parameters
+                    .iter()
+                    .map_while(|parameter| parameter.default.to_owned()),
+            );
         }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum VaultStatement {
+    /// Assigns to variable, or declares it when given `type_name`. Declared
+    /// variables are immutable unless `mutable`, and live until end of block
+    /// they were declared in.
     MakeVariable {
         name: String,
         expression: VaultExpression,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        type_name: Option<String>,
+        #[serde(default)]
+        mutable: bool,
     },
     Expression(VaultExpression),
     Return(VaultExpression),
@@ -294,6 +462,22 @@ pub enum VaultStatement {
         advancement: Vec<VaultStatement>,
         statements: Vec<VaultStatement>,
     },
+    /// Calls `set_index` method of target type, which takes target, index
+    /// and value and gives back changed target. Target has to be variable
+    /// or indexed item of one, which is then updated with `set_index` too.
+    IndexAssign {
+        target: VaultExpression,
+        index: VaultExpression,
+        value: VaultExpression,
+    },
+    /// Prints value of expression with debug printer of context and drops it.
+    DebugPrint {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        label: Option<String>,
+        expression: VaultExpression,
+    },
+    /// Marks following statements as written at given source location.
+    Span(ScriptSpan),
 }
 
 impl VaultStatement {
@@ -303,24 +487,43 @@ impl VaultStatement {
         registers: &mut Vec<String>,
     ) {
         match self {
-            Self::MakeVariable { name, expression } => {
+            Self::MakeVariable {
+                name,
+                expression,
+                type_name,
+                mutable,
+            } => {
                 expression.compile(result, registers);
+                if let Some(type_name) = type_name {
+                    if registers.iter().any(|n| n == name) {
+                        panic!("Variable `{}` is already declared", name);
+                    }
+                    registers.push(name.to_owned());
+                    result.push(ScriptOperation::DefineRegister {
+                        query: TypeQuery::named(type_name.to_owned()),
+                        immutable: !*mutable,
+                    });
+                }
                 result.push(ScriptOperation::PopToRegister {
-                    index: registers.iter().position(|n| n == name.as_str()).unwrap(),
+                    index: registers
+                        .iter()
+                        .position(|n| n == name.as_str())
+                        .unwrap_or_else(|| panic!("Variable `{}` is not declared", name)),
                 });
             }
             Self::Expression(expression) => {
                 expression.compile(result, registers);
-                result.push(ScriptOperation::Expression {
-                    expression: VaultScriptExpression::StackDrop,
-                });
+                // branches do not leave values - returns inside them unwind
+                // whole function instead.
+                if !matches!(expression, VaultExpression::If { .. }) {
+                    result.push(ScriptOperation::Expression {
+                        expression: VaultScriptExpression::StackDrop,
+                    });
+                }
             }
             Self::Return(expression) => {
                 expression.compile(result, registers);
-                result.push(ScriptOperation::Expression {
-                    expression: VaultScriptExpression::Literal(VaultLiteral::Bool(false)),
-                });
-                result.push(ScriptOperation::ContinueScopeConditionally);
+                result.push(ScriptOperation::ReturnScope);
             }
             Self::Scope(expressions) => {
                 let mut operations = vec![];
@@ -329,18 +532,16 @@ impl VaultStatement {
                 }
                 result.push(ScriptOperation::PushScope {
                     scope: ScriptHandle::new(operations),
+                    frame: false,
                 });
             }
             Self::While {
                 condition,
                 statements,
             } => {
-                let mut operations = vec![];
+                let mut operations = Self::compile_block(statements, registers);
                 condition.compile(&mut operations, registers);
-                operations.push(ScriptOperation::ContinueScopeConditionally);
-                for statement in statements {
-                    statement.compile(&mut operations, registers);
-                }
+                condition.compile(result, registers);
                 result.push(ScriptOperation::LoopScope {
                     scope: ScriptHandle::new(operations),
                 });
@@ -351,22 +552,124 @@ impl VaultStatement {
                 advancement,
                 statements,
             } => {
-                for statement in setup {
-                    statement.compile(result, registers);
+                result.extend(Self::compile_framed(registers, |result, registers| {
+                    for statement in setup {
+                        statement.compile(result, registers);
+                    }
+                    let mut operations = Self::compile_block(statements, registers);
+                    operations.extend(Self::compile_block(advancement, registers));
+                    condition.compile(&mut operations, registers);
+                    condition.compile(result, registers);
+                    result.push(ScriptOperation::LoopScope {
+                        scope: ScriptHandle::new(operations),
+                    });
+                }));
+            }
+            Self::IndexAssign {
+                target,
+                index,
+                value,
+            } => {
+                value.compile(result, registers);
+                VaultExpression::compile_set_index(target, index, result, registers);
+            }
+            Self::DebugPrint { label, expression } => {
+                expression.compile(result, registers);
+                result.push(ScriptOperation::Expression {
+                    expression: VaultScriptExpression::DebugPrint {
+                        label: label.to_owned(),
+                    },
+                });
+                result.push(ScriptOperation::Expression {
+                    expression: VaultScriptExpression::StackDrop,
+                });
+            }
+            Self::Span(span) => {
+                result.push(ScriptOperation::Span { span: span.clone() });
+            }
+        }
+    }
+}
+
+impl VaultStatement {
+    fn compile_block<'a>(
+        statements: &[VaultStatement],
+        registers: &mut Vec<String>,
+    ) -> Vec<ScriptOperation<'a, VaultScriptExpression>> {
+        Self::compile_framed(registers, |result, registers| {
+            for statement in statements {
+                statement.compile(result, registers);
+            }
+        })
+    }
+
+    /// Operations declaring variables get wrapped in registers frame, so
+    /// these variables are dropped at the end of it - otherwise blocks ran
+    /// many times would define their registers many times.
+    fn compile_framed<'a>(
+        registers: &mut Vec<String>,
+        f: impl FnOnce(&mut Vec<ScriptOperation<'a, VaultScriptExpression>>, &mut Vec<String>),
+    ) -> Vec<ScriptOperation<'a, VaultScriptExpression>> {
+        let count = registers.len();
+        let mut result = vec![];
+        f(&mut result, registers);
+        if registers.len() > count {
+            registers.truncate(count);
+            result = vec![ScriptOperation::PushScope {
+                scope: ScriptHandle::new(result),
+                frame: true,
+            }];
+        }
+        result
+    }
+
+    fn fill_default_arguments(&mut self, module_name: &str, functions: &FunctionParameters) {
+        match self {
+            Self::MakeVariable { expression, .. }
+            | Self::Expression(expression)
+            | Self::Return(expression)
+            | Self::DebugPrint { expression, .. } => {
+                expression.fill_default_arguments(module_name, functions)
+            }
+            Self::Scope(statements) => {
+                for statement in statements {
+                    statement.fill_default_arguments(module_name, functions);
                 }
-                let mut operations = vec![];
-                condition.compile(&mut operations, registers);
-                operations.push(ScriptOperation::ContinueScopeConditionally);
+            }
+            Self::While {
+                condition,
+                statements,
+            } => {
+                condition.fill_default_arguments(module_name, functions);
                 for statement in statements {
-                    statement.compile(&mut operations, registers);
+                    statement.fill_default_arguments(module_name, functions);
                 }
-                for statement in advancement {
-                    statement.compile(result, registers);
+            }
+            Self::For {
+                setup,
+                condition,
+                advancement,
+                statements,
+            } => {
+                condition.fill_default_arguments(module_name, functions);
+                for statement in setup
+                    .iter_mut()
+                    .chain(advancement.iter_mut())
+                    .chain(statements.iter_mut())
+                {
+                    statement.fill_default_arguments(module_name, functions);
                 }
-                result.push(ScriptOperation::LoopScope {
-                    scope: ScriptHandle::new(operations),
-                });
             }
+            Self::IndexAssign {
+                target,
+                index,
+                value,
+            } => {
+                target.fill_default_arguments(module_name, functions);
+                index.fill_default_arguments(module_name, functions);
+                value.fill_default_arguments(module_name, functions);
+            }
+            Self::Span(_) => {}
         }
     }
 }
@@ -375,6 +678,13 @@ impl VaultStatement {
 pub struct VaultFunctionParameter {
     pub name: String,
     pub arg_type: String,
+    /// Expression evaluated in caller when call site omits this argument.
+    /// Only trailing parameters can have default value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<VaultExpression>,
+    /// Immutable argument cannot be reassigned, it can only be read.
+    #[serde(default)]
+    pub mutable: bool,
 }
 
 impl VaultFunctionParameter {
@@ -382,10 +692,7 @@ impl VaultFunctionParameter {
         ScriptFunctionParameter {
             meta: None,
             name: self.name.to_owned(),
-            type_query: TypeQuery {
-                name: Some(self.arg_type.as_str().to_owned().into()),
-                ..Default::default()
-            },
+            type_query: TypeQuery::named(self.arg_type.to_owned()),
         }
     }
 }
@@ -417,15 +724,27 @@ impl VaultFunction {
                 meta: None,
                 name: "result".to_owned(),
                 type_query: if let Some(return_type) = &self.return_type {
-                    TypeQuery {
-                        name: Some(return_type.to_owned().into()),
-                        ..Default::default()
-                    }
+                    TypeQuery::named(return_type.to_owned())
                 } else {
                     TypeQuery::of::<()>()
                 },
             }],
         };
+        if let Some(index) = self
+            .arguments
+            .iter()
+            .position(|argument| argument.default.is_some())
+        {
+            if let Some(argument) = self.arguments[index..]
+                .iter()
+                .find(|argument| argument.default.is_none())
+            {
+                panic!(
+                    "Function `{}` has required parameter `{}` after parameter with default value",
+                    self.name, argument.name
+                );
+            }
+        }
         let mut registers = Vec::<String>::new();
         let mut operations = vec![];
         for argument in &self.arguments {
@@ -435,10 +754,8 @@ impl VaultFunction {
                 registers.push(argument.name.to_owned());
             }
             operations.push(ScriptOperation::DefineRegister {
-                query: TypeQuery {
-                    name: Some(argument.arg_type.to_owned().into()),
-                    ..Default::default()
-                },
+                query: TypeQuery::named(argument.arg_type.to_owned()),
+                immutable: !argument.mutable,
             });
             operations.push(ScriptOperation::PopToRegister {
                 index: registers.iter().position(|n| n == &argument.name).unwrap(),
@@ -466,10 +783,7 @@ impl VaultStructField {
             meta: None,
             name: self.name.to_owned(),
             visibility: Visibility::Public,
-            type_query: TypeQuery {
-                name: Some(self.type_name.as_str().to_owned().into()),
-                ..Default::default()
-            },
+            type_query: TypeQuery::named(self.type_name.to_owned()),
         }
     }
 }
@@ -577,6 +891,8 @@ pub struct VaultModule {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub dependencies: Vec<String>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub imports: Vec<ScriptModuleImport>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub definitions: Vec<VaultDefinition>,
 }
 
@@ -585,8 +901,23 @@ impl VaultModule {
         serde_lexpr::from_str(content)
     }
 
+    fn fill_default_arguments(&mut self, functions: &FunctionParameters) {
+        for definition in &mut self.definitions {
+            let definition_functions = match definition {
+                VaultDefinition::Function(function) => std::slice::from_mut(function),
+                VaultDefinition::Struct(struct_type) => struct_type.methods.as_mut_slice(),
+                VaultDefinition::Enum(enum_type) => enum_type.methods.as_mut_slice(),
+            };
+            for function in definition_functions {
+                for statement in &mut function.statements {
+                    statement.fill_default_arguments(&self.name, functions);
+                }
+            }
+        }
+    }
+
     pub fn compile(&self) -> ScriptModule<'static, VaultScriptExpression> {
-        ScriptModule {
+        let mut result = ScriptModule {
             name: self.name.to_owned(),
             structs: self
                 .definitions
@@ -628,7 +959,9 @@ impl VaultModule {
                         .flatten(),
                 )
                 .collect(),
-        }
+        };
+        result.resolve_imports(&self.imports);
+        result
     }
 }
 
@@ -648,32 +981,79 @@ impl VaultPackage {
     }
 
     pub fn load<CP>(&mut self, path: &str, content_provider: &mut CP) -> Result<(), Box<dyn Error>>
+    where
+        CP: ScriptContentProvider<VaultModule>,
+    {
+        self.load_chained(path, content_provider, &mut vec![])
+    }
+
+    /// `chain` holds paths being loaded that (transitively) depend on `path`.
+    fn load_chained<CP>(
+        &mut self,
+        path: &str,
+        content_provider: &mut CP,
+        chain: &mut Vec<String>,
+    ) -> Result<(), Box<dyn Error>>
     where
         CP: ScriptContentProvider<VaultModule>,
     {
         let path = content_provider.sanitize_path(path)?;
+        if let Some(index) = chain.iter().position(|item| *item == path) {
+            let mut cycle = chain[index..].to_vec();
+            cycle.push(path);
+            return Err(Box::new(CircularDependencyError { cycle }));
+        }
         if self.modules.contains_key(&path) {
             return Ok(());
         }
+        chain.push(path.to_owned());
         for content in content_provider.unpack_load(&path)? {
             if let Some(module) = content.data? {
                 let dependencies = module.dependencies.to_owned();
                 self.modules.insert(content.name, module);
                 for relative in dependencies {
                     let path = content_provider.join_paths(&content.path, &relative)?;
-                    self.load(&path, content_provider)?;
+                    self.load_chained(&path, content_provider, chain)?;
                 }
             }
         }
+        chain.pop();
         Ok(())
     }
 
+    /// Calls to package functions that omit trailing arguments get default
+    /// values of those parameters filled in.
     pub fn compile(&self) -> ScriptPackage<'static, VaultScriptExpression> {
+        let mut functions = FunctionParameters::new();
+        for module in self.modules.values() {
+            for definition in &module.definitions {
+                let (type_name, definition_functions) = match definition {
+                    VaultDefinition::Function(function) => (None, std::slice::from_ref(function)),
+                    VaultDefinition::Struct(struct_type) => (
+                        Some(struct_type.name.as_str()),
+                        struct_type.methods.as_slice(),
+                    ),
+                    VaultDefinition::Enum(enum_type) => {
+                        (Some(enum_type.name.as_str()), enum_type.methods.as_slice())
+                    }
+                };
+                for function in definition_functions {
+                    functions.insert(
+                        (module.name.as_str(), type_name, function.name.as_str()),
+                        function.arguments.as_slice(),
+                    );
+                }
+            }
+        }
         ScriptPackage {
             modules: self
                 .modules
                 .values()
-                .map(|module| module.compile())
+                .map(|module| {
+                    let mut module = module.to_owned();
+                    module.fill_default_arguments(&functions);
+                    module.compile()
+                })
                 .collect(),
         }
     }
@@ -696,7 +1076,7 @@ macro_rules! define_vault_function {
         $(mod $module_name:ident)?
         fn
         $name:ident
-        ($( $argument_name:ident : $argument_type:ty),*)
+        ($( $(#[doc = $argument_doc:literal])* $argument_name:ident : $argument_type:ty),*)
         ->
         $return_type:ty
         $code:block
@@ -708,7 +1088,7 @@ macro_rules! define_vault_function {
                 $(mod $module_name)?
                 fn
                 $name
-                ($($argument_name : $argument_type),*)
+                ($($(#[doc = $argument_doc])* $argument_name : $argument_type),*)
                 ->
                 (result : $return_type)
             },
@@ -731,7 +1111,7 @@ macro_rules! define_vault_method {
         type ($type:ty)
         fn
         $name:ident
-        ($( $argument_name:ident : $argument_type:ty),*)
+        ($( $(#[doc = $argument_doc:literal])* $argument_name:ident : $argument_type:ty),*)
         ->
         $return_type:ty
         $code:block
@@ -744,7 +1124,7 @@ macro_rules! define_vault_method {
                 type ($type)
                 fn
                 $name
-                ($($argument_name : $argument_type),*)
+                ($($(#[doc = $argument_doc])* $argument_name : $argument_type),*)
                 ->
                 (result : $return_type)
             },
@@ -772,11 +1152,20 @@ mod tests {
                 println!("PRINT: {}", content);
             }
         });
-        registry.add_function(define_vault_function! {
-            registry => mod intrinsics fn add(a: usize, b: usize) -> usize {
+        let add = registry.add_function(define_vault_function! {
+            registry => mod intrinsics fn add(
+                /// Left operand.
+                a: usize,
+                b: usize
+            ) -> usize {
                 a + b
             }
         });
+        assert_eq!(
+            add.signature().inputs[0].meta.as_ref().unwrap().doc(),
+            Some("Left operand.")
+        );
+        assert!(add.signature().inputs[1].meta.is_none());
         registry.add_function(define_vault_function! {
             registry => mod intrinsics fn sub(a: usize, b: usize) -> usize {
                 a - b
@@ -821,4 +1210,777 @@ mod tests {
         assert_eq!(vm.context().stack().position(), 0);
         assert_eq!(result, 6765);
     }
+
+    #[test]
+    fn test_circular_dependency() {
+        struct MemoryContentProvider(HashMap<String, VaultModule>);
+
+        impl ScriptContentProvider<VaultModule> for MemoryContentProvider {
+            fn load(&mut self, path: &str) -> Result<Option<VaultModule>, Box<dyn Error>> {
+                Ok(self.0.get(path).cloned())
+            }
+
+            fn join_paths(&self, _: &str, relative: &str) -> Result<String, Box<dyn Error>> {
+                Ok(relative.to_owned())
+            }
+        }
+
+        let module = |name: &str, dependencies: &[&str]| {
+            (
+                name.to_owned(),
+                VaultModule {
+                    name: name.to_owned(),
+                    dependencies: dependencies.iter().map(|name| name.to_string()).collect(),
+                    imports: vec![],
+                    definitions: vec![],
+                },
+            )
+        };
+        let cycle = |content_provider: &mut MemoryContentProvider| {
+            VaultPackage::new("a", content_provider)
+                .err()
+                .unwrap()
+                .downcast::<CircularDependencyError>()
+                .unwrap()
+                .cycle
+        };
+
+        let mut content_provider =
+            MemoryContentProvider(HashMap::from([module("a", &["b"]), module("b", &["a"])]));
+        assert_eq!(cycle(&mut content_provider), vec!["a", "b", "a"]);
+
+        let mut content_provider = MemoryContentProvider(HashMap::from([module("a", &["a"])]));
+        assert_eq!(cycle(&mut content_provider), vec!["a", "a"]);
+
+        let mut content_provider = MemoryContentProvider(HashMap::from([
+            module("a", &["b", "c"]),
+            module("b", &["c"]),
+            module("c", &[]),
+        ]));
+        let package = VaultPackage::new("a", &mut content_provider).unwrap();
+        assert_eq!(package.modules.len(), 3);
+    }
+
+    #[test]
+    fn test_nested_return() {
+        fn call(name: &str, arguments: Vec<VaultExpression>) -> VaultExpression {
+            VaultExpression::CallFunction {
+                module_name: Some("intrinsics".to_owned()),
+                name: name.to_owned(),
+                arguments,
+            }
+        }
+
+        fn clone_n() -> VaultExpression {
+            VaultExpression::CloneVariable {
+                name: "n".to_owned(),
+            }
+        }
+
+        let mut registry = Registry::default().with_basic_types();
+        registry.add_function(define_vault_function! {
+            registry => mod intrinsics fn add(a: usize, b: usize) -> usize {
+                a + b
+            }
+        });
+        registry.add_function(define_vault_function! {
+            registry => mod intrinsics fn less_than(a: usize, b: usize) -> bool {
+                a < b
+            }
+        });
+        registry.add_function(define_function! {
+            registry => mod intrinsics type (usize) fn clone(this: usize) -> (original: usize, clone: usize) {
+                (this, this)
+            }
+        });
+        // if n < 100 { while n < 100 { if 5 < n { return n; } n = n + 1; } }
+        // return 0;
+        let function = VaultFunction {
+            name: "find".to_owned(),
+            arguments: vec![VaultFunctionParameter {
+                name: "n".to_owned(),
+                arg_type: "usize".to_owned(),
+                default: None,
+                mutable: true,
+            }],
+            return_type: Some("usize".to_owned()),
+            statements: vec![
+                VaultStatement::Expression(VaultExpression::If {
+                    condition: Box::new(call(
+                        "less_than",
+                        vec![
+                            clone_n(),
+                            VaultExpression::Literal(VaultLiteral::Usize(100)),
+                        ],
+                    )),
+                    success: vec![VaultStatement::While {
+                        condition: Box::new(call(
+                            "less_than",
+                            vec![
+                                clone_n(),
+                                VaultExpression::Literal(VaultLiteral::Usize(100)),
+                            ],
+                        )),
+                        statements: vec![
+                            VaultStatement::Expression(VaultExpression::If {
+                                condition: Box::new(call(
+                                    "less_than",
+                                    vec![
+                                        VaultExpression::Literal(VaultLiteral::Usize(5)),
+                                        clone_n(),
+                                    ],
+                                )),
+                                success: vec![VaultStatement::Return(
+                                    VaultExpression::TakeVariable {
+                                        name: "n".to_owned(),
+                                    },
+                                )],
+                                failure: None,
+                            }),
+                            VaultStatement::MakeVariable {
+                                name: "n".to_owned(),
+                                expression: call(
+                                    "add",
+                                    vec![
+                                        VaultExpression::TakeVariable {
+                                            name: "n".to_owned(),
+                                        },
+                                        VaultExpression::Literal(VaultLiteral::Usize(1)),
+                                    ],
+                                ),
+                                type_name: None,
+                                mutable: false,
+                            },
+                        ],
+                    }],
+                    failure: None,
+                }),
+                VaultStatement::Return(VaultExpression::Literal(VaultLiteral::Usize(0))),
+            ],
+        };
+        let mut immutable = function.clone();
+        immutable.name = "find_immutable".to_owned();
+        immutable.arguments[0].mutable = false;
+        ScriptPackage {
+            modules: vec![VaultModule {
+                name: "test".to_owned(),
+                dependencies: vec![],
+                imports: vec![],
+                definitions: vec![
+                    VaultDefinition::Function(function),
+                    VaultDefinition::Function(immutable),
+                ],
+            }
+            .compile()],
+        }
+        .install::<VmScope<VaultScriptExpression>>(&mut registry, None);
+        let mut vm = Host::new(Context::new(10240, 10240), registry.into());
+        let (result,) = vm
+            .call_function::<(usize,), (usize,)>("find", "test", None)
+            .unwrap()
+            .run((0,));
+        assert_eq!(vm.context().stack().position(), 0);
+        assert_eq!(result, 6);
+        let (result,) = vm
+            .call_function::<(usize,), (usize,)>("find", "test", None)
+            .unwrap()
+            .run((200,));
+        assert_eq!(vm.context().stack().position(), 0);
+        assert_eq!(result, 0);
+        // `n = n + 1` reassigns argument, which is immutable by default.
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            vm.call_function::<(usize,), (usize,)>("find_immutable", "test", None)
+                .unwrap()
+                .run((0,))
+        }));
+        assert_eq!(
+            *result.unwrap_err().downcast::<String>().unwrap(),
+            "Could not reassign immutable register: 0"
+        );
+    }
+
+    #[test]
+    fn test_variables() {
+        fn call(name: &str, arguments: Vec<VaultExpression>) -> VaultExpression {
+            VaultExpression::CallFunction {
+                module_name: Some("intrinsics".to_owned()),
+                name: name.to_owned(),
+                arguments,
+            }
+        }
+
+        fn take(name: &str) -> VaultExpression {
+            VaultExpression::TakeVariable {
+                name: name.to_owned(),
+            }
+        }
+
+        fn clone(name: &str) -> VaultExpression {
+            VaultExpression::CloneVariable {
+                name: name.to_owned(),
+            }
+        }
+
+        fn usize(value: usize) -> VaultExpression {
+            VaultExpression::Literal(VaultLiteral::Usize(value))
+        }
+
+        fn assign(name: &str, expression: VaultExpression) -> VaultStatement {
+            VaultStatement::MakeVariable {
+                name: name.to_owned(),
+                expression,
+                type_name: None,
+                mutable: false,
+            }
+        }
+
+        fn declare(name: &str, expression: VaultExpression, mutable: bool) -> VaultStatement {
+            VaultStatement::MakeVariable {
+                name: name.to_owned(),
+                expression,
+                type_name: Some("usize".to_owned()),
+                mutable,
+            }
+        }
+
+        fn function(name: &str, statements: Vec<VaultStatement>) -> VaultDefinition {
+            VaultDefinition::Function(VaultFunction {
+                name: name.to_owned(),
+                arguments: vec![VaultFunctionParameter {
+                    name: "n".to_owned(),
+                    arg_type: "usize".to_owned(),
+                    default: None,
+                    mutable: false,
+                }],
+                return_type: Some("usize".to_owned()),
+                statements,
+            })
+        }
+
+        let mut registry = Registry::default().with_basic_types();
+        registry.add_function(define_vault_function! {
+            registry => mod intrinsics fn add(a: usize, b: usize) -> usize {
+                a + b
+            }
+        });
+        registry.add_function(define_vault_function! {
+            registry => mod intrinsics fn less_than(a: usize, b: usize) -> bool {
+                a < b
+            }
+        });
+        registry.add_function(define_function! {
+            registry => mod intrinsics type (usize) fn clone(this: usize) -> (original: usize, clone: usize) {
+                (this, this)
+            }
+        });
+        // let mut total = 0; let mut i = 0;
+        // while i < n { let step = i; i = i + 1; total = total + step + n; }
+        // return total;
+        let sum = function(
+            "sum",
+            vec![
+                declare("total", usize(0), true),
+                declare("i", usize(0), true),
+                VaultStatement::While {
+                    condition: Box::new(call("less_than", vec![clone("i"), clone("n")])),
+                    statements: vec![
+                        declare("step", clone("i"), false),
+                        assign("i", call("add", vec![take("i"), usize(1)])),
+                        assign(
+                            "total",
+                            call(
+                                "add",
+                                vec![take("total"), call("add", vec![clone("step"), clone("n")])],
+                            ),
+                        ),
+                    ],
+                },
+                VaultStatement::Return(take("total")),
+            ],
+        );
+        // let total = n; total = total + 1; return total;
+        let reassign = function(
+            "reassign",
+            vec![
+                declare("total", clone("n"), false),
+                assign("total", call("add", vec![take("total"), usize(1)])),
+                VaultStatement::Return(take("total")),
+            ],
+        );
+        ScriptPackage {
+            modules: vec![VaultModule {
+                name: "test".to_owned(),
+                dependencies: vec![],
+                imports: vec![],
+                definitions: vec![sum, reassign],
+            }
+            .compile()],
+        }
+        .install::<VmScope<VaultScriptExpression>>(&mut registry, None);
+        let mut vm = Host::new(Context::new(10240, 10240), registry.into());
+        let (result,) = vm
+            .call_function::<(usize,), (usize,)>("sum", "test", None)
+            .unwrap()
+            .run((4,));
+        assert_eq!(result, 22);
+        assert_eq!(vm.context().stack().position(), 0);
+        assert_eq!(vm.context().registers().registers_count(), 0);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            vm.call_function::<(usize,), (usize,)>("reassign", "test", None)
+                .unwrap()
+                .run((4,))
+        }));
+        assert_eq!(
+            *result.unwrap_err().downcast::<String>().unwrap(),
+            "Could not reassign immutable register: 1"
+        );
+    }
+
+    #[test]
+    fn test_high_water_mark() {
+        fn call(module_name: &str, name: &str, arguments: Vec<VaultExpression>) -> VaultExpression {
+            VaultExpression::CallFunction {
+                module_name: Some(module_name.to_owned()),
+                name: name.to_owned(),
+                arguments,
+            }
+        }
+
+        fn clone_n() -> VaultExpression {
+            VaultExpression::CloneVariable {
+                name: "n".to_owned(),
+            }
+        }
+
+        let mut registry = Registry::default().with_basic_types();
+        registry.add_function(define_vault_function! {
+            registry => mod intrinsics fn add(a: usize, b: usize) -> usize {
+                a + b
+            }
+        });
+        registry.add_function(define_vault_function! {
+            registry => mod intrinsics fn sub(a: usize, b: usize) -> usize {
+                a - b
+            }
+        });
+        registry.add_function(define_vault_function! {
+            registry => mod intrinsics fn less_than(a: usize, b: usize) -> bool {
+                a < b
+            }
+        });
+        registry.add_function(define_function! {
+            registry => mod intrinsics type (usize) fn clone(this: usize) -> (original: usize, clone: usize) {
+                (this, this)
+            }
+        });
+        // if n < 2 { return n; }
+        // return fib(n - 1) + fib(n - 2);
+        let fib = |offset| {
+            call(
+                "test",
+                "fib",
+                vec![call(
+                    "intrinsics",
+                    "sub",
+                    vec![
+                        clone_n(),
+                        VaultExpression::Literal(VaultLiteral::Usize(offset)),
+                    ],
+                )],
+            )
+        };
+        let function = VaultFunction {
+            name: "fib".to_owned(),
+            arguments: vec![VaultFunctionParameter {
+                name: "n".to_owned(),
+                arg_type: "usize".to_owned(),
+                default: None,
+                mutable: false,
+            }],
+            return_type: Some("usize".to_owned()),
+            statements: vec![
+                VaultStatement::Expression(VaultExpression::If {
+                    condition: Box::new(call(
+                        "intrinsics",
+                        "less_than",
+                        vec![clone_n(), VaultExpression::Literal(VaultLiteral::Usize(2))],
+                    )),
+                    success: vec![VaultStatement::Return(VaultExpression::TakeVariable {
+                        name: "n".to_owned(),
+                    })],
+                    failure: None,
+                }),
+                VaultStatement::Return(call("intrinsics", "add", vec![fib(1), fib(2)])),
+            ],
+        };
+        ScriptPackage {
+            modules: vec![VaultModule {
+                name: "test".to_owned(),
+                dependencies: vec![],
+                imports: vec![],
+                definitions: vec![VaultDefinition::Function(function)],
+            }
+            .compile()],
+        }
+        .install::<VmScope<VaultScriptExpression>>(&mut registry, None);
+        let mut vm = Host::new(Context::new(10240, 10240), registry.into());
+        assert_eq!(vm.context().stack_high_water(), 0);
+        assert_eq!(vm.context().registers_high_water(), 0);
+        let (result,) = vm
+            .call_function::<(usize,), (usize,)>("fib", "test", None)
+            .unwrap()
+            .run((20,));
+        assert_eq!(result, 6765);
+        let stack = vm.context().stack_high_water();
+        let registers = vm.context().registers_high_water();
+        assert!(stack > 0);
+        assert!(stack <= vm.context().stack_capacity());
+        assert!(registers > 0);
+        assert!(registers <= vm.context().registers_capacity());
+        vm.context().reset_high_water();
+        assert_eq!(vm.context().stack_high_water(), 0);
+        assert_eq!(vm.context().registers_high_water(), 0);
+    }
+
+    #[test]
+    fn test_default_arguments() {
+        fn call(module_name: &str, name: &str, arguments: Vec<VaultExpression>) -> VaultExpression {
+            VaultExpression::CallFunction {
+                module_name: Some(module_name.to_owned()),
+                name: name.to_owned(),
+                arguments,
+            }
+        }
+
+        fn parameter(name: &str, default: Option<usize>) -> VaultFunctionParameter {
+            VaultFunctionParameter {
+                name: name.to_owned(),
+                arg_type: "usize".to_owned(),
+                default: default.map(|value| VaultExpression::Literal(VaultLiteral::Usize(value))),
+                mutable: false,
+            }
+        }
+
+        fn take(name: &str) -> VaultExpression {
+            VaultExpression::TakeVariable {
+                name: name.to_owned(),
+            }
+        }
+
+        // fn sub_default(a, b = 2) { return a - b; }
+        let sub_default = VaultFunction {
+            name: "sub_default".to_owned(),
+            arguments: vec![parameter("a", None), parameter("b", Some(2))],
+            return_type: Some("usize".to_owned()),
+            statements: vec![VaultStatement::Return(call(
+                "intrinsics",
+                "sub",
+                vec![take("a"), take("b")],
+            ))],
+        };
+        let main = |arguments| VaultFunction {
+            name: "main".to_owned(),
+            arguments: vec![],
+            return_type: Some("usize".to_owned()),
+            statements: vec![VaultStatement::Return(call(
+                "test",
+                "sub_default",
+                arguments,
+            ))],
+        };
+        let install = |arguments| {
+            let mut registry = Registry::default().with_basic_types();
+            registry.add_function(define_vault_function! {
+                registry => mod intrinsics fn sub(a: usize, b: usize) -> usize {
+                    a - b
+                }
+            });
+            let mut package = VaultPackage::default();
+            package.modules.insert(
+                "test".to_owned(),
+                VaultModule {
+                    name: "test".to_owned(),
+                    dependencies: vec![],
+                    imports: vec![],
+                    definitions: vec![
+                        VaultDefinition::Function(sub_default.clone()),
+                        VaultDefinition::Function(main(arguments)),
+                    ],
+                },
+            );
+            package
+                .compile()
+                .install::<VmScope<VaultScriptExpression>>(&mut registry, None);
+            Host::new(Context::new(10240, 10240), registry.into())
+        };
+
+        let mut vm = install(vec![VaultExpression::Literal(VaultLiteral::Usize(44))]);
+        let (result,) = vm
+            .call_function::<(usize,), _>("main", "test", None)
+            .unwrap()
+            .run(());
+        assert_eq!(vm.context().stack().position(), 0);
+        assert_eq!(result, 42);
+
+        let mut vm = install(vec![
+            VaultExpression::Literal(VaultLiteral::Usize(44)),
+            VaultExpression::Literal(VaultLiteral::Usize(4)),
+        ]);
+        let (result,) = vm
+            .call_function::<(usize,), _>("main", "test", None)
+            .unwrap()
+            .run(());
+        assert_eq!(result, 40);
+
+        let mut invalid = sub_default.clone();
+        invalid.arguments = vec![parameter("a", Some(1)), parameter("b", None)];
+        assert!(std::panic::catch_unwind(|| invalid.compile("test", None)).is_err());
+    }
+
+    #[test]
+    fn test_index() {
+        fn take(name: &str) -> VaultExpression {
+            VaultExpression::TakeVariable {
+                name: name.to_owned(),
+            }
+        }
+
+        fn index(target: VaultExpression, index: VaultLiteral) -> VaultExpression {
+            VaultExpression::Index {
+                target: Box::new(target),
+                index: Box::new(VaultExpression::Literal(index)),
+            }
+        }
+
+        let mut registry = Registry::default().with_basic_types();
+        registry.add_type(NativeStructBuilder::new_named::<Vec<usize>>("Items").build());
+        registry
+            .add_type(NativeStructBuilder::new_named::<HashMap<String, usize>>("Scores").build());
+        registry.add_function(define_vault_function! {
+            registry => mod intrinsics fn add(a: usize, b: usize) -> usize {
+                a + b
+            }
+        });
+        registry.add_function(define_function! {
+            registry => mod intrinsics type (Vec<usize>) fn index(this: Vec<usize>, index: usize) -> (original: Vec<usize>, item: usize) {
+                let item = this[index];
+                (this, item)
+            }
+        });
+        registry.add_function(define_function! {
+            registry => mod intrinsics type (HashMap<String, usize>) fn index(this: HashMap<String, usize>, key: String) -> (original: HashMap<String, usize>, item: usize) {
+                let item = this.get(&key).copied().unwrap_or_default();
+                (this, item)
+            }
+        });
+        registry.add_function(define_function! {
+            registry => mod intrinsics type (HashMap<String, usize>) fn set_index(this: HashMap<String, usize>, key: String, value: usize) -> (original: HashMap<String, usize>) {
+                let mut this = this;
+                this.insert(key, value);
+                (this,)
+            }
+        });
+        // scores["b"] = items[1];
+        // return scores["b"] + items[2];
+        let function = VaultFunction {
+            name: "main".to_owned(),
+            arguments: vec![
+                VaultFunctionParameter {
+                    name: "items".to_owned(),
+                    arg_type: "Items".to_owned(),
+                    default: None,
+                    mutable: false,
+                },
+                VaultFunctionParameter {
+                    name: "scores".to_owned(),
+                    arg_type: "Scores".to_owned(),
+                    default: None,
+                    mutable: true,
+                },
+            ],
+            return_type: Some("usize".to_owned()),
+            statements: vec![
+                VaultStatement::IndexAssign {
+                    target: take("scores"),
+                    index: VaultExpression::Literal(VaultLiteral::String("b".to_owned())),
+                    value: index(take("items"), VaultLiteral::Usize(1)),
+                },
+                VaultStatement::Return(VaultExpression::CallFunction {
+                    module_name: Some("intrinsics".to_owned()),
+                    name: "add".to_owned(),
+                    arguments: vec![
+                        index(take("scores"), VaultLiteral::String("b".to_owned())),
+                        index(take("items"), VaultLiteral::Usize(2)),
+                    ],
+                }),
+            ],
+        };
+        ScriptPackage {
+            modules: vec![VaultModule {
+                name: "test".to_owned(),
+                dependencies: vec![],
+                imports: vec![],
+                definitions: vec![VaultDefinition::Function(function)],
+            }
+            .compile()],
+        }
+        .install::<VmScope<VaultScriptExpression>>(&mut registry, None);
+        let mut vm = Host::new(Context::new(10240, 10240), registry.into());
+        let (result,) = vm
+            .call_function::<(usize,), _>("main", "test", None)
+            .unwrap()
+            .run((
+                vec![10usize, 20, 30],
+                HashMap::from([("a".to_owned(), 1usize), ("b".to_owned(), 2)]),
+            ));
+        assert_eq!(vm.context().stack().position(), 0);
+        assert_eq!(result, 50);
+    }
+
+    #[test]
+    fn test_index_assign_nested() {
+        fn take(name: &str) -> VaultExpression {
+            VaultExpression::TakeVariable {
+                name: name.to_owned(),
+            }
+        }
+
+        fn index(target: VaultExpression, index: usize) -> VaultExpression {
+            VaultExpression::Index {
+                target: Box::new(target),
+                index: Box::new(VaultExpression::Literal(VaultLiteral::Usize(index))),
+            }
+        }
+
+        fn module(target: VaultExpression) -> VaultModule {
+            // target[0] = 7;
+            // return grid[1][0];
+            VaultModule {
+                name: "test".to_owned(),
+                dependencies: vec![],
+                imports: vec![],
+                definitions: vec![VaultDefinition::Function(VaultFunction {
+                    name: "main".to_owned(),
+                    arguments: vec![VaultFunctionParameter {
+                        name: "grid".to_owned(),
+                        arg_type: "Grid".to_owned(),
+                        default: None,
+                        mutable: true,
+                    }],
+                    return_type: Some("usize".to_owned()),
+                    statements: vec![
+                        VaultStatement::IndexAssign {
+                            target,
+                            index: VaultExpression::Literal(VaultLiteral::Usize(0)),
+                            value: VaultExpression::Literal(VaultLiteral::Usize(7)),
+                        },
+                        VaultStatement::Return(index(index(take("grid"), 1), 0)),
+                    ],
+                })],
+            }
+        }
+
+        let mut registry = Registry::default().with_basic_types();
+        registry.add_type(NativeStructBuilder::new_named::<Vec<usize>>("Items").build());
+        registry.add_type(NativeStructBuilder::new_named::<Vec<Vec<usize>>>("Grid").build());
+        registry.add_function(define_function! {
+            registry => mod intrinsics type (Vec<usize>) fn index(this: Vec<usize>, index: usize) -> (original: Vec<usize>, item: usize) {
+                let item = this[index];
+                (this, item)
+            }
+        });
+        registry.add_function(define_function! {
+            registry => mod intrinsics type (Vec<usize>) fn set_index(this: Vec<usize>, index: usize, value: usize) -> (original: Vec<usize>) {
+                let mut this = this;
+                this[index] = value;
+                (this,)
+            }
+        });
+        registry.add_function(define_function! {
+            registry => mod intrinsics type (Vec<Vec<usize>>) fn index(this: Vec<Vec<usize>>, index: usize) -> (original: Vec<Vec<usize>>, item: Vec<usize>) {
+                let item = this[index].clone();
+                (this, item)
+            }
+        });
+        registry.add_function(define_function! {
+            registry => mod intrinsics type (Vec<Vec<usize>>) fn set_index(this: Vec<Vec<usize>>, index: usize, value: Vec<usize>) -> (original: Vec<Vec<usize>>) {
+                let mut this = this;
+                this[index] = value;
+                (this,)
+            }
+        });
+        ScriptPackage {
+            modules: vec![module(index(take("grid"), 1)).compile()],
+        }
+        .install::<VmScope<VaultScriptExpression>>(&mut registry, None);
+        let mut vm = Host::new(Context::new(10240, 10240), registry.into());
+        let (result,) = vm
+            .call_function::<(usize,), _>("main", "test", None)
+            .unwrap()
+            .run((vec![vec![1usize, 2], vec![3, 4]],));
+        assert_eq!(vm.context().stack().position(), 0);
+        assert_eq!(result, 7);
+
+        let call = VaultExpression::CallFunction {
+            module_name: Some("intrinsics".to_owned()),
+            name: "grid".to_owned(),
+            arguments: vec![],
+        };
+        assert!(std::panic::catch_unwind(|| module(index(call, 1)).compile()).is_err());
+    }
+
+    #[test]
+    fn test_debug_print() {
+        use std::sync::{Arc, Mutex};
+
+        let mut registry = Registry::default().with_basic_types();
+        let lines = Arc::new(Mutex::new(vec![]));
+        let lines2 = lines.clone();
+        let mut context = Context::new(1024, 1024);
+        context
+            .debug_printer_mut()
+            .set_sink(move |line| lines2.lock().unwrap().push(line.to_owned()));
+        context.stack().push(42usize);
+        let position = context.stack().position();
+        VaultScriptExpression::DebugPrint {
+            label: Some("answer".to_owned()),
+        }
+        .evaluate(&mut context, &registry);
+        assert_eq!(context.stack().position(), position);
+        assert_eq!(context.stack().pop::<usize>().unwrap(), 42);
+
+        let function = VaultFunction {
+            name: "main".to_owned(),
+            arguments: vec![],
+            return_type: Some("usize".to_owned()),
+            statements: vec![
+                VaultStatement::DebugPrint {
+                    label: None,
+                    expression: VaultExpression::Literal(VaultLiteral::String("hello".to_owned())),
+                },
+                VaultStatement::Return(VaultExpression::Literal(VaultLiteral::Usize(7))),
+            ],
+        };
+        ScriptPackage {
+            modules: vec![VaultModule {
+                name: "test".to_owned(),
+                dependencies: vec![],
+                imports: vec![],
+                definitions: vec![VaultDefinition::Function(function)],
+            }
+            .compile()],
+        }
+        .install::<VmScope<VaultScriptExpression>>(&mut registry, None);
+        let mut vm = Host::new(context, registry.into());
+        let (result,) = vm
+            .call_function::<(usize,), _>("main", "test", None)
+            .unwrap()
+            .run(());
+        assert_eq!(vm.context().stack().position(), 0);
+        assert_eq!(result, 7);
+        assert_eq!(
+            lines.lock().unwrap().as_slice(),
+            &["answer: 42".to_owned(), "\"hello\"".to_owned()]
+        );
+    }
 }
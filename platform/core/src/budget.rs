@@ -0,0 +1,140 @@
+use std::{
+    error::Error,
+    time::{Duration, Instant},
+};
+
+/// Number of operations between samples of elapsed time, so that time limit
+/// does not cost a clock read per operation.
+pub const BUDGET_DURATION_SAMPLE_INTERVAL: usize = 256;
+
+/// Execution limits of a call, checked by script backends per operation.
+/// Limits left as `None` are not enforced.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Budget {
+    pub max_operations: Option<usize>,
+    pub max_duration: Option<Duration>,
+}
+
+impl Budget {
+    pub fn with_max_operations(mut self, operations: usize) -> Self {
+        self.max_operations = Some(operations);
+        self
+    }
+
+    pub fn with_max_duration(mut self, duration: Duration) -> Self {
+        self.max_duration = Some(duration);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetExceeded {
+    Operations { limit: usize },
+    Duration { limit: Duration },
+}
+
+impl std::fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Operations { limit } => {
+                write!(f, "Execution exceeded budget of {} operations", limit)
+            }
+            Self::Duration { limit } => {
+                write!(f, "Execution exceeded budget of {:?}", limit)
+            }
+        }
+    }
+}
+
+impl Error for BudgetExceeded {}
+
+#[derive(Debug)]
+pub struct BudgetState {
+    budget: Budget,
+    operations: usize,
+    started: Instant,
+    exceeded: Option<BudgetExceeded>,
+}
+
+impl BudgetState {
+    pub fn new(budget: Budget) -> Self {
+        Self {
+            budget,
+            operations: 0,
+            started: Instant::now(),
+            exceeded: None,
+        }
+    }
+
+    pub fn budget(&self) -> &Budget {
+        &self.budget
+    }
+
+    pub fn operations(&self) -> usize {
+        self.operations
+    }
+
+    pub fn exceeded(&self) -> Option<BudgetExceeded> {
+        self.exceeded
+    }
+
+    /// Counts single operation, returns false once budget is exceeded.
+    pub fn consume(&mut self) -> bool {
+        if self.exceeded.is_some() {
+            return false;
+        }
+        if let Some(limit) = self.budget.max_operations {
+            if self.operations >= limit {
+                self.exceeded = Some(BudgetExceeded::Operations { limit });
+                return false;
+            }
+        }
+        if let Some(limit) = self.budget.max_duration {
+            if self
+                .operations
+                .is_multiple_of(BUDGET_DURATION_SAMPLE_INTERVAL)
+                && self.started.elapsed() >= limit
+            {
+                self.exceeded = Some(BudgetExceeded::Duration { limit });
+                return false;
+            }
+        }
+        self.operations += 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_state() {
+        let mut state = BudgetState::new(Budget::default().with_max_operations(3));
+        assert!(state.consume());
+        assert!(state.consume());
+        assert!(state.consume());
+        assert!(!state.consume());
+        assert!(!state.consume());
+        assert_eq!(state.operations(), 3);
+        assert_eq!(
+            state.exceeded(),
+            Some(BudgetExceeded::Operations { limit: 3 })
+        );
+
+        let mut state = BudgetState::new(Budget::default().with_max_duration(Duration::ZERO));
+        assert!(!state.consume());
+        assert_eq!(
+            state.exceeded(),
+            Some(BudgetExceeded::Duration {
+                limit: Duration::ZERO
+            })
+        );
+
+        let mut state = BudgetState::new(Budget::default());
+        for _ in 0..10000 {
+            assert!(state.consume());
+        }
+        assert_eq!(state.exceeded(), None);
+    }
+}
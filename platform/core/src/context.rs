@@ -1,11 +1,96 @@
-use intuicio_data::data_stack::{DataStack, DataStackMode, DataStackRegisterAccess};
-use std::{any::Any, collections::HashMap};
+use crate::{
+    budget::{Budget, BudgetExceeded, BudgetState},
+    debug::DebugPrinter,
+    event::EventBus,
+    function::FunctionSignature,
+    random::Random,
+    registry::Registry,
+    script::{ScriptError, ScriptSpan},
+    types::TypeQuery,
+};
+use intuicio_data::{
+    data_stack::{DataStack, DataStackMode, DataStackRegisterAccess, DataStackToken},
+    lifetime::{Lifetime, ValueReadAccess, ValueWriteAccess},
+    managed::DynamicManaged,
+    type_hash::TypeHash,
+};
+use std::{
+    alloc::{alloc, dealloc, Layout},
+    any::Any,
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
+use typid::ID;
+
+pub type HeapId = ID<DynamicManaged>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisterInfo {
+    pub type_hash: TypeHash,
+    pub occupied: bool,
+    /// Copy of register value bytes, if occupied.
+    pub bytes: Option<Vec<u8>>,
+}
+
+/// Script operation executed by script backend, recorded for post-mortems.
+#[derive(Debug, Clone)]
+pub struct OperationTrace {
+    pub label: &'static str,
+    /// Position of operation in its scope.
+    pub position: usize,
+    /// Signature of function operation belongs to, if backend knows it.
+    pub function: Option<Arc<FunctionSignature>>,
+    /// Source location operation was compiled from, if known.
+    pub span: Option<ScriptSpan>,
+}
+
+impl std::fmt::Display for OperationTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.function {
+            Some(function) => {
+                if let Some(module_name) = function.module_name.as_ref() {
+                    write!(f, "{}::", module_name)?;
+                }
+                write!(f, "{}", function.name)?;
+            }
+            None => write!(f, "<unknown>")?,
+        }
+        write!(f, " #{}: {}", self.position, self.label)?;
+        if let Some(span) = self.span.as_ref() {
+            write!(f, " at {}", span)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct ContextToken {
+    stack: DataStackToken,
+    registers: DataStackToken,
+    registers_barriers: usize,
+}
 
 pub struct Context {
     stack: DataStack,
     registers: DataStack,
     registers_barriers: Vec<usize>,
+    /// Values owned by context rather than by any scope, so they outlive
+    /// stack frames that produced them. Scripts move escaping values there
+    /// with `HeapPromote` operation and get them back with `HeapDemote`.
+    /// Each value stays alive until it gets freed, taken or demoted, otherwise
+    /// it is finalized when context drops. Heap is neither forked nor affected
+    /// by `store`/`restore`.
+    heap: HashMap<HeapId, DynamicManaged>,
+    /// Events stay queued until drained, they are not forked.
+    events: EventBus,
+    /// Starts with default seed in every context, forks included.
+    random: Random,
+    /// Not forked, so forks print with defaults into stderr.
+    debug_printer: DebugPrinter,
     custom: HashMap<String, Box<dyn Any + Send + Sync>>,
+    error: Option<ScriptError>,
+    budget: Option<BudgetState>,
+    operations_trace: Option<VecDeque<OperationTrace>>,
+    operations_trace_capacity: usize,
 }
 
 impl Context {
@@ -14,7 +99,15 @@ impl Context {
             stack: DataStack::new(stack_capacity, DataStackMode::Values),
             registers: DataStack::new(registers_capacity, DataStackMode::Registers),
             registers_barriers: vec![],
+            heap: Default::default(),
+            events: Default::default(),
+            random: Default::default(),
+            debug_printer: Default::default(),
             custom: Default::default(),
+            error: None,
+            budget: None,
+            operations_trace: None,
+            operations_trace_capacity: 0,
         }
     }
 
@@ -30,6 +123,21 @@ impl Context {
         self.registers.size()
     }
 
+    /// Highest data stack position reached since creation or last reset.
+    pub fn stack_high_water(&self) -> usize {
+        self.stack.high_water_mark()
+    }
+
+    /// Highest registers stack position reached since creation or last reset.
+    pub fn registers_high_water(&self) -> usize {
+        self.registers.high_water_mark()
+    }
+
+    pub fn reset_high_water(&mut self) {
+        self.stack.reset_high_water_mark();
+        self.registers.reset_high_water_mark();
+    }
+
     pub fn stack(&mut self) -> &mut DataStack {
         &mut self.stack
     }
@@ -49,12 +157,35 @@ impl Context {
 
     pub fn restore_registers(&mut self) {
         if let Some(count) = self.registers_barriers.pop() {
-            while self.registers.registers_count() > count {
-                self.registers.drop_register();
-            }
+            self.truncate_registers(count);
         }
     }
 
+    /// Drops registers, finalizing their values, until only `count` of them
+    /// is left.
+    pub fn truncate_registers(&mut self, count: usize) {
+        while self.registers.registers_count() > count {
+            self.registers.drop_register();
+        }
+    }
+
+    pub fn store(&self) -> ContextToken {
+        ContextToken {
+            stack: self.stack.store(),
+            registers: self.registers.store(),
+            registers_barriers: self.registers_barriers.len(),
+        }
+    }
+
+    /// Unwinds stack, registers and registers barriers back to state stored
+    /// in token, finalizing values on the way. Useful for recovering after
+    /// interrupted function calls.
+    pub fn restore(&mut self, token: ContextToken) {
+        self.stack.restore(token.stack);
+        self.registers.restore(token.registers);
+        self.registers_barriers.truncate(token.registers_barriers);
+    }
+
     pub fn registers_barriers(&self) -> &[usize] {
         &self.registers_barriers
     }
@@ -71,6 +202,256 @@ impl Context {
         self.registers.access_register(index)
     }
 
+    /// Describes all live registers, indexed by absolute register index.
+    pub fn registers_snapshot(&self) -> Vec<RegisterInfo> {
+        let mut result = Vec::with_capacity(self.registers.registers_count());
+        self.registers.visit(|type_hash, _, bytes, _, occupied| {
+            result.push(RegisterInfo {
+                type_hash,
+                occupied,
+                bytes: occupied.then(|| bytes.to_vec()),
+            });
+        });
+        // registers are visited from the top of the stack.
+        result.reverse();
+        result
+    }
+
+    /// Index is relative to current registers frame, same as in `access_register`.
+    pub fn register_type(&self, index: usize) -> Option<TypeHash> {
+        let index = self.absolute_register_index(index);
+        let mut current = self.registers.registers_count();
+        let mut result = None;
+        self.registers.visit(|type_hash, _, _, _, _| {
+            current = current.saturating_sub(1);
+            if current == index {
+                result = Some(type_hash);
+            }
+        });
+        result
+    }
+
+    pub fn heap_alloc<T: Send + Sync>(&mut self, value: T) -> HeapId {
+        let Ok(value) = DynamicManaged::new(value) else {
+            panic!("Could not allocate heap value!");
+        };
+        let id = HeapId::new();
+        self.heap.insert(id, value);
+        id
+    }
+
+    /// Returns `None` for zero sized layouts or when allocation fails.
+    ///
+    /// # Safety
+    /// Allocated memory is uninitialized and has to be initialized with value
+    /// of `type_hash` type before it gets read or freed. That type has to be
+    /// `Send` and `Sync`, same as values given to `heap_alloc`.
+    pub unsafe fn heap_alloc_raw(
+        &mut self,
+        type_hash: TypeHash,
+        layout: Layout,
+        finalizer: unsafe fn(*mut ()),
+    ) -> Option<HeapId> {
+        if layout.size() == 0 {
+            return None;
+        }
+        let value = DynamicManaged::new_raw(
+            type_hash,
+            Lifetime::default(),
+            alloc(layout),
+            layout,
+            finalizer,
+        )?;
+        let id = HeapId::new();
+        self.heap.insert(id, value);
+        Some(id)
+    }
+
+    pub fn heap_read<T>(&self, id: HeapId) -> Option<ValueReadAccess<'_, T>> {
+        self.heap.get(&id)?.read::<T>()
+    }
+
+    pub fn heap_write<T>(&mut self, id: HeapId) -> Option<ValueWriteAccess<'_, T>> {
+        self.heap.get_mut(&id)?.write::<T>()
+    }
+
+    pub fn heap_value(&self, id: HeapId) -> Option<&DynamicManaged> {
+        self.heap.get(&id)
+    }
+
+    pub fn heap_value_mut(&mut self, id: HeapId) -> Option<&mut DynamicManaged> {
+        self.heap.get_mut(&id)
+    }
+
+    /// Moves value out of heap. Value stays on heap if it is of other type or
+    /// is currently accessed.
+    pub fn heap_take<T>(&mut self, id: HeapId) -> Option<T> {
+        let value = self.heap.remove(&id)?;
+        match value.consume::<T>() {
+            Ok(value) => Some(value),
+            Err(value) => {
+                self.heap.insert(id, value);
+                None
+            }
+        }
+    }
+
+    /// Moves value from top of stack onto heap, for values escaping scope
+    /// that produced them. Returns `None` if there is no value on stack, it
+    /// is zero sized or its type is not registered as `Send` and `Sync` (same
+    /// as values given to `heap_alloc`), in which case stack stays unchanged.
+    pub fn heap_promote(&mut self, registry: &Registry) -> Option<HeapId> {
+        let type_hash = self.stack.peek()?;
+        let handle = registry.find_type(TypeQuery::default().with_hash(type_hash))?;
+        if !handle.is_send() || !handle.is_sync() {
+            return None;
+        }
+        unsafe {
+            let (layout, type_hash, finalizer, data) = self.stack.pop_raw()?;
+            if layout.size() == 0 {
+                self.stack.push_raw(layout, type_hash, finalizer, &data);
+                return None;
+            }
+            let value =
+                DynamicManaged::from_bytes(type_hash, Lifetime::default(), data, layout, finalizer);
+            let id = HeapId::new();
+            self.heap.insert(id, value);
+            Some(id)
+        }
+    }
+
+    /// Moves value from heap back on top of stack. Value stays on heap if it
+    /// is currently accessed or does not fit on stack.
+    pub fn heap_demote(&mut self, id: HeapId) -> bool {
+        let Some(value) = self.heap.get(&id) else {
+            return false;
+        };
+        if value.lifetime().state().is_in_use() {
+            return false;
+        }
+        let pushed = unsafe {
+            self.stack.push_raw(
+                *value.layout(),
+                *value.type_hash(),
+                value.finalizer(),
+                value.memory(),
+            )
+        };
+        if pushed {
+            let (_, _, memory, layout, _) = self.heap.remove(&id).unwrap().into_inner();
+            unsafe { dealloc(memory, layout) };
+        }
+        pushed
+    }
+
+    /// Finalizes value and releases its memory.
+    pub fn heap_free(&mut self, id: HeapId) -> bool {
+        self.heap.remove(&id).is_some()
+    }
+
+    pub fn heap_len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+
+    pub fn events_mut(&mut self) -> &mut EventBus {
+        &mut self.events
+    }
+
+    pub fn random(&self) -> &Random {
+        &self.random
+    }
+
+    pub fn random_mut(&mut self) -> &mut Random {
+        &mut self.random
+    }
+
+    pub fn debug_printer(&self) -> &DebugPrinter {
+        &self.debug_printer
+    }
+
+    pub fn debug_printer_mut(&mut self) -> &mut DebugPrinter {
+        &mut self.debug_printer
+    }
+
+    /// Prints value on top of stack with debug printer, leaving stack as is.
+    pub fn debug_print(&mut self, label: Option<&str>, registry: &Registry) {
+        self.debug_printer.print(label, &mut self.stack, registry);
+    }
+
+    /// Marks error as pending - script backends unwind scopes until it gets
+    /// caught, native functions should return right after throwing.
+    pub fn throw(&mut self, error: ScriptError) {
+        self.error = Some(error);
+    }
+
+    pub fn has_error(&self) -> bool {
+        self.error.is_some()
+    }
+
+    pub fn error(&self) -> Option<&ScriptError> {
+        self.error.as_ref()
+    }
+
+    pub fn error_mut(&mut self) -> Option<&mut ScriptError> {
+        self.error.as_mut()
+    }
+
+    pub fn take_error(&mut self) -> Option<ScriptError> {
+        self.error.take()
+    }
+
+    /// Starts counting execution against budget, `None` removes limits.
+    pub fn set_budget(&mut self, budget: Option<Budget>) {
+        self.budget = budget.map(BudgetState::new);
+    }
+
+    pub fn budget(&self) -> Option<&BudgetState> {
+        self.budget.as_ref()
+    }
+
+    /// Counts single operation against budget, returns false once budget is
+    /// exceeded - script backends should then stop executing and unwind.
+    pub fn consume_budget(&mut self) -> bool {
+        self.budget
+            .as_mut()
+            .map(|budget| budget.consume())
+            .unwrap_or(true)
+    }
+
+    pub fn budget_exceeded(&self) -> Option<BudgetExceeded> {
+        self.budget.as_ref().and_then(|budget| budget.exceeded())
+    }
+
+    /// Starts recording last `capacity` executed operations, `None` stops it.
+    pub fn set_operations_trace(&mut self, capacity: Option<usize>) {
+        let capacity = capacity.unwrap_or_default();
+        self.operations_trace = (capacity > 0).then(|| VecDeque::with_capacity(capacity));
+        self.operations_trace_capacity = capacity;
+    }
+
+    pub fn is_tracing_operations(&self) -> bool {
+        self.operations_trace.is_some()
+    }
+
+    /// Records operation if tracing is enabled, full trace drops its oldest one.
+    pub fn trace_operation(&mut self, trace: OperationTrace) {
+        if let Some(operations) = self.operations_trace.as_mut() {
+            if operations.len() >= self.operations_trace_capacity {
+                operations.pop_front();
+            }
+            operations.push_back(trace);
+        }
+    }
+
+    /// Recorded operations, from oldest to latest.
+    pub fn recent_operations(&self) -> impl Iterator<Item = &OperationTrace> {
+        self.operations_trace.iter().flatten()
+    }
+
     pub fn custom<T: Send + Sync + 'static>(&self, name: &str) -> Option<&T> {
         self.custom.get(name)?.downcast_ref::<T>()
     }
@@ -87,6 +468,8 @@ impl Context {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::struct_type::NativeStructBuilder;
+    use std::rc::Rc;
 
     #[test]
     fn test_async() {
@@ -94,4 +477,118 @@ mod tests {
 
         is_async::<Context>();
     }
+
+    #[test]
+    fn test_registers_snapshot() {
+        let mut context = Context::new(1024, 1024);
+        context.registers().push_register::<i32>().unwrap();
+        context.registers().push_register::<bool>().unwrap();
+        context.stack().push(42i32);
+        let (stack, registers) = context.stack_and_registers();
+        stack.pop_to_register(&mut registers.access_register(0).unwrap());
+        let snapshot = context.registers_snapshot();
+        assert_eq!(
+            snapshot,
+            vec![
+                RegisterInfo {
+                    type_hash: TypeHash::of::<i32>(),
+                    occupied: true,
+                    bytes: Some(42i32.to_ne_bytes().to_vec()),
+                },
+                RegisterInfo {
+                    type_hash: TypeHash::of::<bool>(),
+                    occupied: false,
+                    bytes: None,
+                },
+            ]
+        );
+        assert_eq!(context.register_type(0), Some(TypeHash::of::<i32>()));
+        assert_eq!(context.register_type(1), Some(TypeHash::of::<bool>()));
+        assert_eq!(context.register_type(2), None);
+        assert_eq!(context.registers_snapshot(), snapshot);
+        context.store_registers();
+        context.registers().push_register::<u8>().unwrap();
+        assert_eq!(context.register_type(0), Some(TypeHash::of::<u8>()));
+        assert_eq!(context.registers_snapshot().len(), 3);
+    }
+
+    #[test]
+    fn test_heap() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static FINALIZED: AtomicUsize = AtomicUsize::new(0);
+
+        struct Tracked(i32);
+
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                FINALIZED.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let mut registry = Registry::default();
+        for builder in [
+            NativeStructBuilder::new::<()>(),
+            NativeStructBuilder::new::<i32>(),
+            NativeStructBuilder::new_uninitialized::<Tracked>(),
+        ] {
+            registry.add_type(unsafe { builder.override_send(true).override_sync(true) }.build());
+        }
+        registry.add_type(
+            unsafe { NativeStructBuilder::new_uninitialized::<Rc<i32>>().override_send(false) }
+                .build(),
+        );
+        let mut context = Context::new(1024, 1024);
+        let token = context.store();
+        context.stack().push(42i32);
+        context.stack().push(Tracked(1));
+        let tracked = context.heap_promote(&registry).unwrap();
+        let value = context.heap_promote(&registry).unwrap();
+        let text = context.heap_alloc("hello".to_owned());
+        context.stack().push(());
+        assert!(context.heap_promote(&registry).is_none());
+        context.stack().push(Rc::new(0i32));
+        assert!(context.heap_promote(&registry).is_none());
+        context.stack().push(0i32);
+        assert!(context.heap_promote(&Registry::default()).is_none());
+        context.restore(token);
+        assert_eq!(context.stack().position(), 0);
+        assert_eq!(FINALIZED.load(Ordering::SeqCst), 0);
+
+        assert_eq!(*context.heap_read::<i32>(value).unwrap(), 42);
+        assert!(context.heap_read::<f32>(value).is_none());
+        *context.heap_write::<i32>(value).unwrap() = 10;
+        assert_eq!(context.heap_take::<f32>(value), None);
+        assert_eq!(context.heap_take::<i32>(value), Some(10));
+        assert!(context.heap_read::<i32>(value).is_none());
+        assert!(!context.heap_free(value));
+
+        assert_eq!(context.heap_read::<Tracked>(tracked).unwrap().0, 1);
+        assert!(context.heap_demote(tracked));
+        assert_eq!(context.stack().pop::<Tracked>().unwrap().0, 1);
+        assert_eq!(FINALIZED.load(Ordering::SeqCst), 1);
+        let tracked = context.heap_alloc(Tracked(2));
+        assert!(context.heap_free(tracked));
+        assert_eq!(FINALIZED.load(Ordering::SeqCst), 2);
+
+        let raw = unsafe {
+            let id = context
+                .heap_alloc_raw(
+                    TypeHash::of::<u64>(),
+                    std::alloc::Layout::new::<u64>(),
+                    <u64 as intuicio_data::Finalize>::finalize_raw,
+                )
+                .unwrap();
+            context
+                .heap_value_mut(id)
+                .unwrap()
+                .memory_mut()
+                .copy_from_slice(&7u64.to_ne_bytes());
+            id
+        };
+        assert_eq!(*context.heap_read::<u64>(raw).unwrap(), 7);
+        assert_eq!(context.heap_len(), 2);
+        assert!(context.fork().heap_value(text).is_none());
+        drop(context);
+    }
 }
@@ -0,0 +1,147 @@
+use crate::{registry::Registry, types::TypeQuery};
+use intuicio_data::{data_stack::DataStack, type_hash::TypeHash};
+use std::{collections::HashMap, fmt::Debug};
+
+pub type DebugPrintSink = Box<dyn FnMut(&str) + Send + Sync>;
+pub type DebugPrintFormatter = fn(&mut DataStack) -> Option<String>;
+
+/// Formats value on top of stack for debug prints authored in scripts and
+/// writes it into sink (stderr by default). Types without registered
+/// formatter are printed by their registered type name.
+pub struct DebugPrinter {
+    formatters: HashMap<TypeHash, DebugPrintFormatter>,
+    sink: Option<DebugPrintSink>,
+}
+
+impl Default for DebugPrinter {
+    fn default() -> Self {
+        Self {
+            formatters: Default::default(),
+            sink: None,
+        }
+        .with_basic_types()
+    }
+}
+
+impl DebugPrinter {
+    pub fn with_basic_types(mut self) -> Self {
+        self.register::<()>();
+        self.register::<bool>();
+        self.register::<i8>();
+        self.register::<i16>();
+        self.register::<i32>();
+        self.register::<i64>();
+        self.register::<i128>();
+        self.register::<isize>();
+        self.register::<u8>();
+        self.register::<u16>();
+        self.register::<u32>();
+        self.register::<u64>();
+        self.register::<u128>();
+        self.register::<usize>();
+        self.register::<f32>();
+        self.register::<f64>();
+        self.register::<char>();
+        self.register::<String>();
+        self
+    }
+
+    pub fn register<T: Debug + 'static>(&mut self) {
+        self.formatters
+            .insert(TypeHash::of::<T>(), Self::format_value::<T>);
+    }
+
+    pub fn register_formatter(&mut self, type_hash: TypeHash, formatter: DebugPrintFormatter) {
+        self.formatters.insert(type_hash, formatter);
+    }
+
+    pub fn unregister(&mut self, type_hash: TypeHash) {
+        self.formatters.remove(&type_hash);
+    }
+
+    pub fn set_sink(&mut self, sink: impl FnMut(&str) + Send + Sync + 'static) {
+        self.sink = Some(Box::new(sink));
+    }
+
+    /// Makes printer write to stderr again.
+    pub fn reset_sink(&mut self) {
+        self.sink = None;
+    }
+
+    /// Formats value on top of stack, leaving stack unchanged.
+    pub fn format(&self, stack: &mut DataStack, registry: &Registry) -> String {
+        let Some(type_hash) = stack.peek() else {
+            return "<empty>".to_owned();
+        };
+        if let Some(text) = self
+            .formatters
+            .get(&type_hash)
+            .and_then(|formatter| formatter(stack))
+        {
+            return text;
+        }
+        match registry.find_type(TypeQuery::default().with_hash(type_hash)) {
+            Some(type_) => format!("<{}>", type_.name()),
+            None => "<unknown>".to_owned(),
+        }
+    }
+
+    pub fn print(&mut self, label: Option<&str>, stack: &mut DataStack, registry: &Registry) {
+        let text = self.format(stack, registry);
+        let line = match label {
+            Some(label) => format!("{}: {}", label, text),
+            None => text,
+        };
+        match self.sink.as_mut() {
+            Some(sink) => sink(&line),
+            None => eprintln!("{}", line),
+        }
+    }
+
+    fn format_value<T: Debug + 'static>(stack: &mut DataStack) -> Option<String> {
+        let value = stack.pop::<T>()?;
+        let result = format!("{:?}", value);
+        stack.push(value);
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use intuicio_data::data_stack::DataStackMode;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug)]
+    struct Foo;
+
+    #[test]
+    fn test_debug_printer() {
+        let registry = Registry::default().with_basic_types();
+        let lines = Arc::new(Mutex::new(vec![]));
+        let lines2 = lines.clone();
+        let mut printer = DebugPrinter::default();
+        printer.set_sink(move |line| lines2.lock().unwrap().push(line.to_owned()));
+        let mut stack = DataStack::new(1024, DataStackMode::Values);
+        printer.print(None, &mut stack, &registry);
+        stack.push(42i32);
+        stack.push("hello".to_owned());
+        printer.print(Some("text"), &mut stack, &registry);
+        stack.push(Foo);
+        printer.print(None, &mut stack, &registry);
+        printer.register::<Foo>();
+        printer.print(None, &mut stack, &registry);
+        assert_eq!(
+            lines.lock().unwrap().as_slice(),
+            &[
+                "<empty>".to_owned(),
+                "text: \"hello\"".to_owned(),
+                "<unknown>".to_owned(),
+                "Foo".to_owned(),
+            ]
+        );
+        assert!(stack.pop::<Foo>().is_some());
+        assert_eq!(stack.pop::<String>().unwrap(), "hello");
+        assert_eq!(stack.pop::<i32>().unwrap(), 42);
+    }
+}
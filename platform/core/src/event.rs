@@ -0,0 +1,112 @@
+use crate::{
+    function::{Function, FunctionBody, FunctionParameter, FunctionSignature},
+    registry::Registry,
+    script::ScriptError,
+    types::TypeQuery,
+};
+use intuicio_data::{managed::DynamicManaged, type_hash::TypeHash};
+use std::collections::HashMap;
+
+/// Queues of events grouped by event type, used by scripts and host to
+/// notify each other without polling shared state. Events are not cleared
+/// between runs - they stay queued until drained, so host decides when to
+/// react to them (usually by draining once per frame).
+#[derive(Default)]
+pub struct EventBus {
+    events: HashMap<TypeHash, Vec<DynamicManaged>>,
+}
+
+impl EventBus {
+    /// Gives event back if it is zero sized or could not be allocated.
+    pub fn emit<T: Send + Sync + 'static>(&mut self, event: T) -> Result<(), T> {
+        if std::mem::size_of::<T>() == 0 {
+            return Err(event);
+        }
+        self.emit_dynamic(DynamicManaged::new(event)?);
+        Ok(())
+    }
+
+    pub fn emit_dynamic(&mut self, event: DynamicManaged) {
+        self.events
+            .entry(*event.type_hash())
+            .or_default()
+            .push(event);
+    }
+
+    /// Takes all queued events of `T` type, in order they were emitted.
+    pub fn drain<T>(&mut self) -> Vec<T> {
+        self.drain_dynamic(TypeHash::of::<T>())
+            .into_iter()
+            .filter_map(|event| event.consume::<T>().ok())
+            .collect()
+    }
+
+    pub fn drain_dynamic(&mut self, type_hash: TypeHash) -> Vec<DynamicManaged> {
+        self.events.remove(&type_hash).unwrap_or_default()
+    }
+
+    pub fn len(&self, type_hash: TypeHash) -> usize {
+        self.events
+            .get(&type_hash)
+            .map(|events| events.len())
+            .unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.values().all(|events| events.is_empty())
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    /// Creates function that takes event of `T` type and emits it into event
+    /// bus of context, so scripts can emit events with regular function call.
+    /// Events that could not be emitted are thrown as script errors.
+    /// Returns `None` if `T` type is not registered.
+    pub fn define_emit_function<T: Send + Sync + 'static>(
+        name: impl ToString,
+        module_name: impl ToString,
+        registry: &Registry,
+    ) -> Option<Function> {
+        let signature = FunctionSignature::new(name)
+            .with_module_name(module_name)
+            .with_input(FunctionParameter::new(
+                "event",
+                registry.find_type(TypeQuery::of::<T>())?,
+            ));
+        Some(Function::new(
+            signature,
+            FunctionBody::closure(|context, _| {
+                let event = context
+                    .stack()
+                    .pop::<T>()
+                    .expect("Could not pop event from stack!");
+                if context.events_mut().emit(event).is_err() {
+                    context.throw(ScriptError::new("Could not emit event!"));
+                }
+            }),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_bus() {
+        let mut events = EventBus::default();
+        assert!(events.is_empty());
+        events.emit(1u8).unwrap();
+        events.emit("hello".to_owned()).unwrap();
+        events.emit(2u8).unwrap();
+        assert!(events.emit(()).is_err());
+        assert_eq!(events.len(TypeHash::of::<u8>()), 2);
+        assert_eq!(events.drain::<u8>(), vec![1, 2]);
+        assert!(events.drain::<u8>().is_empty());
+        assert!(!events.is_empty());
+        assert_eq!(events.drain::<String>(), vec!["hello".to_owned()]);
+        assert!(events.is_empty());
+    }
+}
@@ -2,20 +2,26 @@ use crate::{
     context::Context,
     meta::Meta,
     registry::Registry,
+    script::ScriptError,
     types::{Type, TypeHandle, TypeQuery},
     Visibility,
 };
+use arc_swap::ArcSwap;
 use intuicio_data::data_stack::DataStackPack;
 use rustc_hash::FxHasher;
 use std::{
     borrow::Cow,
     hash::{Hash, Hasher},
-    sync::Arc,
+    sync::{Arc, Mutex},
+    time::Instant,
 };
+use typid::ID;
 
 pub type FunctionHandle = Arc<Function>;
+pub type FunctionId = ID<Function>;
 pub type FunctionMetaQuery = fn(&Meta) -> bool;
 
+#[derive(Clone)]
 pub enum FunctionBody {
     Pointer(fn(&mut Context, &Registry)),
     #[allow(clippy::type_complexity)]
@@ -34,6 +40,20 @@ impl FunctionBody {
         Self::Closure(Arc::new(closure))
     }
 
+    /// State is owned by function body and locked for the time of the call,
+    /// so function should not call itself recursively.
+    pub fn stateful_closure<S, T>(state: S, closure: T) -> Self
+    where
+        S: Send + 'static,
+        T: Fn(&mut S, &mut Context, &Registry) + Send + Sync + 'static,
+    {
+        let state = Mutex::new(state);
+        Self::closure(move |context, registry| {
+            let mut state = state.lock().expect("Function state is poisoned!");
+            closure(&mut state, context, registry);
+        })
+    }
+
     pub fn invoke(&self, context: &mut Context, registry: &Registry) {
         match self {
             Self::Pointer(pointer) => pointer(context, registry),
@@ -201,25 +221,76 @@ impl std::fmt::Display for FunctionSignature {
 
 #[derive(Debug)]
 pub struct Function {
+    id: FunctionId,
     signature: FunctionSignature,
-    body: FunctionBody,
+    body: ArcSwap<FunctionBody>,
+    calls: Vec<FunctionQuery<'static>>,
 }
 
 impl Function {
     pub fn new(signature: FunctionSignature, body: FunctionBody) -> Self {
-        Self { signature, body }
+        Self {
+            id: FunctionId::new(),
+            signature,
+            body: ArcSwap::from_pointee(body),
+            calls: vec![],
+        }
+    }
+
+    /// Queries of functions called by this function, usually collected from
+    /// script it was generated from. Native functions do not report any.
+    pub fn with_calls(mut self, calls: Vec<FunctionQuery<'static>>) -> Self {
+        self.calls = calls;
+        self
+    }
+
+    pub fn id(&self) -> FunctionId {
+        self.id
     }
 
     pub fn signature(&self) -> &FunctionSignature {
         &self.signature
     }
 
+    pub fn calls(&self) -> &[FunctionQuery<'static>] {
+        &self.calls
+    }
+
+    /// Swaps body of this function in place, so every holder of its handle
+    /// calls new body from now on. Calls already running finish with old body.
+    pub fn replace_body(&self, body: FunctionBody) {
+        self.body.store(Arc::new(body));
+    }
+
+    pub fn into_body(self) -> FunctionBody {
+        Arc::unwrap_or_clone(self.body.into_inner())
+    }
+
     pub fn invoke(&self, context: &mut Context, registry: &Registry) {
+        // loaded body is kept alive for the time of the call without locking,
+        // so it can be replaced, even by itself.
+        let body = self.body.load();
         context.store_registers();
-        self.body.invoke(context, registry);
+        self.observe(registry, || body.invoke(context, registry));
         context.restore_registers();
     }
 
+    /// Reports `f` to metrics sink of `registry` as call of this function.
+    /// Backends that restart invocation in place instead of invoking function
+    /// again, like for self-recursive tail calls, observe restarts with it.
+    pub fn observe<R>(&self, registry: &Registry, f: impl FnOnce() -> R) -> R {
+        if let Some(sink) = registry.metrics_sink() {
+            let timer = Instant::now();
+            let result = f();
+            sink.record(self.id, timer.elapsed());
+            result
+        } else {
+            f()
+        }
+    }
+
+    /// Panics if function leaves pending script error, use `try_call` to
+    /// handle it instead.
     pub fn call<O: DataStackPack, I: DataStackPack>(
         &self,
         context: &mut Context,
@@ -227,12 +298,33 @@ impl Function {
         inputs: I,
         verify: bool,
     ) -> O {
+        self.try_call(context, registry, inputs, verify)
+            .unwrap_or_else(|error| {
+                panic!("Function: {} raised error: {}", self.signature.name, error)
+            })
+    }
+
+    /// If function leaves pending script error, stack is restored to its
+    /// position from before the call and error is taken from context, so
+    /// native callers can rethrow it with `Context::throw`.
+    pub fn try_call<O: DataStackPack, I: DataStackPack>(
+        &self,
+        context: &mut Context,
+        registry: &Registry,
+        inputs: I,
+        verify: bool,
+    ) -> Result<O, ScriptError> {
         if verify {
             self.verify_inputs_outputs::<O, I>();
         }
-        inputs.stack_push_reversed(context.stack());
+        let token = context.stack().store();
+        context.stack().push_pack(inputs);
         self.invoke(context, registry);
-        O::stack_pop(context.stack())
+        if let Some(error) = context.take_error() {
+            context.stack().restore(token);
+            return Err(error);
+        }
+        Ok(context.stack().pop_pack())
     }
 
     pub fn verify_inputs_outputs<O: DataStackPack, I: DataStackPack>(&self) {
@@ -318,7 +410,49 @@ pub struct FunctionQuery<'a> {
     pub meta: Option<FunctionMetaQuery>,
 }
 
-impl FunctionQuery<'_> {
+impl<'a> FunctionQuery<'a> {
+    pub fn named(name: impl Into<Cow<'a, str>>) -> Self {
+        Self {
+            name: Some(name.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn in_module(mut self, module_name: impl Into<Cow<'a, str>>) -> Self {
+        self.module_name = Some(module_name.into());
+        self
+    }
+
+    pub fn of_type(mut self, type_query: TypeQuery<'a>) -> Self {
+        self.type_query = Some(type_query);
+        self
+    }
+
+    pub fn with_visibility(mut self, visibility: Visibility) -> Self {
+        self.visibility = Some(visibility);
+        self
+    }
+
+    /// Appends input parameters matching given types, in order.
+    pub fn taking(mut self, inputs: impl IntoIterator<Item = TypeQuery<'a>>) -> Self {
+        self.inputs
+            .to_mut()
+            .extend(inputs.into_iter().map(|type_query| FunctionQueryParameter {
+                type_query: Some(type_query),
+                ..Default::default()
+            }));
+        self
+    }
+
+    /// Appends output parameter matching given type.
+    pub fn returning(mut self, output: TypeQuery<'a>) -> Self {
+        self.outputs.to_mut().push(FunctionQueryParameter {
+            type_query: Some(output),
+            ..Default::default()
+        });
+        self
+    }
+
     pub fn is_valid(&self, signature: &FunctionSignature) -> bool {
         self.name
             .as_ref()
@@ -411,9 +545,9 @@ macro_rules! function_signature {
         $(type ($type:ty))?
         fn
         $name:ident
-        ($( $input_name:ident : $input_type:ty ),*)
+        ($( $(#[doc = $input_doc:literal])* $input_name:ident : $input_type:ty ),*)
         ->
-        ($( $output_name:ident : $output_type:ty ),*)
+        ($( $(#[doc = $output_doc:literal])* $output_name:ident : $output_type:ty ),*)
     ) => {{
         let mut result = $crate::function::FunctionSignature::new(stringify!($name));
         $(
@@ -423,25 +557,27 @@ macro_rules! function_signature {
             result.type_handle = Some($registry.find_type($crate::types::TypeQuery::of::<$type>()).unwrap());
         )?
         $(
-            result.inputs.push(
-                $crate::function::FunctionParameter::new(
-                    stringify!($input_name).to_owned(),
-                    $registry.find_type($crate::types::TypeQuery::of::<$input_type>()).unwrap()
-                )
+            let mut parameter = $crate::function::FunctionParameter::new(
+                stringify!($input_name).to_owned(),
+                $registry.find_type($crate::types::TypeQuery::of::<$input_type>()).unwrap()
             );
+            parameter.meta = $crate::meta::Meta::from_doc_lines(&[$($input_doc),*]);
+            result.inputs.push(parameter);
         )*
         $(
-            result.outputs.push(
-                $crate::function::FunctionParameter::new(
-                    stringify!($output_name).to_owned(),
-                    $registry.find_type($crate::types::TypeQuery::of::<$output_type>()).unwrap()
-                )
+            let mut parameter = $crate::function::FunctionParameter::new(
+                stringify!($output_name).to_owned(),
+                $registry.find_type($crate::types::TypeQuery::of::<$output_type>()).unwrap()
             );
+            parameter.meta = $crate::meta::Meta::from_doc_lines(&[$($output_doc),*]);
+            result.outputs.push(parameter);
         )*
         result
     }};
 }
 
+/// Parameters can be documented with doc comments, which get stored in
+/// their meta, see `Meta::from_doc_lines`.
 #[macro_export]
 macro_rules! define_function {
     (
@@ -451,9 +587,9 @@ macro_rules! define_function {
         $(type ($type:ty))?
         fn
         $name:ident
-        ($( $input_name:ident : $input_type:ty),*)
+        ($( $(#[doc = $input_doc:literal])* $input_name:ident : $input_type:ty),*)
         ->
-        ($( $output_name:ident : $output_type:ty),*)
+        ($( $(#[doc = $output_doc:literal])* $output_name:ident : $output_type:ty),*)
         $code:block
     ) => {
         $crate::function::Function::new(
@@ -464,15 +600,14 @@ macro_rules! define_function {
                 $(type ($type))?
                 fn
                 $name
-                ($($input_name : $input_type),*)
+                ($($(#[doc = $input_doc])* $input_name : $input_type),*)
                 ->
-                ($($output_name : $output_type),*)
+                ($($(#[doc = $output_doc])* $output_name : $output_type),*)
             },
             $crate::function::FunctionBody::closure(move |context, registry| {
-                use intuicio_data::data_stack::DataStackPack;
                 #[allow(unused_mut)]
-                let ($(mut $input_name,)*) = <($($input_type,)*)>::stack_pop(context.stack());
-                $code.stack_push_reversed(context.stack());
+                let ($(mut $input_name,)*) = context.stack().pop_pack::<($($input_type,)*)>();
+                context.stack().push_pack::<($($output_type,)*)>($code);
             }),
         )
     };
@@ -482,7 +617,7 @@ macro_rules! define_function {
 mod tests {
     use crate as intuicio_core;
     use crate::{context::*, function::*, registry::*, types::struct_type::*};
-    use intuicio_data;
+    use intuicio_data::{self, type_hash::TypeHash};
     use intuicio_derive::*;
 
     #[intuicio_function(meta = "foo")]
@@ -566,4 +701,158 @@ mod tests {
             Some(Meta::Identifier("foo".to_owned()))
         );
     }
+
+    #[test]
+    fn test_stateful_closure() {
+        let usize_handle = NativeStructBuilder::new::<usize>()
+            .build()
+            .into_type()
+            .into_handle();
+        let function = Function::new(
+            FunctionSignature::new("counter")
+                .with_output(FunctionParameter::new("result", usize_handle)),
+            FunctionBody::stateful_closure(0usize, |counter, context, _| {
+                *counter += 1;
+                context.stack().push(*counter);
+            }),
+        );
+
+        let mut context = Context::new(10240, 10240);
+        let registry = Registry::default();
+        for expected in 1..=3 {
+            function.invoke(&mut context, &registry);
+            assert_eq!(context.stack().pop::<usize>().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_query_builders() {
+        let module_name = Some("intrinsics".to_owned());
+        assert_eq!(
+            TypeQuery::named("Foo")
+                .in_module("test")
+                .with_hash(TypeHash::of::<usize>()),
+            TypeQuery {
+                name: Some("Foo".into()),
+                module_name: Some("test".into()),
+                type_hash: Some(TypeHash::of::<usize>()),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            TypeQuery::named("usize".to_owned()),
+            TypeQuery {
+                name: Some("usize".to_owned().into()),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            FunctionQuery::named("add".to_owned()).in_module(module_name.clone().unwrap()),
+            FunctionQuery {
+                name: Some("add".to_owned().into()),
+                module_name: module_name.as_ref().map(|name| name.to_owned().into()),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            FunctionQuery::named("new")
+                .in_module("test")
+                .of_type(TypeQuery::named("Foo"))
+                .with_visibility(Visibility::Public),
+            FunctionQuery {
+                name: Some("new".into()),
+                module_name: Some("test".into()),
+                type_query: Some(TypeQuery {
+                    name: Some("Foo".into()),
+                    ..Default::default()
+                }),
+                visibility: Some(Visibility::Public),
+                ..Default::default()
+            }
+        );
+        let type_hash = TypeHash::of::<usize>();
+        assert_eq!(
+            FunctionQuery::named("clone")
+                .of_type(TypeQuery::default().with_hash(type_hash))
+                .taking([TypeQuery::default().with_hash(type_hash)])
+                .taking([TypeQuery::of::<usize>()])
+                .returning(TypeQuery::of::<usize>())
+                .returning(TypeQuery::of::<usize>()),
+            FunctionQuery {
+                name: Some("clone".into()),
+                type_query: Some(TypeQuery {
+                    type_hash: Some(type_hash),
+                    ..Default::default()
+                }),
+                inputs: [
+                    FunctionQueryParameter {
+                        type_query: Some(TypeQuery {
+                            type_hash: Some(type_hash),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    },
+                    FunctionQueryParameter {
+                        type_query: Some(TypeQuery::of::<usize>()),
+                        ..Default::default()
+                    },
+                ]
+                .as_slice()
+                .into(),
+                outputs: vec![
+                    FunctionQueryParameter {
+                        type_query: Some(TypeQuery::of::<usize>()),
+                        ..Default::default()
+                    };
+                    2
+                ]
+                .into(),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    #[allow(unused_variables)]
+    fn test_parameter_docs() {
+        let mut registry = Registry::default().with_basic_types();
+        let handle = registry.add_function(define_function! {
+            registry => mod math fn clamp(
+                /// Value to clamp.
+                value: i32,
+                #[doc = "Lower bound."]
+                #[doc = "Inclusive."]
+                min: i32,
+                max: i32
+            ) -> (
+                /// Clamped value.
+                result: i32
+            ) {
+                (value.clamp(min, max),)
+            }
+        });
+        let signature = handle.signature();
+        let docs = signature
+            .inputs
+            .iter()
+            .map(|parameter| parameter.meta.as_ref().and_then(|meta| meta.doc()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            docs,
+            vec![
+                Some("Value to clamp."),
+                Some("Lower bound.\nInclusive."),
+                None
+            ]
+        );
+        assert!(signature.inputs[2].meta.is_none());
+        assert_eq!(
+            signature.outputs[0].meta.as_ref().unwrap().doc(),
+            Some("Clamped value.")
+        );
+
+        let mut context = Context::new(10240, 10240);
+        let (result,) = handle.call::<(i32,), _>(&mut context, &registry, (42, 0, 10), true);
+        assert_eq!(result, 10);
+    }
 }
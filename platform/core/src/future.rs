@@ -0,0 +1,217 @@
+use intuicio_data::{data_stack::DataStack, type_hash::TypeHash};
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context as TaskContext, Waker},
+};
+
+type Task = Pin<Box<dyn Future<Output = ()> + Send>>;
+pub type FutureReadiness = fn(&mut DataStack) -> Option<bool>;
+
+/// Executor for futures spawned by async functions.
+///
+/// There is no background runtime - host creates poller, puts it into context
+/// custom data under `FuturePoller::CUSTOM` and is responsible for calling
+/// `poll` regularly (between frames, between script calls, or from native
+/// function that scripts call in a loop while awaiting results). Futures are
+/// polled with no-op waker, so they get polled on every `poll` call. Backends
+/// awaiting handles poll it too, while handle they await is not ready.
+#[derive(Default, Clone)]
+pub struct FuturePoller {
+    tasks: Arc<Mutex<Vec<Task>>>,
+    readiness: Arc<Mutex<HashMap<TypeHash, FutureReadiness>>>,
+}
+
+impl FuturePoller {
+    pub const CUSTOM: &'static str = "FuturePoller";
+
+    pub fn spawn<T: Send + 'static>(
+        &self,
+        future: impl Future<Output = T> + Send + 'static,
+    ) -> FutureHandle<T> {
+        self.readiness.lock().unwrap().insert(
+            TypeHash::of::<FutureHandle<T>>(),
+            FutureHandle::<T>::is_ready_on_stack,
+        );
+        let handle = FutureHandle::default();
+        let result = handle.result.clone();
+        self.tasks.lock().unwrap().push(Box::pin(async move {
+            let value = future.await;
+            *result.lock().unwrap() = Some(value);
+        }));
+        handle
+    }
+
+    /// Polls all pending tasks once and returns number of tasks still pending.
+    pub fn poll(&self) -> usize {
+        let mut tasks = std::mem::take(&mut *self.tasks.lock().unwrap());
+        let mut context = TaskContext::from_waker(Waker::noop());
+        tasks.retain_mut(|task| task.as_mut().poll(&mut context).is_pending());
+        let mut current = self.tasks.lock().unwrap();
+        // tasks spawned while polling.
+        tasks.append(&mut current);
+        *current = tasks;
+        current.len()
+    }
+
+    pub fn pending(&self) -> usize {
+        self.tasks.lock().unwrap().len()
+    }
+
+    /// Tells if handle on top of stack is ready, leaving stack unchanged.
+    /// Returns `None` if there is no handle of future spawned by this poller.
+    pub fn is_ready(&self, stack: &mut DataStack) -> Option<bool> {
+        let type_hash = stack.peek()?;
+        let readiness = *self.readiness.lock().unwrap().get(&type_hash)?;
+        readiness(stack)
+    }
+}
+
+/// Result of async function call, pushed on stack right when function gets
+/// called and filled once its future completes.
+pub struct FutureHandle<T> {
+    result: Arc<Mutex<Option<T>>>,
+}
+
+impl<T> Default for FutureHandle<T> {
+    fn default() -> Self {
+        Self {
+            result: Default::default(),
+        }
+    }
+}
+
+impl<T> Clone for FutureHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            result: self.result.clone(),
+        }
+    }
+}
+
+impl<T> FutureHandle<T> {
+    pub fn is_ready(&self) -> bool {
+        self.result.lock().unwrap().is_some()
+    }
+
+    /// Returns `None` if result is not ready yet or was already taken.
+    pub fn take(&self) -> Option<T> {
+        self.result.lock().unwrap().take()
+    }
+}
+
+impl<T: 'static> FutureHandle<T> {
+    fn is_ready_on_stack(stack: &mut DataStack) -> Option<bool> {
+        let handle = stack.pop::<Self>()?;
+        let result = handle.is_ready();
+        stack.push(handle);
+        Some(result)
+    }
+}
+
+/// Defines function which spawns its async body on `FuturePoller` found in
+/// context custom data and pushes `FutureHandle` with outputs tuple on stack,
+/// which scripts can suspend on with `Await` operation. Type of that handle
+/// gets registered in `registry`, so it has to be mutable.
+#[macro_export]
+macro_rules! define_async_function {
+    (
+        $registry:expr
+        =>
+        $(mod $module_name:ident)?
+        $(type ($type:ty))?
+        fn
+        $name:ident
+        ($( $input_name:ident : $input_type:ty),*)
+        ->
+        ($( $output_name:ident : $output_type:ty),*)
+        $code:block
+    ) => {{
+        $registry.add_type(
+            $crate::types::struct_type::NativeStructBuilder::new::<
+                $crate::future::FutureHandle<($($output_type,)*)>,
+            >()
+            .build(),
+        );
+        $crate::function::Function::new(
+            $crate::function_signature! {
+                $registry
+                =>
+                $(mod $module_name)?
+                $(type ($type))?
+                fn
+                $name
+                ($($input_name : $input_type),*)
+                ->
+                (future : $crate::future::FutureHandle<($($output_type,)*)>)
+            },
+            $crate::function::FunctionBody::closure(move |context, _| {
+                #[allow(unused_mut)]
+                let ($(mut $input_name,)*) = context.stack().pop_pack::<($($input_type,)*)>();
+                let poller = context
+                    .custom::<$crate::future::FuturePoller>($crate::future::FuturePoller::CUSTOM)
+                    .expect("`FuturePoller` is not present in context custom data!")
+                    .clone();
+                let future = poller.spawn::<($($output_type,)*)>(async move $code);
+                context.stack().push(future);
+            }),
+        )
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use intuicio_data::data_stack::DataStackMode;
+    use std::task::Poll;
+
+    struct Tick(bool);
+
+    impl Future for Tick {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, _: &mut TaskContext<'_>) -> Poll<Self::Output> {
+            if self.0 {
+                Poll::Ready(())
+            } else {
+                self.0 = true;
+                Poll::Pending
+            }
+        }
+    }
+
+    #[test]
+    fn test_future_poller() {
+        let poller = FuturePoller::default();
+        let handle = poller.spawn(async {
+            Tick(false).await;
+            42
+        });
+        assert_eq!(poller.pending(), 1);
+        assert!(!handle.is_ready());
+        assert_eq!(poller.poll(), 1);
+        assert!(handle.take().is_none());
+        assert_eq!(poller.poll(), 0);
+        assert!(handle.is_ready());
+        assert_eq!(handle.take(), Some(42));
+        assert!(handle.take().is_none());
+    }
+
+    #[test]
+    fn test_future_readiness() {
+        let poller = FuturePoller::default();
+        let mut stack = DataStack::new(1024, DataStackMode::Values);
+        assert_eq!(poller.is_ready(&mut stack), None);
+        stack.push(poller.spawn(Tick(false)));
+        assert_eq!(poller.is_ready(&mut stack), Some(false));
+        poller.poll();
+        assert_eq!(poller.is_ready(&mut stack), Some(false));
+        poller.poll();
+        assert_eq!(poller.is_ready(&mut stack), Some(true));
+        assert!(stack.pop::<FutureHandle<()>>().unwrap().take().is_some());
+        stack.push(42i32);
+        assert_eq!(poller.is_ready(&mut stack), None);
+    }
+}
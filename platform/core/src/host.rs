@@ -1,11 +1,19 @@
 use crate::{
+    budget::{Budget, BudgetExceeded},
     context::Context,
-    function::{FunctionHandle, FunctionQuery, FunctionQueryParameter},
+    function::{FunctionHandle, FunctionQuery, FunctionQueryParameter, FunctionSignature},
     registry::{Registry, RegistryHandle},
+    script::ScriptError,
     types::TypeQuery,
 };
-use intuicio_data::data_stack::DataStackPack;
-use std::{cell::RefCell, marker::PhantomData, sync::Arc};
+use intuicio_data::{data_stack::DataStackPack, type_hash::TypeHash};
+use std::{
+    cell::RefCell,
+    error::Error,
+    marker::PhantomData,
+    panic::{catch_unwind, resume_unwind, AssertUnwindSafe},
+    sync::Arc,
+};
 use typid::ID;
 
 thread_local! {
@@ -14,6 +22,107 @@ thread_local! {
 
 pub type HostId = ID<Host>;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HostCallError {
+    FunctionNotFound {
+        name: String,
+        module_name: String,
+        type_name: Option<String>,
+    },
+    InputsCount {
+        function: String,
+        expected: usize,
+        provided: usize,
+    },
+    OutputsCount {
+        function: String,
+        expected: usize,
+        provided: usize,
+    },
+    InputType {
+        function: String,
+        parameter: String,
+        expected: String,
+        provided: TypeHash,
+    },
+    OutputType {
+        function: String,
+        parameter: String,
+        expected: String,
+        provided: TypeHash,
+    },
+    /// Function left pending script error.
+    Script {
+        function: String,
+        error: ScriptError,
+    },
+}
+
+impl std::fmt::Display for HostCallError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::FunctionNotFound {
+                name,
+                module_name,
+                type_name,
+            } => {
+                if let Some(type_name) = type_name {
+                    write!(
+                        f,
+                        "Function not found: {}::{}::{}",
+                        module_name, type_name, name
+                    )
+                } else {
+                    write!(f, "Function not found: {}::{}", module_name, name)
+                }
+            }
+            Self::InputsCount {
+                function,
+                expected,
+                provided,
+            } => write!(
+                f,
+                "Function `{}` expects {} inputs, provided {}",
+                function, expected, provided
+            ),
+            Self::OutputsCount {
+                function,
+                expected,
+                provided,
+            } => write!(
+                f,
+                "Function `{}` returns {} outputs, requested {}",
+                function, expected, provided
+            ),
+            Self::InputType {
+                function,
+                parameter,
+                expected,
+                provided,
+            } => write!(
+                f,
+                "Function `{}` input `{}` expects type `{}`, provided type {}",
+                function, parameter, expected, provided
+            ),
+            Self::OutputType {
+                function,
+                parameter,
+                expected,
+                provided,
+            } => write!(
+                f,
+                "Function `{}` output `{}` has type `{}`, requested type {}",
+                function, parameter, expected, provided
+            ),
+            Self::Script { function, error } => {
+                write!(f, "Function `{}` raised error: {}", function, error)
+            }
+        }
+    }
+}
+
+impl Error for HostCallError {}
+
 #[derive(Clone)]
 pub struct HostProducer {
     producer: Arc<Box<dyn Fn() -> Host + Send + Sync>>,
@@ -108,12 +217,46 @@ impl Host {
         })
     }
 
+    /// Fast path that trusts inputs and outputs types to identify function,
+    /// without telling why function could not be called.
     pub fn call_function<O: DataStackPack, I: DataStackPack>(
         &mut self,
         name: &str,
         module_name: &str,
         type_name: Option<&str>,
     ) -> Option<HostFunctionCall<I, O>> {
+        let handle = self.find_function_of::<O, I>(name, module_name, type_name)?;
+        Some(HostFunctionCall {
+            context: &mut self.context,
+            registry: &self.registry,
+            handle,
+            _phantom: Default::default(),
+        })
+    }
+
+    /// Resolves function once, so it can be called many times without
+    /// searching registry on every call.
+    pub fn prepare<O: DataStackPack, I: DataStackPack>(
+        &mut self,
+        name: &str,
+        module_name: &str,
+        type_name: Option<&str>,
+    ) -> Option<PreparedCall<'_, I, O>> {
+        let handle = self.find_function_of::<O, I>(name, module_name, type_name)?;
+        Some(PreparedCall {
+            context: &mut self.context,
+            registry: &self.registry,
+            handle,
+            _phantom: Default::default(),
+        })
+    }
+
+    fn find_function_of<O: DataStackPack, I: DataStackPack>(
+        &self,
+        name: &str,
+        module_name: &str,
+        type_name: Option<&str>,
+    ) -> Option<FunctionHandle> {
         let inputs_query = I::pack_types()
             .into_iter()
             .map(|type_hash| FunctionQueryParameter {
@@ -134,7 +277,7 @@ impl Host {
                 ..Default::default()
             })
             .collect::<Vec<_>>();
-        let handle = self.registry.find_function(FunctionQuery {
+        self.registry.find_function(FunctionQuery {
             name: Some(name.into()),
             module_name: Some(module_name.into()),
             type_query: type_name.map(|type_name| TypeQuery {
@@ -144,8 +287,26 @@ impl Host {
             inputs: inputs_query.into(),
             outputs: outputs_query.into(),
             ..Default::default()
-        })?;
-        Some(HostFunctionCall {
+        })
+    }
+
+    /// Checked path that verifies inputs and outputs types against function
+    /// signature before any data gets onto the stack.
+    pub fn try_call_function<O: DataStackPack, I: DataStackPack>(
+        &mut self,
+        name: &str,
+        module_name: &str,
+        type_name: Option<&str>,
+    ) -> Result<HostFunctionCall<'_, I, O>, HostCallError> {
+        let handle = self
+            .find_function(name, module_name, type_name)
+            .ok_or_else(|| HostCallError::FunctionNotFound {
+                name: name.to_owned(),
+                module_name: module_name.to_owned(),
+                type_name: type_name.map(|type_name| type_name.to_owned()),
+            })?;
+        verify_signature::<O, I>(handle.signature())?;
+        Ok(HostFunctionCall {
             context: &mut self.context,
             registry: &self.registry,
             handle,
@@ -154,6 +315,48 @@ impl Host {
     }
 }
 
+fn verify_signature<O: DataStackPack, I: DataStackPack>(
+    signature: &FunctionSignature,
+) -> Result<(), HostCallError> {
+    let inputs = I::pack_types();
+    if inputs.len() != signature.inputs.len() {
+        return Err(HostCallError::InputsCount {
+            function: signature.name.to_owned(),
+            expected: signature.inputs.len(),
+            provided: inputs.len(),
+        });
+    }
+    let outputs = O::pack_types();
+    if outputs.len() != signature.outputs.len() {
+        return Err(HostCallError::OutputsCount {
+            function: signature.name.to_owned(),
+            expected: signature.outputs.len(),
+            provided: outputs.len(),
+        });
+    }
+    for (parameter, type_hash) in signature.inputs.iter().zip(inputs) {
+        if parameter.type_handle.type_hash() != type_hash {
+            return Err(HostCallError::InputType {
+                function: signature.name.to_owned(),
+                parameter: parameter.name.to_owned(),
+                expected: parameter.type_handle.type_name().to_owned(),
+                provided: type_hash,
+            });
+        }
+    }
+    for (parameter, type_hash) in signature.outputs.iter().zip(outputs) {
+        if parameter.type_handle.type_hash() != type_hash {
+            return Err(HostCallError::OutputType {
+                function: signature.name.to_owned(),
+                parameter: parameter.name.to_owned(),
+                expected: parameter.type_handle.type_name().to_owned(),
+                provided: type_hash,
+            });
+        }
+    }
+    Ok(())
+}
+
 pub struct HostFunctionCall<'a, I: DataStackPack, O: DataStackPack> {
     context: &'a mut Context,
     registry: &'a Registry,
@@ -162,7 +365,282 @@ pub struct HostFunctionCall<'a, I: DataStackPack, O: DataStackPack> {
 }
 
 impl<I: DataStackPack, O: DataStackPack> HostFunctionCall<'_, I, O> {
+    /// Panics if function leaves pending script error.
     pub fn run(self, inputs: I) -> O {
         self.handle.call(self.context, self.registry, inputs, false)
     }
+
+    pub fn try_run(self, inputs: I) -> Result<O, HostCallError> {
+        try_run(&self.handle, self.context, self.registry, inputs)
+    }
+
+    /// Runs call with execution limits. When budget gets exceeded, context is
+    /// restored to state from before the call, finalizing values left by it.
+    /// Panics if function leaves pending script error, after same restore.
+    pub fn run_budgeted(self, inputs: I, budget: Budget) -> Result<O, BudgetExceeded> {
+        let Self {
+            context,
+            registry,
+            handle,
+            ..
+        } = self;
+        let token = context.store();
+        context.set_budget(Some(budget));
+        context.stack().push_pack(inputs);
+        let result = catch_unwind(AssertUnwindSafe(|| handle.invoke(context, registry)));
+        let exceeded = context.budget_exceeded();
+        context.set_budget(None);
+        if let Some(error) = exceeded {
+            // native code may panic on missing values of aborted scripts.
+            context.restore(token);
+            return Err(error);
+        }
+        if let Err(payload) = result {
+            resume_unwind(payload);
+        }
+        if let Some(error) = context.take_error() {
+            context.restore(token);
+            panic!(
+                "{}",
+                HostCallError::Script {
+                    function: handle.signature().name.to_owned(),
+                    error,
+                }
+            );
+        }
+        Ok(context.stack().pop_pack())
+    }
+}
+
+pub struct PreparedCall<'a, I: DataStackPack, O: DataStackPack> {
+    context: &'a mut Context,
+    registry: &'a Registry,
+    handle: FunctionHandle,
+    _phantom: PhantomData<(I, O)>,
+}
+
+impl<I: DataStackPack, O: DataStackPack> PreparedCall<'_, I, O> {
+    pub fn handle(&self) -> &FunctionHandle {
+        &self.handle
+    }
+
+    /// Panics if function leaves pending script error.
+    pub fn run(&mut self, inputs: I) -> O {
+        self.handle.call(self.context, self.registry, inputs, false)
+    }
+
+    pub fn try_run(&mut self, inputs: I) -> Result<O, HostCallError> {
+        try_run(&self.handle, self.context, self.registry, inputs)
+    }
+
+    pub fn run_each(&mut self, inputs: impl IntoIterator<Item = I>) -> Vec<O> {
+        inputs.into_iter().map(|inputs| self.run(inputs)).collect()
+    }
+}
+
+/// Calls functions from within running function bodies, for example native
+/// function calling back script closure it got. Callee runs on top of caller
+/// stack data, which stays intact - if callee leaves pending error, stack is
+/// restored to its position from before the call and error is returned, so
+/// caller decides whether to rethrow it.
+pub struct Reentry<'a> {
+    context: &'a mut Context,
+    registry: &'a Registry,
+}
+
+impl<'a> Reentry<'a> {
+    pub fn new(context: &'a mut Context, registry: &'a Registry) -> Self {
+        Self { context, registry }
+    }
+
+    pub fn context(&mut self) -> &mut Context {
+        self.context
+    }
+
+    pub fn registry(&self) -> &Registry {
+        self.registry
+    }
+
+    pub fn invoke<'q, O: DataStackPack, I: DataStackPack>(
+        &'q mut self,
+        query: FunctionQuery<'q>,
+        inputs: I,
+    ) -> Result<O, HostCallError> {
+        let handle = self.registry.find_function(query.clone()).ok_or_else(|| {
+            HostCallError::FunctionNotFound {
+                name: query.name.as_deref().unwrap_or_default().to_owned(),
+                module_name: query.module_name.as_deref().unwrap_or_default().to_owned(),
+                type_name: query
+                    .type_query
+                    .as_ref()
+                    .and_then(|type_query| type_query.name.as_deref())
+                    .map(|type_name| type_name.to_owned()),
+            }
+        })?;
+        self.invoke_handle(&handle, inputs)
+    }
+
+    pub fn invoke_handle<O: DataStackPack, I: DataStackPack>(
+        &mut self,
+        handle: &FunctionHandle,
+        inputs: I,
+    ) -> Result<O, HostCallError> {
+        verify_signature::<O, I>(handle.signature())?;
+        try_run(handle, self.context, self.registry, inputs)
+    }
+}
+
+fn try_run<O: DataStackPack, I: DataStackPack>(
+    handle: &FunctionHandle,
+    context: &mut Context,
+    registry: &Registry,
+    inputs: I,
+) -> Result<O, HostCallError> {
+    handle
+        .try_call(context, registry, inputs, false)
+        .map_err(|error| HostCallError::Script {
+            function: handle.signature().name.to_owned(),
+            error,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    fn add_registry() -> Registry {
+        let mut registry = Registry::default().with_basic_types();
+        let usize_handle = registry.find_type(TypeQuery::of::<usize>()).unwrap();
+        registry.add_function(Function::new(
+            FunctionSignature::new("add")
+                .with_module_name("intrinsics")
+                .with_input(FunctionParameter::new("a", usize_handle.clone()))
+                .with_input(FunctionParameter::new("b", usize_handle.clone()))
+                .with_output(FunctionParameter::new("result", usize_handle)),
+            FunctionBody::closure(|context, _| {
+                let a = context.stack().pop::<usize>().unwrap();
+                let b = context.stack().pop::<usize>().unwrap();
+                context.stack().push(a + b);
+            }),
+        ));
+        registry
+    }
+
+    #[test]
+    fn test_try_call_function() {
+        let mut host = Host::new(
+            Context::new(10240, 10240),
+            RegistryHandle::new(add_registry()),
+        );
+        let (result,) = host
+            .try_call_function::<(usize,), _>("add", "intrinsics", None)
+            .unwrap()
+            .run((40usize, 2usize));
+        assert_eq!(result, 42);
+        assert!(matches!(
+            host.try_call_function::<(f32,), (usize, usize)>("add", "intrinsics", None),
+            Err(HostCallError::OutputType { parameter, .. }) if parameter == "result"
+        ));
+        assert!(matches!(
+            host.try_call_function::<(usize,), (usize, u8)>("add", "intrinsics", None),
+            Err(HostCallError::InputType { parameter, .. }) if parameter == "b"
+        ));
+        assert!(matches!(
+            host.try_call_function::<(usize,), (usize,)>("add", "intrinsics", None),
+            Err(HostCallError::InputsCount {
+                expected: 2,
+                provided: 1,
+                ..
+            })
+        ));
+        assert!(matches!(
+            host.try_call_function::<(usize,), (usize, usize)>("sub", "intrinsics", None),
+            Err(HostCallError::FunctionNotFound { .. })
+        ));
+        assert_eq!(host.context().stack().position(), 0);
+    }
+
+    #[test]
+    fn test_prepared_call() {
+        let mut host = Host::new(
+            Context::new(10240, 10240),
+            RegistryHandle::new(add_registry()),
+        );
+        let expected = (0..1000usize)
+            .map(|index| {
+                host.call_function::<(usize,), _>("add", "intrinsics", None)
+                    .unwrap()
+                    .run((index, index * 2))
+            })
+            .collect::<Vec<_>>();
+        let mut prepared = host
+            .prepare::<(usize,), _>("add", "intrinsics", None)
+            .unwrap();
+        let results = (0..1000usize)
+            .map(|index| prepared.run((index, index * 2)))
+            .collect::<Vec<_>>();
+        assert_eq!(results, expected);
+        let results = prepared.run_each((0..1000usize).map(|index| (index, index * 2)));
+        assert_eq!(results, expected);
+        assert_eq!(results[999], (2997,));
+        assert!(host
+            .prepare::<(usize,), (usize, u8)>("add", "intrinsics", None)
+            .is_none());
+        assert_eq!(host.context().stack().position(), 0);
+    }
+
+    #[test]
+    fn test_uncaught_error() {
+        let mut registry = Registry::default().with_basic_types();
+        let usize_handle = registry.find_type(TypeQuery::of::<usize>()).unwrap();
+        registry.add_function(Function::new(
+            FunctionSignature::new("fail")
+                .with_module_name("intrinsics")
+                .with_input(FunctionParameter::new("value", usize_handle.clone()))
+                .with_output(FunctionParameter::new("result", usize_handle.clone())),
+            FunctionBody::closure(|context, _| {
+                let value = context.stack().pop::<usize>().unwrap();
+                context.throw(ScriptError::new(format!("{} failed", value)));
+            }),
+        ));
+        let fail = registry
+            .find_function(FunctionQuery {
+                name: Some("fail".into()),
+                ..Default::default()
+            })
+            .unwrap();
+        registry.add_function(Function::new(
+            FunctionSignature::new("outer")
+                .with_module_name("intrinsics")
+                .with_output(FunctionParameter::new("result", usize_handle)),
+            FunctionBody::closure(move |context, registry| {
+                match fail.try_call::<(usize,), _>(context, registry, (42usize,), true) {
+                    Ok((result,)) => {
+                        context.stack().push(result);
+                    }
+                    Err(error) => context.throw(error),
+                }
+            }),
+        ));
+        let mut host = Host::new(Context::new(10240, 10240), RegistryHandle::new(registry));
+        host.context().stack().push(7u8);
+        let error = host
+            .try_call_function::<(usize,), _>("fail", "intrinsics", None)
+            .unwrap()
+            .try_run((1usize,))
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Function `fail` raised error: 1 failed");
+        let error = host
+            .prepare::<(usize,), ()>("outer", "intrinsics", None)
+            .unwrap()
+            .try_run(())
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Function `outer` raised error: 42 failed"
+        );
+        assert!(!host.context().has_error());
+        assert_eq!(host.context().stack().pop::<u8>(), Some(7));
+        assert_eq!(host.context().stack().position(), 0);
+    }
 }
@@ -1,14 +1,21 @@
+pub mod budget;
 pub mod context;
+pub mod debug;
+pub mod event;
 pub mod function;
+pub mod future;
 pub mod host;
 pub mod meta;
+pub mod metrics;
 pub mod object;
+pub mod random;
 pub mod registry;
 pub mod script;
 pub mod transformer;
 pub mod types;
 pub mod utils;
 
+pub use intuicio_data::type_hash::TypeHash as __internal__TypeHash__;
 pub use memoffset::offset_of as __internal__offset_of__;
 
 /// Assumes `repr(u8)` enums only.
@@ -63,10 +70,16 @@ macro_rules! __internal__offset_of_enum__ {
 
 pub mod prelude {
     pub use crate::{
+        budget::*,
         context::*,
+        debug::*,
+        event::*,
         function::*,
+        future::*,
         host::*,
+        metrics::*,
         object::*,
+        random::{Random, RANDOM_DEFAULT_SEED},
         registry::*,
         script::*,
         transformer::*,
@@ -74,8 +87,8 @@ pub mod prelude {
         IntuicioEnum, IntuicioStruct, IntuicioVersion, Visibility,
     };
     pub use crate::{
-        define_function, define_native_enum, define_native_struct, define_runtime_enum,
-        define_runtime_struct, function_signature,
+        define_async_function, define_function, define_native_enum, define_native_struct,
+        define_runtime_enum, define_runtime_struct, function_signature,
     };
 }
 
@@ -186,6 +186,27 @@ impl Meta {
             _ => None,
         }
     }
+
+    /// Makes `{doc: '...'}` map out of `#[doc = "..."]` attribute lines,
+    /// stripping single leading space that doc comments leave.
+    pub fn from_doc_lines(lines: &[&str]) -> Option<Self> {
+        if lines.is_empty() {
+            return None;
+        }
+        let doc = lines
+            .iter()
+            .map(|line| line.strip_prefix(' ').unwrap_or(line))
+            .collect::<Vec<_>>()
+            .join("\n");
+        Some(Self::Map(HashMap::from([(
+            "doc".to_owned(),
+            Self::Value(MetaValue::String(doc)),
+        )])))
+    }
+
+    pub fn doc(&self) -> Option<&str> {
+        self.as_map()?.get("doc")?.as_value()?.as_str()
+    }
 }
 
 impl std::fmt::Display for Meta {
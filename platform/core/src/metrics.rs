@@ -0,0 +1,76 @@
+use crate::function::FunctionId;
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+pub type MetricsSinkHandle = Arc<dyn MetricsSink>;
+
+/// Observer of function invocations, installed into registry.
+///
+/// Gets called after every `Function::invoke` - both script calls and direct
+/// native invocations - with time it took, including nested calls. Restarts
+/// of self-recursive tail calls are recorded as calls too, with time of
+/// given restart up to the next one.
+pub trait MetricsSink: Send + Sync {
+    fn record(&self, id: FunctionId, duration: Duration);
+}
+
+impl<F> MetricsSink for F
+where
+    F: Fn(FunctionId, Duration) + Send + Sync,
+{
+    fn record(&self, id: FunctionId, duration: Duration) {
+        self(id, duration)
+    }
+}
+
+impl std::fmt::Debug for dyn MetricsSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<MetricsSink>")
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FunctionStats {
+    pub calls: usize,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+/// Sink aggregating number of calls and durations per function.
+#[derive(Debug, Default)]
+pub struct FunctionMetrics {
+    stats: RwLock<HashMap<FunctionId, FunctionStats>>,
+}
+
+impl FunctionMetrics {
+    pub fn stats(&self, id: FunctionId) -> Option<FunctionStats> {
+        self.stats.read().ok()?.get(&id).copied()
+    }
+
+    pub fn snapshot(&self) -> HashMap<FunctionId, FunctionStats> {
+        self.stats
+            .read()
+            .map(|stats| stats.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut stats) = self.stats.write() {
+            stats.clear();
+        }
+    }
+}
+
+impl MetricsSink for FunctionMetrics {
+    fn record(&self, id: FunctionId, duration: Duration) {
+        if let Ok(mut stats) = self.stats.write() {
+            let stats = stats.entry(id).or_default();
+            stats.calls += 1;
+            stats.total += duration;
+            stats.max = stats.max.max(duration);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use crate::{
+    function::{Function, FunctionBody},
+    function_signature,
+    registry::Registry,
+};
+
+/// Seed of random generator of every new context.
+pub const RANDOM_DEFAULT_SEED: u64 = 0x853c_49e6_748f_ea9b;
+
+/// Deterministic pseudo random generator (SplitMix64). Its state lives in
+/// context, so runs that start with same seed and ask for same values in same
+/// order get identical results on every platform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Random {
+    state: u64,
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        Self::new(RANDOM_DEFAULT_SEED)
+    }
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn seed(&mut self, seed: u64) {
+        self.state = seed;
+    }
+
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut result = self.state;
+        result = (result ^ (result >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        result = (result ^ (result >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        result ^ (result >> 31)
+    }
+
+    /// Gives integer in `min..max` range, or `min` if range is empty.
+    pub fn integer(&mut self, min: i64, max: i64) -> i64 {
+        if max <= min {
+            return min;
+        }
+        let range = max.wrapping_sub(min) as u64;
+        let offset = ((self.next_u64() as u128 * range as u128) >> 64) as u64;
+        min.wrapping_add(offset as i64)
+    }
+
+    /// Gives real number in `0.0..1.0` range.
+    pub fn real(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Installs `random_seed(seed: u64)`, `random_integer(min: i64, max: i64)`
+/// and `random_real()` functions in `random` module, all using generator of
+/// context they are called in.
+pub fn install(registry: &mut Registry) {
+    registry.add_function(Function::new(
+        function_signature!(registry => mod random fn random_seed(seed: u64) -> ()),
+        FunctionBody::closure(|context, _| {
+            let seed = context.stack().pop::<u64>().unwrap();
+            context.random_mut().seed(seed);
+        }),
+    ));
+    registry.add_function(Function::new(
+        function_signature!(registry => mod random fn random_integer(min: i64, max: i64) -> (result: i64)),
+        FunctionBody::closure(|context, _| {
+            let min = context.stack().pop::<i64>().unwrap();
+            let max = context.stack().pop::<i64>().unwrap();
+            let result = context.random_mut().integer(min, max);
+            context.stack().push(result);
+        }),
+    ));
+    registry.add_function(Function::new(
+        function_signature!(registry => mod random fn random_real() -> (result: f64)),
+        FunctionBody::closure(|context, _| {
+            let result = context.random_mut().real();
+            context.stack().push(result);
+        }),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    #[test]
+    fn test_random() {
+        let mut registry = Registry::default().with_basic_types();
+        crate::random::install(&mut registry);
+        let mut host = Host::new(Context::new(1024, 1024), registry.into());
+        let sequence = |host: &mut Host, seed: u64| {
+            host.call_function::<(), _>("random_seed", "random", None)
+                .unwrap()
+                .run((seed,));
+            (0..100)
+                .map(|_| {
+                    let (integer,) = host
+                        .call_function::<(i64,), _>("random_integer", "random", None)
+                        .unwrap()
+                        .run((-10i64, 10i64));
+                    let (real,) = host
+                        .call_function::<(f64,), _>("random_real", "random", None)
+                        .unwrap()
+                        .run(());
+                    (integer, real)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let first = sequence(&mut host, 42);
+        assert!(first
+            .iter()
+            .all(|(integer, real)| (-10..10).contains(integer) && (0.0..1.0).contains(real)));
+        assert!(first.iter().any(|(integer, _)| *integer != first[0].0));
+        assert_eq!(sequence(&mut host, 42), first);
+        assert_ne!(sequence(&mut host, 7), first);
+        assert_eq!(host.context().stack().position(), 0);
+
+        // same seed gives same values regardless of host.
+        let mut random = Random::new(0);
+        assert_eq!(random.next_u64(), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(random.integer(5, 5), 5);
+        assert_eq!(Context::new(0, 0).random(), &Random::default());
+    }
+}
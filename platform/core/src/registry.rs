@@ -1,14 +1,83 @@
 use crate::{
-    function::{Function, FunctionHandle, FunctionQuery},
+    function::{Function, FunctionHandle, FunctionId, FunctionParameter, FunctionQuery},
+    metrics::{MetricsSink, MetricsSinkHandle},
+    script::{CustomExpressionHandle, CustomExpressionHandler, ScriptError},
     types::{struct_type::NativeStructBuilder, Type, TypeHandle, TypeQuery},
+    Visibility,
 };
+use intuicio_data::{Finalize, Initialize};
 use std::{
-    collections::BTreeMap,
-    sync::{Arc, RwLock},
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
 };
 
 pub type RegistryHandle = Arc<Registry>;
 
+static FUNCTIONS_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+fn next_functions_generation() -> u64 {
+    FUNCTIONS_GENERATION.fetch_add(1, Ordering::Relaxed)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FunctionResolveError {
+    NotFound,
+    NotVisible {
+        name: String,
+        module_name: Option<String>,
+        visibility: Visibility,
+        caller_module: String,
+    },
+}
+
+impl std::fmt::Display for FunctionResolveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound => write!(f, "Function not found"),
+            Self::NotVisible {
+                name,
+                module_name,
+                visibility,
+                caller_module,
+            } => write!(
+                f,
+                "Function `{}::{}` with {:?} visibility cannot be called from module `{}`",
+                module_name.as_deref().unwrap_or_default(),
+                name,
+                visibility,
+                caller_module
+            ),
+        }
+    }
+}
+
+impl Error for FunctionResolveError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FunctionReplaceError {
+    NotFound,
+    IncompatibleSignature { expected: String, provided: String },
+}
+
+impl std::fmt::Display for FunctionReplaceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound => write!(f, "Function not found"),
+            Self::IncompatibleSignature { expected, provided } => write!(
+                f,
+                "Function signature `{}` does not match replaced `{}`",
+                provided, expected
+            ),
+        }
+    }
+}
+
+impl Error for FunctionReplaceError {}
+
 #[derive(Debug, Default)]
 pub struct Registry {
     functions: Vec<FunctionHandle>,
@@ -17,6 +86,13 @@ pub struct Registry {
     pub use_indexing_threshold: usize,
     functions_index: RwLock<BTreeMap<u64, FunctionHandle>>,
     types_index: RwLock<BTreeMap<u64, TypeHandle>>,
+    parent: Option<RegistryHandle>,
+    metrics_sink: Option<MetricsSinkHandle>,
+    /// Sink of this registry or of closest parent, resolved upfront since
+    /// parents cannot change once shared.
+    resolved_metrics_sink: Option<MetricsSinkHandle>,
+    custom_expressions: HashMap<String, CustomExpressionHandle>,
+    functions_generation: u64,
 }
 
 impl Clone for Registry {
@@ -40,6 +116,11 @@ impl Clone for Registry {
                     .map(|items| items.clone())
                     .unwrap_or_default(),
             ),
+            parent: self.parent.clone(),
+            metrics_sink: self.metrics_sink.clone(),
+            resolved_metrics_sink: self.resolved_metrics_sink.clone(),
+            custom_expressions: self.custom_expressions.clone(),
+            functions_generation: self.functions_generation,
         }
     }
 }
@@ -48,7 +129,13 @@ impl Registry {
     pub fn with_basic_types(self) -> Self {
         self.with_type(NativeStructBuilder::new::<()>().build())
             .with_type(NativeStructBuilder::new::<bool>().build())
-            .with_type(NativeStructBuilder::new::<i8>().build())
+            .with_integer_types()
+            .with_float_types()
+            .with_text_types()
+    }
+
+    pub fn with_integer_types(self) -> Self {
+        self.with_type(NativeStructBuilder::new::<i8>().build())
             .with_type(NativeStructBuilder::new::<i16>().build())
             .with_type(NativeStructBuilder::new::<i32>().build())
             .with_type(NativeStructBuilder::new::<i64>().build())
@@ -60,12 +147,102 @@ impl Registry {
             .with_type(NativeStructBuilder::new::<u64>().build())
             .with_type(NativeStructBuilder::new::<u128>().build())
             .with_type(NativeStructBuilder::new::<usize>().build())
-            .with_type(NativeStructBuilder::new::<f32>().build())
+    }
+
+    pub fn with_float_types(self) -> Self {
+        self.with_type(NativeStructBuilder::new::<f32>().build())
             .with_type(NativeStructBuilder::new::<f64>().build())
-            .with_type(NativeStructBuilder::new::<char>().build())
+    }
+
+    pub fn with_text_types(self) -> Self {
+        self.with_type(NativeStructBuilder::new::<char>().build())
             .with_type(NativeStructBuilder::new_named::<String>("String").build())
     }
 
+    /// Needed by scripts that catch errors with `TryScope` or build errors
+    /// to `Throw`, since both move `ScriptError` through stack.
+    pub fn with_error_types(self) -> Self {
+        self.with_type(NativeStructBuilder::new::<ScriptError>().build())
+    }
+
+    /// Makes this registry an overlay of `parent` - lookups fall through to
+    /// parent when nothing matches locally, while adding and removing only
+    /// affects this registry.
+    pub fn with_parent(mut self, parent: RegistryHandle) -> Self {
+        self.parent = Some(parent);
+        self.functions_generation = next_functions_generation();
+        self.resolve_metrics_sink();
+        self
+    }
+
+    /// Stamp of functions set, unique across registries and changed whenever
+    /// functions get added or removed, so results of function resolution can
+    /// be cached until it changes. Replacing function bodies keeps it, since
+    /// resolved handles stay valid.
+    pub fn functions_generation(&self) -> u64 {
+        self.functions_generation
+    }
+
+    pub fn parent(&self) -> Option<&RegistryHandle> {
+        self.parent.as_ref()
+    }
+
+    /// Iterates over this registry and all its parents, from local one.
+    fn layers(&self) -> impl Iterator<Item = &Self> {
+        std::iter::successors(Some(self), |registry| registry.parent.as_deref())
+    }
+
+    pub fn with_metrics_sink(mut self, sink: impl MetricsSink + 'static) -> Self {
+        self.set_metrics_sink(Some(Arc::new(sink)));
+        self
+    }
+
+    pub fn set_metrics_sink(&mut self, sink: Option<MetricsSinkHandle>) {
+        self.metrics_sink = sink;
+        self.resolve_metrics_sink();
+    }
+
+    /// Returns sink of this registry or of closest parent that has one.
+    pub fn metrics_sink(&self) -> Option<&MetricsSinkHandle> {
+        self.resolved_metrics_sink.as_ref()
+    }
+
+    fn resolve_metrics_sink(&mut self) {
+        self.resolved_metrics_sink = self.metrics_sink.clone().or_else(|| {
+            self.parent
+                .as_ref()
+                .and_then(|parent| parent.resolved_metrics_sink.clone())
+        });
+    }
+
+    pub fn with_custom_expression(
+        mut self,
+        id: impl ToString,
+        handler: impl CustomExpressionHandler + 'static,
+    ) -> Self {
+        self.add_custom_expression(id, handler);
+        self
+    }
+
+    pub fn add_custom_expression(
+        &mut self,
+        id: impl ToString,
+        handler: impl CustomExpressionHandler + 'static,
+    ) {
+        self.custom_expressions
+            .insert(id.to_string(), Arc::new(handler));
+    }
+
+    pub fn remove_custom_expression(&mut self, id: &str) -> Option<CustomExpressionHandle> {
+        self.custom_expressions.remove(id)
+    }
+
+    /// Returns handler of this registry or of closest parent that has one.
+    pub fn find_custom_expression(&self, id: &str) -> Option<&CustomExpressionHandle> {
+        self.layers()
+            .find_map(|registry| registry.custom_expressions.get(id))
+    }
+
     pub fn with_index_capacity(mut self, capacity: usize) -> Self {
         self.index_capacity = capacity;
         self
@@ -102,24 +279,25 @@ impl Registry {
 
     pub fn add_function_handle(&mut self, function_handle: FunctionHandle) {
         if !self
-            .functions
-            .iter()
+            .functions()
             .any(|handle| handle.signature() == function_handle.signature())
         {
             self.functions.push(function_handle);
+            self.functions_generation = next_functions_generation();
         }
     }
 
     pub fn add_function(&mut self, function: Function) -> FunctionHandle {
-        if let Some(handle) = self
-            .functions
-            .iter()
+        let found = self
+            .functions()
             .find(|handle| handle.signature() == function.signature())
-        {
-            handle.clone()
+            .cloned();
+        if let Some(handle) = found {
+            handle
         } else {
             let handle = FunctionHandle::new(function);
             self.functions.push(handle.clone());
+            self.functions_generation = next_functions_generation();
             handle
         }
     }
@@ -131,6 +309,7 @@ impl Registry {
             .position(|handle| handle.signature() == function_handle.signature())
         {
             self.functions.swap_remove(position);
+            self.functions_generation = next_functions_generation();
         }
     }
 
@@ -141,31 +320,57 @@ impl Registry {
             .position(|handle| query.is_valid(handle.signature()))
         {
             self.functions.swap_remove(position);
+            self.functions_generation = next_functions_generation();
         }
     }
 
+    /// Iterates over functions of this registry and then of its parents.
     pub fn functions(&self) -> impl Iterator<Item = &FunctionHandle> {
-        self.functions.iter()
+        self.layers().flat_map(|registry| registry.functions.iter())
+    }
+
+    /// Maps every function to queries of functions it calls. Queries can be
+    /// partial (for example name-only), so resolve them by validating against
+    /// function signatures, same as calls do. Native functions have no calls.
+    pub fn call_graph(&self) -> HashMap<FunctionId, Vec<FunctionQuery<'static>>> {
+        self.functions()
+            .map(|function| (function.id(), function.calls().to_vec()))
+            .collect()
     }
 
     pub fn find_functions<'a>(
         &'a self,
         query: FunctionQuery<'a>,
     ) -> impl Iterator<Item = FunctionHandle> + 'a {
+        self.functions()
+            .filter(move |handle| query.is_valid(handle.signature()))
+            .cloned()
+    }
+
+    pub fn find_function(&self, query: FunctionQuery) -> Option<FunctionHandle> {
+        match self.parent.as_deref() {
+            Some(parent) => self
+                .find_function_local(query.clone())
+                .or_else(|| parent.find_function(query)),
+            None => self.find_function_local(query),
+        }
+    }
+
+    fn find_local_function(&self, query: &FunctionQuery) -> Option<FunctionHandle> {
         self.functions
             .iter()
-            .filter(move |handle| query.is_valid(handle.signature()))
+            .find(|handle| query.is_valid(handle.signature()))
             .cloned()
     }
 
-    pub fn find_function<'a>(&'a self, query: FunctionQuery<'a>) -> Option<FunctionHandle> {
+    fn find_function_local(&self, query: FunctionQuery) -> Option<FunctionHandle> {
         if self.index_capacity == 0 || self.functions.len() < self.use_indexing_threshold {
-            self.find_functions(query).next()
+            self.find_local_function(&query)
         } else if let Ok(mut index) = self.functions_index.try_write() {
             let hash = query.as_hash();
             if let Some(found) = index.get(&hash) {
                 Some(found.clone())
-            } else if let Some(found) = self.find_functions(query).next() {
+            } else if let Some(found) = self.find_local_function(&query) {
                 for _ in 0..(index.len().saturating_sub(self.index_capacity)) {
                     if let Some(hash) = index.keys().next().copied() {
                         index.remove(&hash);
@@ -177,14 +382,90 @@ impl Registry {
                 None
             }
         } else {
-            self.find_functions(query).next()
+            self.find_local_function(&query)
         }
     }
 
+    /// Swaps body of found function in place, so existing handles (also those
+    /// already resolved by callers) run `function` body from now on. Inputs and
+    /// outputs of `function` have to match types of replaced function, other
+    /// parts of its signature are ignored.
+    pub fn replace_function<'a>(
+        &'a self,
+        query: FunctionQuery<'a>,
+        function: Function,
+    ) -> Result<(), FunctionReplaceError> {
+        let handle = self
+            .find_function(query)
+            .ok_or(FunctionReplaceError::NotFound)?;
+        let types_match = |a: &[FunctionParameter], b: &[FunctionParameter]| {
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b)
+                    .all(|(a, b)| a.type_handle.type_hash() == b.type_handle.type_hash())
+        };
+        let (expected, provided) = (handle.signature(), function.signature());
+        if !types_match(&expected.inputs, &provided.inputs)
+            || !types_match(&expected.outputs, &provided.outputs)
+        {
+            return Err(FunctionReplaceError::IncompatibleSignature {
+                expected: expected.to_string(),
+                provided: provided.to_string(),
+            });
+        }
+        handle.replace_body(function.into_body());
+        Ok(())
+    }
+
+    /// Finds function callable from `caller_module`. Non-public functions are
+    /// visible only from their own module. Calls without module context
+    /// (host calls) bypass visibility check.
+    pub fn resolve_function(
+        &self,
+        query: &FunctionQuery,
+        caller_module: Option<&str>,
+    ) -> Result<FunctionHandle, FunctionResolveError> {
+        let mut result = Err(FunctionResolveError::NotFound);
+        for handle in self.functions() {
+            let signature = handle.signature();
+            if !query.is_valid(signature) {
+                continue;
+            }
+            match caller_module {
+                Some(caller_module)
+                    if !signature.visibility.is_public()
+                        && signature.module_name.as_deref() != Some(caller_module) =>
+                {
+                    if result.is_err() {
+                        result = Err(FunctionResolveError::NotVisible {
+                            name: signature.name.clone(),
+                            module_name: signature.module_name.clone(),
+                            visibility: signature.visibility,
+                            caller_module: caller_module.to_owned(),
+                        });
+                    }
+                }
+                _ => return Ok(handle.clone()),
+            }
+        }
+        result
+    }
+
+    /// Approximate lookup for tooling (completion, "did you mean" hints).
+    /// Names starting with `prefix` come first, then names within small edit
+    /// distance. Does not affect exact query path.
+    pub fn suggest_functions(&self, prefix: &str, limit: usize) -> Vec<FunctionHandle> {
+        suggest(
+            self.functions(),
+            |handle| handle.signature().name.as_str(),
+            prefix,
+            limit,
+        )
+    }
+
     pub fn add_type_handle(&mut self, type_handle: TypeHandle) {
         if !self
-            .types
-            .iter()
+            .types()
             .any(|handle| handle.as_ref() == type_handle.as_ref())
         {
             self.types.push(type_handle);
@@ -193,8 +474,12 @@ impl Registry {
 
     pub fn add_type(&mut self, type_: impl Into<Type>) -> TypeHandle {
         let type_ = type_.into();
-        if let Some(handle) = self.types.iter().find(|handle| handle.as_ref() == &type_) {
-            handle.clone()
+        let found = self
+            .types()
+            .find(|handle| handle.as_ref() == &type_)
+            .cloned();
+        if let Some(handle) = found {
+            handle
         } else {
             let handle = TypeHandle::new(type_);
             self.types.push(handle.clone());
@@ -202,6 +487,15 @@ impl Registry {
         }
     }
 
+    /// Registers instantiation `T` of generic native wrapper, named from its
+    /// type parameter `P`. See [`NativeStructBuilder::new_generic`].
+    pub fn register_native_generic<T: Initialize + Finalize + 'static, P: 'static>(
+        &mut self,
+        name: impl FnOnce(&str) -> String,
+    ) -> TypeHandle {
+        self.add_type(NativeStructBuilder::new_generic::<T, P>(name).build())
+    }
+
     pub fn remove_type(&mut self, type_handle: TypeHandle) {
         if let Some(position) = self.types.iter().position(|handle| handle == &type_handle) {
             self.types.swap_remove(position);
@@ -214,25 +508,49 @@ impl Registry {
         }
     }
 
+    /// Iterates over types of this registry and then of its parents.
     pub fn types(&self) -> impl Iterator<Item = &TypeHandle> {
-        self.types.iter()
+        self.layers().flat_map(|registry| registry.types.iter())
     }
 
     pub fn find_types<'a>(&'a self, query: TypeQuery<'a>) -> impl Iterator<Item = TypeHandle> + 'a {
+        self.types()
+            .filter(move |handle| query.is_valid(handle))
+            .cloned()
+    }
+
+    /// Same as [`Registry::suggest_functions`], but for type names.
+    pub fn suggest_types(&self, prefix: &str, limit: usize) -> Vec<TypeHandle> {
+        suggest(self.types(), |handle| handle.name(), prefix, limit)
+    }
+
+    pub fn find_type<'a>(&'a self, query: TypeQuery<'a>) -> Option<TypeHandle> {
+        match self.parent.as_deref() {
+            Some(parent) => self
+                .find_type_local(query.clone())
+                .or_else(|| parent.find_type(query)),
+            None => self.find_type_local(query),
+        }
+    }
+
+    fn find_local_types<'a>(
+        &'a self,
+        query: TypeQuery<'a>,
+    ) -> impl Iterator<Item = TypeHandle> + 'a {
         self.types
             .iter()
             .filter(move |handle| query.is_valid(handle))
             .cloned()
     }
 
-    pub fn find_type<'a>(&'a self, query: TypeQuery<'a>) -> Option<TypeHandle> {
+    fn find_type_local<'a>(&'a self, query: TypeQuery<'a>) -> Option<TypeHandle> {
         if self.index_capacity == 0 || self.types.len() < self.use_indexing_threshold {
-            self.find_types(query).next()
+            self.find_local_types(query).next()
         } else if let Ok(mut index) = self.types_index.try_write() {
             let hash = query.as_hash();
             if let Some(found) = index.get(&hash) {
                 Some(found.clone())
-            } else if let Some(found) = self.find_types(query).next() {
+            } else if let Some(found) = self.find_local_types(query).next() {
                 for _ in 0..(index.len().saturating_sub(self.index_capacity)) {
                     if let Some(hash) = index.keys().next().copied() {
                         index.remove(&hash);
@@ -244,9 +562,56 @@ impl Registry {
                 None
             }
         } else {
-            self.find_types(query).next()
+            self.find_local_types(query).next()
+        }
+    }
+}
+
+fn suggest<'a, T: Clone + 'a>(
+    items: impl Iterator<Item = &'a T>,
+    name: impl Fn(&T) -> &str,
+    prefix: &str,
+    limit: usize,
+) -> Vec<T> {
+    let max_distance = (prefix.chars().count() / 3).max(1);
+    let mut result = items
+        .filter_map(|item| {
+            let item_name = name(item);
+            let rank = if item_name.starts_with(prefix) {
+                (0, item_name.len() - prefix.len())
+            } else {
+                let distance = edit_distance(prefix, item_name);
+                if distance > max_distance {
+                    return None;
+                }
+                (1, distance)
+            };
+            Some((rank, item_name.to_owned(), item.clone()))
+        })
+        .collect::<Vec<_>>();
+    result.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+    result
+        .into_iter()
+        .take(limit)
+        .map(|(_, _, item)| item)
+        .collect()
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            current[j + 1] = (previous[j] + cost)
+                .min(previous[j + 1] + 1)
+                .min(current[j] + 1);
         }
+        std::mem::swap(&mut previous, &mut current);
     }
+    previous[b.len()]
 }
 
 #[cfg(test)]
@@ -259,4 +624,227 @@ mod tests {
 
         is_async::<Registry>();
     }
+
+    #[test]
+    fn test_metrics_sink() {
+        let registry = Registry::default();
+        assert!(registry.metrics_sink().is_none());
+        let base = Arc::new(Registry::default().with_metrics_sink(|_, _| {}));
+        let overlay = Registry::default().with_parent(base.clone());
+        assert!(Arc::ptr_eq(
+            overlay.metrics_sink().unwrap(),
+            base.metrics_sink().unwrap()
+        ));
+        let mut overlay = overlay.with_metrics_sink(|_, _| {});
+        assert!(!Arc::ptr_eq(
+            overlay.metrics_sink().unwrap(),
+            base.metrics_sink().unwrap()
+        ));
+        overlay.set_metrics_sink(None);
+        assert!(Arc::ptr_eq(
+            overlay.metrics_sink().unwrap(),
+            base.metrics_sink().unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_granular_types() {
+        let registry = Registry::default().with_integer_types();
+        assert!(registry.find_type(TypeQuery::of::<i32>()).is_some());
+        assert!(registry.find_type(TypeQuery::of::<usize>()).is_some());
+        assert!(registry.find_type(TypeQuery::of::<f32>()).is_none());
+        assert!(registry.find_type(TypeQuery::of::<f64>()).is_none());
+        assert!(registry.find_type(TypeQuery::of::<String>()).is_none());
+    }
+
+    #[test]
+    fn test_resolve_function_visibility() {
+        use crate::function::{FunctionBody, FunctionSignature};
+
+        let mut registry = Registry::default();
+        registry.add_function(Function::new(
+            FunctionSignature::new("secret")
+                .with_module_name("a")
+                .with_visibility(Visibility::Private),
+            FunctionBody::closure(|_, _| {}),
+        ));
+        let query = FunctionQuery {
+            name: Some("secret".into()),
+            ..Default::default()
+        };
+        assert!(registry.resolve_function(&query, Some("a")).is_ok());
+        assert!(registry.resolve_function(&query, None).is_ok());
+        assert_eq!(
+            registry.resolve_function(&query, Some("b")).err(),
+            Some(FunctionResolveError::NotVisible {
+                name: "secret".to_owned(),
+                module_name: Some("a".to_owned()),
+                visibility: Visibility::Private,
+                caller_module: "b".to_owned(),
+            })
+        );
+        let query = FunctionQuery {
+            name: Some("missing".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            registry.resolve_function(&query, Some("a")).err(),
+            Some(FunctionResolveError::NotFound)
+        );
+    }
+
+    #[test]
+    fn test_suggest() {
+        use crate::function::{FunctionBody, FunctionSignature};
+
+        let mut registry = Registry::default().with_basic_types();
+        for name in ["add", "add_assign", "sub", "mul"] {
+            registry.add_function(Function::new(
+                FunctionSignature::new(name),
+                FunctionBody::closure(|_, _| {}),
+            ));
+        }
+        let names = |prefix, limit| {
+            registry
+                .suggest_functions(prefix, limit)
+                .into_iter()
+                .map(|handle| handle.signature().name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("ad", 10), vec!["add", "add_assign"]);
+        assert_eq!(names("ad", 1), vec!["add"]);
+        assert_eq!(names("sup", 10), vec!["sub"]);
+        assert!(names("xyz", 10).is_empty());
+        assert!(registry
+            .suggest_types("i3", 10)
+            .iter()
+            .any(|handle| handle.name() == "i32"));
+    }
+
+    #[test]
+    #[allow(unused_variables)]
+    fn test_replace_function() {
+        use crate::{
+            context::Context,
+            define_function,
+            function::{FunctionBody, FunctionSignature},
+        };
+
+        let mut registry = Registry::default().with_basic_types();
+        let handle = registry.add_function(define_function! {
+            registry => mod math fn add(a: i32, b: i32) -> (result: i32) {
+                (a + b,)
+            }
+        });
+        let query = || FunctionQuery {
+            name: Some("add".into()),
+            module_name: Some("math".into()),
+            ..Default::default()
+        };
+        let mut context = Context::new(10240, 10240);
+        let (result,) = handle.call::<(i32,), _>(&mut context, &registry, (3, 2), true);
+        assert_eq!(result, 5);
+
+        registry
+            .replace_function(
+                query(),
+                define_function! {
+                    registry => mod math fn add(a: i32, b: i32) -> (result: i32) {
+                        (a * b,)
+                    }
+                },
+            )
+            .unwrap();
+        let (result,) = handle.call::<(i32,), _>(&mut context, &registry, (3, 2), true);
+        assert_eq!(result, 6);
+        let found = registry.find_function(query()).unwrap();
+        assert!(Arc::ptr_eq(&handle, &found));
+        let (result,) = found.call::<(i32,), _>(&mut context, &registry, (4, 2), true);
+        assert_eq!(result, 8);
+
+        assert!(matches!(
+            registry.replace_function(
+                query(),
+                define_function! {
+                    registry => mod math fn add(a: f32, b: f32) -> (result: f32) {
+                        (a * b,)
+                    }
+                },
+            ),
+            Err(FunctionReplaceError::IncompatibleSignature { .. })
+        ));
+        assert_eq!(
+            registry.replace_function(
+                FunctionQuery {
+                    name: Some("mul".into()),
+                    ..Default::default()
+                },
+                Function::new(
+                    FunctionSignature::new("mul"),
+                    FunctionBody::pointer(|_, _| {})
+                ),
+            ),
+            Err(FunctionReplaceError::NotFound)
+        );
+        let (result,) = handle.call::<(i32,), _>(&mut context, &registry, (3, 2), true);
+        assert_eq!(result, 6);
+    }
+
+    #[test]
+    fn test_layered_registry() {
+        use crate::function::{FunctionBody, FunctionSignature};
+        use intuicio_data::type_hash::TypeHash;
+
+        #[derive(Default)]
+        struct PhysicsThing;
+
+        #[derive(Default)]
+        struct RenderThing;
+
+        let base = RegistryHandle::new(Registry::default().with_basic_types().with_function(
+            Function::new(
+                FunctionSignature::new("add").with_module_name("math"),
+                FunctionBody::closure(|_, _| {}),
+            ),
+        ));
+        let mut physics = Registry::default().with_parent(base.clone());
+        let mut render = Registry::default().with_parent(base.clone());
+        physics.add_type(NativeStructBuilder::new_named::<PhysicsThing>("Thing").build());
+        render.add_type(NativeStructBuilder::new_named::<RenderThing>("Thing").build());
+
+        let thing = TypeQuery {
+            name: Some("Thing".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            physics.find_type(thing.clone()).unwrap().type_hash(),
+            TypeHash::of::<PhysicsThing>()
+        );
+        assert_eq!(
+            render.find_type(thing.clone()).unwrap().type_hash(),
+            TypeHash::of::<RenderThing>()
+        );
+        assert!(base.find_type(thing).is_none());
+
+        fn add<'a>() -> FunctionQuery<'a> {
+            FunctionQuery {
+                name: Some("add".into()),
+                module_name: Some("math".into()),
+                ..Default::default()
+            }
+        }
+        for overlay in [&physics, &render] {
+            assert!(overlay.find_type(TypeQuery::of::<f32>()).is_some());
+            assert!(overlay.find_function(add()).is_some());
+        }
+
+        // base entries are shared, not duplicated nor removed by overlays.
+        let handle = physics.add_type(NativeStructBuilder::new::<f32>().build());
+        assert_eq!(handle, base.find_type(TypeQuery::of::<f32>()).unwrap());
+        assert_eq!(physics.types.len(), 1);
+        physics.remove_types(TypeQuery::of::<f32>());
+        physics.remove_functions(add());
+        assert!(physics.find_type(TypeQuery::of::<f32>()).is_some());
+        assert!(physics.find_function(add()).is_some());
+    }
 }
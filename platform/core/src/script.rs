@@ -10,9 +10,11 @@ use crate::{
     },
     Visibility,
 };
+use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
     error::Error,
+    panic::AssertUnwindSafe,
     path::{Path, PathBuf},
     sync::Arc,
 };
@@ -20,6 +22,72 @@ use std::{
 pub type ScriptHandle<'a, SE> = Arc<Script<'a, SE>>;
 pub type Script<'a, SE> = Vec<ScriptOperation<'a, SE>>;
 
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ScriptError {
+    pub message: String,
+    /// Source location of operation that raised this error, if known.
+    pub span: Option<ScriptSpan>,
+}
+
+impl ScriptError {
+    pub fn new(message: impl ToString) -> Self {
+        Self {
+            message: message.to_string(),
+            span: None,
+        }
+    }
+
+    pub fn with_span(mut self, span: Option<ScriptSpan>) -> Self {
+        self.span = span;
+        self
+    }
+}
+
+impl std::fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(span) = self.span.as_ref() {
+            write!(f, " at {}", span)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ScriptError {}
+
+/// Location in source that script operations were compiled from.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ScriptSpan {
+    #[serde(default)]
+    pub file: Option<Arc<str>>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl ScriptSpan {
+    pub fn new(line: usize, column: usize) -> Self {
+        Self {
+            file: None,
+            line,
+            column,
+        }
+    }
+
+    pub fn with_file(mut self, file: impl Into<Arc<str>>) -> Self {
+        self.file = Some(file.into());
+        self
+    }
+}
+
+impl std::fmt::Display for ScriptSpan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(file) = self.file.as_ref() {
+            write!(f, "{}:", file)?;
+        }
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
 pub trait ScriptExpression: Send + Sync {
     fn evaluate(&self, context: &mut Context, registry: &Registry);
 }
@@ -28,6 +96,114 @@ impl ScriptExpression for () {
     fn evaluate(&self, _: &mut Context, _: &Registry) {}
 }
 
+pub type CustomExpressionHandle = Arc<dyn CustomExpressionHandler>;
+
+/// Host-provided expression logic, registered in registry under an id and
+/// invoked by `CustomScriptExpression` with its payload.
+pub trait CustomExpressionHandler: Send + Sync {
+    fn evaluate(&self, payload: &[u8], context: &mut Context, registry: &Registry);
+}
+
+impl<F> CustomExpressionHandler for F
+where
+    F: Fn(&[u8], &mut Context, &Registry) + Send + Sync,
+{
+    fn evaluate(&self, payload: &[u8], context: &mut Context, registry: &Registry) {
+        self(payload, context, registry)
+    }
+}
+
+impl std::fmt::Debug for dyn CustomExpressionHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<CustomExpressionHandler>")
+    }
+}
+
+/// Expression that dispatches to handler registered under `id` at evaluation.
+///
+/// `payload` is opaque to frontends and VM - it is passed to handler as is,
+/// so encoding of it (raw bytes, serialized value, etc.) is defined by handler.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CustomScriptExpression {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub payload: Vec<u8>,
+}
+
+impl CustomScriptExpression {
+    pub fn new(id: impl ToString) -> Self {
+        Self {
+            id: id.to_string(),
+            payload: Default::default(),
+        }
+    }
+
+    pub fn with_payload(mut self, payload: impl Into<Vec<u8>>) -> Self {
+        self.payload = payload.into();
+        self
+    }
+}
+
+impl ScriptExpression for CustomScriptExpression {
+    fn evaluate(&self, context: &mut Context, registry: &Registry) {
+        registry
+            .find_custom_expression(&self.id)
+            .unwrap_or_else(|| panic!("Could not find custom expression: {}", self.id))
+            .evaluate(&self.payload, context, registry);
+    }
+}
+
+pub trait ScriptConstantExpression: ScriptExpression + Sized {
+    fn is_constant(&self) -> bool;
+
+    fn constant_from_stack(context: &mut Context, registry: &Registry) -> Option<Self>;
+}
+
+/// Pops value of basic type from stack of context into literal enum, which
+/// has to have variants named after basic types: `Unit`, `Bool`, `I8` ...
+/// `Usize`, `F32`, `F64`, `Char`, `String` and `Bytes` (for `Vec<u8>`).
+#[macro_export]
+macro_rules! literal_from_stack {
+    ($literal:ident, $context:expr) => {
+        $crate::literal_from_stack!(@pop $literal, $context, [
+            bool => Bool,
+            i8 => I8,
+            i16 => I16,
+            i32 => I32,
+            i64 => I64,
+            i128 => I128,
+            isize => Isize,
+            u8 => U8,
+            u16 => U16,
+            u32 => U32,
+            u64 => U64,
+            u128 => U128,
+            usize => Usize,
+            f32 => F32,
+            f64 => F64,
+            char => Char,
+            String => String,
+            Vec<u8> => Bytes
+        ])
+    };
+    (@pop $literal:ident, $context:expr, [$( $type:ty => $variant:ident ),*]) => {{
+        use $crate::__internal__TypeHash__ as TypeHash;
+        let stack = $context.stack();
+        let type_hash = stack.peek();
+        if type_hash == Some(TypeHash::of::<()>()) {
+            stack.pop::<()>().map(|_| $literal::Unit)
+        }
+        $(
+            else if type_hash == Some(TypeHash::of::<$type>()) {
+                stack.pop::<$type>().map($literal::$variant)
+            }
+        )*
+        else {
+            None
+        }
+    }};
+}
+
 #[allow(clippy::type_complexity)]
 pub struct InlineExpression(Arc<dyn Fn(&mut Context, &Registry) + Send + Sync>);
 
@@ -61,8 +237,12 @@ pub enum ScriptOperation<'a, SE: ScriptExpression> {
     Expression {
         expression: SE,
     },
+    /// Immutable register can be assigned only once, with `PopToRegister` or
+    /// `MoveRegister`. Value moved out of it can only be put back with
+    /// `RestoreRegister`.
     DefineRegister {
         query: TypeQuery<'a>,
+        immutable: bool,
     },
     DropRegister {
         index: usize,
@@ -73,10 +253,23 @@ pub enum ScriptOperation<'a, SE: ScriptExpression> {
     PopToRegister {
         index: usize,
     },
+    /// Puts value back into empty register it was pushed from, which unlike
+    /// `PopToRegister` is allowed for already assigned immutable registers.
+    /// Used by frontends to give back values lent for reads.
+    RestoreRegister {
+        index: usize,
+    },
     MoveRegister {
         from: usize,
         to: usize,
     },
+    /// Moves value from top of stack onto context heap and pushes its
+    /// `HeapId` instead, so value outlives scope that produced it. Value type
+    /// has to be registered as `Send` and `Sync`. No frontend emits it yet,
+    /// scripts get it only when assembled with `ScriptBuilder`.
+    HeapPromote,
+    /// Pops `HeapId` and moves its value from context heap back on stack.
+    HeapDemote,
     CallFunction {
         query: FunctionQuery<'a>,
     },
@@ -87,15 +280,41 @@ pub enum ScriptOperation<'a, SE: ScriptExpression> {
     LoopScope {
         scope: ScriptHandle<'a, SE>,
     },
+    /// Runs `scope` as child scope. With `frame` set, registers defined in
+    /// `scope` are local to it and get dropped once it completes, while
+    /// registers of enclosing scopes keep their indices. Otherwise `scope`
+    /// starts new registers barrier that `PopScope` restores.
     PushScope {
         scope: ScriptHandle<'a, SE>,
+        frame: bool,
     },
     PopScope,
     ContinueScopeConditionally,
+    /// Stops current scope and all enclosing scopes up to function boundary,
+    /// leaving stack as is for function outputs.
+    ReturnScope,
+    /// Polls `FuturePoller` from context custom data and suspends scope on
+    /// this operation, giving control back to host, until `FutureHandle` on
+    /// top of stack is ready.
+    Await,
+    /// Pops `ScriptError` from stack and starts unwinding scopes with it.
+    Throw,
+    /// Runs `scope_try` and if it ends with pending error, stack and registers
+    /// are restored to the state from before `scope_try` and `scope_catch`
+    /// runs with `ScriptError` pushed on stack.
+    TryScope {
+        scope_try: ScriptHandle<'a, SE>,
+        scope_catch: ScriptHandle<'a, SE>,
+    },
+    /// Marks following operations of this scope and its child scopes as
+    /// compiled from `span`, until next span marker.
+    Span {
+        span: ScriptSpan,
+    },
 }
 
-impl<SE: ScriptExpression> ScriptOperation<'_, SE> {
-    pub fn label(&self) -> &str {
+impl<'a, SE: ScriptExpression> ScriptOperation<'a, SE> {
+    pub fn label(&self) -> &'static str {
         match self {
             Self::None => "None",
             Self::Expression { .. } => "Expression",
@@ -103,15 +322,181 @@ impl<SE: ScriptExpression> ScriptOperation<'_, SE> {
             Self::DropRegister { .. } => "DropRegister",
             Self::PushFromRegister { .. } => "PushFromRegister",
             Self::PopToRegister { .. } => "PopToRegister",
+            Self::RestoreRegister { .. } => "RestoreRegister",
             Self::MoveRegister { .. } => "MoveRegister",
+            Self::HeapPromote => "HeapPromote",
+            Self::HeapDemote => "HeapDemote",
             Self::CallFunction { .. } => "CallFunction",
             Self::BranchScope { .. } => "BranchScope",
             Self::LoopScope { .. } => "LoopScope",
             Self::PushScope { .. } => "PushScope",
             Self::PopScope => "PopScope",
             Self::ContinueScopeConditionally => "ContinueScopeConditionally",
+            Self::ReturnScope => "ReturnScope",
+            Self::Await => "Await",
+            Self::Throw => "Throw",
+            Self::TryScope { .. } => "TryScope",
+            Self::Span { .. } => "Span",
         }
     }
+
+    /// Child scopes of this operation, in order of appearance.
+    pub fn scopes(&self) -> impl Iterator<Item = &Script<'a, SE>> {
+        let (first, second) = match self {
+            Self::BranchScope {
+                scope_success,
+                scope_failure,
+            } => (Some(scope_success), scope_failure.as_ref()),
+            Self::LoopScope { scope } | Self::PushScope { scope, .. } => (Some(scope), None),
+            Self::TryScope {
+                scope_try,
+                scope_catch,
+            } => (Some(scope_try), Some(scope_catch)),
+            _ => (None, None),
+        };
+        first.into_iter().chain(second).map(|scope| scope.as_ref())
+    }
+
+    /// Child scopes of this operation which are not shared with other
+    /// scripts, in order of appearance.
+    pub fn scopes_mut(&mut self) -> impl Iterator<Item = &mut Script<'a, SE>> {
+        let (first, second) = match self {
+            Self::BranchScope {
+                scope_success,
+                scope_failure,
+            } => (Some(scope_success), scope_failure.as_mut()),
+            Self::LoopScope { scope } | Self::PushScope { scope, .. } => (Some(scope), None),
+            Self::TryScope {
+                scope_try,
+                scope_catch,
+            } => (Some(scope_try), Some(scope_catch)),
+            _ => (None, None),
+        };
+        first.into_iter().chain(second).filter_map(Arc::get_mut)
+    }
+}
+
+/// Visits every operation of script and its child scopes, depth first.
+pub fn visit_script<'a, 'b, SE: ScriptExpression>(
+    script: &'b Script<'a, SE>,
+    visitor: &mut impl FnMut(&'b ScriptOperation<'a, SE>),
+) {
+    for operation in script {
+        visitor(operation);
+        for scope in operation.scopes() {
+            visit_script(scope, visitor);
+        }
+    }
+}
+
+/// Visits every operation of script and its child scopes, depth first.
+/// Child scopes shared with other scripts are skipped.
+pub fn visit_script_mut<'a, SE: ScriptExpression>(
+    script: &mut Script<'a, SE>,
+    visitor: &mut impl FnMut(&mut ScriptOperation<'a, SE>),
+) {
+    for operation in script {
+        visitor(operation);
+        for scope in operation.scopes_mut() {
+            visit_script_mut(scope, visitor);
+        }
+    }
+}
+
+pub fn is_function_pure(signature: &FunctionSignature) -> bool {
+    fn is_pure(meta: &Meta) -> bool {
+        match meta {
+            Meta::Identifier(name) => name == "pure",
+            Meta::Array(items) => items.iter().any(is_pure),
+            Meta::Map(items) => items
+                .get("pure")
+                .and_then(|meta| meta.as_value())
+                .and_then(|value| value.as_bool())
+                .unwrap_or(false),
+            _ => false,
+        }
+    }
+
+    signature.meta.as_ref().map(is_pure).unwrap_or(false)
+}
+
+pub fn fold_constants<SE: ScriptConstantExpression>(
+    script: &mut Script<'_, SE>,
+    registry: &Registry,
+) {
+    let mut index = 0;
+    let mut constants = 0;
+    while index < script.len() {
+        match &mut script[index] {
+            ScriptOperation::Expression { expression } => {
+                if expression.is_constant() {
+                    constants += 1;
+                } else {
+                    constants = 0;
+                }
+                index += 1;
+            }
+            ScriptOperation::CallFunction { query } => {
+                let query = query.clone();
+                if let Some((expression, consumed)) =
+                    fold_function_call(&script[(index - constants)..index], &query, registry)
+                {
+                    let start = index - consumed;
+                    script.splice(
+                        start..=index,
+                        std::iter::once(ScriptOperation::Expression { expression }),
+                    );
+                    index = start + 1;
+                    constants = constants - consumed + 1;
+                } else {
+                    index += 1;
+                    constants = 0;
+                }
+            }
+            operation => {
+                for scope in operation.scopes_mut() {
+                    fold_constants(scope, registry);
+                }
+                index += 1;
+                constants = 0;
+            }
+        }
+    }
+}
+
+fn fold_function_call<SE: ScriptConstantExpression>(
+    constants: &[ScriptOperation<'_, SE>],
+    query: &FunctionQuery,
+    registry: &Registry,
+) -> Option<(SE, usize)> {
+    let handle = registry.find_function(query.to_static())?;
+    let signature = handle.signature();
+    if !is_function_pure(signature)
+        || signature.outputs.len() != 1
+        || signature.inputs.len() > constants.len()
+    {
+        return None;
+    }
+    let consumed = signature.inputs.len();
+    let mut context = Context::new(10240, 10240);
+    // Function gets called at compile time, so its failure only means that
+    // call has to stay in script and be performed at runtime.
+    let status = std::panic::catch_unwind(AssertUnwindSafe(|| {
+        for operation in &constants[(constants.len() - consumed)..] {
+            if let ScriptOperation::Expression { expression } = operation {
+                expression.evaluate(&mut context, registry);
+            }
+        }
+        handle.invoke(&mut context, registry);
+    }));
+    if status.is_err() || context.has_error() {
+        return None;
+    }
+    let result = SE::constant_from_stack(&mut context, registry)?;
+    if context.stack().position() != 0 {
+        return None;
+    }
+    Some((result, consumed))
 }
 
 pub struct ScriptBuilder<'a, SE: ScriptExpression>(Script<'a, SE>);
@@ -133,7 +518,18 @@ impl<'a, SE: ScriptExpression> ScriptBuilder<'a, SE> {
     }
 
     pub fn define_register(mut self, query: TypeQuery<'a>) -> Self {
-        self.0.push(ScriptOperation::DefineRegister { query });
+        self.0.push(ScriptOperation::DefineRegister {
+            query,
+            immutable: false,
+        });
+        self
+    }
+
+    pub fn define_immutable_register(mut self, query: TypeQuery<'a>) -> Self {
+        self.0.push(ScriptOperation::DefineRegister {
+            query,
+            immutable: true,
+        });
         self
     }
 
@@ -152,11 +548,26 @@ impl<'a, SE: ScriptExpression> ScriptBuilder<'a, SE> {
         self
     }
 
+    pub fn restore_register(mut self, index: usize) -> Self {
+        self.0.push(ScriptOperation::RestoreRegister { index });
+        self
+    }
+
     pub fn move_register(mut self, from: usize, to: usize) -> Self {
         self.0.push(ScriptOperation::MoveRegister { from, to });
         self
     }
 
+    pub fn heap_promote(mut self) -> Self {
+        self.0.push(ScriptOperation::HeapPromote);
+        self
+    }
+
+    pub fn heap_demote(mut self) -> Self {
+        self.0.push(ScriptOperation::HeapDemote);
+        self
+    }
+
     pub fn call_function(mut self, query: FunctionQuery<'a>) -> Self {
         self.0.push(ScriptOperation::CallFunction { query });
         self
@@ -180,7 +591,16 @@ impl<'a, SE: ScriptExpression> ScriptBuilder<'a, SE> {
     }
 
     pub fn push_scope(mut self, scope: ScriptHandle<'a, SE>) -> Self {
-        self.0.push(ScriptOperation::PushScope { scope });
+        self.0.push(ScriptOperation::PushScope {
+            scope,
+            frame: false,
+        });
+        self
+    }
+
+    pub fn push_frame_scope(mut self, scope: ScriptHandle<'a, SE>) -> Self {
+        self.0
+            .push(ScriptOperation::PushScope { scope, frame: true });
         self
     }
 
@@ -193,6 +613,38 @@ impl<'a, SE: ScriptExpression> ScriptBuilder<'a, SE> {
         self.0.push(ScriptOperation::ContinueScopeConditionally);
         self
     }
+
+    pub fn return_scope(mut self) -> Self {
+        self.0.push(ScriptOperation::ReturnScope);
+        self
+    }
+
+    pub fn await_future(mut self) -> Self {
+        self.0.push(ScriptOperation::Await);
+        self
+    }
+
+    pub fn throw(mut self) -> Self {
+        self.0.push(ScriptOperation::Throw);
+        self
+    }
+
+    pub fn try_scope(
+        mut self,
+        scope_try: ScriptHandle<'a, SE>,
+        scope_catch: ScriptHandle<'a, SE>,
+    ) -> Self {
+        self.0.push(ScriptOperation::TryScope {
+            scope_try,
+            scope_catch,
+        });
+        self
+    }
+
+    pub fn span(mut self, span: ScriptSpan) -> Self {
+        self.0.push(ScriptOperation::Span { span });
+        self
+    }
 }
 
 #[derive(Debug)]
@@ -262,6 +714,14 @@ pub struct ScriptFunction<'a, SE: ScriptExpression> {
 }
 
 impl<SE: ScriptExpression> ScriptFunction<'static, SE> {
+    /// Collects queries of all functions called from script, including nested
+    /// scopes, in order of appearance.
+    pub fn calls(&self) -> Vec<FunctionQuery<'static>> {
+        let mut result = vec![];
+        collect_script_calls(&self.script, &mut result);
+        result
+    }
+
     pub fn install<SFG: ScriptFunctionGenerator<SE>>(
         &self,
         registry: &mut Registry,
@@ -273,6 +733,17 @@ impl<SE: ScriptExpression> ScriptFunction<'static, SE> {
     }
 }
 
+fn collect_script_calls<'a, SE: ScriptExpression>(
+    script: &Script<'a, SE>,
+    result: &mut Vec<FunctionQuery<'a>>,
+) {
+    visit_script(script, &mut |operation| {
+        if let ScriptOperation::CallFunction { query } = operation {
+            result.push(query.clone());
+        }
+    });
+}
+
 pub trait ScriptFunctionGenerator<SE: ScriptExpression> {
     type Input;
     type Output;
@@ -289,7 +760,7 @@ pub trait ScriptFunctionGenerator<SE: ScriptExpression> {
     ) -> Option<(Function, Self::Output)> {
         let (body, output) = Self::generate_function_body(function.script.clone(), input)?;
         Some((
-            Function::new(function.signature.build(registry), body),
+            Function::new(function.signature.build(registry), body).with_calls(function.calls()),
             output,
         ))
     }
@@ -485,6 +956,33 @@ impl ScriptEnum<'_> {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScriptModuleImport {
+    pub module_name: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+}
+
+impl ScriptModuleImport {
+    pub fn new(module_name: impl ToString, name: impl ToString) -> Self {
+        Self {
+            module_name: module_name.to_string(),
+            name: name.to_string(),
+            alias: None,
+        }
+    }
+
+    pub fn alias(mut self, alias: impl ToString) -> Self {
+        self.alias = Some(alias.to_string());
+        self
+    }
+
+    pub fn local_name(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct ScriptModule<'a, SE: ScriptExpression> {
     pub name: String,
@@ -506,6 +1004,60 @@ impl<SE: ScriptExpression> ScriptModule<'_, SE> {
         }
     }
 
+    /// Qualifies type queries that refer to types by name only: imported
+    /// names resolve to imported types and names of types defined in this
+    /// module resolve to this module. Imports shadow local types of the same
+    /// name, while queries with explicit module name are left untouched.
+    pub fn resolve_imports(&mut self, imports: &[ScriptModuleImport]) {
+        let mut names = HashMap::new();
+        for type_ in &self.structs {
+            names.insert(
+                type_.name.to_owned(),
+                (self.name.to_owned(), type_.name.to_owned()),
+            );
+        }
+        for type_ in &self.enums {
+            names.insert(
+                type_.name.to_owned(),
+                (self.name.to_owned(), type_.name.to_owned()),
+            );
+        }
+        for import in imports {
+            names.insert(
+                import.local_name().to_owned(),
+                (import.module_name.to_owned(), import.name.to_owned()),
+            );
+        }
+        for type_ in &mut self.structs {
+            for field in &mut type_.fields {
+                resolve_type_query(&mut field.type_query, &names);
+            }
+        }
+        for type_ in &mut self.enums {
+            for variant in &mut type_.variants {
+                for field in &mut variant.fields {
+                    resolve_type_query(&mut field.type_query, &names);
+                }
+            }
+        }
+        for function in &mut self.functions {
+            let signature = &mut function.signature;
+            if let Some(type_query) = signature.type_query.as_mut() {
+                resolve_type_query(type_query, &names);
+            }
+            for parameter in signature
+                .inputs
+                .iter_mut()
+                .chain(signature.outputs.iter_mut())
+            {
+                resolve_type_query(&mut parameter.type_query, &names);
+            }
+            if let Some(script) = Arc::get_mut(&mut function.script) {
+                resolve_script_imports(script, &names);
+            }
+        }
+    }
+
     pub fn declare_types(&self, registry: &mut Registry) {
         for type_ in &self.structs {
             type_.declare(registry);
@@ -544,25 +1096,330 @@ impl<SE: ScriptExpression> ScriptModule<'static, SE> {
     }
 }
 
+fn resolve_type_query(query: &mut TypeQuery, names: &HashMap<String, (String, String)>) {
+    if query.module_name.is_some() || query.type_hash.is_some() {
+        return;
+    }
+    if let Some((module_name, name)) = query
+        .name
+        .as_ref()
+        .and_then(|name| names.get(name.as_ref()))
+    {
+        query.name = Some(name.to_owned().into());
+        query.module_name = Some(module_name.to_owned().into());
+    }
+}
+
+fn resolve_script_imports<SE: ScriptExpression>(
+    script: &mut Script<SE>,
+    names: &HashMap<String, (String, String)>,
+) {
+    visit_script_mut(script, &mut |operation| match operation {
+        ScriptOperation::DefineRegister { query, .. } => {
+            resolve_type_query(query, names);
+        }
+        ScriptOperation::CallFunction { query } => {
+            if let Some(type_query) = query.type_query.as_mut() {
+                resolve_type_query(type_query, names);
+            }
+        }
+        _ => {}
+    });
+}
+
+fn collect_script_type_queries<'a, 'b, SE: ScriptExpression>(
+    script: &'b Script<'a, SE>,
+    result: &mut Vec<&'b TypeQuery<'a>>,
+) {
+    visit_script(script, &mut |operation| {
+        if let ScriptOperation::DefineRegister { query, .. } = operation {
+            result.push(query);
+        }
+    });
+}
+
+fn qualified_name(module_name: Option<&str>, type_name: Option<&str>, name: &str) -> String {
+    module_name
+        .into_iter()
+        .chain(type_name)
+        .chain(std::iter::once(name))
+        .collect::<Vec<_>>()
+        .join("::")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstallCollision {
+    pub name: String,
+    /// Module that defined item first, or `None` if it was already registered.
+    pub first_source: Option<String>,
+    pub second_source: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InstallUnresolvedQuery {
+    Type(TypeQuery<'static>),
+    Function(FunctionQuery<'static>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstallUnresolved {
+    /// Qualified name of type or function that refers to missing item.
+    pub source: String,
+    pub query: InstallUnresolvedQuery,
+}
+
+/// Describes what package installation added to registry. Types with
+/// unresolved field types stay declared without fields, and functions with
+/// unresolved signature types are not installed.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct InstallReport {
+    pub types: Vec<String>,
+    pub functions: Vec<String>,
+    pub collisions: Vec<InstallCollision>,
+    pub unresolved: Vec<InstallUnresolved>,
+}
+
+impl InstallReport {
+    pub fn is_clean(&self) -> bool {
+        self.collisions.is_empty() && self.unresolved.is_empty()
+    }
+
+    fn check_types<'a: 'b, 'b>(
+        &mut self,
+        source: &str,
+        queries: impl IntoIterator<Item = &'b TypeQuery<'a>>,
+        registry: &Registry,
+    ) -> bool {
+        let mut result = true;
+        for query in queries {
+            if !registry.types().any(|type_| query.is_valid(type_)) {
+                self.unresolved.push(InstallUnresolved {
+                    source: source.to_owned(),
+                    query: InstallUnresolvedQuery::Type(query.to_static()),
+                });
+                result = false;
+            }
+        }
+        result
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstallError {
+    Collisions(Vec<InstallCollision>),
+}
+
+impl std::fmt::Display for InstallError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Collisions(collisions) => {
+                write!(f, "Package has colliding items:")?;
+                for collision in collisions {
+                    match collision.first_source.as_ref() {
+                        Some(first_source) => write!(
+                            f,
+                            " `{}` from `{}` and `{}`;",
+                            collision.name, first_source, collision.second_source
+                        )?,
+                        None => write!(
+                            f,
+                            " `{}` already registered and from `{}`;",
+                            collision.name, collision.second_source
+                        )?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for InstallError {}
+
+/// Chain of dependencies leading back to its first entry, which for
+/// self-dependency is just that entry repeated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CircularDependencyError {
+    pub cycle: Vec<String>,
+}
+
+impl std::fmt::Display for CircularDependencyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Found circular dependency: {}", self.cycle.join(" -> "))
+    }
+}
+
+impl Error for CircularDependencyError {}
+
 #[derive(Debug, Default)]
 pub struct ScriptPackage<'a, SE: ScriptExpression> {
     pub modules: Vec<ScriptModule<'a, SE>>,
 }
 
+impl<SE: ScriptExpression> ScriptPackage<'_, SE> {
+    /// Finds types and functions defined more than once, either by package
+    /// modules or by package and items already registered.
+    pub fn collisions(&self, registry: &Registry) -> Vec<InstallCollision> {
+        let mut result = vec![];
+        let mut types = registry
+            .types()
+            .map(|type_| {
+                (
+                    qualified_name(type_.module_name(), None, type_.name()),
+                    None,
+                )
+            })
+            .collect::<HashMap<_, _>>();
+        let mut functions = registry
+            .functions()
+            .map(|function| {
+                let signature = function.signature();
+                let name = qualified_name(
+                    signature.module_name.as_deref(),
+                    signature.type_handle.as_ref().map(|handle| handle.name()),
+                    &signature.name,
+                );
+                (name, None)
+            })
+            .collect::<HashMap<_, _>>();
+        let mut check = |sources: &mut HashMap<String, Option<String>>, name, source: &str| {
+            if let Some(first_source) = sources.get(&name) {
+                result.push(InstallCollision {
+                    name,
+                    first_source: first_source.to_owned(),
+                    second_source: source.to_owned(),
+                });
+            } else {
+                sources.insert(name, Some(source.to_owned()));
+            }
+        };
+        for module in &self.modules {
+            for type_ in &module.structs {
+                let name = qualified_name(type_.module_name.as_deref(), None, &type_.name);
+                check(&mut types, name, &module.name);
+            }
+            for type_ in &module.enums {
+                let name = qualified_name(type_.module_name.as_deref(), None, &type_.name);
+                check(&mut types, name, &module.name);
+            }
+            for function in &module.functions {
+                let signature = &function.signature;
+                let name = qualified_name(
+                    signature.module_name.as_deref(),
+                    signature
+                        .type_query
+                        .as_ref()
+                        .and_then(|query| query.name.as_deref()),
+                    &signature.name,
+                );
+                check(&mut functions, name, &module.name);
+            }
+        }
+        result
+    }
+}
+
 impl<SE: ScriptExpression> ScriptPackage<'static, SE> {
     pub fn install<SFG: ScriptFunctionGenerator<SE>>(
         &self,
         registry: &mut Registry,
         input: SFG::Input,
-    ) where
+    ) -> InstallReport
+    where
         SFG::Input: Clone,
     {
+        let mut report = InstallReport {
+            collisions: self.collisions(registry),
+            ..Default::default()
+        };
         for module in &self.modules {
-            module.install_types(registry);
+            module.declare_types(registry);
+            for type_ in &module.structs {
+                let name = qualified_name(type_.module_name.as_deref(), None, &type_.name);
+                let queries = type_.fields.iter().map(|field| &field.type_query);
+                if report.check_types(&name, queries, registry) {
+                    type_.define(registry);
+                }
+                report.types.push(name);
+            }
+            for type_ in &module.enums {
+                let name = qualified_name(type_.module_name.as_deref(), None, &type_.name);
+                let queries = type_
+                    .variants
+                    .iter()
+                    .flat_map(|variant| variant.fields.iter())
+                    .map(|field| &field.type_query);
+                if report.check_types(&name, queries, registry) {
+                    type_.define(registry);
+                }
+                report.types.push(name);
+            }
         }
+        let mut installed = vec![];
         for module in &self.modules {
-            module.install_functions::<SFG>(registry, input.clone());
+            for function in &module.functions {
+                let signature = &function.signature;
+                let name = qualified_name(
+                    signature.module_name.as_deref(),
+                    signature
+                        .type_query
+                        .as_ref()
+                        .and_then(|query| query.name.as_deref()),
+                    &signature.name,
+                );
+                let mut queries = vec![];
+                queries.extend(signature.type_query.as_ref());
+                queries.extend(
+                    signature
+                        .inputs
+                        .iter()
+                        .chain(signature.outputs.iter())
+                        .map(|parameter| &parameter.type_query),
+                );
+                if !report.check_types(&name, queries, registry) {
+                    continue;
+                }
+                let mut queries = vec![];
+                collect_script_type_queries(&function.script, &mut queries);
+                report.check_types(&name, queries, registry);
+                function.install::<SFG>(registry, input.clone());
+                installed.push((name, function));
+            }
+        }
+        // calls can refer to any function of the package, so they get
+        // resolved only after all functions are installed.
+        for (name, function) in installed {
+            for query in function.calls() {
+                if !registry
+                    .functions()
+                    .any(|handle| query.is_valid(handle.signature()))
+                {
+                    report.unresolved.push(InstallUnresolved {
+                        source: name.to_owned(),
+                        query: InstallUnresolvedQuery::Function(query),
+                    });
+                }
+            }
+            report.functions.push(name);
+        }
+        report
+    }
+
+    /// Strict variant of `install`, which installs nothing if package has
+    /// any colliding types or functions.
+    pub fn install_or_err<SFG: ScriptFunctionGenerator<SE>>(
+        &self,
+        registry: &mut Registry,
+        input: SFG::Input,
+    ) -> Result<InstallReport, InstallError>
+    where
+        SFG::Input: Clone,
+    {
+        let collisions = self.collisions(registry);
+        if !collisions.is_empty() {
+            return Err(InstallError::Collisions(collisions));
         }
+        Ok(self.install::<SFG>(registry, input))
     }
 }
 
@@ -752,3 +1609,364 @@ impl<T> ScriptContentProvider<T> for FileContentProvider<T> {
         Ok(path.join(relative).to_string_lossy().into_owned())
     }
 }
+
+#[cfg(feature = "archive")]
+#[derive(Debug)]
+pub enum ArchiveContentProviderError {
+    EntryNotFound(String),
+    PathOutsideArchive(String),
+    EntryTooLarge(String),
+    ArchiveTooLarge,
+}
+
+#[cfg(feature = "archive")]
+impl std::fmt::Display for ArchiveContentProviderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArchiveContentProviderError::EntryNotFound(path) => {
+                write!(f, "Archive entry not found: {}", path)
+            }
+            ArchiveContentProviderError::PathOutsideArchive(path) => {
+                write!(f, "Path points outside of archive: {}", path)
+            }
+            ArchiveContentProviderError::EntryTooLarge(path) => {
+                write!(f, "Archive entry exceeds size limit: {}", path)
+            }
+            ArchiveContentProviderError::ArchiveTooLarge => {
+                write!(f, "Archive content exceeds total size limit")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "archive")]
+impl Error for ArchiveContentProviderError {}
+
+/// Caps on uncompressed bytes read from archive, so small archive cannot
+/// expand into arbitrary amount of memory.
+#[cfg(feature = "archive")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArchiveLimits {
+    pub max_entry_size: u64,
+    pub max_total_size: u64,
+}
+
+#[cfg(feature = "archive")]
+impl Default for ArchiveLimits {
+    fn default() -> Self {
+        Self {
+            max_entry_size: 16 * 1024 * 1024,
+            max_total_size: 64 * 1024 * 1024,
+        }
+    }
+}
+
+#[cfg(feature = "archive")]
+impl ArchiveLimits {
+    pub fn with_max_entry_size(mut self, size: u64) -> Self {
+        self.max_entry_size = size;
+        self
+    }
+
+    pub fn with_max_total_size(mut self, size: u64) -> Self {
+        self.max_total_size = size;
+        self
+    }
+
+    /// Reads entry content, failing as soon as it goes past any limit.
+    /// Sizes from headers are not trusted, content grows as it gets read.
+    fn read_entry(
+        &self,
+        reader: impl std::io::Read,
+        path: &str,
+        total_size: &mut u64,
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        use std::io::Read;
+
+        let remaining = self.max_total_size.saturating_sub(*total_size);
+        let limit = self.max_entry_size.min(remaining);
+        let mut content = Vec::new();
+        reader
+            .take(limit.saturating_add(1))
+            .read_to_end(&mut content)?;
+        let size = content.len() as u64;
+        if size > self.max_entry_size {
+            return Err(Box::new(ArchiveContentProviderError::EntryTooLarge(
+                path.to_owned(),
+            )));
+        }
+        if size > remaining {
+            return Err(Box::new(ArchiveContentProviderError::ArchiveTooLarge));
+        }
+        *total_size += size;
+        Ok(content)
+    }
+}
+
+/// Provides content from `.zip` or `.tar` archive entries, with paths being
+/// archive-internal, `/` separated and relative to archive root.
+#[cfg(feature = "archive")]
+pub struct ZipContentProvider<T> {
+    extension: String,
+    entries: HashMap<String, Vec<u8>>,
+    parser: Box<dyn BytesContentParser<T>>,
+}
+
+#[cfg(feature = "archive")]
+impl<T> ZipContentProvider<T> {
+    pub fn from_zip(
+        bytes: impl AsRef<[u8]>,
+        extension: impl ToString,
+        parser: impl BytesContentParser<T> + 'static,
+    ) -> Result<Self, Box<dyn Error>> {
+        Self::from_zip_with_limits(bytes, extension, parser, Default::default())
+    }
+
+    pub fn from_zip_with_limits(
+        bytes: impl AsRef<[u8]>,
+        extension: impl ToString,
+        parser: impl BytesContentParser<T> + 'static,
+        limits: ArchiveLimits,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes.as_ref()))?;
+        let mut entries = HashMap::with_capacity(archive.len());
+        let mut total_size = 0;
+        for index in 0..archive.len() {
+            let file = archive.by_index(index)?;
+            if file.is_dir() {
+                continue;
+            }
+            let path = Self::normalize_path(file.name())?;
+            let content = limits.read_entry(file, &path, &mut total_size)?;
+            entries.insert(path, content);
+        }
+        Ok(Self {
+            extension: extension.to_string(),
+            entries,
+            parser: Box::new(parser),
+        })
+    }
+
+    pub fn from_tar(
+        bytes: impl AsRef<[u8]>,
+        extension: impl ToString,
+        parser: impl BytesContentParser<T> + 'static,
+    ) -> Result<Self, Box<dyn Error>> {
+        Self::from_tar_with_limits(bytes, extension, parser, Default::default())
+    }
+
+    pub fn from_tar_with_limits(
+        bytes: impl AsRef<[u8]>,
+        extension: impl ToString,
+        parser: impl BytesContentParser<T> + 'static,
+        limits: ArchiveLimits,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut archive = tar::Archive::new(bytes.as_ref());
+        let mut entries = HashMap::default();
+        let mut total_size = 0;
+        for file in archive.entries()? {
+            let file = file?;
+            if !file.header().entry_type().is_file() {
+                continue;
+            }
+            let path = Self::normalize_path(&file.path()?.to_string_lossy())?;
+            let content = limits.read_entry(file, &path, &mut total_size)?;
+            entries.insert(path, content);
+        }
+        Ok(Self {
+            extension: extension.to_string(),
+            entries,
+            parser: Box::new(parser),
+        })
+    }
+
+    /// Reads archive file, picking archive format by its extension.
+    pub fn from_file(
+        path: impl AsRef<Path>,
+        extension: impl ToString,
+        parser: impl BytesContentParser<T> + 'static,
+    ) -> Result<Self, Box<dyn Error>> {
+        Self::from_file_with_limits(path, extension, parser, Default::default())
+    }
+
+    pub fn from_file_with_limits(
+        path: impl AsRef<Path>,
+        extension: impl ToString,
+        parser: impl BytesContentParser<T> + 'static,
+        limits: ArchiveLimits,
+    ) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)?;
+        if path
+            .extension()
+            .map(|extension| extension == "tar")
+            .unwrap_or_default()
+        {
+            Self::from_tar_with_limits(bytes, extension, parser, limits)
+        } else {
+            Self::from_zip_with_limits(bytes, extension, parser, limits)
+        }
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(|path| path.as_str())
+    }
+
+    fn normalize_path(path: &str) -> Result<String, Box<dyn Error>> {
+        let mut parts = vec![];
+        for part in path.split(['/', '\\']) {
+            match part {
+                "" | "." => {}
+                ".." => {
+                    if parts.pop().is_none() {
+                        return Err(Box::new(ArchiveContentProviderError::PathOutsideArchive(
+                            path.to_owned(),
+                        )));
+                    }
+                }
+                part => parts.push(part),
+            }
+        }
+        Ok(parts.join("/"))
+    }
+}
+
+#[cfg(feature = "archive")]
+impl<T> ScriptContentProvider<T> for ZipContentProvider<T> {
+    fn load(&mut self, path: &str) -> Result<Option<T>, Box<dyn Error>> {
+        match self.entries.get(path) {
+            Some(bytes) => Ok(Some(self.parser.parse(bytes.to_owned())?)),
+            None => Err(Box::new(ArchiveContentProviderError::EntryNotFound(
+                path.to_owned(),
+            ))),
+        }
+    }
+
+    fn sanitize_path(&self, path: &str) -> Result<String, Box<dyn Error>> {
+        let mut result = Self::normalize_path(path)?;
+        if Path::new(&result).extension().is_none() {
+            result.push('.');
+            result.push_str(&self.extension);
+        }
+        Ok(result)
+    }
+
+    fn join_paths(&self, parent: &str, relative: &str) -> Result<String, Box<dyn Error>> {
+        let parent = match parent.rfind('/') {
+            Some(index) => &parent[..index],
+            None => "",
+        };
+        Self::normalize_path(&format!("{}/{}", parent, relative))
+    }
+}
+
+#[cfg(all(test, feature = "archive"))]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    struct TextContentParser;
+
+    impl BytesContentParser<String> for TextContentParser {
+        fn parse(&self, bytes: Vec<u8>) -> Result<String, Box<dyn Error>> {
+            Ok(String::from_utf8(bytes)?)
+        }
+    }
+
+    #[test]
+    fn test_zip_content_provider() {
+        let mut bytes = vec![];
+        {
+            let mut writer = zip::ZipWriter::new(std::io::Cursor::new(&mut bytes));
+            let options = zip::write::SimpleFileOptions::default();
+            writer.start_file("scripts/main.txt", options).unwrap();
+            writer.write_all(b"main").unwrap();
+            writer.start_file("scripts/lib/utils.txt", options).unwrap();
+            writer.write_all(b"utils").unwrap();
+            writer.finish().unwrap();
+        }
+        let mut provider = ZipContentProvider::from_zip(bytes, "txt", TextContentParser).unwrap();
+        let path = provider.sanitize_path("./scripts/main").unwrap();
+        assert_eq!(path, "scripts/main.txt");
+        assert_eq!(provider.load(&path).unwrap().unwrap(), "main");
+        let path = provider.join_paths(&path, "lib/../lib/utils.txt").unwrap();
+        assert_eq!(path, "scripts/lib/utils.txt");
+        assert_eq!(provider.load(&path).unwrap().unwrap(), "utils");
+        assert!(provider.load("scripts/missing.txt").is_err());
+        assert!(provider.join_paths("main.txt", "../outside.txt").is_err());
+    }
+
+    #[test]
+    fn test_archive_limits() {
+        let mut bytes = vec![];
+        {
+            let mut writer = zip::ZipWriter::new(std::io::Cursor::new(&mut bytes));
+            let options = zip::write::SimpleFileOptions::default();
+            writer.start_file("bomb.txt", options).unwrap();
+            writer.write_all(&[b'a'; 1024 * 1024]).unwrap();
+            writer.finish().unwrap();
+        }
+        assert!(bytes.len() < 64 * 1024);
+        let limits = ArchiveLimits::default().with_max_entry_size(1024);
+        let error =
+            ZipContentProvider::from_zip_with_limits(&bytes, "txt", TextContentParser, limits)
+                .err()
+                .unwrap();
+        assert!(matches!(
+            error.downcast_ref::<ArchiveContentProviderError>(),
+            Some(ArchiveContentProviderError::EntryTooLarge(path)) if path == "bomb.txt"
+        ));
+
+        let mut builder = tar::Builder::new(vec![]);
+        for path in ["a.txt", "b.txt"] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(100);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, [b'a'; 100].as_slice())
+                .unwrap();
+        }
+        let bytes = builder.into_inner().unwrap();
+        let limits = ArchiveLimits::default().with_max_total_size(150);
+        let error =
+            ZipContentProvider::from_tar_with_limits(&bytes, "txt", TextContentParser, limits)
+                .err()
+                .unwrap();
+        assert!(matches!(
+            error.downcast_ref::<ArchiveContentProviderError>(),
+            Some(ArchiveContentProviderError::ArchiveTooLarge)
+        ));
+        let limits = ArchiveLimits::default().with_max_total_size(200);
+        assert!(
+            ZipContentProvider::from_tar_with_limits(&bytes, "txt", TextContentParser, limits)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_tar_content_provider() {
+        let mut builder = tar::Builder::new(vec![]);
+        for (path, content) in [
+            ("scripts/main.txt", b"main".as_slice()),
+            ("scripts/lib/utils.txt", b"utils".as_slice()),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, content).unwrap();
+        }
+        let bytes = builder.into_inner().unwrap();
+        let mut provider = ZipContentProvider::from_tar(bytes, "txt", TextContentParser).unwrap();
+        let mut entries = provider.entries().collect::<Vec<_>>();
+        entries.sort();
+        assert_eq!(entries, vec!["scripts/lib/utils.txt", "scripts/main.txt"]);
+        let path = provider.sanitize_path("./scripts/main").unwrap();
+        assert_eq!(path, "scripts/main.txt");
+        assert_eq!(provider.load(&path).unwrap().unwrap(), "main");
+        let path = provider.join_paths(&path, "lib/utils.txt").unwrap();
+        assert_eq!(provider.load(&path).unwrap().unwrap(), "utils");
+        assert!(provider.load("scripts/missing.txt").is_err());
+    }
+}
@@ -327,6 +327,122 @@ impl<T: Clone + 'static> ValueTransformer for DynamicManagedBoxValueTransformer<
     }
 }
 
+/// Wraps another transformer to pass nullable values across the script boundary.
+/// `None` is carried as-is, without touching the inner transformer, while `Some`
+/// gets transformed by the inner one. Dependency is forwarded to inner transformer
+/// only when there is a value to borrow.
+pub struct OptionValueTransformer<T: ValueTransformer>(PhantomData<fn() -> T>)
+where
+    T::Type: 'static;
+
+impl<T: ValueTransformer> ValueTransformer for OptionValueTransformer<T>
+where
+    T::Type: 'static,
+{
+    type Type = Option<T::Type>;
+    type Borrow<'r> = Option<T::Borrow<'r>>;
+    type BorrowMut<'r> = Option<T::BorrowMut<'r>>;
+    type Dependency = T::Dependency;
+    type Owned = Option<T::Owned>;
+    type Ref = Option<T::Ref>;
+    type RefMut = Option<T::RefMut>;
+
+    fn from_owned(registry: &Registry, value: Self::Type) -> Self::Owned {
+        value.map(|value| T::from_owned(registry, value))
+    }
+
+    fn from_ref(
+        registry: &Registry,
+        value: &Self::Type,
+        dependency: Option<Self::Dependency>,
+    ) -> Self::Ref {
+        value
+            .as_ref()
+            .map(|value| T::from_ref(registry, value, dependency))
+    }
+
+    fn from_ref_mut(
+        registry: &Registry,
+        value: &mut Self::Type,
+        dependency: Option<Self::Dependency>,
+    ) -> Self::RefMut {
+        value
+            .as_mut()
+            .map(|value| T::from_ref_mut(registry, value, dependency))
+    }
+
+    fn into_owned(value: Self::Owned) -> Self::Type {
+        value.map(T::into_owned)
+    }
+
+    fn into_ref(value: &Self::Ref) -> Self::Borrow<'_> {
+        value.as_ref().map(T::into_ref)
+    }
+
+    fn into_ref_mut(value: &mut Self::RefMut) -> Self::BorrowMut<'_> {
+        value.as_mut().map(T::into_ref_mut)
+    }
+}
+
+/// Exposes `Vec<T>` to scripts as a slice view - references point at the
+/// vector's items, not the vector itself, so scripts can read and write items
+/// in place but cannot change its length. Owned values are moved into `Managed`.
+pub struct SliceValueTransformer<T: 'static>(PhantomData<fn() -> T>);
+
+impl<T: 'static> ValueTransformer for SliceValueTransformer<T> {
+    type Type = Vec<T>;
+    type Borrow<'r> = ValueReadAccess<'r, [T]>;
+    type BorrowMut<'r> = ValueWriteAccess<'r, [T]>;
+    type Dependency = ManagedValueDependency;
+    type Owned = Managed<Vec<T>>;
+    type Ref = ManagedRef<[T]>;
+    type RefMut = ManagedRefMut<[T]>;
+
+    fn from_owned(_: &Registry, value: Self::Type) -> Self::Owned {
+        Managed::new(value)
+    }
+
+    fn from_ref(
+        _: &Registry,
+        value: &Self::Type,
+        dependency: Option<Self::Dependency>,
+    ) -> Self::Ref {
+        if let ManagedValueDependency::Ref(lifetime) =
+            dependency.expect("`ManagedRef` require dependency for lifetime bound!")
+        {
+            ManagedRef::new(value.as_slice(), lifetime)
+        } else {
+            panic!("Could not borrow lifetime to create `ManagedRef`!")
+        }
+    }
+
+    fn from_ref_mut(
+        _: &Registry,
+        value: &mut Self::Type,
+        dependency: Option<Self::Dependency>,
+    ) -> Self::RefMut {
+        if let ManagedValueDependency::RefMut(lifetime) =
+            dependency.expect("`ManagedRefMut` require dependency for lifetime bound!")
+        {
+            ManagedRefMut::new(value.as_mut_slice(), lifetime)
+        } else {
+            panic!("Could not borrow lifetime mutably to create `ManagedRefMut`!")
+        }
+    }
+
+    fn into_owned(value: Self::Owned) -> Self::Type {
+        value.consume().ok().unwrap()
+    }
+
+    fn into_ref(value: &Self::Ref) -> Self::Borrow<'_> {
+        value.read().unwrap()
+    }
+
+    fn into_ref_mut(value: &mut Self::RefMut) -> Self::BorrowMut<'_> {
+        value.write().unwrap()
+    }
+}
+
 pub enum ManagedValueDependency {
     Ref(LifetimeRef),
     RefMut(LifetimeRefMut),
@@ -767,4 +883,54 @@ mod tests {
         let b = *get_wrapped(foo.borrow().unwrap()).read().unwrap();
         assert_eq!(a, b,);
     }
+
+    #[test]
+    fn test_option_value_transformer() {
+        type Transformer = OptionValueTransformer<ManagedValueTransformer<i32>>;
+
+        let registry = Registry::default();
+        let owned = Transformer::from_owned(&registry, Some(42));
+        assert_eq!(*owned.as_ref().unwrap().read().unwrap(), 42);
+        assert_eq!(Transformer::into_owned(owned), Some(42));
+        let owned = Transformer::from_owned(&registry, None);
+        assert!(owned.is_none());
+        assert_eq!(Transformer::into_owned(owned), None);
+
+        let lifetime = Lifetime::default();
+        let mut value = Some(40);
+        let dependency = ManagedValueDependency::RefMut(lifetime.borrow_mut().unwrap());
+        let mut reference = Transformer::from_ref_mut(&registry, &mut value, Some(dependency));
+        *Transformer::into_ref_mut(&mut reference).unwrap() += 2;
+        drop(reference);
+        assert_eq!(value, Some(42));
+
+        let reference = Transformer::from_ref(&registry, &None, None);
+        assert!(Transformer::into_ref(&reference).is_none());
+    }
+
+    #[test]
+    fn test_slice_value_transformer() {
+        fn sum(items: &[i32]) -> i32 {
+            items.iter().sum()
+        }
+
+        let registry = Registry::default();
+        let lifetime = Lifetime::default();
+        let mut items = vec![1, 2, 3];
+        let dependency = ManagedValueDependency::Ref(lifetime.borrow().unwrap());
+        let reference = SliceValueTransformer::from_ref(&registry, &items, Some(dependency));
+        assert_eq!(sum(&SliceValueTransformer::into_ref(&reference)), 6);
+        assert!(lifetime.borrow_mut().is_none());
+        drop(reference);
+
+        let dependency = ManagedValueDependency::RefMut(lifetime.borrow_mut().unwrap());
+        let mut reference =
+            SliceValueTransformer::from_ref_mut(&registry, &mut items, Some(dependency));
+        SliceValueTransformer::into_ref_mut(&mut reference)[1] = 20;
+        drop(reference);
+        assert_eq!(items, vec![1, 20, 3]);
+
+        let owned = SliceValueTransformer::from_owned(&registry, vec![4, 5]);
+        assert_eq!(SliceValueTransformer::into_owned(owned), vec![4, 5]);
+    }
 }
@@ -440,6 +440,28 @@ impl<'a> TypeQuery<'a> {
         }
     }
 
+    pub fn named(name: impl Into<Cow<'a, str>>) -> Self {
+        Self {
+            name: Some(name.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn in_module(mut self, module_name: impl Into<Cow<'a, str>>) -> Self {
+        self.module_name = Some(module_name.into());
+        self
+    }
+
+    pub fn with_hash(mut self, type_hash: TypeHash) -> Self {
+        self.type_hash = Some(type_hash);
+        self
+    }
+
+    pub fn with_visibility(mut self, visibility: Visibility) -> Self {
+        self.visibility = Some(visibility);
+        self
+    }
+
     pub fn is_valid(&self, type_: &Type) -> bool {
         self.name
             .as_ref()
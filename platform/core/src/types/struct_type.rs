@@ -159,6 +159,16 @@ impl NativeStructBuilder {
         }
     }
 
+    /// Builder for instantiation `T` of generic wrapper, named by `name` from
+    /// short name of its type parameter `P`, so `Handle<Texture>` and
+    /// `Handle<Mesh>` get distinct names. Same as other native structs, `T`
+    /// gets finalized with its `Drop` and is `Send` only if Rust says so.
+    pub fn new_generic<T: Initialize + Finalize + 'static, P: 'static>(
+        name: impl FnOnce(&str) -> String,
+    ) -> Self {
+        Self::new_named::<T>(name(&short_type_name(std::any::type_name::<P>())))
+    }
+
     pub fn new_uninitialized<T: Finalize + 'static>() -> Self {
         Self {
             meta: None,
@@ -570,6 +580,22 @@ impl<'a> StructQuery<'a> {
     }
 }
 
+/// Strips module paths from type name, e.g. `alloc::vec::Vec<foo::Bar>`
+/// becomes `Vec<Bar>`.
+fn short_type_name(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    let mut segment_start = 0;
+    for (index, character) in name.char_indices() {
+        if !(character.is_alphanumeric() || character == '_' || character == ':') {
+            result.push_str(name[segment_start..index].rsplit("::").next().unwrap());
+            result.push(character);
+            segment_start = index + character.len_utf8();
+        }
+    }
+    result.push_str(name[segment_start..].rsplit("::").next().unwrap());
+    result
+}
+
 #[macro_export]
 macro_rules! define_native_struct {
     (
@@ -791,4 +817,52 @@ mod tests {
             Some(Meta::Identifier("foo".to_owned()))
         );
     }
+
+    #[test]
+    fn test_generic_native_struct() {
+        use crate::types::TypeQuery;
+        use intuicio_data::type_hash::TypeHash;
+
+        #[derive(Default)]
+        struct Texture;
+
+        #[derive(Default)]
+        struct Mesh;
+
+        #[derive(Default)]
+        struct Handle<T>(std::marker::PhantomData<T>);
+
+        assert_eq!(
+            super::short_type_name(std::any::type_name::<Vec<Handle<Mesh>>>()),
+            "Vec<Handle<Mesh>>"
+        );
+
+        let mut registry = Registry::default();
+        let texture = registry
+            .register_native_generic::<Handle<Texture>, Texture>(|name| format!("{}Handle", name));
+        let mesh = registry
+            .register_native_generic::<Handle<Mesh>, Mesh>(|name| format!("{}Handle", name));
+        assert_eq!(texture.name(), "TextureHandle");
+        assert_eq!(mesh.name(), "MeshHandle");
+        assert_eq!(
+            registry
+                .find_type(TypeQuery {
+                    name: Some("TextureHandle".into()),
+                    ..Default::default()
+                })
+                .unwrap()
+                .type_hash(),
+            TypeHash::of::<Handle<Texture>>()
+        );
+        assert_eq!(
+            registry
+                .find_type(TypeQuery {
+                    name: Some("MeshHandle".into()),
+                    ..Default::default()
+                })
+                .unwrap()
+                .type_hash(),
+            TypeHash::of::<Handle<Mesh>>()
+        );
+    }
 }
@@ -0,0 +1,214 @@
+use crate::{pointer_alignment_padding, Finalize};
+use std::{
+    alloc::Layout,
+    marker::PhantomData,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+static GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+fn next_generation() -> usize {
+    GENERATION.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Points to value allocated in `BumpArena`. Handles are valid only for the
+/// arena that produced them and only until its next reset.
+pub struct BumpArenaHandle<T> {
+    offset: usize,
+    generation: usize,
+    _phantom: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for BumpArenaHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for BumpArenaHandle<T> {}
+
+impl<T> std::fmt::Debug for BumpArenaHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BumpArenaHandle")
+            .field("offset", &self.offset)
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
+
+/// Allocates values by bumping position within single fixed size block.
+/// There is no individual free - `reset` finalizes all values at once and
+/// makes whole block available again.
+pub struct BumpArena {
+    memory: Vec<u8>,
+    position: usize,
+    /// Unique across all arenas, so handles from other arenas or from before
+    /// reset never match.
+    generation: usize,
+    #[allow(clippy::type_complexity)]
+    finalizers: Vec<(usize, unsafe fn(*mut ()))>,
+}
+
+impl Drop for BumpArena {
+    fn drop(&mut self) {
+        self.reset();
+    }
+}
+
+impl BumpArena {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            memory: vec![0; capacity],
+            position: 0,
+            generation: next_generation(),
+            finalizers: vec![],
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.memory.len()
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.position == 0
+    }
+
+    /// Gives value back if there is not enough space left in block.
+    pub fn alloc<T: Finalize + Send + 'static>(
+        &mut self,
+        value: T,
+    ) -> Result<BumpArenaHandle<T>, T> {
+        unsafe {
+            let Some(pointer) = self.alloc_raw(Layout::new::<T>(), Some(T::finalize_raw)) else {
+                return Err(value);
+            };
+            pointer.cast::<T>().write(value);
+            Ok(BumpArenaHandle {
+                offset: pointer.offset_from(self.memory.as_ptr()) as usize,
+                generation: self.generation,
+                _phantom: PhantomData,
+            })
+        }
+    }
+
+    pub fn read<T: 'static>(&self, handle: BumpArenaHandle<T>) -> Option<&T> {
+        if handle.generation == self.generation {
+            unsafe { self.memory.as_ptr().add(handle.offset).cast::<T>().as_ref() }
+        } else {
+            None
+        }
+    }
+
+    pub fn write<T: 'static>(&mut self, handle: BumpArenaHandle<T>) -> Option<&mut T> {
+        if handle.generation == self.generation {
+            unsafe {
+                self.memory
+                    .as_mut_ptr()
+                    .add(handle.offset)
+                    .cast::<T>()
+                    .as_mut()
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Returns `None` when there is not enough space left in block.
+    ///
+    /// # Safety
+    /// Returned memory is uninitialized, if `finalizer` is provided then it
+    /// has to be initialized before reset, since reset calls finalizer on it.
+    /// Values put there have to be `Send`, since arena can be moved to other
+    /// thread.
+    pub unsafe fn alloc_raw(
+        &mut self,
+        layout: Layout,
+        finalizer: Option<unsafe fn(*mut ())>,
+    ) -> Option<*mut u8> {
+        let padding =
+            pointer_alignment_padding(self.memory.as_ptr().add(self.position), layout.align());
+        let offset = self.position + padding;
+        if offset + layout.size() > self.memory.len() {
+            return None;
+        }
+        self.position = offset + layout.size();
+        if let Some(finalizer) = finalizer {
+            self.finalizers.push((offset, finalizer));
+        }
+        Some(self.memory.as_mut_ptr().add(offset))
+    }
+
+    /// Finalizes allocated values in reverse order of allocation and
+    /// invalidates all handles.
+    pub fn reset(&mut self) {
+        while let Some((offset, finalizer)) = self.finalizers.pop() {
+            unsafe { finalizer(self.memory.as_mut_ptr().add(offset).cast::<()>()) };
+        }
+        self.position = 0;
+        self.generation = next_generation();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bump_arena() {
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(Debug)]
+        struct Tracked(u64);
+
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                DROPPED.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let mut arena = BumpArena::new(512);
+        let mut other = BumpArena::new(512);
+        let fill = |arena: &mut BumpArena| {
+            (0..32)
+                .map(|index| {
+                    arena.alloc(index as u8).unwrap();
+                    arena.alloc(Tracked(index)).unwrap()
+                })
+                .collect::<Vec<_>>()
+        };
+        let handles = fill(&mut arena);
+        // block is full, it does not grow.
+        assert_eq!(arena.alloc(Tracked(99)).unwrap_err().0, 99);
+        DROPPED.store(0, Ordering::SeqCst);
+        assert_eq!(arena.position(), arena.capacity());
+        for (index, handle) in handles.iter().enumerate() {
+            assert_eq!(arena.read(*handle).unwrap().0, index as u64);
+            assert!(other.read(*handle).is_none());
+        }
+        arena.write(handles[3]).unwrap().0 = 42;
+        assert_eq!(arena.read(handles[3]).unwrap().0, 42);
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 0);
+
+        arena.reset();
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 32);
+        assert!(arena.is_empty());
+        assert!(arena.read(handles[0]).is_none());
+
+        // same memory gets reused after reset.
+        let reused = fill(&mut arena);
+        for (handle, old) in reused.iter().zip(handles.iter()) {
+            assert_eq!(handle.offset, old.offset);
+        }
+        assert_eq!(arena.read(reused[31]).unwrap().0, 31);
+        assert_eq!(arena.position(), arena.capacity());
+
+        other.alloc(Tracked(0)).unwrap();
+        drop(arena);
+        drop(other);
+        assert_eq!(DROPPED.load(Ordering::SeqCst), 65);
+    }
+}
@@ -18,6 +18,10 @@ struct DataStackRegisterTag {
     layout: Layout,
     finalizer: Option<unsafe fn(*mut ())>,
     padding: u8,
+    immutable: bool,
+    /// Set once register gets its first value, stays set after value is
+    /// moved out or freed.
+    assigned: bool,
 }
 
 pub struct DataStackToken(usize);
@@ -85,6 +89,48 @@ impl<'a> DataStackRegisterAccess<'a> {
         }
     }
 
+    /// Immutable register is not meant to be assigned more than once - it is
+    /// up to register users to respect that.
+    pub fn is_immutable(&self) -> bool {
+        unsafe {
+            self.stack
+                .memory
+                .as_ptr()
+                .add(self.position)
+                .cast::<DataStackRegisterTag>()
+                .read_unaligned()
+                .immutable
+        }
+    }
+
+    /// Tells if register was ever given value, regardless if it still holds
+    /// it.
+    pub fn is_assigned(&self) -> bool {
+        unsafe {
+            self.stack
+                .memory
+                .as_ptr()
+                .add(self.position)
+                .cast::<DataStackRegisterTag>()
+                .read_unaligned()
+                .assigned
+        }
+    }
+
+    pub fn set_immutable(&mut self, immutable: bool) {
+        unsafe {
+            let tag = self
+                .stack
+                .memory
+                .as_mut_ptr()
+                .add(self.position)
+                .cast::<DataStackRegisterTag>();
+            let mut value = tag.read_unaligned();
+            value.immutable = immutable;
+            tag.write_unaligned(value);
+        }
+    }
+
     pub fn read<T: 'static>(&'a self) -> Option<&'a T> {
         unsafe {
             let tag = self
@@ -212,6 +258,7 @@ impl<'a> DataStackRegisterAccess<'a> {
                 } else {
                     tag.finalizer = Some(T::finalize_raw);
                 }
+                tag.assigned = true;
                 self.stack
                     .memory
                     .as_mut_ptr()
@@ -240,14 +287,17 @@ impl<'a> DataStackRegisterAccess<'a> {
                 .add(self.position)
                 .cast::<DataStackRegisterTag>()
                 .read_unaligned();
-            let other_tag = other
+            let mut other_tag = other
                 .stack
                 .memory
                 .as_ptr()
-                .add(self.position)
+                .add(other.position)
                 .cast::<DataStackRegisterTag>()
                 .read_unaligned();
-            if tag.type_hash == other_tag.type_hash && tag.layout == other_tag.layout {
+            if tag.finalizer.is_some()
+                && tag.type_hash == other_tag.type_hash
+                && tag.layout == other_tag.layout
+            {
                 if let Some(finalizer) = other_tag.finalizer {
                     (finalizer)(
                         self.stack
@@ -257,7 +307,8 @@ impl<'a> DataStackRegisterAccess<'a> {
                             .cast::<()>(),
                     );
                 }
-                tag.finalizer = None;
+                other_tag.finalizer = tag.finalizer.take();
+                other_tag.assigned = true;
                 let source = self
                     .stack
                     .memory
@@ -275,6 +326,12 @@ impl<'a> DataStackRegisterAccess<'a> {
                     .add(self.position)
                     .cast::<DataStackRegisterTag>()
                     .write_unaligned(tag);
+                self.stack
+                    .memory
+                    .as_mut_ptr()
+                    .add(other.position)
+                    .cast::<DataStackRegisterTag>()
+                    .write_unaligned(other_tag);
             }
         }
     }
@@ -301,6 +358,7 @@ impl DataStackMode {
 pub struct DataStack {
     memory: Vec<u8>,
     position: usize,
+    high_water_mark: usize,
     mode: DataStackMode,
     finalizers: HashMap<TypeHash, DataStackFinalizer>,
     registers: Vec<usize>,
@@ -310,7 +368,19 @@ pub struct DataStack {
 impl Drop for DataStack {
     fn drop(&mut self) {
         if self.drop {
-            self.restore(DataStackToken(0));
+            // finalizers run in LIFO order and each value is popped before its
+            // finalizer gets called, so when one panics, guard continues with
+            // remaining values without dropping the same value twice.
+            struct Guard<'a>(&'a mut DataStack);
+
+            impl Drop for Guard<'_> {
+                fn drop(&mut self) {
+                    self.0.restore(DataStackToken(0));
+                }
+            }
+
+            let guard = Guard(self);
+            guard.0.restore(DataStackToken(0));
         }
     }
 }
@@ -321,6 +391,7 @@ impl DataStack {
         Self {
             memory: vec![0; capacity],
             position: 0,
+            high_water_mark: 0,
             mode,
             finalizers: Default::default(),
             registers: vec![],
@@ -332,6 +403,15 @@ impl DataStack {
         self.position
     }
 
+    /// Highest position reached since creation or last reset.
+    pub fn high_water_mark(&self) -> usize {
+        self.high_water_mark
+    }
+
+    pub fn reset_high_water_mark(&mut self) {
+        self.high_water_mark = self.position;
+    }
+
     pub fn size(&self) -> usize {
         self.memory.len()
     }
@@ -432,6 +512,7 @@ impl DataStack {
                 .write_unaligned(type_hash);
             self.position += type_layout.size();
         }
+        self.high_water_mark = self.high_water_mark.max(self.position);
         true
     }
 
@@ -467,6 +548,7 @@ impl DataStack {
             .cast::<TypeHash>()
             .write_unaligned(type_hash);
         self.position += type_layout.size();
+        self.high_water_mark = self.high_water_mark.max(self.position);
         true
     }
 
@@ -510,6 +592,8 @@ impl DataStack {
                     layout: value_layout,
                     finalizer: None,
                     padding: padding as u8,
+                    immutable: false,
+                    assigned: false,
                 });
             self.position += tag_layout.size();
             self.memory
@@ -518,6 +602,7 @@ impl DataStack {
                 .cast::<TypeHash>()
                 .write_unaligned(TypeHash::of::<DataStackRegisterTag>());
             self.position += type_layout.size();
+            self.high_water_mark = self.high_water_mark.max(self.position);
             self.registers.push(position);
             Some(self.registers.len() - 1)
         }
@@ -530,6 +615,7 @@ impl DataStack {
         self.memory[self.position..(self.position + other.position)]
             .copy_from_slice(&other.memory[0..other.position]);
         self.position += other.position;
+        self.high_water_mark = self.high_water_mark.max(self.position);
         self.finalizers
             .extend(other.finalizers.iter().map(|(key, value)| {
                 (
@@ -585,6 +671,7 @@ impl DataStack {
                 .cast::<TypeHash>()
                 .write_unaligned(tag.type_hash);
             self.position += type_layout.size();
+            self.high_water_mark = self.high_water_mark.max(self.position);
             register
                 .stack
                 .memory
@@ -748,6 +835,7 @@ impl DataStack {
         result.finalizers.extend(finalizers);
         self.position = position;
         result.position = size;
+        result.high_water_mark = size;
         result
     }
 
@@ -797,6 +885,7 @@ impl DataStack {
                 );
             }
             tag.finalizer = Some(finalizer);
+            tag.assigned = true;
             let source = self
                 .memory
                 .as_ptr()
@@ -847,11 +936,14 @@ impl DataStack {
                         .read_unaligned();
                     self.position -= tag_layout.size();
                     self.position -= tag.layout.size();
-                    if let Some(finalizer) = tag.finalizer {
-                        (finalizer)(self.memory.as_mut_ptr().add(self.position).cast::<()>());
-                    }
+                    let data = self.memory.as_mut_ptr().add(self.position).cast::<()>();
+                    // bookkeeping goes first, so panicking finalizer leaves
+                    // stack consistent.
                     self.position -= tag.padding as usize;
                     self.registers.pop();
+                    if let Some(finalizer) = tag.finalizer {
+                        (finalizer)(data);
+                    }
                 }
             } else if let Some(finalizer) = self.finalizers.get(&type_hash) {
                 self.position -= finalizer.layout.size();
@@ -968,6 +1060,16 @@ impl DataStack {
         }
     }
 
+    /// Pushes values so that popping them back yields them in tuple order,
+    /// which matches the order of declared function outputs.
+    pub fn push_pack<T: DataStackPack>(&mut self, pack: T) {
+        pack.stack_push_reversed(self);
+    }
+
+    pub fn pop_pack<T: DataStackPack>(&mut self) -> T {
+        T::stack_pop(self)
+    }
+
     /// # Safety
     pub unsafe fn prevent_drop(&mut self) {
         self.drop = false;
@@ -1047,13 +1149,77 @@ impl_data_stack_tuple!(A, B, C, D, E, F, G, H, I, J, K, L, M, N);
 impl_data_stack_tuple!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O);
 impl_data_stack_tuple!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P);
 
+/// Typed builder of multiple return values, where each value gets appended in
+/// the order of declared function outputs.
+pub struct StackReturn<T: DataStackPack>(T);
+
+impl Default for StackReturn<()> {
+    fn default() -> Self {
+        Self(())
+    }
+}
+
+impl StackReturn<()> {
+    pub fn new() -> Self {
+        Self(())
+    }
+}
+
+impl<T: DataStackPack> StackReturn<T> {
+    pub fn pack_types() -> Vec<TypeHash> {
+        T::pack_types()
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+
+    pub fn push(self, stack: &mut DataStack) {
+        stack.push_pack(self.0);
+    }
+}
+
+macro_rules! impl_stack_return_value {
+    ($($type:ident),*) => {
+        impl<$($type: 'static),*> StackReturn<($($type,)*)> {
+            #[allow(non_snake_case)]
+            pub fn value<X: 'static>(self, value: X) -> StackReturn<($($type,)* X,)> {
+                let ($($type,)*) = self.0;
+                StackReturn(($($type,)* value,))
+            }
+        }
+    };
+}
+
+impl_stack_return_value!();
+impl_stack_return_value!(A);
+impl_stack_return_value!(A, B);
+impl_stack_return_value!(A, B, C);
+impl_stack_return_value!(A, B, C, D);
+impl_stack_return_value!(A, B, C, D, E);
+impl_stack_return_value!(A, B, C, D, E, F);
+impl_stack_return_value!(A, B, C, D, E, F, G);
+impl_stack_return_value!(A, B, C, D, E, F, G, H);
+impl_stack_return_value!(A, B, C, D, E, F, G, H, I);
+impl_stack_return_value!(A, B, C, D, E, F, G, H, I, J);
+impl_stack_return_value!(A, B, C, D, E, F, G, H, I, J, K);
+impl_stack_return_value!(A, B, C, D, E, F, G, H, I, J, K, L);
+impl_stack_return_value!(A, B, C, D, E, F, G, H, I, J, K, L, M);
+impl_stack_return_value!(A, B, C, D, E, F, G, H, I, J, K, L, M, N);
+impl_stack_return_value!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O);
+
 #[cfg(test)]
 mod tests {
     use crate::{
-        data_stack::{DataStack, DataStackMode},
+        data_stack::{DataStack, DataStackMode, StackReturn},
         type_hash::TypeHash,
     };
-    use std::{alloc::Layout, cell::RefCell, rc::Rc};
+    use std::{
+        alloc::Layout,
+        cell::{Cell, RefCell},
+        panic::{catch_unwind, AssertUnwindSafe},
+        rc::Rc,
+    };
 
     #[test]
     fn test_data_stack() {
@@ -1131,4 +1297,85 @@ mod tests {
             42
         );
     }
+
+    #[test]
+    fn test_push_pack() {
+        let mut stack = DataStack::new(1024, DataStackMode::Values);
+        stack.push_pack((42_usize, true, 4.2_f32));
+        assert_eq!(
+            stack.pop_pack::<(usize, bool, f32)>(),
+            (42_usize, true, 4.2_f32)
+        );
+        assert_eq!(stack.position(), 0);
+
+        let result = StackReturn::new()
+            .value(42_usize)
+            .value(true)
+            .value(4.2_f32);
+        assert_eq!(
+            StackReturn::<(usize, bool, f32)>::pack_types(),
+            vec![
+                TypeHash::of::<usize>(),
+                TypeHash::of::<bool>(),
+                TypeHash::of::<f32>()
+            ]
+        );
+        result.push(&mut stack);
+        assert_eq!(stack.pop::<usize>().unwrap(), 42_usize);
+        assert!(stack.pop::<bool>().unwrap());
+        assert_eq!(stack.pop::<f32>().unwrap(), 4.2_f32);
+        assert_eq!(stack.position(), 0);
+    }
+
+    #[test]
+    fn test_drop_on_unwind() {
+        struct Droppable(Rc<Cell<usize>>, bool);
+
+        impl Drop for Droppable {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+                if self.1 {
+                    panic!("Droppable panicked");
+                }
+            }
+        }
+
+        let dropped = Rc::new(Cell::new(0));
+        let mut stack = DataStack::new(1024, DataStackMode::Mixed);
+        let early_return = |stack: &mut DataStack| -> Option<()> {
+            stack.push(Droppable(dropped.clone(), false));
+            stack.pop::<u8>()?;
+            Some(())
+        };
+        assert!(early_return(&mut stack).is_none());
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            stack.push_register_value(Droppable(dropped.clone(), false));
+            stack.push(Droppable(dropped.clone(), false));
+            panic!("Native function panicked");
+        }));
+        assert!(result.is_err());
+        assert_eq!(dropped.get(), 0);
+        drop(stack);
+        assert_eq!(dropped.get(), 3);
+
+        let dropped = Rc::new(Cell::new(0));
+        let mut stack = DataStack::new(1024, DataStackMode::Values);
+        stack.push(Droppable(dropped.clone(), false));
+        stack.push(Droppable(dropped.clone(), true));
+        stack.push(Droppable(dropped.clone(), false));
+        assert!(catch_unwind(AssertUnwindSafe(|| drop(stack))).is_err());
+        assert_eq!(dropped.get(), 3);
+
+        let dropped = Rc::new(Cell::new(0));
+        let mut stack = DataStack::new(1024, DataStackMode::Mixed);
+        stack.push(1u8);
+        let position = stack.position();
+        let token = stack.store();
+        stack.push_register_value(Droppable(dropped.clone(), true));
+        assert!(catch_unwind(AssertUnwindSafe(|| stack.restore(token))).is_err());
+        assert_eq!(dropped.get(), 1);
+        assert_eq!(stack.position(), position);
+        assert_eq!(stack.registers_count(), 0);
+        assert_eq!(stack.pop::<u8>(), Some(1));
+    }
 }
@@ -1,3 +1,4 @@
+pub mod bump_arena;
 pub mod data_stack;
 pub mod lifetime;
 pub mod managed;
@@ -7,8 +8,8 @@ pub mod type_hash;
 
 pub mod prelude {
     pub use crate::{
-        data_stack::*, lifetime::*, managed::*, managed_box::*, shared::*, type_hash::*, Finalize,
-        Initialize,
+        bump_arena::*, data_stack::*, lifetime::*, managed::*, managed_box::*, shared::*,
+        type_hash::*, Finalize, Initialize,
     };
 }
 
@@ -709,6 +709,39 @@ impl LifetimeLazy {
     }
 }
 
+/// Lazy lifetime detached from Rust types, meant for passing lifetimes
+/// through foreign code, which can store it as opaque integer.
+pub struct LifetimeToken(LifetimeLazy);
+
+impl LifetimeToken {
+    pub fn new(lifetime: LifetimeLazy) -> Self {
+        Self(lifetime)
+    }
+
+    pub fn into_lazy(self) -> LifetimeLazy {
+        self.0
+    }
+
+    /// Converts token into opaque integer. Token memory is leaked until it
+    /// gets reconstructed with `from_opaque`.
+    pub fn into_opaque(self) -> usize {
+        Box::into_raw(Box::new(self)) as usize
+    }
+
+    /// # Safety
+    /// `value` must come from `into_opaque` and can be used only once - every
+    /// next use is use-after-free.
+    pub unsafe fn from_opaque(value: usize) -> Self {
+        *Box::from_raw(value as *mut Self)
+    }
+}
+
+impl From<LifetimeLazy> for LifetimeToken {
+    fn from(value: LifetimeLazy) -> Self {
+        Self(value)
+    }
+}
+
 pub struct ValueReadAccess<'a, T: 'a + ?Sized> {
     lifetime: LifetimeState,
     data: &'a T,
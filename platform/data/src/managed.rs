@@ -1,6 +1,7 @@
 use crate::{
     lifetime::{
-        Lifetime, LifetimeLazy, LifetimeRef, LifetimeRefMut, ValueReadAccess, ValueWriteAccess,
+        Lifetime, LifetimeLazy, LifetimeRef, LifetimeRefMut, LifetimeToken, ValueReadAccess,
+        ValueWriteAccess,
     },
     type_hash::TypeHash,
     Finalize,
@@ -8,6 +9,7 @@ use crate::{
 use std::{
     alloc::{alloc, dealloc, Layout},
     mem::MaybeUninit,
+    sync::Arc,
 };
 
 #[derive(Default)]
@@ -124,9 +126,38 @@ impl<T> TryFrom<ManagedValue<T>> for Managed<T> {
     }
 }
 
+/// Access tracking of one part of split mutable reference. Original
+/// reference stays borrowed for as long as any part (or its borrow) lives.
+struct ManagedSplit {
+    lifetime: Lifetime,
+    parent: Arc<ManagedSplitParent>,
+    owner: Option<Arc<ManagedSplit>>,
+}
+
+impl ManagedSplit {
+    fn exists(split: &Option<Arc<Self>>) -> bool {
+        split
+            .as_ref()
+            .map(|split| split.parent.0.exists() && Self::exists(&split.owner))
+            .unwrap_or(true)
+    }
+}
+
+/// Holds write access to original data while its split parts are alive.
+struct ManagedSplitParent(LifetimeRefMut);
+
+impl Drop for ManagedSplitParent {
+    fn drop(&mut self) {
+        if let Some(state) = self.0.state().upgrade() {
+            unsafe { state.lock_unchecked().release_write_access() };
+        }
+    }
+}
+
 pub struct ManagedRef<T: ?Sized> {
     lifetime: LifetimeRef,
     data: *const T,
+    split: Option<Arc<ManagedSplit>>,
 }
 
 unsafe impl<T: ?Sized> Send for ManagedRef<T> where T: Send {}
@@ -137,6 +168,7 @@ impl<T: ?Sized> ManagedRef<T> {
         Self {
             lifetime,
             data: data as *const T,
+            split: None,
         }
     }
 
@@ -145,7 +177,11 @@ impl<T: ?Sized> ManagedRef<T> {
         if data.is_null() {
             None
         } else {
-            Some(Self { lifetime, data })
+            Some(Self {
+                lifetime,
+                data,
+                split: None,
+            })
         }
     }
 
@@ -179,20 +215,38 @@ impl<T: ?Sized> ManagedRef<T> {
         Some(ManagedRef {
             lifetime: self.lifetime.borrow()?,
             data: self.data,
+            split: self.split.clone(),
         })
     }
 
     pub fn read(&self) -> Option<ValueReadAccess<T>> {
+        if !ManagedSplit::exists(&self.split) {
+            return None;
+        }
         unsafe { self.lifetime.read_ptr(self.data) }
     }
 
+    /// Projects reference to part of referenced data (e.g. a field), keeping
+    /// the same lifetime. Returns `None` if data is no longer accessible.
+    pub fn project<U: ?Sized>(self, f: impl FnOnce(&T) -> &U) -> Option<ManagedRef<U>> {
+        let data = f(&*self.read()?) as *const U;
+        Some(ManagedRef {
+            lifetime: self.lifetime,
+            data,
+            split: self.split,
+        })
+    }
+
     /// # Safety
+    /// Lifetime is not checked - referenced data has to be alive and returned
+    /// reference has to point within the original allocation.
     pub unsafe fn map<U>(self, f: impl FnOnce(&T) -> &U) -> ManagedRef<U> {
         unsafe {
             let data = f(&*self.data);
             ManagedRef {
                 lifetime: self.lifetime,
                 data: data as *const U,
+                split: self.split,
             }
         }
     }
@@ -203,13 +257,14 @@ impl<T: ?Sized> ManagedRef<T> {
             f(&*self.data).map(|data| ManagedRef {
                 lifetime: self.lifetime,
                 data: data as *const U,
+                split: self.split,
             })
         }
     }
 
     /// # Safety
     pub unsafe fn as_ptr(&self) -> Option<*const T> {
-        if self.lifetime.exists() {
+        if self.lifetime.exists() && ManagedSplit::exists(&self.split) {
             Some(self.data)
         } else {
             None
@@ -231,6 +286,7 @@ impl<T> TryFrom<ManagedValue<T>> for ManagedRef<T> {
 pub struct ManagedRefMut<T: ?Sized> {
     lifetime: LifetimeRefMut,
     data: *mut T,
+    split: Option<Arc<ManagedSplit>>,
 }
 
 unsafe impl<T: ?Sized> Send for ManagedRefMut<T> where T: Send {}
@@ -241,6 +297,7 @@ impl<T: ?Sized> ManagedRefMut<T> {
         Self {
             lifetime,
             data: data as *mut T,
+            split: None,
         }
     }
 
@@ -249,7 +306,11 @@ impl<T: ?Sized> ManagedRefMut<T> {
         if data.is_null() {
             None
         } else {
-            Some(Self { lifetime, data })
+            Some(Self {
+                lifetime,
+                data,
+                split: None,
+            })
         }
     }
 
@@ -283,6 +344,7 @@ impl<T: ?Sized> ManagedRefMut<T> {
         Some(ManagedRef {
             lifetime: self.lifetime.borrow()?,
             data: self.data,
+            split: self.split.clone(),
         })
     }
 
@@ -290,24 +352,122 @@ impl<T: ?Sized> ManagedRefMut<T> {
         Some(ManagedRefMut {
             lifetime: self.lifetime.borrow_mut()?,
             data: self.data,
+            split: self.split.clone(),
         })
     }
 
     pub fn read(&self) -> Option<ValueReadAccess<T>> {
+        if !ManagedSplit::exists(&self.split) {
+            return None;
+        }
         unsafe { self.lifetime.read_ptr(self.data) }
     }
 
     pub fn write(&mut self) -> Option<ValueWriteAccess<T>> {
+        if !ManagedSplit::exists(&self.split) {
+            return None;
+        }
         unsafe { self.lifetime.write_ptr(self.data) }
     }
 
+    /// Projects mutable reference to part of referenced data (e.g. a field),
+    /// keeping the same lifetime. Returns `None` if data is no longer accessible.
+    pub fn project_mut<U: ?Sized>(
+        mut self,
+        f: impl FnOnce(&mut T) -> &mut U,
+    ) -> Option<ManagedRefMut<U>> {
+        let data = f(&mut *self.write()?) as *mut U;
+        Some(ManagedRefMut {
+            lifetime: self.lifetime,
+            data,
+            split: self.split,
+        })
+    }
+
+    /// Splits mutable reference into two references to disjoint parts of
+    /// referenced data (e.g. two different fields).
+    ///
+    /// Projections have to point to non-overlapping memory within referenced
+    /// value itself - returns `None` if their byte ranges overlap, either one
+    /// points outside of value (e.g. into its boxed contents) or data is no
+    /// longer accessible. Each
+    /// part tracks value access on its own, so both can be written at the
+    /// same time, while original data stays borrowed until both parts (and
+    /// their borrows) are gone. Lifetime taken out of a part with
+    /// `into_inner` or `into_dynamic` gets invalidated once that part is
+    /// dropped.
+    pub fn split<A, B>(
+        mut self,
+        fa: fn(&mut T) -> &mut A,
+        fb: fn(&mut T) -> &mut B,
+    ) -> Option<(ManagedRefMut<A>, ManagedRefMut<B>)> {
+        let mut access = self.write()?;
+        let base = &*access as *const T as *const u8 as usize;
+        let end = base + std::mem::size_of_val(&*access);
+        let a = fa(&mut access) as *mut A;
+        let b = fb(&mut access) as *mut B;
+        let size_a = std::mem::size_of::<A>();
+        let size_b = std::mem::size_of::<B>();
+        // Parts pointing outside of referenced value (e.g. into heap memory
+        // it owns) could get freed by writes through the other part.
+        if (a as usize) < base
+            || a as usize + size_a > end
+            || (b as usize) < base
+            || b as usize + size_b > end
+        {
+            return None;
+        }
+        if size_a > 0 && size_b > 0 {
+            let start_a = a as usize;
+            let start_b = b as usize;
+            if start_a < start_b + size_b && start_b < start_a + size_a {
+                return None;
+            }
+        }
+        drop(access);
+        // Write access is released by split parent once both parts are gone.
+        let state = self.lifetime.state().upgrade()?;
+        let mut lock = state.lock();
+        if !lock.state().is_write_accessible() {
+            return None;
+        }
+        lock.acquire_write_access();
+        drop(lock);
+        let parent = Arc::new(ManagedSplitParent(self.lifetime));
+        let split_a = Arc::new(ManagedSplit {
+            lifetime: Lifetime::default(),
+            parent: parent.clone(),
+            owner: self.split.clone(),
+        });
+        let split_b = Arc::new(ManagedSplit {
+            lifetime: Lifetime::default(),
+            parent,
+            owner: self.split,
+        });
+        Some((
+            ManagedRefMut {
+                lifetime: split_a.lifetime.borrow_mut()?,
+                data: a,
+                split: Some(split_a),
+            },
+            ManagedRefMut {
+                lifetime: split_b.lifetime.borrow_mut()?,
+                data: b,
+                split: Some(split_b),
+            },
+        ))
+    }
+
     /// # Safety
+    /// Lifetime is not checked - referenced data has to be alive and returned
+    /// reference has to point within the original allocation.
     pub unsafe fn map<U>(self, f: impl FnOnce(&mut T) -> &mut U) -> ManagedRefMut<U> {
         unsafe {
             let data = f(&mut *self.data);
             ManagedRefMut {
                 lifetime: self.lifetime,
                 data: data as *mut U,
+                split: self.split,
             }
         }
     }
@@ -321,13 +481,14 @@ impl<T: ?Sized> ManagedRefMut<T> {
             f(&mut *self.data).map(|data| ManagedRefMut {
                 lifetime: self.lifetime,
                 data: data as *mut U,
+                split: self.split,
             })
         }
     }
 
     /// # Safety
     pub unsafe fn as_ptr(&self) -> Option<*const T> {
-        if self.lifetime.exists() {
+        if self.lifetime.exists() && ManagedSplit::exists(&self.split) {
             Some(self.data)
         } else {
             None
@@ -336,7 +497,7 @@ impl<T: ?Sized> ManagedRefMut<T> {
 
     /// # Safety
     pub unsafe fn as_mut_ptr(&mut self) -> Option<*mut T> {
-        if self.lifetime.exists() {
+        if self.lifetime.exists() && ManagedSplit::exists(&self.split) {
             Some(self.data)
         } else {
             None
@@ -419,6 +580,7 @@ impl<T: ?Sized> ManagedLazy<T> {
         Some(ManagedRef {
             lifetime: self.lifetime.borrow()?,
             data: self.data,
+            split: None,
         })
     }
 
@@ -426,6 +588,7 @@ impl<T: ?Sized> ManagedLazy<T> {
         Some(ManagedRefMut {
             lifetime: self.lifetime.borrow_mut()?,
             data: self.data,
+            split: None,
         })
     }
 
@@ -437,6 +600,18 @@ impl<T: ?Sized> ManagedLazy<T> {
         unsafe { self.lifetime.write_ptr(self.data) }
     }
 
+    /// Runs closure under single read access, so multiple field reads do not
+    /// acquire access each time.
+    pub fn with_read<R>(&self, f: impl FnOnce(&T) -> R) -> Option<R> {
+        self.read().map(|access| f(&access))
+    }
+
+    /// Runs closure under single write access, so multiple field writes do
+    /// not acquire access each time.
+    pub fn with_write<R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        self.write().map(|mut access| f(&mut access))
+    }
+
     /// # Safety
     pub unsafe fn map<U>(self, f: impl FnOnce(&mut T) -> &mut U) -> ManagedLazy<U> {
         unsafe {
@@ -480,6 +655,22 @@ impl<T: ?Sized> ManagedLazy<T> {
     }
 }
 
+impl<T> ManagedLazy<T> {
+    /// Splits handle into data address and lifetime token, both of which can
+    /// be passed through foreign code as integers.
+    pub fn into_raw(self) -> (usize, LifetimeToken) {
+        (self.data as usize, LifetimeToken::new(self.lifetime))
+    }
+
+    /// # Safety
+    /// `data` must be address of `T` got from `into_raw`, paired with its
+    /// lifetime token. Data validity is then guarded by token lifetime, same
+    /// as with handle before it was split.
+    pub unsafe fn from_raw(data: usize, lifetime: LifetimeToken) -> Option<Self> {
+        Self::new_raw(data as *mut T, lifetime.into_lazy())
+    }
+}
+
 impl<T> TryFrom<ManagedValue<T>> for ManagedLazy<T> {
     type Error = ();
 
@@ -1717,4 +1908,146 @@ mod tests {
             assert_eq!(*value.read::<i32>().unwrap(), 2);
         }
     }
+
+    #[test]
+    fn test_managed_projection() {
+        struct Inner {
+            a: usize,
+        }
+
+        struct Outer {
+            b: Inner,
+        }
+
+        let mut value = Managed::new(Outer { b: Inner { a: 40 } });
+        let field = value
+            .borrow_mut()
+            .unwrap()
+            .project_mut(|outer| &mut outer.b)
+            .unwrap()
+            .project_mut(|inner| &mut inner.a);
+        let mut field = field.unwrap();
+        *field.write().unwrap() += 2;
+        assert!(value.borrow().is_none());
+        drop(field);
+        assert_eq!(value.read().unwrap().b.a, 42);
+        let field = value.borrow().unwrap().project(|outer| &outer.b.a).unwrap();
+        assert_eq!(*field.read().unwrap(), 42);
+        assert!(value.borrow_mut().is_none());
+    }
+
+    #[test]
+    fn test_managed_split() {
+        struct Foo {
+            a: usize,
+            b: bool,
+        }
+
+        let mut value = Managed::new(Foo { a: 40, b: false });
+        let (mut a, mut b) = value
+            .borrow_mut()
+            .unwrap()
+            .split(|foo| &mut foo.a, |foo| &mut foo.b)
+            .unwrap();
+        {
+            let mut access_a = a.write().unwrap();
+            let mut access_b = b.write().unwrap();
+            *access_a += 2;
+            *access_b = true;
+            assert!(value.read().is_none());
+        }
+        assert!(value.borrow().is_none());
+        drop(b);
+        assert!(value.borrow().is_none());
+        drop(a);
+        let foo = value.read().unwrap();
+        assert_eq!(foo.a, 42);
+        assert!(foo.b);
+        drop(foo);
+
+        assert!(value
+            .borrow_mut()
+            .unwrap()
+            .split(|foo| &mut foo.a, |foo| &mut foo.a)
+            .is_none());
+        assert!(value.borrow_mut().is_some());
+
+        let (mut a, b) = value
+            .borrow_mut()
+            .unwrap()
+            .split(|foo| &mut foo.a, |foo| &mut foo.b)
+            .unwrap();
+        let mut c = a.borrow_mut().unwrap();
+        drop(a);
+        *c.write().unwrap() = 0;
+        drop(value);
+        assert!(c.read().is_none());
+        assert!(b.read().is_none());
+
+        let mut value = Managed::new(Box::new([1usize, 2]));
+        assert!(value
+            .borrow_mut()
+            .unwrap()
+            .split(|boxed| &mut boxed[0], |boxed| boxed)
+            .is_none());
+        assert!(value.borrow_mut().is_some());
+    }
+
+    #[test]
+    fn test_lazy_batched_access() {
+        struct Foo {
+            a: usize,
+            b: bool,
+            c: f32,
+        }
+
+        let mut value = Managed::new(Foo {
+            a: 42,
+            b: true,
+            c: 4.2,
+        });
+        let lazy = value.lazy();
+        let a = lazy.read().unwrap().a;
+        let b = lazy.read().unwrap().b;
+        let c = lazy.read().unwrap().c;
+        let result = lazy.with_read(|foo| {
+            assert!(lazy.write().is_none());
+            (foo.a, foo.b, foo.c)
+        });
+        assert_eq!(result, Some((a, b, c)));
+        lazy.with_write(|foo| {
+            foo.a += 1;
+            foo.b = !foo.b;
+            foo.c *= 2.0;
+        })
+        .unwrap();
+        assert_eq!(
+            lazy.with_read(|foo| (foo.a, foo.b, foo.c)),
+            Some((43, false, 8.4))
+        );
+        let access = lazy.write().unwrap();
+        assert!(lazy.with_read(|foo| foo.a).is_none());
+        drop(access);
+        drop(value);
+        assert!(lazy.with_read(|foo| foo.a).is_none());
+    }
+
+    #[test]
+    fn test_managed_lazy_raw() {
+        let mut value = Managed::new(42usize);
+        let (data, token) = value.lazy().into_raw();
+        let token = token.into_opaque();
+        let lazy = unsafe {
+            ManagedLazy::<usize>::from_raw(data, LifetimeToken::from_opaque(token)).unwrap()
+        };
+        assert_eq!(*lazy.read().unwrap(), 42);
+        *lazy.write().unwrap() = 7;
+        assert_eq!(*value.read().unwrap(), 7);
+        drop(value);
+        assert!(lazy.read().is_none());
+        assert!(unsafe {
+            ManagedLazy::<usize>::from_raw(0, LifetimeToken::new(lazy.lifetime().clone()))
+        }
+        .is_none());
+    }
 }
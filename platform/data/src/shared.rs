@@ -1,12 +1,95 @@
+#[cfg(debug_assertions)]
+use std::cell::Cell;
 use std::{
     cell::{Ref, RefCell, RefMut},
+    panic::Location,
     rc::Rc,
     sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
 };
 
+/// Borrow locations are recorded only in builds with debug assertions,
+/// otherwise they are always `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SharedBorrowError {
+    /// Value is currently borrowed for write, by last recorded writer.
+    BeingWritten {
+        writer: Option<&'static Location<'static>>,
+    },
+    /// Value is currently borrowed for read. Only the latest reader gets
+    /// recorded, so with many readers alive it might not be the one that
+    /// still holds the borrow.
+    BeingRead {
+        reader: Option<&'static Location<'static>>,
+    },
+}
+
+impl std::fmt::Display for SharedBorrowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BeingWritten {
+                writer: Some(location),
+            } => write!(f, "Value is being written at: {}", location),
+            Self::BeingWritten { writer: None } => write!(f, "Value is being written"),
+            Self::BeingRead {
+                reader: Some(location),
+            } => write!(f, "Value is being read, last read at: {}", location),
+            Self::BeingRead { reader: None } => write!(f, "Value is being read"),
+        }
+    }
+}
+
+impl std::error::Error for SharedBorrowError {}
+
+#[derive(Default)]
+struct SharedInner<T> {
+    value: RefCell<T>,
+    #[cfg(debug_assertions)]
+    last_reader: Cell<Option<&'static Location<'static>>>,
+    #[cfg(debug_assertions)]
+    last_writer: Cell<Option<&'static Location<'static>>>,
+}
+
+impl<T> SharedInner<T> {
+    fn new(value: T) -> Self {
+        Self {
+            value: RefCell::new(value),
+            #[cfg(debug_assertions)]
+            last_reader: Default::default(),
+            #[cfg(debug_assertions)]
+            last_writer: Default::default(),
+        }
+    }
+
+    #[allow(unused_variables)]
+    fn record_reader(&self, location: &'static Location<'static>) {
+        #[cfg(debug_assertions)]
+        self.last_reader.set(Some(location));
+    }
+
+    #[allow(unused_variables)]
+    fn record_writer(&self, location: &'static Location<'static>) {
+        #[cfg(debug_assertions)]
+        self.last_writer.set(Some(location));
+    }
+
+    fn last_reader(&self) -> Option<&'static Location<'static>> {
+        #[cfg(debug_assertions)]
+        return self.last_reader.get();
+        #[cfg(not(debug_assertions))]
+        None
+    }
+
+    fn last_writer(&self) -> Option<&'static Location<'static>> {
+        #[cfg(debug_assertions)]
+        return self.last_writer.get();
+        #[cfg(not(debug_assertions))]
+        None
+    }
+}
+
 #[derive(Default)]
 pub struct Shared<T> {
-    data: Rc<RefCell<T>>,
+    data: Rc<SharedInner<T>>,
 }
 
 impl<T> Clone for Shared<T> {
@@ -20,27 +103,67 @@ impl<T> Clone for Shared<T> {
 impl<T> Shared<T> {
     pub fn new(data: T) -> Self {
         Self {
-            data: Rc::new(RefCell::new(data)),
+            data: Rc::new(SharedInner::new(data)),
         }
     }
 
     pub fn try_consume(self) -> Result<T, Self> {
         match Rc::try_unwrap(self.data) {
-            Ok(data) => Ok(data.into_inner()),
+            Ok(data) => Ok(data.value.into_inner()),
             Err(data) => Err(Self { data }),
         }
     }
 
+    #[track_caller]
     pub fn read(&self) -> Option<Ref<T>> {
-        self.data.try_borrow().ok()
+        self.try_read().ok()
     }
 
+    #[track_caller]
     pub fn write(&self) -> Option<RefMut<T>> {
-        self.data.try_borrow_mut().ok()
+        self.try_write().ok()
+    }
+
+    /// Same as `read`, but on borrow conflict reports where conflicting
+    /// write borrow was made.
+    #[track_caller]
+    pub fn try_read(&self) -> Result<Ref<'_, T>, SharedBorrowError> {
+        match self.data.value.try_borrow() {
+            Ok(result) => {
+                self.data.record_reader(Location::caller());
+                Ok(result)
+            }
+            Err(_) => Err(SharedBorrowError::BeingWritten {
+                writer: self.data.last_writer(),
+            }),
+        }
+    }
+
+    /// Same as `write`, but on borrow conflict reports where conflicting
+    /// borrow was made.
+    #[track_caller]
+    pub fn try_write(&self) -> Result<RefMut<'_, T>, SharedBorrowError> {
+        match self.data.value.try_borrow_mut() {
+            Ok(result) => {
+                self.data.record_writer(Location::caller());
+                Ok(result)
+            }
+            Err(_) => {
+                if self.data.value.try_borrow().is_err() {
+                    Err(SharedBorrowError::BeingWritten {
+                        writer: self.data.last_writer(),
+                    })
+                } else {
+                    Err(SharedBorrowError::BeingRead {
+                        reader: self.data.last_reader(),
+                    })
+                }
+            }
+        }
     }
 
     pub fn swap(&self, data: T) -> Option<T> {
-        let mut value = self.data.try_borrow_mut().ok()?;
+        let mut value = self.data.value.try_borrow_mut().ok()?;
         Some(std::mem::replace(&mut value, data))
     }
 
@@ -104,7 +227,7 @@ impl<T> AsyncShared<T> {
 
 #[cfg(test)]
 mod tests {
-    use super::Shared;
+    use super::{Shared, SharedBorrowError};
 
     #[test]
     fn test_shared() {
@@ -121,4 +244,30 @@ mod tests {
         assert!(b.try_consume().is_err());
         assert_eq!(a.try_consume().ok().unwrap(), 10);
     }
+
+    #[test]
+    fn test_shared_borrow_conflict() {
+        let a = Shared::new(42);
+        let guard = a.write().unwrap();
+        assert!(a.read().is_none());
+        match a.try_read() {
+            Err(SharedBorrowError::BeingWritten { writer }) => {
+                if cfg!(debug_assertions) {
+                    assert_eq!(writer.unwrap().file(), file!());
+                } else {
+                    assert!(writer.is_none());
+                }
+            }
+            _ => panic!("Expected borrow conflict error!"),
+        }
+        drop(guard);
+        let guard = a.read().unwrap();
+        assert!(matches!(
+            a.try_write(),
+            Err(SharedBorrowError::BeingRead { reader })
+                if reader.is_some() == cfg!(debug_assertions)
+        ));
+        drop(guard);
+        assert!(a.try_write().is_ok());
+    }
 }
@@ -358,10 +358,11 @@ pub fn intuicio_enum(input: TokenStream) -> TokenStream {
                             if ignore {
                                 return None;
                             }
+                            let field_name = Index::from(index);
                             let name = if let Some(name) = name {
                                 quote! { stringify!(#name) }
                             } else {
-                                quote! { stringify!(#index) }
+                                quote! { stringify!(#field_name) }
                             };
                             let field_type = &field.ty;
                             let meta = if let Some(meta) = meta {
@@ -369,7 +370,6 @@ pub fn intuicio_enum(input: TokenStream) -> TokenStream {
                             } else {
                                 quote! {}
                             };
-                            let field_name = Index::from(index);
                             Some(quote! {
                                 let mut field = intuicio_core::types::struct_type::StructField::new(
                                     #name,
@@ -5,7 +5,7 @@ use serde_intermediate::{
     de::intermediate::DeserializeMode, error::Result as IntermediateResult, Intermediate,
 };
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{HashMap, HashSet, VecDeque},
     error::Error,
     fmt::Display,
     hash::{Hash, Hasher},
@@ -74,6 +74,21 @@ pub trait NodeDefinition: Sized {
         Ok(())
     }
 
+    /// Infers type of output pin from types inferred for this node input pins.
+    /// By default it is type declared by output parameter pin.
+    #[allow(unused_variables)]
+    fn infer_pin_out_type(
+        &self,
+        name: &str,
+        inputs: &HashMap<String, Self::TypeInfo>,
+        registry: &Registry,
+    ) -> Option<Self::TypeInfo> {
+        self.node_pins_out(registry)
+            .into_iter()
+            .find(|pin| pin.name() == name)
+            .and_then(|pin| pin.type_info().cloned())
+    }
+
     #[allow(unused_variables)]
     fn get_property(&self, name: &str) -> Option<PropertyValue> {
         None
@@ -81,6 +96,18 @@ pub trait NodeDefinition: Sized {
 
     #[allow(unused_variables)]
     fn set_property(&mut self, name: &str, value: PropertyValue) {}
+
+    /// Name of template this node instantiates. Such nodes get replaced by
+    /// template subgraph when templates are expanded.
+    fn node_template(&self) -> Option<&str> {
+        None
+    }
+
+    /// Called on every node copied out of template. `prefix` is unique per
+    /// template instance, so nodes can rename registers or variables they
+    /// declare to not collide with other instances.
+    #[allow(unused_variables)]
+    fn rename_for_template(&mut self, prefix: &str) {}
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -240,6 +267,19 @@ impl<T: NodeDefinition> Hash for NodeConnection<T> {
     }
 }
 
+/// Types inferred for input pins, keyed by node and pin name.
+pub type InferredPinTypes<T> =
+    HashMap<(NodeId<T>, String), InferredPinType<<T as NodeDefinition>::TypeInfo>>;
+
+/// Type flowing into input pin from connected output pin.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InferredPinType<TI: NodeTypeInfo> {
+    pub type_info: TI,
+    /// Tells if inferred type is compatible with type declared by input pin.
+    /// Property pins do not declare type so they accept any.
+    pub compatible: bool,
+}
+
 #[derive(Debug)]
 pub enum ConnectionError {
     InternalConnection(String),
@@ -328,14 +368,19 @@ impl Error for ConnectionError {}
 
 #[derive(Debug)]
 pub enum NodeGraphError {
+    NodeNotFound(String),
     Connection(ConnectionError),
     DuplicateFunctionInputNames(String),
     DuplicateFunctionOutputNames(String),
+    TemplateNotFound(String),
+    RecursiveTemplate(String),
+    TemplatePinNotFound(String, String),
 }
 
 impl std::fmt::Display for NodeGraphError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            Self::NodeNotFound(id) => write!(f, "Node not found: {}", id),
             Self::Connection(connection) => connection.fmt(f),
             Self::DuplicateFunctionInputNames(name) => {
                 write!(
@@ -351,6 +396,13 @@ impl std::fmt::Display for NodeGraphError {
                     name
                 )
             }
+            Self::TemplateNotFound(name) => write!(f, "Template not found: {}", name),
+            Self::RecursiveTemplate(name) => {
+                write!(f, "Template `{}` instantiates itself", name)
+            }
+            Self::TemplatePinNotFound(name, pin) => {
+                write!(f, "Template `{}` has no `{}` pin", name, pin)
+            }
         }
     }
 }
@@ -383,6 +435,210 @@ impl<T: NodeDefinition> PointDistance for SpatialNode<T> {
     }
 }
 
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SubgraphClipboard<T: NodeDefinition> {
+    nodes: Vec<Node<T>>,
+    connections: Vec<NodeConnection<T>>,
+}
+
+impl<T: NodeDefinition> Default for SubgraphClipboard<T> {
+    fn default() -> Self {
+        Self {
+            nodes: vec![],
+            connections: vec![],
+        }
+    }
+}
+
+impl<T: NodeDefinition> SubgraphClipboard<T> {
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = &Node<T>> {
+        self.nodes.iter()
+    }
+
+    pub fn connections(&self) -> impl Iterator<Item = &NodeConnection<T>> {
+        self.connections.iter()
+    }
+}
+
+impl<T: NodeDefinition + std::fmt::Debug> std::fmt::Debug for SubgraphClipboard<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SubgraphClipboard")
+            .field("nodes", &self.nodes)
+            .field("connections", &self.connections)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeLayoutOptions {
+    pub horizontal_spacing: i64,
+    pub vertical_spacing: i64,
+}
+
+impl Default for NodeLayoutOptions {
+    fn default() -> Self {
+        Self {
+            horizontal_spacing: 250,
+            vertical_spacing: 150,
+        }
+    }
+}
+
+/// Change set between two versions of same graph, with nodes matched by ids.
+#[derive(Clone)]
+pub struct GraphPatch<T: NodeDefinition> {
+    pub added_nodes: Vec<Node<T>>,
+    pub removed_nodes: Vec<NodeId<T>>,
+    pub moved_nodes: Vec<(NodeId<T>, i64, i64)>,
+    pub changed_nodes: Vec<(NodeId<T>, T)>,
+    pub added_connections: Vec<NodeConnection<T>>,
+    pub removed_connections: Vec<NodeConnection<T>>,
+}
+
+impl<T: NodeDefinition> Default for GraphPatch<T> {
+    fn default() -> Self {
+        Self {
+            added_nodes: vec![],
+            removed_nodes: vec![],
+            moved_nodes: vec![],
+            changed_nodes: vec![],
+            added_connections: vec![],
+            removed_connections: vec![],
+        }
+    }
+}
+
+impl<T: NodeDefinition> GraphPatch<T> {
+    pub fn is_empty(&self) -> bool {
+        self.added_nodes.is_empty()
+            && self.removed_nodes.is_empty()
+            && self.moved_nodes.is_empty()
+            && self.changed_nodes.is_empty()
+            && self.added_connections.is_empty()
+            && self.removed_connections.is_empty()
+    }
+}
+
+impl<T: NodeDefinition + std::fmt::Debug> std::fmt::Debug for GraphPatch<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GraphPatch")
+            .field("added_nodes", &self.added_nodes)
+            .field("removed_nodes", &self.removed_nodes)
+            .field("moved_nodes", &self.moved_nodes)
+            .field("changed_nodes", &self.changed_nodes)
+            .field("added_connections", &self.added_connections)
+            .field("removed_connections", &self.removed_connections)
+            .finish()
+    }
+}
+
+/// Named subgraph that can be instantiated as single node. Instance node
+/// pins are mapped into pins of template nodes - one input pin can feed many
+/// template nodes, while output pin comes from exactly one.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "NodeGraph<T>: Serialize",
+    deserialize = "NodeGraph<T>: Deserialize<'de>"
+))]
+pub struct NodeTemplate<T: NodeDefinition> {
+    pub graph: NodeGraph<T>,
+    pub inputs: HashMap<String, Vec<(NodeId<T>, String)>>,
+    pub outputs: HashMap<String, (NodeId<T>, String)>,
+}
+
+impl<T: NodeDefinition> Default for NodeTemplate<T> {
+    fn default() -> Self {
+        Self {
+            graph: Default::default(),
+            inputs: Default::default(),
+            outputs: Default::default(),
+        }
+    }
+}
+
+impl<T: NodeDefinition> NodeTemplate<T> {
+    pub fn new(graph: NodeGraph<T>) -> Self {
+        Self {
+            graph,
+            inputs: Default::default(),
+            outputs: Default::default(),
+        }
+    }
+
+    pub fn with_input(mut self, name: impl ToString, node: NodeId<T>, pin: impl ToString) -> Self {
+        self.inputs
+            .entry(name.to_string())
+            .or_default()
+            .push((node, pin.to_string()));
+        self
+    }
+
+    pub fn with_output(mut self, name: impl ToString, node: NodeId<T>, pin: impl ToString) -> Self {
+        self.outputs
+            .insert(name.to_string(), (node, pin.to_string()));
+        self
+    }
+}
+
+impl<T: NodeDefinition + std::fmt::Debug> std::fmt::Debug for NodeTemplate<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NodeTemplate")
+            .field("graph", &self.graph)
+            .field("inputs", &self.inputs)
+            .field("outputs", &self.outputs)
+            .finish()
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "NodeGraph<T>: Serialize",
+    deserialize = "NodeGraph<T>: Deserialize<'de>"
+))]
+pub struct NodeTemplates<T: NodeDefinition> {
+    templates: HashMap<String, NodeTemplate<T>>,
+}
+
+impl<T: NodeDefinition> Default for NodeTemplates<T> {
+    fn default() -> Self {
+        Self {
+            templates: Default::default(),
+        }
+    }
+}
+
+impl<T: NodeDefinition> NodeTemplates<T> {
+    pub fn add(&mut self, name: impl ToString, template: NodeTemplate<T>) {
+        self.templates.insert(name.to_string(), template);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<NodeTemplate<T>> {
+        self.templates.remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&NodeTemplate<T>> {
+        self.templates.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &NodeTemplate<T>)> {
+        self.templates
+            .iter()
+            .map(|(name, template)| (name.as_str(), template))
+    }
+}
+
+impl<T: NodeDefinition + std::fmt::Debug> std::fmt::Debug for NodeTemplates<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NodeTemplates")
+            .field("templates", &self.templates)
+            .finish()
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct NodeGraph<T: NodeDefinition> {
     nodes: Vec<Node<T>>,
@@ -645,6 +901,456 @@ impl<T: NodeDefinition> NodeGraph<T> {
             .map(move |connection| connection.to_node)
     }
 
+    pub fn extract_subgraph(&self, node_ids: &[NodeId<T>]) -> SubgraphClipboard<T>
+    where
+        T: Clone,
+    {
+        let nodes = self
+            .nodes
+            .iter()
+            .filter(|node| node_ids.contains(&node.id))
+            .cloned()
+            .collect();
+        let connections = self
+            .connections
+            .iter()
+            .filter(|connection| {
+                node_ids.contains(&connection.from_node) && node_ids.contains(&connection.to_node)
+            })
+            .cloned()
+            .collect();
+        SubgraphClipboard { nodes, connections }
+    }
+
+    pub fn paste_subgraph(
+        &mut self,
+        clipboard: &SubgraphClipboard<T>,
+        offset_x: i64,
+        offset_y: i64,
+        registry: &Registry,
+    ) -> Vec<NodeId<T>>
+    where
+        T: Clone,
+    {
+        let mut remap = HashMap::with_capacity(clipboard.nodes.len());
+        let mut result = Vec::with_capacity(clipboard.nodes.len());
+        for node in &clipboard.nodes {
+            let pasted = Node::new(node.x + offset_x, node.y + offset_y, node.data.clone());
+            if let Some(id) = self.add_node(pasted, registry) {
+                remap.insert(node.id, id);
+                result.push(id);
+            }
+        }
+        for connection in &clipboard.connections {
+            if let (Some(from_node), Some(to_node)) = (
+                remap.get(&connection.from_node),
+                remap.get(&connection.to_node),
+            ) {
+                self.connect_nodes(NodeConnection::new(
+                    *from_node,
+                    *to_node,
+                    &connection.from_pin,
+                    &connection.to_pin,
+                ));
+            }
+        }
+        result
+    }
+
+    /// Returns copy of this graph with every template instance node replaced
+    /// by nodes of its template, including templates instantiated within
+    /// templates. Copied nodes get fresh ids and are renamed with prefix
+    /// unique per instance, so instances never clash with each other. Start
+    /// nodes of templates are skipped.
+    #[allow(clippy::result_large_err)]
+    pub fn expand_templates(
+        &self,
+        templates: &NodeTemplates<T>,
+        registry: &Registry,
+    ) -> Result<Self, NodeGraphError>
+    where
+        T: Clone,
+    {
+        let mut result = Self {
+            nodes: self.nodes.clone(),
+            connections: self.connections.clone(),
+            rtree: Default::default(),
+        };
+        let mut queue = self
+            .nodes
+            .iter()
+            .filter(|node| node.data.node_template().is_some())
+            .map(|node| (node.id, vec![]))
+            .collect::<VecDeque<_>>();
+        let mut instances = 0;
+        while let Some((id, mut ancestors)) = queue.pop_front() {
+            let index = result
+                .nodes
+                .iter()
+                .position(|node| node.id == id)
+                .ok_or_else(|| NodeGraphError::NodeNotFound(id.to_string()))?;
+            let instance = result.nodes.swap_remove(index);
+            let name = instance.data.node_template().unwrap_or_default().to_owned();
+            if ancestors.contains(&name) {
+                return Err(NodeGraphError::RecursiveTemplate(name));
+            }
+            let template = templates
+                .get(&name)
+                .ok_or_else(|| NodeGraphError::TemplateNotFound(name.to_owned()))?;
+            let prefix = format!("{}#{}/", name, instances);
+            instances += 1;
+            ancestors.push(name.to_owned());
+            let mut remap = HashMap::with_capacity(template.graph.nodes.len());
+            for node in &template.graph.nodes {
+                if node.data.node_is_start(registry) {
+                    continue;
+                }
+                let mut data = node.data.clone();
+                data.rename_for_template(&prefix);
+                let copy = Node::new(instance.x + node.x, instance.y + node.y, data);
+                remap.insert(node.id, copy.id);
+                if copy.data.node_template().is_some() {
+                    queue.push_back((copy.id, ancestors.clone()));
+                }
+                result.nodes.push(copy);
+            }
+            let map_node = |id: &NodeId<T>| {
+                remap
+                    .get(id)
+                    .copied()
+                    .ok_or_else(|| NodeGraphError::NodeNotFound(id.to_string()))
+            };
+            let mut connections = Vec::with_capacity(result.connections.len());
+            for connection in std::mem::take(&mut result.connections) {
+                if connection.from_node != id && connection.to_node != id {
+                    connections.push(connection);
+                    continue;
+                }
+                let from = if connection.from_node == id {
+                    let (node, pin) =
+                        template.outputs.get(&connection.from_pin).ok_or_else(|| {
+                            NodeGraphError::TemplatePinNotFound(
+                                name.to_owned(),
+                                connection.from_pin.to_owned(),
+                            )
+                        })?;
+                    (map_node(node)?, pin.as_str())
+                } else {
+                    (connection.from_node, connection.from_pin.as_str())
+                };
+                if connection.to_node == id {
+                    let targets = template.inputs.get(&connection.to_pin).ok_or_else(|| {
+                        NodeGraphError::TemplatePinNotFound(
+                            name.to_owned(),
+                            connection.to_pin.to_owned(),
+                        )
+                    })?;
+                    for (node, pin) in targets {
+                        connections.push(NodeConnection::new(from.0, map_node(node)?, from.1, pin));
+                    }
+                } else {
+                    connections.push(NodeConnection::new(
+                        from.0,
+                        connection.to_node,
+                        from.1,
+                        &connection.to_pin,
+                    ));
+                }
+            }
+            connections.extend(template.graph.connections.iter().filter_map(|connection| {
+                Some(NodeConnection::new(
+                    *remap.get(&connection.from_node)?,
+                    *remap.get(&connection.to_node)?,
+                    &connection.from_pin,
+                    &connection.to_pin,
+                ))
+            }));
+            result.connections = connections;
+        }
+        Ok(result)
+    }
+
+    /// Computes patch turning `old` graph into `new` one. Diff with swapped
+    /// arguments gives patch that reverts it, which makes it usable for undo.
+    pub fn diff(old: &Self, new: &Self) -> GraphPatch<T>
+    where
+        T: Clone + PartialEq,
+    {
+        let mut result = GraphPatch::default();
+        for node in &new.nodes {
+            let Some(old_node) = old.node(node.id) else {
+                result.added_nodes.push(node.clone());
+                continue;
+            };
+            if old_node.x != node.x || old_node.y != node.y {
+                result.moved_nodes.push((node.id, node.x, node.y));
+            }
+            if old_node.data != node.data {
+                result.changed_nodes.push((node.id, node.data.clone()));
+            }
+        }
+        result.removed_nodes = old
+            .nodes
+            .iter()
+            .filter(|node| new.node(node.id).is_none())
+            .map(|node| node.id)
+            .collect();
+        let old_connections = old.connections.iter().collect::<HashSet<_>>();
+        let new_connections = new.connections.iter().collect::<HashSet<_>>();
+        result.added_connections = new
+            .connections
+            .iter()
+            .filter(|connection| !old_connections.contains(connection))
+            .cloned()
+            .collect();
+        result.removed_connections = old
+            .connections
+            .iter()
+            .filter(|connection| !new_connections.contains(connection))
+            .cloned()
+            .collect();
+        result
+    }
+
+    /// Replays patch as is - unlike regular editing methods, it does not
+    /// protect start nodes nor disconnect pins of added connections, so graph
+    /// ends up exactly in state patch was computed against.
+    pub fn apply(&mut self, patch: &GraphPatch<T>)
+    where
+        T: Clone,
+    {
+        for connection in &patch.removed_connections {
+            self.disconnect_nodes(
+                connection.from_node,
+                connection.to_node,
+                &connection.from_pin,
+                &connection.to_pin,
+            );
+        }
+        for id in &patch.removed_nodes {
+            if let Some(index) = self.nodes.iter().position(|node| node.id == *id) {
+                self.disconnect_node(*id, None);
+                self.nodes.swap_remove(index);
+            }
+        }
+        for node in &patch.added_nodes {
+            if let Some(index) = self.nodes.iter().position(|other| other.id == node.id) {
+                self.nodes.swap_remove(index);
+            }
+            self.nodes.push(node.clone());
+        }
+        for (id, x, y) in &patch.moved_nodes {
+            if let Some(node) = self.node_mut(*id) {
+                node.x = *x;
+                node.y = *y;
+            }
+        }
+        for (id, data) in &patch.changed_nodes {
+            if let Some(node) = self.node_mut(*id) {
+                node.data = data.clone();
+            }
+        }
+        for connection in &patch.added_connections {
+            if !self.connections.contains(connection) {
+                self.connections.push(connection.clone());
+            }
+        }
+        self.refresh_spatial_cache();
+    }
+
+    /// Places nodes in columns following connections, so every node lands to
+    /// the right of nodes connected to its inputs and start nodes are placed
+    /// in leftmost column. Only positions of nodes are changed.
+    pub fn auto_layout(&mut self, options: NodeLayoutOptions, registry: &Registry) {
+        let count = self.nodes.len();
+        let indices = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (node.id, index))
+            .collect::<HashMap<_, _>>();
+        let mut outputs = vec![vec![]; count];
+        let mut inputs = vec![vec![]; count];
+        for connection in &self.connections {
+            if let (Some(&from), Some(&to)) = (
+                indices.get(&connection.from_node),
+                indices.get(&connection.to_node),
+            ) {
+                if from != to && !outputs[from].contains(&to) {
+                    outputs[from].push(to);
+                    inputs[to].push(from);
+                }
+            }
+        }
+        // longest path from sources, in topological order.
+        let mut layers = vec![0usize; count];
+        let mut remaining = inputs.iter().map(|inputs| inputs.len()).collect::<Vec<_>>();
+        let mut queue = (0..count)
+            .filter(|index| remaining[*index] == 0)
+            .collect::<VecDeque<_>>();
+        let mut order = Vec::with_capacity(count);
+        while let Some(index) = queue.pop_front() {
+            order.push(index);
+            for &to in &outputs[index] {
+                layers[to] = layers[to].max(layers[index] + 1);
+                remaining[to] -= 1;
+                if remaining[to] == 0 {
+                    queue.push_back(to);
+                }
+            }
+        }
+        // nodes stuck on cycles go after everything else.
+        if order.len() < count {
+            let last = layers.iter().copied().max().unwrap_or_default() + 1;
+            for index in 0..count {
+                if remaining[index] > 0 {
+                    layers[index] = last;
+                }
+            }
+        }
+        // pull data sources right next to nodes they feed.
+        for &index in order.iter().rev() {
+            if self.nodes[index].data.node_is_start(registry) {
+                continue;
+            }
+            if let Some(layer) = outputs[index].iter().map(|to| layers[*to]).min() {
+                layers[index] = layers[index].max(layer.saturating_sub(1));
+            }
+        }
+        let mut columns = vec![vec![]; layers.iter().copied().max().map_or(0, |max| max + 1)];
+        for (index, layer) in layers.iter().enumerate() {
+            columns[*layer].push(index);
+        }
+        // order nodes in column by average row of their inputs.
+        let mut rows = vec![None; count];
+        for column in &mut columns {
+            let keys = column
+                .iter()
+                .map(|index| {
+                    let placed = inputs[*index]
+                        .iter()
+                        .filter_map(|from| rows[*from])
+                        .collect::<Vec<usize>>();
+                    if placed.is_empty() {
+                        (false, 0.0)
+                    } else {
+                        let sum = placed.iter().sum::<usize>() as f64;
+                        (true, sum / placed.len() as f64)
+                    }
+                })
+                .collect::<Vec<_>>();
+            let mut order = (0..column.len()).collect::<Vec<_>>();
+            order.sort_by(|a, b| {
+                keys[*a]
+                    .partial_cmp(&keys[*b])
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            *column = order.into_iter().map(|index| column[index]).collect();
+            for (row, index) in column.iter().enumerate() {
+                rows[*index] = Some(row);
+            }
+        }
+        for (index, node) in self.nodes.iter_mut().enumerate() {
+            node.x = layers[index] as i64 * options.horizontal_spacing;
+            node.y = rows[index].unwrap_or_default() as i64 * options.vertical_spacing;
+        }
+        self.refresh_spatial_cache();
+    }
+
+    /// Propagates types from output pins to connected input pins in order of
+    /// data flow, so nodes can infer their output types from inferred inputs.
+    /// Connections to missing nodes or pins and pins of unknown type are skipped.
+    pub fn infer_types(&self, registry: &Registry) -> InferredPinTypes<T> {
+        let count = self.nodes.len();
+        let indices = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (node.id, index))
+            .collect::<HashMap<_, _>>();
+        let mut outputs = vec![vec![]; count];
+        let mut connections_out = vec![vec![]; count];
+        let mut remaining = vec![0usize; count];
+        for connection in &self.connections {
+            if let (Some(&from), Some(&to)) = (
+                indices.get(&connection.from_node),
+                indices.get(&connection.to_node),
+            ) {
+                connections_out[from].push((connection, to));
+                if from != to && !outputs[from].contains(&to) {
+                    outputs[from].push(to);
+                    remaining[to] += 1;
+                }
+            }
+        }
+        let mut queue = (0..count)
+            .filter(|index| remaining[*index] == 0)
+            .collect::<VecDeque<_>>();
+        let mut order = Vec::with_capacity(count);
+        while let Some(index) = queue.pop_front() {
+            order.push(index);
+            for &to in &outputs[index] {
+                remaining[to] -= 1;
+                if remaining[to] == 0 {
+                    queue.push_back(to);
+                }
+            }
+        }
+        // nodes stuck on cycles get only what was inferred before them.
+        order.extend((0..count).filter(|index| remaining[*index] > 0));
+        // inferred types indexed by target node, so node finds its inputs
+        // without scanning everything inferred so far.
+        let mut inferred = vec![HashMap::<String, InferredPinType<T::TypeInfo>>::new(); count];
+        for index in order {
+            let node = &self.nodes[index];
+            let inputs = inferred[index]
+                .iter()
+                .map(|(pin, inferred)| (pin.to_owned(), inferred.type_info.clone()))
+                .collect::<HashMap<_, _>>();
+            let mut pin_types = HashMap::<&str, Option<T::TypeInfo>>::new();
+            for &(connection, to) in &connections_out[index] {
+                let type_info = pin_types
+                    .entry(connection.from_pin.as_str())
+                    .or_insert_with(|| {
+                        node.data
+                            .infer_pin_out_type(&connection.from_pin, &inputs, registry)
+                    });
+                let Some(type_info) = type_info else {
+                    continue;
+                };
+                let compatible = match self.nodes[to]
+                    .data
+                    .node_pins_in(registry)
+                    .into_iter()
+                    .find(|pin| pin.name() == connection.to_pin)
+                {
+                    Some(NodePin::Parameter {
+                        type_info: declared,
+                        ..
+                    }) => type_info.are_compatible(&declared),
+                    Some(NodePin::Property { .. }) => true,
+                    _ => continue,
+                };
+                inferred[to].insert(
+                    connection.to_pin.to_owned(),
+                    InferredPinType {
+                        type_info: type_info.clone(),
+                        compatible,
+                    },
+                );
+            }
+        }
+        inferred
+            .into_iter()
+            .enumerate()
+            .flat_map(|(index, pins)| {
+                let id = self.nodes[index].id;
+                pins.into_iter()
+                    .map(move |(pin, inferred)| ((id, pin), inferred))
+            })
+            .collect()
+    }
+
     pub fn validate(&self, registry: &Registry) -> Result<(), Vec<NodeGraphError>> {
         let mut errors = self
             .connections
@@ -662,6 +1368,42 @@ impl<T: NodeDefinition> NodeGraph<T> {
         }
     }
 
+    /// Validates only connections of given node and cycles between it and
+    /// its direct neighbors. Longer cycles are detected only by `validate`.
+    pub fn validate_node(
+        &self,
+        id: NodeId<T>,
+        registry: &Registry,
+    ) -> Result<(), Vec<NodeGraphError>> {
+        if !self.nodes.iter().any(|node| node.id == id) {
+            return Err(vec![NodeGraphError::NodeNotFound(id.to_string())]);
+        }
+        let mut errors = self
+            .connections
+            .iter()
+            .filter(|connection| connection.from_node == id || connection.to_node == id)
+            .filter_map(|connection| self.validate_connection(connection, registry))
+            .map(NodeGraphError::Connection)
+            .collect::<Vec<_>>();
+        if self
+            .node_neighbors_out(id, None)
+            .filter(|neighbor| *neighbor != id)
+            .any(|neighbor| {
+                self.node_neighbors_out(neighbor, None)
+                    .any(|other| other == id)
+            })
+        {
+            errors.push(NodeGraphError::Connection(ConnectionError::CycleNodeFound(
+                id.to_string(),
+            )));
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
     fn validate_connection(
         &self,
         connection: &NodeConnection<T>,
@@ -754,42 +1496,42 @@ impl<T: NodeDefinition> NodeGraph<T> {
 
     fn detect_cycles(&self) -> Option<ConnectionError> {
         let mut visited = HashSet::with_capacity(self.nodes.len());
-        let mut available = self.nodes.iter().map(|node| node.id).collect::<Vec<_>>();
-        while let Some(id) = available.first() {
-            if let Some(error) = self.detect_cycle(*id, &mut available, &mut visited) {
-                return Some(error);
-            }
-            available.swap_remove(0);
-        }
-        None
+        let mut path = HashSet::with_capacity(self.nodes.len());
+        self.nodes
+            .iter()
+            .find_map(|node| self.detect_cycle(node.id, &mut visited, &mut path))
     }
 
     fn detect_cycle(
         &self,
         id: NodeId<T>,
-        available: &mut Vec<NodeId<T>>,
         visited: &mut HashSet<NodeId<T>>,
+        path: &mut HashSet<NodeId<T>>,
     ) -> Option<ConnectionError> {
-        if visited.contains(&id) {
+        if path.contains(&id) {
             return Some(ConnectionError::CycleNodeFound(id.to_string()));
         }
-        visited.insert(id);
-        for id in self.node_neighbors_out(id, None) {
-            if let Some(index) = available.iter().position(|item| item == &id) {
-                available.swap_remove(index);
-                if let Some(error) = self.detect_cycle(id, available, visited) {
-                    return Some(error);
-                }
+        if !visited.insert(id) {
+            return None;
+        }
+        path.insert(id);
+        for next in self.node_neighbors_out(id, None) {
+            if let Some(error) = self.detect_cycle(next, visited, path) {
+                return Some(error);
             }
         }
+        path.remove(&id);
         None
     }
 
+    /// Fails with `ConnectionError::CycleNodeFound` when it reaches node
+    /// that is already being visited, so graphs that did not pass `validate`
+    /// cannot make it recurse forever.
     pub fn visit<V: NodeGraphVisitor<T>>(
         &self,
         visitor: &mut V,
         registry: &Registry,
-    ) -> Vec<V::Output> {
+    ) -> Result<Vec<V::Output>, Box<ConnectionError>> {
         let starts = self
             .nodes
             .iter()
@@ -797,59 +1539,85 @@ impl<T: NodeDefinition> NodeGraph<T> {
             .map(|node| node.id)
             .collect::<HashSet<_>>();
         let mut result = Vec::with_capacity(self.nodes.len());
+        let mut path = HashSet::with_capacity(self.nodes.len());
         for id in starts {
-            self.visit_statement(id, &mut result, visitor, registry);
+            self.visit_statement(id, &mut result, visitor, registry, &mut path)?;
         }
-        result
+        Ok(result)
     }
 
-    fn visit_statement<V: NodeGraphVisitor<T>>(
+    /// Expands template instance nodes and visits resulting graph.
+    #[allow(clippy::result_large_err)]
+    pub fn visit_with_templates<V: NodeGraphVisitor<T>>(
         &self,
-        id: NodeId<T>,
-        result: &mut Vec<V::Output>,
         visitor: &mut V,
+        templates: &NodeTemplates<T>,
         registry: &Registry,
-    ) {
-        if let Some(node) = self.node(id) {
-            let inputs = node
-                .data
-                .node_pins_in(registry)
-                .into_iter()
-                .filter(|pin| pin.is_parameter())
-                .filter_map(|pin| {
-                    self.node_neighbors_in(id, Some(pin.name()))
-                        .next()
-                        .map(|id| (pin.name().to_owned(), id))
-                })
-                .filter_map(|(name, id)| {
-                    self.visit_expression(id, visitor, registry)
-                        .map(|input| (name, input))
-                })
-                .collect();
-            let pins_out = node.data.node_pins_out(registry);
-            let scopes = pins_out
-                .iter()
-                .filter(|pin| pin.has_subscope())
-                .filter_map(|pin| {
-                    let id = self.node_neighbors_out(id, Some(pin.name())).next()?;
-                    Some((id, pin.name().to_owned()))
-                })
-                .map(|(id, name)| {
-                    let mut result = Vec::with_capacity(self.nodes.len());
-                    self.visit_statement(id, &mut result, visitor, registry);
-                    (name, result)
-                })
-                .collect();
-            if visitor.visit_statement(node, inputs, scopes, result) {
-                for pin in pins_out {
-                    if pin.is_execute() && !pin.has_subscope() {
-                        for id in self.node_neighbors_out(id, Some(pin.name())) {
-                            self.visit_statement(id, result, visitor, registry);
-                        }
+    ) -> Result<Vec<V::Output>, NodeGraphError>
+    where
+        T: Clone,
+    {
+        self.expand_templates(templates, registry)?
+            .visit(visitor, registry)
+            .map_err(|error| NodeGraphError::Connection(*error))
+    }
+
+    /// Lists nodes in order they execute, without producing any operations.
+    /// Follows the same traversal as `visit`: for each statement, nodes of
+    /// its data inputs come first (in input pin order), then the statement
+    /// itself, then bodies of its subscope pins (in output pin order) and
+    /// finally statements connected to its execute outputs. Nodes reached
+    /// more than once are listed only at first occurrence.
+    pub fn execution_order(
+        &self,
+        registry: &Registry,
+    ) -> Result<Vec<NodeId<T>>, Box<ConnectionError>> {
+        let mut visited = HashSet::with_capacity(self.nodes.len());
+        Ok(self
+            .visit(&mut ExecutionOrderVisitor { registry }, registry)?
+            .into_iter()
+            .filter(|id| visited.insert(*id))
+            .collect())
+    }
+
+    /// `path` holds statements being visited, data inputs are tracked
+    /// separately, since statement can also feed its outputs to statements
+    /// it executes.
+    fn visit_statement<V: NodeGraphVisitor<T>>(
+        &self,
+        id: NodeId<T>,
+        result: &mut Vec<V::Output>,
+        visitor: &mut V,
+        registry: &Registry,
+        path: &mut HashSet<NodeId<T>>,
+    ) -> Result<(), Box<ConnectionError>> {
+        let Some(node) = self.node(id) else {
+            return Ok(());
+        };
+        if !path.insert(id) {
+            return Err(Box::new(ConnectionError::CycleNodeFound(id.to_string())));
+        }
+        let inputs = self.visit_inputs(id, visitor, registry, &mut HashSet::default())?;
+        let pins_out = node.data.node_pins_out(registry);
+        let mut scopes = HashMap::with_capacity(pins_out.len());
+        for pin in pins_out.iter().filter(|pin| pin.has_subscope()) {
+            if let Some(id) = self.node_neighbors_out(id, Some(pin.name())).next() {
+                let mut result = Vec::with_capacity(self.nodes.len());
+                self.visit_statement(id, &mut result, visitor, registry, path)?;
+                scopes.insert(pin.name().to_owned(), result);
+            }
+        }
+        if visitor.visit_statement(node, inputs, scopes, result) {
+            for pin in pins_out {
+                if pin.is_execute() && !pin.has_subscope() {
+                    for id in self.node_neighbors_out(id, Some(pin.name())) {
+                        self.visit_statement(id, result, visitor, registry, path)?;
                     }
                 }
             }
         }
+        path.remove(&id);
+        Ok(())
     }
 
     fn visit_expression<V: NodeGraphVisitor<T>>(
@@ -857,26 +1625,41 @@ impl<T: NodeDefinition> NodeGraph<T> {
         id: NodeId<T>,
         visitor: &mut V,
         registry: &Registry,
-    ) -> Option<V::Input> {
-        if let Some(node) = self.node(id) {
-            let inputs = node
-                .data
-                .node_pins_in(registry)
-                .into_iter()
-                .filter(|pin| pin.is_parameter())
-                .filter_map(|pin| {
-                    self.node_neighbors_in(id, Some(pin.name()))
-                        .next()
-                        .map(|id| (pin.name().to_owned(), id))
-                })
-                .filter_map(|(name, id)| {
-                    self.visit_expression(id, visitor, registry)
-                        .map(|input| (name, input))
-                })
-                .collect();
-            return visitor.visit_expression(node, inputs);
+        path: &mut HashSet<NodeId<T>>,
+    ) -> Result<Option<V::Input>, Box<ConnectionError>> {
+        let Some(node) = self.node(id) else {
+            return Ok(None);
+        };
+        if !path.insert(id) {
+            return Err(Box::new(ConnectionError::CycleNodeFound(id.to_string())));
         }
-        None
+        let inputs = self.visit_inputs(id, visitor, registry, path)?;
+        path.remove(&id);
+        Ok(visitor.visit_expression(node, inputs))
+    }
+
+    fn visit_inputs<V: NodeGraphVisitor<T>>(
+        &self,
+        id: NodeId<T>,
+        visitor: &mut V,
+        registry: &Registry,
+        path: &mut HashSet<NodeId<T>>,
+    ) -> Result<HashMap<String, V::Input>, Box<ConnectionError>> {
+        let Some(node) = self.node(id) else {
+            return Ok(Default::default());
+        };
+        let mut result = HashMap::default();
+        for pin in node.data.node_pins_in(registry) {
+            if !pin.is_parameter() {
+                continue;
+            }
+            if let Some(source) = self.node_neighbors_in(id, Some(pin.name())).next() {
+                if let Some(input) = self.visit_expression(source, visitor, registry, path)? {
+                    result.insert(pin.name().to_owned(), input);
+                }
+            }
+        }
+        Ok(result)
     }
 }
 
@@ -889,6 +1672,8 @@ impl<T: NodeDefinition + std::fmt::Debug> std::fmt::Debug for NodeGraph<T> {
     }
 }
 
+/// Visitors that need registry, for example to read node pins, keep their own
+/// reference to it.
 pub trait NodeGraphVisitor<T: NodeDefinition> {
     type Input;
     type Output;
@@ -901,6 +1686,8 @@ pub trait NodeGraphVisitor<T: NodeDefinition> {
         result: &mut Vec<Self::Output>,
     ) -> bool;
 
+    /// Visits node connected to parameter (data) input pin of other node.
+    /// Data connections are followed recursively.
     fn visit_expression(
         &mut self,
         node: &Node<T>,
@@ -908,11 +1695,56 @@ pub trait NodeGraphVisitor<T: NodeDefinition> {
     ) -> Option<Self::Input>;
 }
 
+struct ExecutionOrderVisitor<'a> {
+    registry: &'a Registry,
+}
+
+impl<T: NodeDefinition> NodeGraphVisitor<T> for ExecutionOrderVisitor<'_> {
+    type Input = Vec<NodeId<T>>;
+    type Output = NodeId<T>;
+
+    fn visit_statement(
+        &mut self,
+        node: &Node<T>,
+        mut inputs: HashMap<String, Self::Input>,
+        mut scopes: HashMap<String, Vec<Self::Output>>,
+        result: &mut Vec<Self::Output>,
+    ) -> bool {
+        for pin in node.data.node_pins_in(self.registry) {
+            if let Some(ids) = inputs.remove(pin.name()) {
+                result.extend(ids);
+            }
+        }
+        result.push(node.id());
+        for pin in node.data.node_pins_out(self.registry) {
+            if let Some(ids) = scopes.remove(pin.name()) {
+                result.extend(ids);
+            }
+        }
+        true
+    }
+
+    fn visit_expression(
+        &mut self,
+        node: &Node<T>,
+        mut inputs: HashMap<String, Self::Input>,
+    ) -> Option<Self::Input> {
+        let mut result = vec![];
+        for pin in node.data.node_pins_in(self.registry) {
+            if let Some(ids) = inputs.remove(pin.name()) {
+                result.extend(ids);
+            }
+        }
+        result.push(node.id());
+        Some(result)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::prelude::*;
     use intuicio_core::prelude::*;
-    use std::collections::HashMap;
+    use std::collections::{HashMap, HashSet};
 
     #[derive(Debug, Clone, PartialEq)]
     enum Script {
@@ -935,13 +1767,14 @@ mod tests {
         }
     }
 
-    #[derive(Debug, Clone)]
+    #[derive(Debug, Clone, PartialEq)]
     enum Nodes {
         Start,
         Expression(i32),
         Result,
         Convert(String),
         Child,
+        Macro(String),
     }
 
     impl NodeDefinition for Nodes {
@@ -967,6 +1800,7 @@ mod tests {
                     NodePin::parameter("Data in", "i32".to_owned()),
                 ],
                 Nodes::Child => vec![NodePin::execute("In", false)],
+                Nodes::Macro(_) => vec![NodePin::execute("In", false)],
             }
         }
 
@@ -986,6 +1820,10 @@ mod tests {
                     NodePin::execute("Out", false),
                     NodePin::execute("Body", true),
                 ],
+                Nodes::Macro(_) => vec![
+                    NodePin::execute("Out", false),
+                    NodePin::parameter("Data", "i32".to_owned()),
+                ],
             }
         }
 
@@ -993,6 +1831,19 @@ mod tests {
             matches!(self, Self::Start)
         }
 
+        fn node_template(&self) -> Option<&str> {
+            match self {
+                Nodes::Macro(name) => Some(name),
+                _ => None,
+            }
+        }
+
+        fn rename_for_template(&mut self, prefix: &str) {
+            if let Nodes::Convert(name) = self {
+                *name = format!("{}{}", prefix, name);
+            }
+        }
+
         fn node_suggestions(
             _: i64,
             _: i64,
@@ -1002,6 +1853,23 @@ mod tests {
             vec![]
         }
 
+        fn infer_pin_out_type(
+            &self,
+            name: &str,
+            _: &HashMap<String, Self::TypeInfo>,
+            registry: &Registry,
+        ) -> Option<Self::TypeInfo> {
+            match self {
+                // converts to type named by its property.
+                Nodes::Convert(type_name) if name == "Data out" => Some(type_name.to_owned()),
+                _ => self
+                    .node_pins_out(registry)
+                    .into_iter()
+                    .find(|pin| pin.name() == name)
+                    .and_then(|pin| pin.type_info().cloned()),
+            }
+        }
+
         fn get_property(&self, property_name: &str) -> Option<PropertyValue> {
             match self {
                 Nodes::Expression(value) => match property_name {
@@ -1134,7 +2002,7 @@ mod tests {
         graph.connect_nodes(NodeConnection::new(convert, result, "Data out", "Data"));
         graph.validate(&registry).unwrap();
         assert_eq!(
-            graph.visit(&mut CompileNodesToScript, &registry),
+            graph.visit(&mut CompileNodesToScript, &registry).unwrap(),
             vec![
                 Script::Scope(vec![
                     Script::Literal(42),
@@ -1146,6 +2014,19 @@ mod tests {
                 Script::Return
             ]
         );
+        assert_eq!(
+            graph.execution_order(&registry).unwrap(),
+            vec![
+                start,
+                child,
+                expression_child,
+                convert_child,
+                result_child,
+                expression,
+                convert,
+                result
+            ]
+        );
         assert_eq!(
             graph
                 .node(expression)
@@ -1169,5 +2050,436 @@ mod tests {
                 .unwrap(),
             PropertyValue::new(&10i32).unwrap(),
         );
+
+        // data cycle between statements gets rejected instead of recursing.
+        graph.connect_nodes(NodeConnection::new(
+            convert_child,
+            convert,
+            "Data out",
+            "Data in",
+        ));
+        graph.connect_nodes(NodeConnection::new(
+            convert,
+            convert_child,
+            "Data out",
+            "Data in",
+        ));
+        assert!(matches!(
+            *graph
+                .visit(&mut CompileNodesToScript, &registry)
+                .unwrap_err(),
+            ConnectionError::CycleNodeFound(_)
+        ));
+        assert!(graph.execution_order(&registry).is_err());
+    }
+
+    #[test]
+    fn test_templates() {
+        let registry = Registry::default().with_basic_types();
+        let mut template = NodeGraph::default();
+        let expression = template
+            .add_node(Node::new(0, 0, Nodes::Expression(2)), &registry)
+            .unwrap();
+        let convert = template
+            .add_node(
+                Node::new(100, 0, Nodes::Convert("mul".to_owned())),
+                &registry,
+            )
+            .unwrap();
+        template.connect_nodes(NodeConnection::new(expression, convert, "Out", "In"));
+        template.connect_nodes(NodeConnection::new(expression, convert, "Data", "Data in"));
+        let mut templates = NodeTemplates::default();
+        templates.add(
+            "double",
+            NodeTemplate::new(template)
+                .with_input("In", expression, "In")
+                .with_output("Out", convert, "Out")
+                .with_output("Data", convert, "Data out"),
+        );
+
+        let mut graph = NodeGraph::default();
+        let start = graph
+            .add_node(Node::new(0, 0, Nodes::Start), &registry)
+            .unwrap();
+        let first = graph
+            .add_node(
+                Node::new(0, 0, Nodes::Macro("double".to_owned())),
+                &registry,
+            )
+            .unwrap();
+        let second = graph
+            .add_node(
+                Node::new(0, 0, Nodes::Macro("double".to_owned())),
+                &registry,
+            )
+            .unwrap();
+        let result = graph
+            .add_node(Node::new(0, 0, Nodes::Result), &registry)
+            .unwrap();
+        graph.connect_nodes(NodeConnection::new(start, first, "Out", "In"));
+        graph.connect_nodes(NodeConnection::new(first, second, "Out", "In"));
+        graph.connect_nodes(NodeConnection::new(second, result, "Out", "In"));
+        graph.connect_nodes(NodeConnection::new(second, result, "Data", "Data"));
+        graph.validate(&registry).unwrap();
+
+        let expanded = graph.expand_templates(&templates, &registry).unwrap();
+        expanded.validate(&registry).unwrap();
+        assert_eq!(expanded.nodes().count(), 6);
+        assert!(expanded.node(first).is_none());
+        assert!(expanded.node(second).is_none());
+        assert_eq!(
+            expanded
+                .nodes()
+                .map(|node| node.id())
+                .collect::<HashSet<_>>()
+                .len(),
+            6
+        );
+        assert_eq!(expanded.node_neighbors_in(result, Some("Data")).count(), 1);
+        assert_eq!(
+            graph
+                .visit_with_templates(&mut CompileNodesToScript, &templates, &registry)
+                .unwrap(),
+            vec![
+                Script::Literal(2),
+                Script::Call("double#0/mul".to_owned()),
+                Script::Literal(2),
+                Script::Call("double#1/mul".to_owned()),
+                Script::Return
+            ]
+        );
+        // original graph stays untouched.
+        assert_eq!(graph.nodes().count(), 4);
+
+        let mut recursive = NodeGraph::default();
+        recursive.add_node(Node::new(0, 0, Nodes::Macro("loop".to_owned())), &registry);
+        templates.add("loop", NodeTemplate::new(recursive));
+        graph.add_node(Node::new(0, 0, Nodes::Macro("loop".to_owned())), &registry);
+        assert!(matches!(
+            graph.expand_templates(&templates, &registry),
+            Err(NodeGraphError::RecursiveTemplate(name)) if name == "loop"
+        ));
+        templates.remove("loop");
+        assert!(matches!(
+            graph.expand_templates(&templates, &registry),
+            Err(NodeGraphError::TemplateNotFound(name)) if name == "loop"
+        ));
+    }
+
+    #[test]
+    fn test_infer_types() {
+        let registry = Registry::default().with_basic_types();
+        let mut graph = NodeGraph::default();
+        let start = graph
+            .add_node(Node::new(0, 0, Nodes::Start), &registry)
+            .unwrap();
+        let [expression, expression_convert, expression_name, expression_missing] = [0, 1, 2, 3]
+            .map(|value| {
+                graph
+                    .add_node(Node::new(0, 0, Nodes::Expression(value)), &registry)
+                    .unwrap()
+            });
+        let result = graph
+            .add_node(Node::new(0, 0, Nodes::Result), &registry)
+            .unwrap();
+        let convert = graph
+            .add_node(Node::new(0, 0, Nodes::Convert("f32".to_owned())), &registry)
+            .unwrap();
+        let result_converted = graph
+            .add_node(Node::new(0, 0, Nodes::Result), &registry)
+            .unwrap();
+        graph.connect_nodes(NodeConnection::new(start, expression, "Out", "In"));
+        graph.connect_nodes(NodeConnection::new(expression, result, "Data", "Data"));
+        graph.connect_nodes(NodeConnection::new(
+            expression_convert,
+            convert,
+            "Data",
+            "Data in",
+        ));
+        graph.connect_nodes(NodeConnection::new(
+            expression_name,
+            convert,
+            "Data",
+            "Name",
+        ));
+        graph.connect_nodes(NodeConnection::new(
+            convert,
+            result_converted,
+            "Data out",
+            "Data",
+        ));
+        graph.connect_nodes(NodeConnection::new(
+            expression_missing,
+            result,
+            "Data",
+            "Missing",
+        ));
+
+        let types = graph.infer_types(&registry);
+        assert_eq!(types.len(), 4);
+        let inferred = |id, pin: &str| types.get(&(id, pin.to_owned())).cloned();
+        assert_eq!(
+            inferred(result, "Data"),
+            Some(InferredPinType {
+                type_info: "i32".to_owned(),
+                compatible: true,
+            })
+        );
+        assert_eq!(
+            inferred(convert, "Data in"),
+            Some(InferredPinType {
+                type_info: "i32".to_owned(),
+                compatible: true,
+            })
+        );
+        assert_eq!(
+            inferred(convert, "Name"),
+            Some(InferredPinType {
+                type_info: "i32".to_owned(),
+                compatible: true,
+            })
+        );
+        assert_eq!(
+            inferred(result_converted, "Data"),
+            Some(InferredPinType {
+                type_info: "f32".to_owned(),
+                compatible: false,
+            })
+        );
+        assert_eq!(inferred(expression, "In"), None);
+        assert_eq!(inferred(result, "Missing"), None);
+    }
+
+    #[test]
+    fn test_validate_node() {
+        let registry = Registry::default().with_basic_types();
+        let mut graph = NodeGraph::default();
+        let start = graph
+            .add_node(Node::new(0, 0, Nodes::Start), &registry)
+            .unwrap();
+        let expression = graph
+            .add_node(Node::new(0, 0, Nodes::Expression(42)), &registry)
+            .unwrap();
+        let result = graph
+            .add_node(Node::new(0, 0, Nodes::Result), &registry)
+            .unwrap();
+        let convert_a = graph
+            .add_node(Node::new(0, 0, Nodes::Convert("a".to_owned())), &registry)
+            .unwrap();
+        let convert_b = graph
+            .add_node(Node::new(0, 0, Nodes::Convert("b".to_owned())), &registry)
+            .unwrap();
+        graph.connect_nodes(NodeConnection::new(start, expression, "Out", "In"));
+        graph.connect_nodes(NodeConnection::new(expression, result, "Data", "In"));
+        graph.connect_nodes(NodeConnection::new(
+            convert_a, convert_b, "Data out", "Data in",
+        ));
+        graph.connect_nodes(NodeConnection::new(
+            convert_b, convert_a, "Data out", "Data in",
+        ));
+        assert!(graph.validate_node(start, &registry).is_ok());
+        let errors = graph.validate_node(result, &registry).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0],
+            NodeGraphError::Connection(ConnectionError::MismatchPins { .. })
+        ));
+        let errors = graph.validate_node(convert_a, &registry).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0],
+            NodeGraphError::Connection(ConnectionError::CycleNodeFound(_))
+        ));
+        assert_eq!(graph.validate(&registry).unwrap_err().len(), 2);
+        graph.remove_node(result, &registry);
+        assert!(matches!(
+            graph.validate_node(result, &registry).unwrap_err()[0],
+            NodeGraphError::NodeNotFound(_)
+        ));
+    }
+
+    #[test]
+    fn test_copy_paste_subgraph() {
+        let registry = Registry::default().with_basic_types();
+        let mut graph = NodeGraph::default();
+        let start = graph
+            .add_node(Node::new(0, 0, Nodes::Start), &registry)
+            .unwrap();
+        let expression = graph
+            .add_node(Node::new(10, 0, Nodes::Expression(42)), &registry)
+            .unwrap();
+        let result = graph
+            .add_node(Node::new(20, 0, Nodes::Result), &registry)
+            .unwrap();
+        graph.connect_nodes(NodeConnection::new(start, expression, "Out", "In"));
+        graph.connect_nodes(NodeConnection::new(expression, result, "Out", "In"));
+        graph.connect_nodes(NodeConnection::new(expression, result, "Data", "Data"));
+
+        let clipboard = graph.extract_subgraph(&[expression, result]);
+        assert_eq!(clipboard.nodes().count(), 2);
+        assert_eq!(clipboard.connections().count(), 2);
+
+        let pasted = graph.paste_subgraph(&clipboard, 5, 10, &registry);
+        assert_eq!(pasted.len(), 2);
+        assert_eq!(graph.nodes().count(), 5);
+        assert!(!pasted.contains(&expression));
+        assert!(!pasted.contains(&result));
+        let new_expression = pasted[0];
+        let new_result = pasted[1];
+        let node = graph.node(new_expression).unwrap();
+        assert_eq!((node.x, node.y), (15, 10));
+        assert!(matches!(node.data, Nodes::Expression(42)));
+        assert_eq!(
+            graph
+                .node_neighbors_out(new_expression, None)
+                .collect::<Vec<_>>(),
+            vec![new_result, new_result]
+        );
+        assert_eq!(graph.node_neighbors_in(new_expression, None).count(), 0);
+        assert_eq!(
+            graph
+                .node_neighbors_out(expression, None)
+                .collect::<Vec<_>>(),
+            vec![result, result]
+        );
+        assert_eq!(
+            graph
+                .node_neighbors_in(expression, None)
+                .collect::<Vec<_>>(),
+            vec![start]
+        );
+        graph.validate(&registry).unwrap();
+    }
+
+    #[test]
+    fn test_auto_layout() {
+        let registry = Registry::default().with_basic_types();
+        let mut graph = NodeGraph::default();
+        let start = graph
+            .add_node(Node::new(0, 0, Nodes::Start), &registry)
+            .unwrap();
+        let child = graph
+            .add_node(Node::new(0, 0, Nodes::Child), &registry)
+            .unwrap();
+        let expression_child = graph
+            .add_node(Node::new(0, 0, Nodes::Expression(42)), &registry)
+            .unwrap();
+        let result_child = graph
+            .add_node(Node::new(0, 0, Nodes::Result), &registry)
+            .unwrap();
+        let expression = graph
+            .add_node(Node::new(0, 0, Nodes::Expression(42)), &registry)
+            .unwrap();
+        let convert = graph
+            .add_node(Node::new(0, 0, Nodes::Convert("bar".to_owned())), &registry)
+            .unwrap();
+        let result = graph
+            .add_node(Node::new(0, 0, Nodes::Result), &registry)
+            .unwrap();
+        graph.connect_nodes(NodeConnection::new(start, child, "Out", "In"));
+        graph.connect_nodes(NodeConnection::new(child, expression_child, "Body", "In"));
+        graph.connect_nodes(NodeConnection::new(
+            expression_child,
+            result_child,
+            "Out",
+            "In",
+        ));
+        graph.connect_nodes(NodeConnection::new(child, convert, "Out", "In"));
+        graph.connect_nodes(NodeConnection::new(expression, convert, "Data", "Data in"));
+        graph.connect_nodes(NodeConnection::new(convert, result, "Out", "In"));
+        let connections = graph.connections().cloned().collect::<Vec<_>>();
+
+        graph.auto_layout(NodeLayoutOptions::default(), &registry);
+        let positions = graph
+            .nodes()
+            .map(|node| (node.x, node.y))
+            .collect::<HashSet<_>>();
+        assert_eq!(positions.len(), graph.nodes().count());
+        let start_x = graph.node(start).unwrap().x;
+        assert!(graph.nodes().all(|node| node.x >= start_x));
+        for connection in &connections {
+            assert!(
+                graph.node(connection.from_node).unwrap().x
+                    < graph.node(connection.to_node).unwrap().x
+            );
+        }
+        // data source is pulled next to node it feeds.
+        assert_eq!(
+            graph.node(expression).unwrap().x,
+            graph.node(child).unwrap().x
+        );
+        assert_eq!(
+            graph.connections().cloned().collect::<Vec<_>>(),
+            connections
+        );
+        assert_eq!(graph.query_nearest_nodes(0, 0).next(), Some(start));
+    }
+
+    #[test]
+    fn test_diff_patch() {
+        let registry = Registry::default().with_basic_types();
+        let mut old = NodeGraph::default();
+        let start = old
+            .add_node(Node::new(0, 0, Nodes::Start), &registry)
+            .unwrap();
+        let expression = old
+            .add_node(Node::new(0, 0, Nodes::Expression(42)), &registry)
+            .unwrap();
+        let removed = old
+            .add_node(Node::new(0, 0, Nodes::Child), &registry)
+            .unwrap();
+        let macro_ = old
+            .add_node(
+                Node::new(100, 100, Nodes::Macro("foo".to_owned())),
+                &registry,
+            )
+            .unwrap();
+        old.connect_nodes(NodeConnection::new(start, expression, "Out", "In"));
+        old.refresh_spatial_cache();
+        let mut new = old.clone();
+        new.remove_node(removed, &registry);
+        let result = new
+            .add_node(Node::new(10, 20, Nodes::Result), &registry)
+            .unwrap();
+        new.connect_nodes(NodeConnection::new(expression, result, "Out", "In"));
+        new.connect_nodes(NodeConnection::new(expression, result, "Data", "Data"));
+        let node = new.node_mut(expression).unwrap();
+        node.x = 5;
+        node.data
+            .set_property("Value", PropertyValue::new(&10i32).unwrap());
+        new.node_mut(macro_).unwrap().data = Nodes::Macro("bar".to_owned());
+
+        let patch = NodeGraph::diff(&old, &new);
+        assert_eq!(patch.added_nodes.len(), 1);
+        assert_eq!(patch.removed_nodes, vec![removed]);
+        assert_eq!(patch.moved_nodes, vec![(expression, 5, 0)]);
+        assert_eq!(
+            patch.changed_nodes,
+            vec![
+                (expression, Nodes::Expression(10)),
+                (macro_, Nodes::Macro("bar".to_owned())),
+            ]
+        );
+        assert_eq!(patch.added_connections.len(), 2);
+        assert!(patch.removed_connections.is_empty());
+
+        let mut patched = old.clone();
+        patched.apply(&patch);
+        assert!(NodeGraph::diff(&patched, &new).is_empty());
+        assert_eq!(patched.query_nearest_nodes(10, 20).next(), Some(result));
+        assert_eq!(
+            patched
+                .nodes()
+                .map(|node| node.id())
+                .collect::<HashSet<_>>(),
+            new.nodes().map(|node| node.id()).collect::<HashSet<_>>(),
+        );
+        assert_eq!(
+            patched.connections().collect::<HashSet<_>>(),
+            new.connections().collect::<HashSet<_>>(),
+        );
+
+        patched.apply(&NodeGraph::diff(&new, &old));
+        assert!(NodeGraph::diff(&patched, &old).is_empty());
     }
 }
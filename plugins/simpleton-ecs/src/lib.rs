@@ -1,8 +1,10 @@
 use bitvec::vec::BitVec;
-use intuicio_core::{core_version, registry::Registry, IntuicioStruct, IntuicioVersion};
+use intuicio_core::{
+    context::Context, core_version, registry::Registry, IntuicioStruct, IntuicioVersion,
+};
 use intuicio_derive::{intuicio_method, intuicio_methods, IntuicioStruct};
 use intuicio_frontend_simpleton::{
-    library::closure::Closure, Array, Function, Integer, Reference, Type,
+    library::closure::Closure, or_error, Array, Function, Integer, Reference, Type,
 };
 use std::collections::{HashMap, HashSet};
 
@@ -30,6 +32,10 @@ pub struct World {
     to_remove: HashMap<Integer, Vec<Type>>,
     #[intuicio(ignore)]
     to_clear: bool,
+    #[intuicio(ignore)]
+    add_hooks: Vec<(Type, Reference)>,
+    #[intuicio(ignore)]
+    remove_hooks: Vec<(Type, Reference)>,
 }
 
 #[intuicio_methods(module_name = "world")]
@@ -42,7 +48,7 @@ impl World {
 
     #[intuicio_method(use_registry)]
     pub fn spawn(registry: &Registry, mut world: Reference) -> Reference {
-        let mut world = world.write::<World>().expect("`world` is not a World!");
+        let mut world = or_error!(world.write::<World>(), "`world` is not a World!");
         let entity = world.entity_generator;
         world.entity_generator = world.entity_generator.wrapping_add(1);
         Reference::new_integer(entity, registry)
@@ -50,27 +56,23 @@ impl World {
 
     #[intuicio_method()]
     pub fn despawn(mut world: Reference, entity: Reference) -> Reference {
-        let mut world = world.write::<World>().expect("`world` is not a World!");
-        let entity = entity
-            .read::<Integer>()
-            .expect("`entity` is not an Integer!");
+        let mut world = or_error!(world.write::<World>(), "`world` is not a World!");
+        let entity = or_error!(entity.read::<Integer>(), "`entity` is not an Integer!");
         world.to_despawn.insert(*entity);
         Reference::null()
     }
 
     #[intuicio_method()]
     pub fn despawn_all(mut world: Reference) -> Reference {
-        let mut world = world.write::<World>().expect("`world` is not a World!");
+        let mut world = or_error!(world.write::<World>(), "`world` is not a World!");
         world.to_clear = true;
         Reference::null()
     }
 
     #[intuicio_method()]
     pub fn add(mut world: Reference, entity: Reference, component: Reference) -> Reference {
-        let mut world = world.write::<World>().expect("`world` is not a World!");
-        let entity = entity
-            .read::<Integer>()
-            .expect("`entity` is not an Integer!");
+        let mut world = or_error!(world.write::<World>(), "`world` is not a World!");
+        let entity = or_error!(entity.read::<Integer>(), "`entity` is not an Integer!");
         world
             .to_add
             .entry(*entity)
@@ -81,29 +83,23 @@ impl World {
 
     #[intuicio_method()]
     pub fn add_bundle(mut world: Reference, entity: Reference, components: Reference) -> Reference {
-        let mut world = world.write::<World>().expect("`world` is not a World!");
-        let entity = entity
-            .read::<Integer>()
-            .expect("`entity` is not an Integer!");
+        let mut world = or_error!(world.write::<World>(), "`world` is not a World!");
+        let entity = or_error!(entity.read::<Integer>(), "`entity` is not an Integer!");
         let target = world.to_add.entry(*entity).or_default();
         target.extend(
-            components
-                .read::<Array>()
-                .expect("`components` is not an Array!")
-                .to_owned(),
+            or_error!(components.read::<Array>(), "`components` is not an Array!").to_owned(),
         );
         components
     }
 
     #[intuicio_method()]
     pub fn remove(mut world: Reference, entity: Reference, component_type: Reference) -> Reference {
-        let mut world = world.write::<World>().expect("`world` is not a World!");
-        let entity = entity
-            .read::<Integer>()
-            .expect("`entity` is not an Integer!");
-        let component_type = component_type
-            .read::<Type>()
-            .expect("`component_type` is not a Type!");
+        let mut world = or_error!(world.write::<World>(), "`world` is not a World!");
+        let entity = or_error!(entity.read::<Integer>(), "`entity` is not an Integer!");
+        let component_type = or_error!(
+            component_type.read::<Type>(),
+            "`component_type` is not a Type!"
+        );
         world
             .to_remove
             .entry(*entity)
@@ -118,27 +114,65 @@ impl World {
         entity: Reference,
         component_types: Reference,
     ) -> Reference {
-        let mut world = world.write::<World>().expect("`world` is not a World!");
-        let entity = entity
-            .read::<Integer>()
-            .expect("`entity` is not an Integer!");
-        world.to_remove.entry(*entity).or_default().extend(
-            component_types
-                .read::<Array>()
-                .expect("`component_types` is not an Array!")
-                .iter()
-                .map(|item| {
-                    item.read::<Type>()
-                        .expect("`component_types` item is not a Type!")
-                        .to_owned()
-                }),
+        let mut world = or_error!(world.write::<World>(), "`world` is not a World!");
+        let entity = or_error!(entity.read::<Integer>(), "`entity` is not an Integer!");
+        let component_types = or_error!(
+            component_types.read::<Array>(),
+            "`component_types` is not an Array!"
         );
+        let mut types = Vec::with_capacity(component_types.len());
+        for item in component_types.iter() {
+            types.push(
+                or_error!(item.read::<Type>(), "`component_types` item is not a Type!").to_owned(),
+            );
+        }
+        world.to_remove.entry(*entity).or_default().extend(types);
         Reference::null()
     }
 
+    /// Immediately removes component from already maintained entity and returns it.
+    /// Components still waiting in `add` queue are not considered, and pending
+    /// `remove` of the same component type becomes a no-op on next `maintain`.
+    /// Same as with `remove`, `on_remove` hooks get invoked before component
+    /// is removed, and entity stays alive even if it has no components left.
+    #[intuicio_method(use_context, use_registry)]
+    pub fn take_component(
+        context: &mut Context,
+        registry: &Registry,
+        mut world: Reference,
+        entity: Reference,
+        component_type: Reference,
+    ) -> Reference {
+        let entity = *or_error!(entity.read::<Integer>(), "`entity` is not an Integer!");
+        let component_type = or_error!(
+            component_type.read::<Type>(),
+            "`component_type` is not a Type!"
+        )
+        .to_owned();
+        let component = or_error!(world.read::<World>(), "`world` is not a World!")
+            .components(entity)
+            .and_then(|components| {
+                components.iter().find(|component| {
+                    component
+                        .type_of()
+                        .map(|ty| component_type.is_same_as(&ty))
+                        .unwrap_or_default()
+                })
+            })
+            .cloned();
+        let Some(component) = component else {
+            return Reference::null();
+        };
+        Self::invoke_hooks(context, registry, &world, vec![(entity, component)], false);
+        or_error!(world.write::<World>(), "`world` is not a World!")
+            .remove_components(entity, &[component_type])
+            .pop()
+            .unwrap_or_default()
+    }
+
     #[intuicio_method()]
     pub fn clear(mut world: Reference) -> Reference {
-        let mut world = world.write::<World>().expect("`world` is not a World!");
+        let mut world = or_error!(world.write::<World>(), "`world` is not a World!");
         world.to_clear = true;
         world.resources.clear();
         Reference::null()
@@ -146,7 +180,7 @@ impl World {
 
     #[intuicio_method(use_registry)]
     pub fn entities(registry: &Registry, world: Reference) -> Reference {
-        let world = world.read::<World>().expect("`world` is not a World!");
+        let world = or_error!(world.read::<World>(), "`world` is not a World!");
         Reference::new_array(
             world
                 .buckets
@@ -164,13 +198,12 @@ impl World {
 
     #[intuicio_method()]
     pub fn get(world: Reference, entity: Reference, component_type: Reference) -> Reference {
-        let world = world.read::<World>().expect("`world` is not a World!");
-        let entity = entity
-            .read::<Integer>()
-            .expect("`entity` is not an Integer!");
-        let component_type = component_type
-            .read::<Type>()
-            .expect("`component_type` is not a Type!");
+        let world = or_error!(world.read::<World>(), "`world` is not a World!");
+        let entity = or_error!(entity.read::<Integer>(), "`entity` is not an Integer!");
+        let component_type = or_error!(
+            component_type.read::<Type>(),
+            "`component_type` is not a Type!"
+        );
         for bucket in world.buckets.values() {
             if let Some(components) = bucket
                 .entitity_components
@@ -196,13 +229,12 @@ impl World {
         entity: Reference,
         component_type: Reference,
     ) -> Reference {
-        let world = world.read::<World>().expect("`world` is not a World!");
-        let entity = entity
-            .read::<Integer>()
-            .expect("`entity` is not an Integer!");
-        let component_type = component_type
-            .read::<Type>()
-            .expect("`component_type` is not a Type!");
+        let world = or_error!(world.read::<World>(), "`world` is not a World!");
+        let entity = or_error!(entity.read::<Integer>(), "`entity` is not an Integer!");
+        let component_type = or_error!(
+            component_type.read::<Type>(),
+            "`component_type` is not a Type!"
+        );
         for bucket in world.buckets.values() {
             if bucket.types.iter().any(|ty| component_type.is_same_as(ty))
                 && bucket
@@ -219,10 +251,11 @@ impl World {
     #[intuicio_method(use_registry)]
     pub fn query(registry: &Registry, world: Reference, component_types: Reference) -> Reference {
         let world_ref = world.clone();
-        let world = world.read::<World>().expect("`world` is not a World!");
-        let component_types = component_types
-            .read::<Array>()
-            .expect("`component_types` is not an Array!");
+        let world = or_error!(world.read::<World>(), "`world` is not a World!");
+        let component_types = or_error!(
+            component_types.read::<Array>(),
+            "`component_types` is not an Array!"
+        );
         let types = component_types
             .iter()
             .filter_map(|item| item.read::<Type>())
@@ -254,9 +287,89 @@ impl World {
         )
     }
 
+    /// Returns `[entity, component]` of the only entity with given component,
+    /// or null if there is none or more than one. Component is returned by
+    /// shared reference, so it can be modified in place.
+    #[intuicio_method(use_registry)]
+    pub fn single(registry: &Registry, world: Reference, component_type: Reference) -> Reference {
+        let world = or_error!(world.read::<World>(), "`world` is not a World!");
+        let component_type = or_error!(
+            component_type.read::<Type>(),
+            "`component_type` is not a Type!"
+        );
+        let mut result = None;
+        for bucket in world.buckets.values() {
+            let Some(index) = bucket
+                .types
+                .iter()
+                .position(|ty| component_type.is_same_as(ty))
+            else {
+                continue;
+            };
+            for (entity, components) in &bucket.entitity_components {
+                if result.is_some() {
+                    return Reference::null();
+                }
+                result = Some((*entity, components[index].clone()));
+            }
+        }
+        match result {
+            Some((entity, component)) => Reference::new_array(
+                vec![Reference::new_integer(entity, registry), component],
+                registry,
+            ),
+            None => Reference::null(),
+        }
+    }
+
+    #[intuicio_method()]
+    pub fn on_add(
+        mut world: Reference,
+        component_type: Reference,
+        closure: Reference,
+    ) -> Reference {
+        let mut world = or_error!(world.write::<World>(), "`world` is not a World!");
+        let component_type = or_error!(
+            component_type.read::<Type>(),
+            "`component_type` is not a Type!"
+        )
+        .to_owned();
+        assert!(
+            closure.read::<Closure>().is_some(),
+            "`closure` is not a Closure!"
+        );
+        world.add_hooks.push((component_type, closure));
+        Reference::null()
+    }
+
     #[intuicio_method()]
-    pub fn maintain(mut world: Reference) -> Reference {
-        let mut world = world.write::<World>().expect("`world` is not a World!");
+    pub fn on_remove(
+        mut world: Reference,
+        component_type: Reference,
+        closure: Reference,
+    ) -> Reference {
+        let mut world = or_error!(world.write::<World>(), "`world` is not a World!");
+        let component_type = or_error!(
+            component_type.read::<Type>(),
+            "`component_type` is not a Type!"
+        )
+        .to_owned();
+        assert!(
+            closure.read::<Closure>().is_some(),
+            "`closure` is not a Closure!"
+        );
+        world.remove_hooks.push((component_type, closure));
+        Reference::null()
+    }
+
+    #[intuicio_method(use_context, use_registry)]
+    pub fn maintain(context: &mut Context, registry: &Registry, world: Reference) -> Reference {
+        // hooks are invoked with world unlocked, so they can freely access it.
+        let removed =
+            or_error!(world.read::<World>(), "`world` is not a World!").removed_components();
+        Self::invoke_hooks(context, registry, &world, removed, false);
+        let mut world_ref = world.clone();
+        let mut world = or_error!(world_ref.write::<World>(), "`world` is not a World!");
         if world.to_clear {
             world.buckets.clear();
             world.to_clear = false;
@@ -265,100 +378,58 @@ impl World {
                 world.take(entity);
             }
             for (entity, component_types) in std::mem::take(&mut world.to_remove) {
-                if let Some(components) = world.take(entity) {
-                    let items = world.to_add.entry(entity).or_default();
-                    items.extend(components.into_iter().filter(|component| {
-                        component
-                            .type_of()
-                            .map(|ty| !component_types.iter().any(|cty| ty.is_same_as(cty)))
-                            .unwrap_or_default()
-                    }));
-                }
+                world.remove_components(entity, &component_types);
             }
         }
+        let mut added = vec![];
         for (entity, components) in std::mem::take(&mut world.to_add) {
-            for component in &components {
-                let component_type = component.type_of().unwrap();
-                if !world
-                    .component_table
-                    .iter()
-                    .any(|ty| component_type.is_same_as(ty))
-                {
-                    world.component_table.push(component_type);
-                }
+            if world.add_hooks.is_empty() {
+                world.insert(entity, components);
+                continue;
             }
-            let original = world.take(entity).unwrap_or_default();
-            let archetype = world.archetype(
-                original
-                    .iter()
-                    .chain(components.iter())
-                    .filter_map(|component| component.type_of()),
-            );
-            let bucket = if let Some(bucket) = world.buckets.get_mut(&archetype) {
-                bucket
-            } else {
-                let types = world
-                    .component_table
-                    .iter()
-                    .enumerate()
-                    .filter(|(index, _)| {
-                        archetype
-                            .get(*index)
-                            .map(|value| *value)
-                            .unwrap_or_default()
-                    })
-                    .map(|(_, ty)| ty.to_owned())
-                    .collect();
-                world.buckets.insert(
-                    archetype.to_owned(),
-                    Bucket {
-                        types,
-                        entitity_components: Default::default(),
-                    },
-                );
-                world.buckets.get_mut(&archetype).unwrap()
-            };
-            let bucket_components = if let Some(bucket_components) = bucket
-                .entitity_components
-                .iter_mut()
-                .find(|(e, _)| entity == *e)
-            {
-                &mut bucket_components.1
-            } else {
-                let count = bucket.types.len();
-                let index = bucket.entitity_components.len();
-                bucket
-                    .entitity_components
-                    .push((entity, vec![Reference::null(); count]));
-                &mut bucket.entitity_components[index].1
-            };
-            for component in original.into_iter().chain(components.into_iter()) {
-                if let Some(component_type) = component.type_of() {
-                    if let Some(index) = bucket
-                        .types
+            // components replacing ones of the same type are not new to entity.
+            let existing = world
+                .components(entity)
+                .map(|components| {
+                    components
                         .iter()
-                        .position(|ty| component_type.is_same_as(ty))
-                    {
-                        bucket_components[index] = component;
-                    }
-                }
+                        .filter_map(|component| component.type_of())
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            world.insert(entity, components);
+            if let Some(components) = world.components(entity) {
+                added.extend(
+                    components
+                        .iter()
+                        .filter(|component| {
+                            component
+                                .type_of()
+                                .map(|ty| !existing.iter().any(|ety| ty.is_same_as(ety)))
+                                .unwrap_or_default()
+                        })
+                        .map(|component| (entity, component.clone())),
+                );
             }
         }
+        // buckets of entities without components are kept, so they stay alive.
         let to_delete = world
             .buckets
             .iter()
-            .filter(|(_, bucket)| bucket.types.is_empty() || bucket.entitity_components.is_empty())
+            .filter(|(_, bucket)| bucket.entitity_components.is_empty())
             .map(|(archetype, _)| archetype.to_owned())
             .collect::<Vec<_>>();
         for archetype in to_delete {
             world.buckets.remove(&archetype);
         }
+        drop(world);
+        Self::invoke_hooks(context, registry, &world_ref, added, true);
         Reference::null()
     }
 
     #[intuicio_method()]
     pub fn add_resource(mut world: Reference, resource: Reference) -> Reference {
-        let mut world = world.write::<World>().expect("`world` is not a World!");
+        let mut world = or_error!(world.write::<World>(), "`world` is not a World!");
         let resource_type = resource.type_of().unwrap();
         if let Some(res) = world
             .resources
@@ -374,10 +445,11 @@ impl World {
 
     #[intuicio_method()]
     pub fn remove_resource(mut world: Reference, resource_type: Reference) -> Reference {
-        let mut world = world.write::<World>().expect("`world` is not a World!");
-        let resource_type = resource_type
-            .read::<Type>()
-            .expect("`resource_type` is not a Type!");
+        let mut world = or_error!(world.write::<World>(), "`world` is not a World!");
+        let resource_type = or_error!(
+            resource_type.read::<Type>(),
+            "`resource_type` is not a Type!"
+        );
         if let Some(index) = world
             .resources
             .iter()
@@ -390,10 +462,11 @@ impl World {
 
     #[intuicio_method()]
     pub fn resource(world: Reference, resource_type: Reference) -> Reference {
-        let world = world.read::<World>().expect("`world` is not a World!");
-        let resource_type = resource_type
-            .read::<Type>()
-            .expect("`resource_type` is not a Type!");
+        let world = or_error!(world.read::<World>(), "`world` is not a World!");
+        let resource_type = or_error!(
+            resource_type.read::<Type>(),
+            "`resource_type` is not a Type!"
+        );
         if let Some(resource) = world
             .resources
             .iter()
@@ -410,17 +483,19 @@ impl World {
         world: Reference,
         resource_types: Reference,
     ) -> Reference {
-        let world = world.read::<World>().expect("`world` is not a World!");
-        let resource_types = resource_types
-            .read::<Array>()
-            .expect("`resource_types` is not an Array!");
+        let world = or_error!(world.read::<World>(), "`world` is not a World!");
+        let resource_types = or_error!(
+            resource_types.read::<Array>(),
+            "`resource_types` is not an Array!"
+        );
         Reference::new_array(
             resource_types
                 .iter()
                 .map(|resource_type| {
-                    let resource_type = resource_type
-                        .read::<Type>()
-                        .expect("`resource_types` item is not a Type!");
+                    let resource_type = or_error!(
+                        resource_type.read::<Type>(),
+                        "`resource_types` item is not a Type!"
+                    );
                     world
                         .resources
                         .iter()
@@ -435,7 +510,7 @@ impl World {
 
     #[intuicio_method(use_registry)]
     pub fn snapshot(registry: &Registry, world: Reference) -> Reference {
-        let world = world.read::<World>().expect("`world` is not a World!");
+        let world = or_error!(world.read::<World>(), "`world` is not a World!");
         Reference::new_array(
             world
                 .buckets
@@ -482,6 +557,122 @@ impl World {
         )
     }
 
+    fn components(&self, entity: Integer) -> Option<&[Reference]> {
+        self.buckets.values().find_map(|bucket| {
+            bucket
+                .entitity_components
+                .iter()
+                .find(|(e, _)| entity == *e)
+                .map(|(_, components)| components.as_slice())
+        })
+    }
+
+    fn removed_components(&self) -> Vec<(Integer, Reference)> {
+        if self.remove_hooks.is_empty() {
+            return vec![];
+        }
+        if self.to_clear {
+            return self
+                .buckets
+                .values()
+                .flat_map(|bucket| {
+                    bucket
+                        .entitity_components
+                        .iter()
+                        .flat_map(|(entity, components)| {
+                            components
+                                .iter()
+                                .map(|component| (*entity, component.clone()))
+                        })
+                })
+                .collect();
+        }
+        let mut result = vec![];
+        for entity in &self.to_despawn {
+            if let Some(components) = self.components(*entity) {
+                result.extend(
+                    components
+                        .iter()
+                        .map(|component| (*entity, component.clone())),
+                );
+            }
+        }
+        for (entity, component_types) in &self.to_remove {
+            if self.to_despawn.contains(entity) {
+                continue;
+            }
+            if let Some(components) = self.components(*entity) {
+                result.extend(
+                    components
+                        .iter()
+                        .filter(|component| {
+                            component
+                                .type_of()
+                                .map(|ty| component_types.iter().any(|cty| ty.is_same_as(cty)))
+                                .unwrap_or_default()
+                        })
+                        .map(|component| (*entity, component.clone())),
+                );
+            }
+        }
+        result
+    }
+
+    fn invoke_hooks(
+        context: &mut Context,
+        registry: &Registry,
+        world: &Reference,
+        components: Vec<(Integer, Reference)>,
+        added: bool,
+    ) {
+        for (entity, component) in components {
+            let Some(component_type) = component.type_of() else {
+                continue;
+            };
+            let closures = {
+                let world = world.read::<World>().expect("`world` is not a World!");
+                let hooks = if added {
+                    &world.add_hooks
+                } else {
+                    &world.remove_hooks
+                };
+                hooks
+                    .iter()
+                    .filter(|(ty, _)| component_type.is_same_as(ty))
+                    .map(|(_, closure)| closure.clone())
+                    .collect::<Vec<_>>()
+            };
+            for closure in closures {
+                closure.read::<Closure>().unwrap().invoke(
+                    context,
+                    registry,
+                    &[Reference::new_integer(entity, registry), component.clone()],
+                );
+            }
+        }
+    }
+
+    /// Removes components of given types from entity and returns them, while
+    /// entity keeps the rest of its components.
+    fn remove_components(&mut self, entity: Integer, component_types: &[Type]) -> Vec<Reference> {
+        let Some(components) = self.take(entity) else {
+            return vec![];
+        };
+        let mut removed = vec![];
+        let mut kept = vec![];
+        for component in components {
+            match component.type_of() {
+                Some(ty) if component_types.iter().any(|cty| ty.is_same_as(cty)) => {
+                    removed.push(component)
+                }
+                Some(_) => kept.push(component),
+                None => {}
+            }
+        }
+        self.insert(entity, kept);
+        removed
+    }
+
     fn take(&mut self, entity: Integer) -> Option<Vec<Reference>> {
         for bucket in self.buckets.values_mut() {
             if let Some(index) = bucket
@@ -495,6 +686,75 @@ impl World {
         None
     }
 
+    fn insert(&mut self, entity: Integer, components: Vec<Reference>) {
+        for component in &components {
+            let component_type = component.type_of().unwrap();
+            if !self
+                .component_table
+                .iter()
+                .any(|ty| component_type.is_same_as(ty))
+            {
+                self.component_table.push(component_type);
+            }
+        }
+        let original = self.take(entity).unwrap_or_default();
+        let archetype = self.archetype(
+            original
+                .iter()
+                .chain(components.iter())
+                .filter_map(|component| component.type_of()),
+        );
+        let bucket = if let Some(bucket) = self.buckets.get_mut(&archetype) {
+            bucket
+        } else {
+            let types = self
+                .component_table
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| {
+                    archetype
+                        .get(*index)
+                        .map(|value| *value)
+                        .unwrap_or_default()
+                })
+                .map(|(_, ty)| ty.to_owned())
+                .collect();
+            self.buckets.insert(
+                archetype.to_owned(),
+                Bucket {
+                    types,
+                    entitity_components: Default::default(),
+                },
+            );
+            self.buckets.get_mut(&archetype).unwrap()
+        };
+        let bucket_components = if let Some(bucket_components) = bucket
+            .entitity_components
+            .iter_mut()
+            .find(|(e, _)| entity == *e)
+        {
+            &mut bucket_components.1
+        } else {
+            let count = bucket.types.len();
+            let index = bucket.entitity_components.len();
+            bucket
+                .entitity_components
+                .push((entity, vec![Reference::null(); count]));
+            &mut bucket.entitity_components[index].1
+        };
+        for component in original.into_iter().chain(components.into_iter()) {
+            if let Some(component_type) = component.type_of() {
+                if let Some(index) = bucket
+                    .types
+                    .iter()
+                    .position(|ty| component_type.is_same_as(ty))
+                {
+                    bucket_components[index] = component;
+                }
+            }
+        }
+    }
+
     fn archetype(&self, component_types: impl Iterator<Item = Type>) -> BitVec {
         let mut result = BitVec::repeat(false, self.component_table.len());
         for component_type in component_types {
@@ -585,11 +845,15 @@ pub extern "C" fn install(registry: &mut Registry) {
     registry.add_function(World::add_bundle__define_function(registry));
     registry.add_function(World::remove__define_function(registry));
     registry.add_function(World::remove_bundle__define_function(registry));
+    registry.add_function(World::take_component__define_function(registry));
     registry.add_function(World::clear__define_function(registry));
     registry.add_function(World::entities__define_function(registry));
     registry.add_function(World::get__define_function(registry));
     registry.add_function(World::has__define_function(registry));
     registry.add_function(World::query__define_function(registry));
+    registry.add_function(World::single__define_function(registry));
+    registry.add_function(World::on_add__define_function(registry));
+    registry.add_function(World::on_remove__define_function(registry));
     registry.add_function(World::maintain__define_function(registry));
     registry.add_function(World::add_resource__define_function(registry));
     registry.add_function(World::remove_resource__define_function(registry));
@@ -598,3 +862,297 @@ pub extern "C" fn install(registry: &mut Registry) {
     registry.add_function(World::snapshot__define_function(registry));
     registry.add_function(IterQuery::next__define_function(registry));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use intuicio_core::context::Context;
+    use intuicio_derive::intuicio_function;
+    use intuicio_frontend_simpleton::{
+        library::{array, iter},
+        Real,
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_invalid_arguments() {
+        let mut registry = Registry::default();
+        intuicio_frontend_simpleton::library::install(&mut registry);
+        install(&mut registry);
+        let world = World::new(&registry);
+        let entity = Reference::new_text("entity".to_owned(), &registry);
+        let component = Reference::new_integer(42, &registry);
+        #[cfg(feature = "safe")]
+        {
+            use intuicio_frontend_simpleton::{library::error::Error, Text};
+
+            let result = World::add(world, entity, component);
+            assert_eq!(
+                Error::message(result).read::<Text>().unwrap().as_str(),
+                "`entity` is not an Integer!"
+            );
+            let result = World::despawn_all(Reference::null());
+            assert!(result.read::<Error>().is_some());
+        }
+        #[cfg(not(feature = "safe"))]
+        {
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                World::add(world, entity, component)
+            }));
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn test_take_component() {
+        let mut registry = Registry::default();
+        intuicio_frontend_simpleton::library::install(&mut registry);
+        install(&mut registry);
+        let mut context = Context::new(10240, 10240);
+        let world = World::new(&registry);
+        let entity = World::spawn(&registry, world.clone());
+        let component = Reference::new_integer(42, &registry);
+        let other = Reference::new_real(4.2, &registry);
+        World::add(world.clone(), entity.clone(), component.clone());
+        World::add(world.clone(), entity.clone(), other.clone());
+        World::maintain(&mut context, &registry, world.clone());
+        let component_type =
+            Reference::new_type(Type::of::<Integer>(&registry).unwrap(), &registry);
+        let taken = World::take_component(
+            &mut context,
+            &registry,
+            world.clone(),
+            entity.clone(),
+            component_type.clone(),
+        );
+        assert!(taken.does_share_reference(&component, false));
+        assert_eq!(*taken.read::<Integer>().unwrap(), 42);
+        assert!(World::get(world.clone(), entity.clone(), component_type.clone()).is_null());
+        let other_type = Reference::new_type(Type::of::<Real>(&registry).unwrap(), &registry);
+        assert!(
+            World::get(world.clone(), entity.clone(), other_type.clone())
+                .does_share_reference(&other, false)
+        );
+        assert!(World::take_component(
+            &mut context,
+            &registry,
+            world.clone(),
+            entity.clone(),
+            component_type.clone()
+        )
+        .is_null());
+        // taking last component keeps entity alive.
+        let taken = World::take_component(
+            &mut context,
+            &registry,
+            world.clone(),
+            entity.clone(),
+            other_type,
+        );
+        assert!(taken.does_share_reference(&other, false));
+        World::maintain(&mut context, &registry, world.clone());
+        let entities = World::entities(&registry, world);
+        let entities = entities.read::<Array>().unwrap();
+        assert_eq!(entities.len(), 1);
+        assert_eq!(
+            *entities[0].read::<Integer>().unwrap(),
+            *entity.read::<Integer>().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_single() {
+        let mut registry = Registry::default();
+        intuicio_frontend_simpleton::library::install(&mut registry);
+        install(&mut registry);
+        let mut context = Context::new(10240, 10240);
+        let world = World::new(&registry);
+        let component_type =
+            Reference::new_type(Type::of::<Integer>(&registry).unwrap(), &registry);
+        assert!(World::single(&registry, world.clone(), component_type.clone()).is_null());
+        let entity = World::spawn(&registry, world.clone());
+        let component = Reference::new_integer(42, &registry);
+        World::add(world.clone(), entity.clone(), component.clone());
+        World::add(
+            world.clone(),
+            entity.clone(),
+            Reference::new_real(4.2, &registry),
+        );
+        let other = World::spawn(&registry, world.clone());
+        World::add(
+            world.clone(),
+            other.clone(),
+            Reference::new_real(2.4, &registry),
+        );
+        World::maintain(&mut context, &registry, world.clone());
+        let result = World::single(&registry, world.clone(), component_type.clone());
+        let result = result.read::<Array>().unwrap();
+        assert_eq!(
+            *result[0].read::<Integer>().unwrap(),
+            *entity.read::<Integer>().unwrap()
+        );
+        assert!(result[1].does_share_reference(&component, false));
+        World::add(world.clone(), other, Reference::new_integer(7, &registry));
+        World::maintain(&mut context, &registry, world.clone());
+        assert!(World::single(&registry, world, component_type).is_null());
+    }
+
+    static ADDED: AtomicUsize = AtomicUsize::new(0);
+    static REMOVED: AtomicUsize = AtomicUsize::new(0);
+
+    #[intuicio_function(module_name = "test")]
+    fn added(_entity: Reference, component: Reference) -> Reference {
+        assert!(component.read::<Integer>().is_some());
+        ADDED.fetch_add(1, Ordering::SeqCst);
+        Reference::null()
+    }
+
+    #[intuicio_function(module_name = "test", use_registry)]
+    fn removed(
+        registry: &Registry,
+        world: Reference,
+        entity: Reference,
+        component: Reference,
+    ) -> Reference {
+        let component_type = Reference::new_type(component.type_of().unwrap(), registry);
+        // hook runs before component gets removed from world.
+        assert!(World::get(world, entity, component_type).does_share_reference(&component, false));
+        REMOVED.fetch_add(1, Ordering::SeqCst);
+        Reference::null()
+    }
+
+    #[test]
+    fn test_hooks() {
+        let mut registry = Registry::default();
+        intuicio_frontend_simpleton::library::install(&mut registry);
+        install(&mut registry);
+        registry.add_function(added::define_function(&registry));
+        registry.add_function(removed::define_function(&registry));
+        let mut context = Context::new(10240, 10240);
+        let world = World::new(&registry);
+        let component_type =
+            Reference::new_type(Type::of::<Integer>(&registry).unwrap(), &registry);
+        World::on_add(
+            world.clone(),
+            component_type.clone(),
+            Reference::new(
+                Closure {
+                    function: Function::by_name("added", "test", &registry).unwrap(),
+                    captured: vec![],
+                },
+                &registry,
+            ),
+        );
+        World::on_remove(
+            world.clone(),
+            component_type.clone(),
+            Reference::new(
+                Closure {
+                    function: Function::by_name("removed", "test", &registry).unwrap(),
+                    captured: vec![world.clone()],
+                },
+                &registry,
+            ),
+        );
+        for _ in 0..3 {
+            let entities = (0..4)
+                .map(|value| {
+                    let entity = World::spawn(&registry, world.clone());
+                    World::add(
+                        world.clone(),
+                        entity.clone(),
+                        Reference::new_integer(value, &registry),
+                    );
+                    World::add(
+                        world.clone(),
+                        entity.clone(),
+                        Reference::new_real(0.0, &registry),
+                    );
+                    entity
+                })
+                .collect::<Vec<_>>();
+            World::maintain(&mut context, &registry, world.clone());
+            World::remove(world.clone(), entities[0].clone(), component_type.clone());
+            World::despawn(world.clone(), entities[1].clone());
+            // replacing component of already present type does not add it.
+            World::add(
+                world.clone(),
+                entities[2].clone(),
+                Reference::new_integer(4, &registry),
+            );
+            World::take_component(
+                &mut context,
+                &registry,
+                world.clone(),
+                entities[3].clone(),
+                component_type.clone(),
+            );
+            World::maintain(&mut context, &registry, world.clone());
+            World::despawn_all(world.clone());
+            World::maintain(&mut context, &registry, world.clone());
+        }
+        assert_eq!(ADDED.load(Ordering::SeqCst), 12);
+        assert_eq!(REMOVED.load(Ordering::SeqCst), 12);
+    }
+
+    #[intuicio_function(module_name = "test", use_registry)]
+    fn is_even(registry: &Registry, item: Reference) -> Reference {
+        let value = *item.read::<Array>().unwrap()[1].read::<Integer>().unwrap();
+        Reference::new_boolean(value % 2 == 0, registry)
+    }
+
+    #[intuicio_function(module_name = "test")]
+    fn component(item: Reference) -> Reference {
+        item.read::<Array>().unwrap()[1].clone()
+    }
+
+    #[test]
+    fn test_query_iterator() {
+        let mut registry = Registry::default();
+        intuicio_frontend_simpleton::library::install(&mut registry);
+        install(&mut registry);
+        registry.add_function(is_even::define_function(&registry));
+        registry.add_function(component::define_function(&registry));
+        let closure = |name: &str| {
+            Reference::new(
+                Closure {
+                    function: Function::by_name(name, "test", &registry).unwrap(),
+                    captured: vec![],
+                },
+                &registry,
+            )
+        };
+        let mut context = Context::new(10240, 10240);
+        let world = World::new(&registry);
+        for value in 1..=6 {
+            let entity = World::spawn(&registry, world.clone());
+            World::add(
+                world.clone(),
+                entity.clone(),
+                Reference::new_integer(value, &registry),
+            );
+            if value > 3 {
+                World::add(world.clone(), entity, Reference::new_real(0.0, &registry));
+            }
+        }
+        World::maintain(&mut context, &registry, world.clone());
+        let component_type =
+            Reference::new_type(Type::of::<Integer>(&registry).unwrap(), &registry);
+        let query = World::query(
+            &registry,
+            world,
+            Reference::new_array(vec![component_type], &registry),
+        );
+        let iterator = iter::filter(&registry, query, closure("is_even"));
+        let iterator = iter::map(&registry, iterator, closure("component"));
+        let result = array::collect(&mut context, &registry, iterator);
+        let mut result = result
+            .read::<Array>()
+            .unwrap()
+            .iter()
+            .map(|value| *value.read::<Integer>().unwrap())
+            .collect::<Vec<_>>();
+        result.sort();
+        assert_eq!(result, vec![2, 4, 6]);
+    }
+}
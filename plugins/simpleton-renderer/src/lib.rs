@@ -1,33 +1,51 @@
 use glow::{
     Buffer, Context as GlowContext, HasContext, PixelUnpackData, Program, Texture, UniformLocation,
-    VertexArray, ARRAY_BUFFER, BLEND, CLAMP_TO_EDGE, COLOR_BUFFER_BIT, ELEMENT_ARRAY_BUFFER, FLOAT,
-    FRAGMENT_SHADER, LINEAR, NEAREST, ONE_MINUS_SRC_ALPHA, RGBA, SRC_ALPHA, STATIC_DRAW, TEXTURE0,
-    TEXTURE_2D, TEXTURE_MAG_FILTER, TEXTURE_MIN_FILTER, TEXTURE_WRAP_S, TEXTURE_WRAP_T, TRIANGLES,
-    UNSIGNED_BYTE, UNSIGNED_INT, VERTEX_SHADER,
+    VertexArray, ARRAY_BUFFER, BLEND, CLAMP_TO_EDGE, COLOR_BUFFER_BIT, DYNAMIC_DRAW,
+    ELEMENT_ARRAY_BUFFER, FLOAT, FRAGMENT_SHADER, LINEAR, NEAREST, ONE_MINUS_SRC_ALPHA, RGBA,
+    SCISSOR_BOX, SCISSOR_TEST, SRC_ALPHA, STATIC_DRAW, TEXTURE0, TEXTURE_2D, TEXTURE_MAG_FILTER,
+    TEXTURE_MIN_FILTER, TEXTURE_WRAP_S, TEXTURE_WRAP_T, TRIANGLES, UNIFORM_BUFFER, UNSIGNED_BYTE,
+    UNSIGNED_INT, VERTEX_SHADER, VIEWPORT,
 };
 use image::ImageReader;
 use intuicio_core::{core_version, prelude::*};
 use intuicio_data::prelude::*;
 use intuicio_derive::{intuicio_method, intuicio_methods, IntuicioStruct};
-use intuicio_frontend_simpleton::prelude::{bytes::Bytes, *};
+use intuicio_frontend_simpleton::{
+    or_error,
+    prelude::{bytes::Bytes, *},
+    raise_error,
+};
 use std::{collections::HashMap, io::Cursor};
 use vek::{FrustumPlanes, Mat4, Quaternion, Transform as VekTransform, Vec3};
 
 pub type Gl = Option<ManagedRef<GlowContext>>;
 
+/// Name of uniform block shaders can declare to read per-frame data:
+/// `layout(std140) uniform Frame { mat4 projection; mat4 view; float time; };`
+const FRAME_BLOCK_NAME: &str = "Frame";
+const FRAME_BLOCK_BINDING: u32 = 0;
+/// Number of floats in std140 layout of frame block.
+const FRAME_BLOCK_SIZE: usize = 36;
+
 #[derive(IntuicioStruct, Default)]
 #[intuicio(name = "Renderer", module_name = "renderer")]
 pub struct Renderer {
     #[intuicio(ignore)]
     gl: Gl,
+    /// {handle: (program, uniform locations, reads frame block)}
     #[intuicio(ignore)]
-    shaders: HashMap<Integer, (Program, HashMap<String, UniformLocation>)>,
+    shaders: HashMap<Integer, (Program, HashMap<String, UniformLocation>, bool)>,
     #[intuicio(ignore)]
     textures: HashMap<Integer, Texture>,
     /// {handle: (vertex array, vertex buffer, index buffer)}
     #[intuicio(ignore)]
     meshes: HashMap<Integer, (VertexArray, Buffer, Buffer)>,
     #[intuicio(ignore)]
+    uniform_buffers: UniformBuffers<Buffer>,
+    /// Frame block buffer, `None` if GL context does not support uniform buffers.
+    #[intuicio(ignore)]
+    frame_buffer: Option<Buffer>,
+    #[intuicio(ignore)]
     handle_generator: Integer,
     #[intuicio(ignore)]
     shader_version: String,
@@ -38,13 +56,34 @@ impl Renderer {
     #[allow(clippy::new_ret_no_self)]
     #[intuicio_method(use_registry)]
     pub fn new(registry: &Registry, gl: Reference, shader_version: Reference) -> Reference {
-        let gl = gl.read::<Gl>().expect("`gl` is not a GL context!");
-        let gl = gl.as_ref().expect("`gl` does not have valid GL context!");
-        unsafe {
+        let gl = or_error!(gl.read::<Gl>(), "`gl` is not a GL context!");
+        let gl = or_error!(gl.as_ref(), "`gl` does not have valid GL context!");
+        let frame_buffer = unsafe {
             let gl = gl.read().expect("Could not read `gl` GL context!");
             gl.clear_color(0.0, 0.0, 0.0, 1.0);
             gl.clear(COLOR_BUFFER_BIT);
-        }
+            let version = gl.version();
+            let supports_uniform_buffers = if version.is_embedded {
+                version.major >= 3
+            } else {
+                (version.major, version.minor) >= (3, 1)
+            };
+            if supports_uniform_buffers {
+                let buffer = gl
+                    .create_buffer()
+                    .expect("Could not create frame uniform buffer!");
+                gl.bind_buffer(UNIFORM_BUFFER, Some(buffer));
+                gl.buffer_data_size(
+                    UNIFORM_BUFFER,
+                    (FRAME_BLOCK_SIZE * std::mem::size_of::<f32>()) as _,
+                    DYNAMIC_DRAW,
+                );
+                gl.bind_buffer(UNIFORM_BUFFER, None);
+                Some(buffer)
+            } else {
+                None
+            }
+        };
         let shader_version = shader_version
             .read::<Text>()
             .map(|version| version.to_owned())
@@ -55,6 +94,8 @@ impl Renderer {
                 shaders: Default::default(),
                 textures: Default::default(),
                 meshes: Default::default(),
+                uniform_buffers: Default::default(),
+                frame_buffer,
                 handle_generator: 0,
                 shader_version,
             },
@@ -71,31 +112,42 @@ impl Renderer {
         uniforms: Reference,
         layout: Reference,
     ) -> Reference {
-        let mut renderer = renderer
-            .write::<Renderer>()
-            .expect("`renderer` is not a Renderer!");
+        let mut renderer = or_error!(
+            renderer.write::<Renderer>(),
+            "`renderer` is not a Renderer!"
+        );
         let handle = renderer.generate_handle();
-        let vertex_content = vertex_content
-            .read::<Text>()
-            .expect("`vertex_content` is not a Text!");
+        let vertex_content = or_error!(
+            vertex_content.read::<Text>(),
+            "`vertex_content` is not a Text!"
+        );
         let vertex_content = format!("#version {}\n{}", renderer.shader_version, vertex_content);
-        let fragment_content = fragment_content
-            .read::<Text>()
-            .expect("`fragment_content` is not a Text!");
+        let fragment_content = or_error!(
+            fragment_content.read::<Text>(),
+            "`fragment_content` is not a Text!"
+        );
         let fragment_content =
             format!("#version {}\n{}", renderer.shader_version, fragment_content);
-        let uniforms = uniforms
-            .read::<Array>()
-            .expect("`uniforms` is not an Array!");
-        let layout = layout.read::<Array>().expect("`layout` is not an Array!");
+        let uniforms = or_error!(uniforms.read::<Array>(), "`uniforms` is not an Array!");
+        let layout = or_error!(layout.read::<Array>(), "`layout` is not an Array!");
         if layout.is_empty() {
-            panic!("`layout` array is empty!");
+            raise_error!("`layout` array is empty!");
+        }
+        let mut uniform_names = Vec::with_capacity(uniforms.len());
+        for item in uniforms.iter() {
+            let item = or_error!(item.read::<Text>(), "`uniforms` item is not a Text!");
+            uniform_names.push(item.to_owned());
+        }
+        let mut layout_names = Vec::with_capacity(layout.len() / 2);
+        for chunk in layout.chunks(2) {
+            let name = or_error!(
+                chunk[0].read::<Text>(),
+                "`layout` array item is not a Text!"
+            );
+            layout_names.push(name.to_owned());
         }
-        let (program, uniforms) = unsafe {
-            let gl = renderer
-                .gl
-                .as_ref()
-                .expect("`renderer` has invalid GL context!");
+        let (program, uniforms, frame_block) = unsafe {
+            let gl = or_error!(renderer.gl.as_ref(), "`renderer` has invalid GL context!");
             let gl = gl.read().unwrap();
             let vertex_shader = gl
                 .create_shader(VERTEX_SHADER)
@@ -103,10 +155,9 @@ impl Renderer {
             gl.shader_source(vertex_shader, &vertex_content);
             gl.compile_shader(vertex_shader);
             if !gl.get_shader_compile_status(vertex_shader) {
-                panic!(
-                    "Could not compile vertex shader: {}",
-                    gl.get_shader_info_log(vertex_shader)
-                );
+                let log = gl.get_shader_info_log(vertex_shader);
+                gl.delete_shader(vertex_shader);
+                raise_error!(format!("Could not compile vertex shader: {}", log));
             }
             let fragment_shader = gl
                 .create_shader(FRAGMENT_SHADER)
@@ -114,59 +165,65 @@ impl Renderer {
             gl.shader_source(fragment_shader, &fragment_content);
             gl.compile_shader(fragment_shader);
             if !gl.get_shader_compile_status(fragment_shader) {
-                panic!(
-                    "Could not compile fragment shader: {}",
-                    gl.get_shader_info_log(fragment_shader)
-                );
+                let log = gl.get_shader_info_log(fragment_shader);
+                gl.delete_shader(vertex_shader);
+                gl.delete_shader(fragment_shader);
+                raise_error!(format!("Could not compile fragment shader: {}", log));
             }
             let program = gl
                 .create_program()
                 .expect("Could not create program object!");
             gl.attach_shader(program, vertex_shader);
             gl.attach_shader(program, fragment_shader);
-            for (index, chunk) in layout.chunks(2).enumerate() {
-                let name = chunk[0]
-                    .read::<Text>()
-                    .expect("`layout` array item is not a Text!");
-                gl.bind_attrib_location(program, index as _, &name);
+            for (index, name) in layout_names.iter().enumerate() {
+                gl.bind_attrib_location(program, index as _, name);
             }
             gl.link_program(program);
-            if !gl.get_program_link_status(program) {
-                panic!(
-                    "Could not link shader: {}",
-                    gl.get_program_info_log(program)
-                );
-            }
             gl.delete_shader(vertex_shader);
             gl.delete_shader(fragment_shader);
-            let uniforms = uniforms
-                .iter()
+            if !gl.get_program_link_status(program) {
+                let log = gl.get_program_info_log(program);
+                gl.delete_program(program);
+                raise_error!(format!("Could not link shader: {}", log));
+            }
+            let frame_block = renderer.frame_buffer.is_some()
+                && match gl.get_uniform_block_index(program, FRAME_BLOCK_NAME) {
+                    Some(index) => {
+                        gl.uniform_block_binding(program, index, FRAME_BLOCK_BINDING);
+                        true
+                    }
+                    None => false,
+                };
+            for buffer in renderer.uniform_buffers.iter() {
+                if let Some(index) = gl.get_uniform_block_index(program, &buffer.name) {
+                    gl.uniform_block_binding(program, index, buffer.binding);
+                }
+            }
+            let uniforms = uniform_names
+                .into_iter()
                 .filter_map(|item| {
-                    let item = item.read::<Text>().expect("`uniforms` item is not a Text!");
-                    let location = gl.get_uniform_location(program, item.as_str())?;
-                    Some((item.to_owned(), location))
+                    let location = gl.get_uniform_location(program, &item)?;
+                    Some((item, location))
                 })
                 .collect();
-            (program, uniforms)
+            (program, uniforms, frame_block)
         };
-        renderer.shaders.insert(handle, (program, uniforms));
+        renderer
+            .shaders
+            .insert(handle, (program, uniforms, frame_block));
         Reference::new_integer(handle, registry)
     }
 
     #[intuicio_method()]
     pub fn destroy_shader(mut renderer: Reference, handle: Reference) -> Reference {
-        let mut renderer = renderer
-            .write::<Renderer>()
-            .expect("`renderer` is not a Renderer!");
-        let handle = *handle
-            .read::<Integer>()
-            .expect("`handle` is not an Integer!");
-        if let Some((program, _)) = renderer.shaders.remove(&handle) {
+        let mut renderer = or_error!(
+            renderer.write::<Renderer>(),
+            "`renderer` is not a Renderer!"
+        );
+        let handle = *or_error!(handle.read::<Integer>(), "`handle` is not an Integer!");
+        if let Some((program, _, _)) = renderer.shaders.remove(&handle) {
             unsafe {
-                renderer
-                    .gl
-                    .as_ref()
-                    .expect("`renderer` has invalid GL context!")
+                or_error!(renderer.gl.as_ref(), "`renderer` has invalid GL context!")
                     .read()
                     .unwrap()
                     .delete_program(program);
@@ -184,30 +241,23 @@ impl Renderer {
         height: Reference,
         interpolated: Reference,
     ) -> Reference {
-        let mut renderer = renderer
-            .write::<Renderer>()
-            .expect("`renderer` is not a Renderer!");
+        let mut renderer = or_error!(
+            renderer.write::<Renderer>(),
+            "`renderer` is not a Renderer!"
+        );
         let handle = renderer.generate_handle();
-        let bytes = bytes.read::<Bytes>().expect("`bytes` is not Bytes!");
-        let width = width
-            .read::<Integer>()
-            .expect("`width` is not an Integer!")
-            .max(1);
-        let height = height
-            .read::<Integer>()
-            .expect("`height` is not an Integer!")
-            .max(1);
-        let interpolated = *interpolated
-            .read::<Boolean>()
-            .expect("`interpolated` is not a Boolean!");
+        let bytes = or_error!(bytes.read::<Bytes>(), "`bytes` is not Bytes!");
+        let width = or_error!(width.read::<Integer>(), "`width` is not an Integer!").max(1);
+        let height = or_error!(height.read::<Integer>(), "`height` is not an Integer!").max(1);
+        let interpolated = *or_error!(
+            interpolated.read::<Boolean>(),
+            "`interpolated` is not a Boolean!"
+        );
         if (width * height) as usize * std::mem::size_of::<u8>() * 4 != bytes.get_ref().len() {
-            panic!("`bytes` buffer size does not match provided `width` and `height`!");
+            raise_error!("`bytes` buffer size does not match provided `width` and `height`!");
         }
         let texture = unsafe {
-            let gl = renderer
-                .gl
-                .as_ref()
-                .expect("`renderer` has invalid GL context!");
+            let gl = or_error!(renderer.gl.as_ref(), "`renderer` has invalid GL context!");
             let gl = gl.read().unwrap();
             let texture = gl
                 .create_texture()
@@ -239,18 +289,14 @@ impl Renderer {
 
     #[intuicio_method()]
     pub fn destroy_texture(mut renderer: Reference, handle: Reference) -> Reference {
-        let mut renderer = renderer
-            .write::<Renderer>()
-            .expect("`renderer` is not a Renderer!");
-        let handle = *handle
-            .read::<Integer>()
-            .expect("`handle` is not an Integer!");
+        let mut renderer = or_error!(
+            renderer.write::<Renderer>(),
+            "`renderer` is not a Renderer!"
+        );
+        let handle = *or_error!(handle.read::<Integer>(), "`handle` is not an Integer!");
         if let Some(texture) = renderer.textures.remove(&handle) {
             unsafe {
-                renderer
-                    .gl
-                    .as_ref()
-                    .expect("`renderer` has invalid GL context!")
+                or_error!(renderer.gl.as_ref(), "`renderer` has invalid GL context!")
                     .read()
                     .unwrap()
                     .delete_texture(texture);
@@ -267,25 +313,31 @@ impl Renderer {
         index_bytes: Reference,
         layout: Reference,
     ) -> Reference {
-        let mut renderer = renderer
-            .write::<Renderer>()
-            .expect("`renderer` is not a Renderer!");
+        let mut renderer = or_error!(
+            renderer.write::<Renderer>(),
+            "`renderer` is not a Renderer!"
+        );
         let handle = renderer.generate_handle();
-        let vertex_bytes = vertex_bytes
-            .read::<Bytes>()
-            .expect("`vertex_bytes` is not Bytes!");
-        let index_bytes = index_bytes
-            .read::<Bytes>()
-            .expect("`index_bytes` is not Bytes!");
-        let layout = layout.read::<Array>().expect("`layout` is not an Array!");
+        let vertex_bytes = or_error!(vertex_bytes.read::<Bytes>(), "`vertex_bytes` is not Bytes!");
+        let index_bytes = or_error!(index_bytes.read::<Bytes>(), "`index_bytes` is not Bytes!");
+        let layout = or_error!(layout.read::<Array>(), "`layout` is not an Array!");
         if layout.is_empty() {
-            panic!("`layout` array is empty!");
+            raise_error!("`layout` array is empty!");
+        }
+        let mut channels = Vec::with_capacity(layout.len() / 2);
+        for chunk in layout.chunks(2) {
+            or_error!(
+                chunk[0].read::<Text>(),
+                "`layout` array item is not a Text!"
+            );
+            let count = *or_error!(
+                chunk.get(1).and_then(|item| item.read::<Integer>()),
+                "`layout` array item is not an Integer!"
+            );
+            channels.push(count.max(0) as usize);
         }
         let (vertex_array, vertex_buffer, index_buffer) = unsafe {
-            let gl = renderer
-                .gl
-                .as_ref()
-                .expect("`renderer` has invalid GL context!");
+            let gl = or_error!(renderer.gl.as_ref(), "`renderer` has invalid GL context!");
             let gl = gl.read().unwrap();
             let vertex_array = gl
                 .create_vertex_array()
@@ -297,24 +349,12 @@ impl Renderer {
             let vertex_buffer = gl.create_buffer().expect("Could not create vertex buffer!");
             gl.bind_buffer(ARRAY_BUFFER, Some(vertex_buffer));
             gl.buffer_data_u8_slice(ARRAY_BUFFER, vertex_bytes.get_ref(), STATIC_DRAW);
-            let layout = layout
-                .chunks(2)
-                .map(|chunk| {
-                    let name = chunk[0]
-                        .read::<Text>()
-                        .expect("`layout` array item is not a Text!");
-                    let channels = *chunk[1]
-                        .read::<Integer>()
-                        .expect("`layout` array item is not an Integer!");
-                    (name.to_owned(), channels as usize)
-                })
-                .collect::<Vec<_>>();
-            let stride = layout
+            let stride = channels
                 .iter()
-                .map(|(_, channels)| (*channels * std::mem::size_of::<f32>()) as i32)
+                .map(|channels| (*channels * std::mem::size_of::<f32>()) as i32)
                 .sum();
             let mut offset = 0;
-            for (index, (_, channels)) in layout.into_iter().enumerate() {
+            for (index, channels) in channels.into_iter().enumerate() {
                 gl.vertex_attrib_pointer_f32(
                     index as _,
                     channels as _,
@@ -337,18 +377,14 @@ impl Renderer {
 
     #[intuicio_method()]
     pub fn destroy_mesh(mut renderer: Reference, handle: Reference) -> Reference {
-        let mut renderer = renderer
-            .write::<Renderer>()
-            .expect("`renderer` is not a Renderer!");
-        let handle = *handle
-            .read::<Integer>()
-            .expect("`handle` is not an Integer!");
+        let mut renderer = or_error!(
+            renderer.write::<Renderer>(),
+            "`renderer` is not a Renderer!"
+        );
+        let handle = *or_error!(handle.read::<Integer>(), "`handle` is not an Integer!");
         if let Some((vertex_array, vertex_buffer, index_buffer)) = renderer.meshes.remove(&handle) {
             unsafe {
-                let gl = renderer
-                    .gl
-                    .as_ref()
-                    .expect("`renderer` has invalid GL context!");
+                let gl = or_error!(renderer.gl.as_ref(), "`renderer` has invalid GL context!");
                 let gl = gl.read().unwrap();
                 gl.delete_vertex_array(vertex_array);
                 gl.delete_buffer(vertex_buffer);
@@ -358,6 +394,120 @@ impl Renderer {
         Reference::null()
     }
 
+    /// Creates buffer for uniform block of `name` with `data` array of reals
+    /// as its initial content, shaders declaring that block read from it.
+    #[intuicio_method(use_registry)]
+    pub fn create_uniform_buffer(
+        registry: &Registry,
+        mut renderer: Reference,
+        name: Reference,
+        data: Reference,
+    ) -> Reference {
+        let mut renderer = or_error!(
+            renderer.write::<Renderer>(),
+            "`renderer` is not a Renderer!"
+        );
+        if renderer.frame_buffer.is_none() {
+            raise_error!("`renderer` GL context does not support uniform buffers!");
+        }
+        let name = or_error!(name.read::<Text>(), "`name` is not a Text!");
+        if renderer.uniform_buffers.contains_block(&name) {
+            raise_error!(format!(
+                "Uniform block `{}` already has a buffer!",
+                name.as_str()
+            ));
+        }
+        let data = or_error!(floats_of(&data), "`data` is not an Array of Reals!");
+        if data.is_empty() {
+            raise_error!("`data` array is empty!");
+        }
+        let handle = renderer.generate_handle();
+        let buffer = unsafe {
+            let gl = or_error!(renderer.gl.as_ref(), "`renderer` has invalid GL context!");
+            let gl = gl.read().unwrap();
+            let buffer = gl
+                .create_buffer()
+                .expect("Could not create uniform buffer!");
+            gl.bind_buffer(UNIFORM_BUFFER, Some(buffer));
+            gl.buffer_data_u8_slice(UNIFORM_BUFFER, &bytes_of(&data), DYNAMIC_DRAW);
+            gl.bind_buffer(UNIFORM_BUFFER, None);
+            buffer
+        };
+        let binding = renderer
+            .uniform_buffers
+            .insert(handle, name.to_owned(), buffer, data.len());
+        unsafe {
+            let gl = or_error!(renderer.gl.as_ref(), "`renderer` has invalid GL context!");
+            let gl = gl.read().unwrap();
+            for (program, _, _) in renderer.shaders.values() {
+                if let Some(index) = gl.get_uniform_block_index(*program, &name) {
+                    gl.uniform_block_binding(*program, index, binding);
+                }
+            }
+        }
+        Reference::new_integer(handle, registry)
+    }
+
+    /// `offset` is optional number of reals to skip and defaults to 0.
+    #[intuicio_method()]
+    pub fn update_uniform_buffer(
+        renderer: Reference,
+        handle: Reference,
+        data: Reference,
+        offset: Reference,
+    ) -> Reference {
+        let renderer = or_error!(renderer.read::<Renderer>(), "`renderer` is not a Renderer!");
+        let handle = *or_error!(handle.read::<Integer>(), "`handle` is not an Integer!");
+        let data = or_error!(floats_of(&data), "`data` is not an Array of Reals!");
+        let offset = offset
+            .read::<Integer>()
+            .map(|value| *value)
+            .unwrap_or(0)
+            .max(0) as usize;
+        let buffer = or_error!(
+            renderer.uniform_buffers.get(handle),
+            "`handle` is not a valid uniform buffer!"
+        );
+        if !buffer.fits(offset, data.len()) {
+            raise_error!("`data` does not fit in uniform buffer at `offset`!");
+        }
+        unsafe {
+            let gl = or_error!(renderer.gl.as_ref(), "`renderer` has invalid GL context!");
+            let gl = gl.read().unwrap();
+            gl.bind_buffer(UNIFORM_BUFFER, Some(buffer.object));
+            gl.buffer_sub_data_u8_slice(
+                UNIFORM_BUFFER,
+                (offset * std::mem::size_of::<f32>()) as _,
+                &bytes_of(&data),
+            );
+            gl.bind_buffer(UNIFORM_BUFFER, None);
+        }
+        Reference::null()
+    }
+
+    #[intuicio_method()]
+    pub fn destroy_uniform_buffer(mut renderer: Reference, handle: Reference) -> Reference {
+        let mut renderer = or_error!(
+            renderer.write::<Renderer>(),
+            "`renderer` is not a Renderer!"
+        );
+        let handle = *or_error!(handle.read::<Integer>(), "`handle` is not an Integer!");
+        if let Some(buffer) = renderer.uniform_buffers.remove(handle) {
+            unsafe {
+                or_error!(renderer.gl.as_ref(), "`renderer` has invalid GL context!")
+                    .read()
+                    .unwrap()
+                    .delete_buffer(buffer.object);
+            }
+        }
+        Reference::null()
+    }
+
+    /// `viewport_x` and `viewport_y` are optional and default to 0, `scissor`
+    /// is optional array of `[x, y, width, height]` integers. Viewport or
+    /// scissor with zero area skips drawing. `time` is optional real exposed
+    /// to shaders along with projection and view, defaults to 0.
+    #[allow(clippy::too_many_arguments)]
     #[intuicio_method()]
     pub fn render(
         renderer: Reference,
@@ -366,36 +516,63 @@ impl Renderer {
         viewport_height: Reference,
         perspective_fov: Reference,
         camera_transform: Reference,
+        viewport_x: Reference,
+        viewport_y: Reference,
+        scissor: Reference,
+        time: Reference,
     ) -> Reference {
-        let renderer = renderer
-            .read::<Renderer>()
-            .expect("`renderer` is not a Renderer!");
-        let buffer = buffer
-            .read::<RenderBuffer>()
-            .expect("`buffer` is not a RenderBuffer!");
-        let viewport_width = *viewport_width
-            .read::<Integer>()
-            .expect("`viewport_width` is not an Integer!") as f32;
-        let viewport_height = *viewport_height
-            .read::<Integer>()
-            .expect("`viewport_height` is not an Integer!") as f32;
+        let renderer = or_error!(renderer.read::<Renderer>(), "`renderer` is not a Renderer!");
+        let buffer = or_error!(
+            buffer.read::<RenderBuffer>(),
+            "`buffer` is not a RenderBuffer!"
+        );
+        let region = match RenderRegion::new(
+            &viewport_x,
+            &viewport_y,
+            &viewport_width,
+            &viewport_height,
+            &scissor,
+        ) {
+            Ok(Some(region)) => region,
+            Ok(None) => return Reference::null(),
+            Err(message) => raise_error!(message),
+        };
+        let viewport_width = region.viewport[2] as f32;
+        let viewport_height = region.viewport[3] as f32;
         let perspective_fov = perspective_fov
             .read::<Real>()
             .map(|value| *value)
             .unwrap_or(0.0)
             .max(0.0) as f32;
-        let camera_transform = camera_transform
-            .read::<Transform>()
-            .expect("`camera_transform` is not a Transform!")
-            .to_matrix();
-        let gl = renderer
-            .gl
-            .as_ref()
-            .expect("`renderer` has invalid GL context!");
+        let time = time.read::<Real>().map(|value| *value).unwrap_or(0.0) as f32;
+        let camera_transform = or_error!(
+            camera_transform.read::<Transform>(),
+            "`camera_transform` is not a Transform!"
+        )
+        .to_matrix();
+        let gl = or_error!(renderer.gl.as_ref(), "`renderer` has invalid GL context!");
         let gl = gl.read().unwrap();
+        let mut last_viewport = [0; 4];
+        let mut last_scissor = [0; 4];
+        let last_scissor_test;
         unsafe {
             gl.enable(BLEND);
             gl.blend_func(SRC_ALPHA, ONE_MINUS_SRC_ALPHA);
+            gl.get_parameter_i32_slice(VIEWPORT, &mut last_viewport);
+            gl.get_parameter_i32_slice(SCISSOR_BOX, &mut last_scissor);
+            last_scissor_test = gl.is_enabled(SCISSOR_TEST);
+            let [x, y, width, height] = region.viewport;
+            gl.viewport(x, y, width, height);
+            if let Some([x, y, width, height]) = region.scissor {
+                gl.enable(SCISSOR_TEST);
+                gl.scissor(x, y, width, height);
+            }
+            if let Some(frame_buffer) = renderer.frame_buffer {
+                gl.bind_buffer_base(UNIFORM_BUFFER, FRAME_BLOCK_BINDING, Some(frame_buffer));
+            }
+            for buffer in renderer.uniform_buffers.iter() {
+                gl.bind_buffer_base(UNIFORM_BUFFER, buffer.binding, Some(buffer.object));
+            }
         }
         let projection = if perspective_fov > 0.0 {
             Mat4::infinite_perspective_rh(
@@ -418,6 +595,7 @@ impl Renderer {
         let projection = projection.as_col_slice();
         let view = camera_transform.inverted();
         let view = view.as_col_slice();
+        let mut frame_uniforms = FrameUniforms::default();
         let mut last_shader = -1;
         let mut last_uniform_locations = None;
         let mut last_mesh = -1;
@@ -427,10 +605,30 @@ impl Renderer {
                     continue;
                 }
                 if last_shader != renderable.shader {
-                    if let Some((program, uniforms)) = renderer.shaders.get(&renderable.shader) {
+                    if let Some((program, uniforms, frame_block)) =
+                        renderer.shaders.get(&renderable.shader)
+                    {
                         gl.use_program(Some(*program));
                         last_shader = renderable.shader;
                         last_uniform_locations = Some(uniforms);
+                        match frame_uniforms.upload(*frame_block) {
+                            FrameUpload::Block => {
+                                let data = frame_block_data(projection, view, time);
+                                gl.bind_buffer(UNIFORM_BUFFER, renderer.frame_buffer);
+                                gl.buffer_sub_data_u8_slice(UNIFORM_BUFFER, 0, &bytes_of(&data));
+                                gl.bind_buffer(UNIFORM_BUFFER, None);
+                            }
+                            FrameUpload::Uniforms => {
+                                gl.uniform_matrix_4_f32_slice(
+                                    uniforms.get("projection"),
+                                    false,
+                                    projection,
+                                );
+                                gl.uniform_matrix_4_f32_slice(uniforms.get("view"), false, view);
+                                gl.uniform_1_f32(uniforms.get("time"), time);
+                            }
+                            FrameUpload::None => {}
+                        }
                     } else {
                         continue;
                     }
@@ -446,8 +644,6 @@ impl Renderer {
                 let mut active_textures = 0;
                 if let Some(locations) = last_uniform_locations {
                     let model = renderable.model_transform.as_col_slice();
-                    gl.uniform_matrix_4_f32_slice(locations.get("projection"), false, projection);
-                    gl.uniform_matrix_4_f32_slice(locations.get("view"), false, view);
                     gl.uniform_matrix_4_f32_slice(locations.get("model"), false, model);
                     for (name, data) in &renderable.uniforms {
                         match data {
@@ -489,6 +685,15 @@ impl Renderer {
             }
             gl.bind_vertex_array(None);
             gl.use_program(None);
+            let [x, y, width, height] = last_viewport;
+            gl.viewport(x, y, width, height);
+            if region.scissor.is_some() {
+                let [x, y, width, height] = last_scissor;
+                gl.scissor(x, y, width, height);
+                if !last_scissor_test {
+                    gl.disable(SCISSOR_TEST);
+                }
+            }
         }
         Reference::null()
     }
@@ -500,6 +705,64 @@ impl Renderer {
     }
 }
 
+/// Target area of render call, in window pixels.
+#[derive(Debug, PartialEq, Eq)]
+struct RenderRegion {
+    viewport: [i32; 4],
+    scissor: Option<[i32; 4]>,
+}
+
+impl RenderRegion {
+    fn new(
+        viewport_x: &Reference,
+        viewport_y: &Reference,
+        viewport_width: &Reference,
+        viewport_height: &Reference,
+        scissor: &Reference,
+    ) -> Result<Option<Self>, &'static str> {
+        let viewport_x = viewport_x
+            .read::<Integer>()
+            .map(|value| *value)
+            .unwrap_or(0) as i32;
+        let viewport_y = viewport_y
+            .read::<Integer>()
+            .map(|value| *value)
+            .unwrap_or(0) as i32;
+        let viewport_width = *viewport_width
+            .read::<Integer>()
+            .ok_or("`viewport_width` is not an Integer!")? as i32;
+        let viewport_height = *viewport_height
+            .read::<Integer>()
+            .ok_or("`viewport_height` is not an Integer!")? as i32;
+        if viewport_width <= 0 || viewport_height <= 0 {
+            return Ok(None);
+        }
+        let scissor = if scissor.is_null() {
+            None
+        } else {
+            let scissor = scissor
+                .read::<Array>()
+                .ok_or("`scissor` is not an Array!")?;
+            let mut result = [0; 4];
+            for (index, value) in result.iter_mut().enumerate() {
+                *value = *scissor
+                    .get(index)
+                    .and_then(|value| value.read::<Integer>())
+                    .ok_or("`scissor` is not an Array of 4 Integers!")?
+                    as i32;
+            }
+            if result[2] <= 0 || result[3] <= 0 {
+                return Ok(None);
+            }
+            Some(result)
+        };
+        Ok(Some(Self {
+            viewport: [viewport_x, viewport_y, viewport_width, viewport_height],
+            scissor,
+        }))
+    }
+}
+
 #[derive(IntuicioStruct, Default)]
 #[intuicio(name = "RenderBuffer", module_name = "render_buffer")]
 pub struct RenderBuffer {
@@ -511,13 +774,15 @@ pub struct RenderBuffer {
 impl RenderBuffer {
     #[intuicio_method()]
     pub fn clear(mut buffer: Reference) -> Reference {
-        let mut buffer = buffer
-            .write::<RenderBuffer>()
-            .expect("`buffer` is not a RenderBuffer!");
+        let mut buffer = or_error!(
+            buffer.write::<RenderBuffer>(),
+            "`buffer` is not a RenderBuffer!"
+        );
         buffer.buffer.clear();
         Reference::null()
     }
 
+    #[allow(clippy::too_many_arguments)]
     #[intuicio_method()]
     pub fn enqueue(
         mut buffer: Reference,
@@ -528,46 +793,40 @@ impl RenderBuffer {
         triangles_count: Reference,
         uniforms: Reference,
     ) -> Reference {
-        let mut buffer = buffer
-            .write::<RenderBuffer>()
-            .expect("`buffer` is not a RenderBuffer!");
-        let shader = *shader
-            .read::<Integer>()
-            .expect("`shader` is not an Integer!");
-        let mesh = *mesh.read::<Integer>().expect("`mesh` is not an Integer!");
-        let model_transform = model_transform
-            .read::<Transform>()
-            .expect("`model_transform` is not a Transform!")
-            .to_matrix();
-        let index_start = *index_start
-            .read::<Integer>()
-            .expect("`index_start` is not an Integer!") as _;
-        let triangles_count = *triangles_count
-            .read::<Integer>()
-            .expect("`triangles_count` is not an Integer!") as _;
-        let uniforms = uniforms.read::<Map>().expect("`uniforms` is not a Map!");
-        let uniforms = uniforms
-            .iter()
-            .filter_map(|(name, data)| {
-                let data = if let Some(data) = data.read::<Array>() {
-                    UniformData::Float(
-                        data.iter()
-                            .map(|item| {
-                                *item
-                                    .read::<Real>()
-                                    .expect("`data` array item is not a Real!")
-                                    as f32
-                            })
-                            .collect(),
-                    )
-                } else if let Some(data) = data.read::<Integer>() {
-                    UniformData::Texture(*data)
-                } else {
-                    return None;
-                };
-                Some((name.to_owned(), data))
-            })
-            .collect();
+        let mut buffer = or_error!(
+            buffer.write::<RenderBuffer>(),
+            "`buffer` is not a RenderBuffer!"
+        );
+        let shader = *or_error!(shader.read::<Integer>(), "`shader` is not an Integer!");
+        let mesh = *or_error!(mesh.read::<Integer>(), "`mesh` is not an Integer!");
+        let model_transform = or_error!(
+            Transform::matrix_of(&model_transform),
+            "`model_transform` is not a Transform or Matrix!"
+        );
+        let index_start = *or_error!(
+            index_start.read::<Integer>(),
+            "`index_start` is not an Integer!"
+        ) as _;
+        let triangles_count = *or_error!(
+            triangles_count.read::<Integer>(),
+            "`triangles_count` is not an Integer!"
+        ) as _;
+        let uniforms = or_error!(uniforms.read::<Map>(), "`uniforms` is not a Map!");
+        let mut result = HashMap::with_capacity(uniforms.len());
+        for (name, data) in uniforms.iter() {
+            let data = if data.read::<Array>().is_some() {
+                UniformData::Float(or_error!(
+                    floats_of(data),
+                    "`data` is not an Array of Reals!"
+                ))
+            } else if let Some(data) = data.read::<Integer>() {
+                UniformData::Texture(*data)
+            } else {
+                continue;
+            };
+            result.insert(name.to_owned(), data);
+        }
+        let uniforms = result;
         buffer.buffer.push(Renderable {
             shader,
             mesh,
@@ -614,6 +873,91 @@ impl Transform {
         }
         .into()
     }
+
+    fn matrix_of(value: &Reference) -> Option<Mat4<f32>> {
+        if let Some(transform) = value.read::<Transform>() {
+            Some(transform.to_matrix())
+        } else {
+            value.read::<Matrix>().map(|matrix| matrix.matrix)
+        }
+    }
+}
+
+#[intuicio_methods(module_name = "transform")]
+impl Transform {
+    #[intuicio_method(use_registry)]
+    pub fn matrix(registry: &Registry, transform: Reference) -> Reference {
+        let matrix = or_error!(
+            Self::matrix_of(&transform),
+            "`transform` is not a Transform or Matrix!"
+        );
+        Reference::new(Matrix { matrix }, registry)
+    }
+
+    #[intuicio_method(use_registry)]
+    pub fn combine(registry: &Registry, parent: Reference, child: Reference) -> Reference {
+        let parent = or_error!(
+            Self::matrix_of(&parent),
+            "`parent` is not a Transform or Matrix!"
+        );
+        let child = or_error!(
+            Self::matrix_of(&child),
+            "`child` is not a Transform or Matrix!"
+        );
+        Reference::new(
+            Matrix {
+                matrix: parent * child,
+            },
+            registry,
+        )
+    }
+
+    #[intuicio_method(use_registry)]
+    pub fn inverse(registry: &Registry, transform: Reference) -> Reference {
+        let matrix = or_error!(
+            Self::matrix_of(&transform),
+            "`transform` is not a Transform or Matrix!"
+        );
+        Reference::new(
+            Matrix {
+                matrix: matrix.inverted(),
+            },
+            registry,
+        )
+    }
+
+    #[intuicio_method(use_registry)]
+    pub fn transform_point(
+        registry: &Registry,
+        transform: Reference,
+        x: Reference,
+        y: Reference,
+        z: Reference,
+    ) -> Reference {
+        let matrix = or_error!(
+            Self::matrix_of(&transform),
+            "`transform` is not a Transform or Matrix!"
+        );
+        let x = *or_error!(x.read::<Real>(), "`x` is not a Real!") as f32;
+        let y = *or_error!(y.read::<Real>(), "`y` is not a Real!") as f32;
+        let z = *or_error!(z.read::<Real>(), "`z` is not a Real!") as f32;
+        let point = matrix.mul_point(Vec3::new(x, y, z));
+        Reference::new_array(
+            vec![
+                Reference::new_real(point.x as Real, registry),
+                Reference::new_real(point.y as Real, registry),
+                Reference::new_real(point.z as Real, registry),
+            ],
+            registry,
+        )
+    }
+}
+
+#[derive(IntuicioStruct, Default)]
+#[intuicio(name = "Matrix", module_name = "matrix")]
+pub struct Matrix {
+    #[intuicio(ignore)]
+    matrix: Mat4<f32>,
 }
 
 #[derive(IntuicioStruct, Default)]
@@ -628,13 +972,16 @@ pub struct Image {
 impl Image {
     #[intuicio_method(use_registry)]
     pub fn decode(registry: &Registry, bytes: Reference) -> Reference {
-        let bytes = bytes.read::<Bytes>().expect("`bytes` is not Bytes!");
+        let bytes = or_error!(bytes.read::<Bytes>(), "`bytes` is not Bytes!");
         let bytes = Cursor::new(bytes.get_ref());
-        let reader = ImageReader::new(bytes).with_guessed_format().unwrap();
-        let buffer = reader
-            .decode()
-            .expect("Could not decode image from `bytes`!")
-            .into_rgba8();
+        let buffer = or_error!(
+            ImageReader::new(bytes)
+                .with_guessed_format()
+                .ok()
+                .and_then(|reader| reader.decode().ok()),
+            "Could not decode image from `bytes`!"
+        )
+        .into_rgba8();
         let bytes = unsafe { buffer.align_to::<u8>().1.to_vec() };
         Reference::new(
             Image {
@@ -661,6 +1008,124 @@ enum UniformData {
     Texture(Integer),
 }
 
+struct UniformBuffer<T> {
+    object: T,
+    name: String,
+    binding: u32,
+    /// Number of floats.
+    size: usize,
+}
+
+impl<T> UniformBuffer<T> {
+    fn fits(&self, offset: usize, count: usize) -> bool {
+        offset
+            .checked_add(count)
+            .map(|end| end <= self.size)
+            .unwrap_or(false)
+    }
+}
+
+/// Uniform buffers by handle, each gets its own binding point above the one
+/// reserved for frame block.
+struct UniformBuffers<T> {
+    buffers: HashMap<Integer, UniformBuffer<T>>,
+}
+
+impl<T> Default for UniformBuffers<T> {
+    fn default() -> Self {
+        Self {
+            buffers: Default::default(),
+        }
+    }
+}
+
+impl<T> UniformBuffers<T> {
+    fn contains_block(&self, name: &str) -> bool {
+        name == FRAME_BLOCK_NAME || self.buffers.values().any(|buffer| buffer.name == name)
+    }
+
+    /// Returns binding point assigned to buffer.
+    fn insert(&mut self, handle: Integer, name: String, object: T, size: usize) -> u32 {
+        let binding = (FRAME_BLOCK_BINDING + 1..)
+            .find(|binding| {
+                !self
+                    .buffers
+                    .values()
+                    .any(|buffer| buffer.binding == *binding)
+            })
+            .unwrap();
+        self.buffers.insert(
+            handle,
+            UniformBuffer {
+                object,
+                name,
+                binding,
+                size,
+            },
+        );
+        binding
+    }
+
+    fn get(&self, handle: Integer) -> Option<&UniformBuffer<T>> {
+        self.buffers.get(&handle)
+    }
+
+    fn remove(&mut self, handle: Integer) -> Option<UniformBuffer<T>> {
+        self.buffers.remove(&handle)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &UniformBuffer<T>> {
+        self.buffers.values()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum FrameUpload {
+    None,
+    Block,
+    Uniforms,
+}
+
+/// Per-frame uniforms state of single render call - frame block is shared by
+/// all shaders so it gets uploaded once, shaders without it get plain
+/// uniforms uploaded on every switch to them.
+#[derive(Default)]
+struct FrameUniforms {
+    block_uploaded: bool,
+}
+
+impl FrameUniforms {
+    fn upload(&mut self, frame_block: bool) -> FrameUpload {
+        if !frame_block {
+            FrameUpload::Uniforms
+        } else if self.block_uploaded {
+            FrameUpload::None
+        } else {
+            self.block_uploaded = true;
+            FrameUpload::Block
+        }
+    }
+}
+
+fn frame_block_data(projection: &[f32], view: &[f32], time: f32) -> [f32; FRAME_BLOCK_SIZE] {
+    let mut result = [0.0; FRAME_BLOCK_SIZE];
+    result[0..16].copy_from_slice(projection);
+    result[16..32].copy_from_slice(view);
+    result[32] = time;
+    result
+}
+
+fn floats_of(data: &Reference) -> Option<Vec<f32>> {
+    data.read::<Array>()?
+        .iter()
+        .map(|item| item.read::<Real>().map(|value| *value as f32))
+        .collect()
+}
+
+fn bytes_of(data: &[f32]) -> Vec<u8> {
+    data.iter().flat_map(|value| value.to_ne_bytes()).collect()
+}
+
 #[no_mangle]
 pub extern "C" fn version() -> IntuicioVersion {
     core_version()
@@ -671,6 +1136,7 @@ pub extern "C" fn install(registry: &mut Registry) {
     registry.add_type(Renderer::define_struct(registry));
     registry.add_type(RenderBuffer::define_struct(registry));
     registry.add_type(Transform::define_struct(registry));
+    registry.add_type(Matrix::define_struct(registry));
     registry.add_type(Image::define_struct(registry));
     registry.add_function(Renderer::new__define_function(registry));
     registry.add_function(Renderer::create_shader__define_function(registry));
@@ -679,8 +1145,197 @@ pub extern "C" fn install(registry: &mut Registry) {
     registry.add_function(Renderer::destroy_texture__define_function(registry));
     registry.add_function(Renderer::create_mesh__define_function(registry));
     registry.add_function(Renderer::destroy_mesh__define_function(registry));
+    registry.add_function(Renderer::create_uniform_buffer__define_function(registry));
+    registry.add_function(Renderer::update_uniform_buffer__define_function(registry));
+    registry.add_function(Renderer::destroy_uniform_buffer__define_function(registry));
     registry.add_function(Renderer::render__define_function(registry));
     registry.add_function(RenderBuffer::clear__define_function(registry));
     registry.add_function(RenderBuffer::enqueue__define_function(registry));
+    registry.add_function(Transform::matrix__define_function(registry));
+    registry.add_function(Transform::combine__define_function(registry));
+    registry.add_function(Transform::inverse__define_function(registry));
+    registry.add_function(Transform::transform_point__define_function(registry));
     registry.add_function(Image::decode__define_function(registry));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transform_combine() {
+        let mut registry = Registry::default();
+        intuicio_frontend_simpleton::library::install(&mut registry);
+        install(&mut registry);
+        let parent = Reference::new(
+            Transform {
+                px: Reference::new_real(10.0, &registry),
+                ..Default::default()
+            },
+            &registry,
+        );
+        let child = Reference::new(
+            Transform {
+                yaw: Reference::new_real(90.0, &registry),
+                ..Default::default()
+            },
+            &registry,
+        );
+        let combined = Transform::combine(&registry, parent, child);
+        let point = Transform::transform_point(
+            &registry,
+            combined.clone(),
+            Reference::new_real(1.0, &registry),
+            Reference::new_real(0.0, &registry),
+            Reference::new_real(0.0, &registry),
+        );
+        let point = point
+            .read::<Array>()
+            .unwrap()
+            .iter()
+            .map(|value| *value.read::<Real>().unwrap())
+            .collect::<Vec<_>>();
+        assert!((point[0] - 10.0).abs() < 1.0e-4);
+        assert!((point[1] - 1.0).abs() < 1.0e-4);
+        assert!(point[2].abs() < 1.0e-4);
+
+        let inverse = Transform::inverse(&registry, combined);
+        let point = Transform::transform_point(
+            &registry,
+            inverse,
+            Reference::new_real(10.0, &registry),
+            Reference::new_real(1.0, &registry),
+            Reference::new_real(0.0, &registry),
+        );
+        let point = point
+            .read::<Array>()
+            .unwrap()
+            .iter()
+            .map(|value| *value.read::<Real>().unwrap())
+            .collect::<Vec<_>>();
+        assert!((point[0] - 1.0).abs() < 1.0e-4);
+        assert!(point[1].abs() < 1.0e-4);
+        assert!(point[2].abs() < 1.0e-4);
+    }
+
+    #[test]
+    fn test_render_region() {
+        let mut registry = Registry::default();
+        intuicio_frontend_simpleton::library::install(&mut registry);
+        install(&mut registry);
+        let integer = |value: Integer| Reference::new_integer(value, &registry);
+        let region = RenderRegion::new(
+            &Reference::null(),
+            &Reference::null(),
+            &integer(800),
+            &integer(600),
+            &Reference::null(),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(region.viewport, [0, 0, 800, 600]);
+        assert_eq!(region.scissor, None);
+
+        let region = RenderRegion::new(
+            &integer(400),
+            &integer(0),
+            &integer(400),
+            &integer(300),
+            &Reference::new_array(
+                vec![integer(410), integer(10), integer(380), integer(280)],
+                &registry,
+            ),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(region.viewport, [400, 0, 400, 300]);
+        assert_eq!(region.scissor, Some([410, 10, 380, 280]));
+
+        assert!(RenderRegion::new(
+            &integer(10),
+            &integer(10),
+            &integer(0),
+            &integer(300),
+            &Reference::null(),
+        )
+        .unwrap()
+        .is_none());
+        assert!(RenderRegion::new(
+            &Reference::null(),
+            &Reference::null(),
+            &integer(800),
+            &integer(600),
+            &Reference::new_array(
+                vec![integer(0), integer(0), integer(100), integer(0)],
+                &registry,
+            ),
+        )
+        .unwrap()
+        .is_none());
+    }
+
+    #[test]
+    fn test_uniform_buffers() {
+        let mut buffers = UniformBuffers::<u32>::default();
+        assert!(buffers.contains_block(FRAME_BLOCK_NAME));
+        assert!(!buffers.contains_block("Lights"));
+        assert_eq!(buffers.insert(0, "Lights".to_owned(), 10, 8), 1);
+        assert_eq!(buffers.insert(1, "Material".to_owned(), 20, 4), 2);
+        assert!(buffers.contains_block("Lights"));
+        assert!(buffers.contains_block("Material"));
+
+        let buffer = buffers.get(0).unwrap();
+        assert_eq!(buffer.object, 10);
+        assert!(buffer.fits(0, 8));
+        assert!(buffer.fits(4, 4));
+        assert!(!buffer.fits(4, 5));
+        assert!(!buffer.fits(usize::MAX, 1));
+        assert!(buffers.get(2).is_none());
+
+        assert_eq!(buffers.remove(0).unwrap().object, 10);
+        assert!(buffers.remove(0).is_none());
+        assert!(!buffers.contains_block("Lights"));
+        assert_eq!(buffers.insert(2, "Camera".to_owned(), 30, 16), 1);
+        let mut bindings = buffers
+            .iter()
+            .map(|buffer| (buffer.name.as_str(), buffer.binding))
+            .collect::<Vec<_>>();
+        bindings.sort();
+        assert_eq!(bindings, vec![("Camera", 1), ("Material", 2)]);
+    }
+
+    #[test]
+    fn test_frame_uniforms() {
+        let projection = (0..16).map(|value| value as f32).collect::<Vec<_>>();
+        let view = (16..32).map(|value| value as f32).collect::<Vec<_>>();
+        let data = frame_block_data(&projection, &view, 42.0);
+        assert_eq!(&data[0..16], projection.as_slice());
+        assert_eq!(&data[16..32], view.as_slice());
+        assert_eq!(data[32], 42.0);
+        assert_eq!(bytes_of(&data).len(), FRAME_BLOCK_SIZE * 4);
+
+        let mut frame_uniforms = FrameUniforms::default();
+        let uploads = [true, true, false, true, false, true]
+            .into_iter()
+            .map(|frame_block| frame_uniforms.upload(frame_block))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            uploads
+                .iter()
+                .filter(|upload| **upload == FrameUpload::Block)
+                .count(),
+            1
+        );
+        assert_eq!(
+            uploads,
+            vec![
+                FrameUpload::Block,
+                FrameUpload::None,
+                FrameUpload::Uniforms,
+                FrameUpload::None,
+                FrameUpload::Uniforms,
+                FrameUpload::None,
+            ]
+        );
+    }
+}